@@ -0,0 +1,171 @@
+use semver::Version;
+use serde_json;
+
+use std::collections::BTreeMap;
+
+use crates::GitOrigin;
+use debian::source::SourceFile;
+
+pub const STATEMENT_TYPE: &'static str = "https://in-toto.io/Statement/v1";
+pub const PREDICATE_TYPE: &'static str = "https://slsa.dev/provenance/v1";
+pub const BUILD_TYPE: &'static str = "https://salsa.debian.org/rust-team/debcargo/orig-tarball/v1";
+pub const BUILDER_ID: &'static str = "https://salsa.debian.org/rust-team/debcargo";
+
+/// What went into packaging a crate and what came out of it, to trace the
+/// orig tarballs back to the upstream release.
+pub struct Attestation<'a> {
+    pub crate_name: &'a str,
+    pub version: &'a Version,
+    /// The SHA-256 of the .crate, for crates from a registry.
+    pub crate_sha256: Option<&'a str>,
+    pub download_url: Option<&'a str>,
+    pub git_origin: Option<&'a GitOrigin>,
+    /// The debcargo.toml files used, least specific first.
+    pub configs: Vec<SourceFile>,
+    /// The config overrides given on the command line or in the environment.
+    pub overrides: &'a [String],
+    /// The orig tarballs.
+    pub outputs: Vec<SourceFile>,
+}
+
+impl<'a> Attestation<'a> {
+    /// An in-toto statement with a SLSA provenance predicate, in JSON, for a
+    /// run of debcargo started at `started`, e.g. "2019-01-01T00:00:00Z".
+    pub fn to_statement(&self, started: &str) -> String {
+        let sha256 = |s: &str| {
+            let mut digest = BTreeMap::new();
+            digest.insert("sha256", s.to_string());
+            digest
+        };
+        let mut dependencies = Vec::new();
+        if let Some(origin) = self.git_origin {
+            let mut digest = BTreeMap::new();
+            digest.insert("gitCommit", origin.commit.clone());
+            dependencies.push(ResourceDescriptor {
+                name: None,
+                uri: Some(format!("git+{}@{}", origin.url, origin.commit)),
+                digest: digest,
+            });
+        }
+        if let Some(checksum) = self.crate_sha256 {
+            dependencies.push(ResourceDescriptor {
+                name: Some(format!("{}-{}.crate", self.crate_name, self.version)),
+                uri: Some(self.download_url.map_or_else(
+                    || format!("pkg:cargo/{}@{}", self.crate_name, self.version),
+                    str::to_string,
+                )),
+                digest: sha256(checksum),
+            });
+        }
+        let statement = Statement {
+            kind: STATEMENT_TYPE,
+            subject: self
+                .outputs
+                .iter()
+                .map(|f| ResourceDescriptor {
+                    name: Some(f.name.clone()),
+                    uri: None,
+                    digest: sha256(&f.sha256),
+                })
+                .collect(),
+            predicate_type: PREDICATE_TYPE,
+            predicate: Provenance {
+                build_definition: BuildDefinition {
+                    build_type: BUILD_TYPE,
+                    external_parameters: ExternalParameters {
+                        crate_name: self.crate_name.to_string(),
+                        version: self.version.to_string(),
+                        configs: self
+                            .configs
+                            .iter()
+                            .map(|f| ResourceDescriptor {
+                                name: Some(f.name.clone()),
+                                uri: None,
+                                digest: sha256(&f.sha256),
+                            })
+                            .collect(),
+                        overrides: self.overrides.to_vec(),
+                    },
+                    resolved_dependencies: dependencies,
+                },
+                run_details: RunDetails {
+                    builder: Builder {
+                        id: BUILDER_ID,
+                        version: {
+                            let mut version = BTreeMap::new();
+                            version.insert("debcargo", env!("CARGO_PKG_VERSION"));
+                            version
+                        },
+                    },
+                    metadata: Metadata {
+                        started_on: started.to_string(),
+                    },
+                },
+            },
+        };
+        serde_json::to_string_pretty(&statement).unwrap() + "\n"
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Statement {
+    #[serde(rename = "_type")]
+    kind: &'static str,
+    subject: Vec<ResourceDescriptor>,
+    predicate_type: &'static str,
+    predicate: Provenance,
+}
+
+#[derive(Serialize)]
+struct ResourceDescriptor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+    digest: BTreeMap<&'static str, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Provenance {
+    build_definition: BuildDefinition,
+    run_details: RunDetails,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildDefinition {
+    build_type: &'static str,
+    external_parameters: ExternalParameters,
+    resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Serialize)]
+struct ExternalParameters {
+    #[serde(rename = "crate")]
+    crate_name: String,
+    version: String,
+    configs: Vec<ResourceDescriptor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    overrides: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunDetails {
+    builder: Builder,
+    metadata: Metadata,
+}
+
+#[derive(Serialize)]
+struct Builder {
+    id: &'static str,
+    version: BTreeMap<&'static str, &'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Metadata {
+    started_on: String,
+}
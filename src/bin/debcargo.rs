@@ -5,6 +5,8 @@ extern crate chrono;
 extern crate clap;
 #[macro_use]
 extern crate debcargo;
+#[macro_use]
+extern crate failure;
 extern crate flate2;
 extern crate glob;
 extern crate itertools;
@@ -12,44 +14,51 @@ extern crate semver;
 extern crate semver_parser;
 extern crate tar;
 extern crate tempfile;
-extern crate walkdir;
 
 use ansi_term::Colour::Red;
-use clap::{App, AppSettings, ArgMatches, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
+use std::time::Duration;
 
-use debcargo::config::{parse_config, Config};
-use debcargo::crates::{update_crates_io, CrateInfo};
+use debcargo::attestation::Attestation;
+use debcargo::build;
+use debcargo::clean;
+use debcargo::compare::CrateDiff;
+use debcargo::config::{env_overrides, load_config, load_config_with_upstream, Config, OVERRIDES_ENV};
+use debcargo::crates::{crates_io_mirror, index_url, local_registry, registry_dependency_line,
+                       registry_index_age, semver_suffix_of, update_registry,
+                       version_req_from_arg, CrateInfo, DependencySelection, GitSelector, Mirror, DL_URL_ENV,
+                       INDEX_URL_ENV, LOCAL_REGISTRY_ENV};
 use debcargo::debian::{self, BaseInfo};
+use debcargo::debian::changelog::Backport;
+use debcargo::debian::control::PackageNaming;
+use debcargo::debian::copyright::{self, CopyrightGuess};
+use debcargo::debian::dep5;
+use debcargo::debian::itp;
+use debcargo::debian::crosscheck;
+use debcargo::debian::patches;
+use debcargo::debian::source::{self, SourceFile};
+use debcargo::deprecation;
 use debcargo::errors::*;
+use debcargo::fixme::{find_fixmes, Fixme, FixmeKind};
+use debcargo::layout::{ConfLayout, ConfPackaging};
+use debcargo::outdated::{find_packagings, packaged_crate, OutdatedChecker};
+use debcargo::regenerate::{self, Outcome, Regenerated};
+use debcargo::sbom::Sbom;
+use debcargo::selftest;
+use debcargo::transition::Transition;
+use debcargo::tree::{dot_graph, Archive, TreeBuilder};
+use debcargo::upstream;
 use debcargo::util;
-
-fn lookup_fixmes(srcdir: &Path) -> Result<Vec<PathBuf>> {
-    let mut fixme_files = Vec::new();
-    for entry in walkdir::WalkDir::new(srcdir) {
-        let entry = entry?;
-        if entry.file_type().is_file() {
-            let file = fs::File::open(entry.path())?;
-            let reader = BufReader::new(file);
-            // If we find one FIXME we break the loop and check next file. Idea
-            // is only to find files with FIXME strings in it.
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    if line.contains("FIXME") {
-                        fixme_files.push(entry.path().to_path_buf());
-                        break;
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(fixme_files)
-}
+use debcargo::validate::{config_skeleton, unknown_override_keys, validate_config};
+use debcargo::vendor::{self, Vendoring};
+use debcargo::verify;
+use debcargo::versions;
 
 fn rel_p<'a>(path: &'a Path, base: &'a Path) -> &'a str {
     path.strip_prefix(base).unwrap_or(path).to_str().unwrap()
@@ -62,52 +71,607 @@ fn new_crate_info(matches: &ArgMatches, update: bool) -> Result<CrateInfo> {
     if let Some(path) = path {
         let path = PathBuf::from_str(path)?.canonicalize()?;
         CrateInfo::new_from_path(&path, version, update)
+    } else if let Some(url) = matches.value_of("git") {
+        let selector = if let Some(rev) = matches.value_of("rev") {
+            GitSelector::Rev(rev.to_string())
+        } else if let Some(tag) = matches.value_of("tag") {
+            GitSelector::Tag(tag.to_string())
+        } else if let Some(branch) = matches.value_of("branch") {
+            GitSelector::Branch(branch.to_string())
+        } else {
+            GitSelector::DefaultBranch
+        };
+        CrateInfo::new_from_git(url, selector, crate_name, version)
+    } else if let Some(dir) = matches.value_of("vendor-dir") {
+        let dir = PathBuf::from_str(dir)?.canonicalize()?;
+        CrateInfo::new_from_vendor_dir(&dir, crate_name, version)
+    } else if let Some(file) = matches.value_of("crate-file") {
+        CrateInfo::new_with_local_crate(crate_name, version, Path::new(file))
     } else {
         CrateInfo::new_from_crates_io(crate_name, version, update)
     }
 }
 
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        s if s < 60 * 60 => format!("{} minutes", s / 60),
+        s if s < 48 * 60 * 60 => format!("{} hours", s / (60 * 60)),
+        s => format!("{} days", s / (24 * 60 * 60)),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 * 1024 => format!("{} KiB", b / 1024),
+        b if b < 1024 * 1024 * 1024 => format!("{} MiB", b / (1024 * 1024)),
+        b => format!("{:.1} GiB", b as f64 / (1024.0 * 1024.0 * 1024.0)),
+    }
+}
+
+/// Whether to update the crates.io index before looking up the crate. By
+/// default we only fetch it if there is no local copy yet; `debcargo update`
+/// or --update refresh it explicitly.
+fn should_update_index(matches: &ArgMatches) -> Result<bool> {
+    if matches.is_present("update") {
+        return Ok(true);
+    }
+    if matches.is_present("vendor-dir") || matches.is_present("crate-file") {
+        // the vendored crates, or the .crate, are all we need
+        return Ok(false);
+    }
+    if let Some(path) = local_registry()? {
+        debcargo_info!("Using the local registry {} instead of crates.io", path.display());
+        return Ok(false);
+    }
+    if let Some(url) = index_url() {
+        debcargo_info!("Using the registry index {} instead of crates.io", url);
+    }
+    match crates_io_mirror(&cargo::Config::default()?)? {
+        Some((name, Mirror::Registry(_))) => {
+            debcargo_info!("Using {} instead of crates.io, as the cargo config says", name)
+        }
+        Some((name, _)) => {
+            debcargo_info!("Using {} instead of crates.io, as the cargo config says", name);
+            return Ok(false);
+        }
+        None => (),
+    }
+    match registry_index_age(None)? {
+        None if matches.is_present("no-update") => {
+            debcargo_bail!("There is no local copy of the crates.io index, and --no-update was given")
+        }
+        None => {
+            debcargo_info!("No local copy of the crates.io index yet, fetching it");
+            Ok(true)
+        }
+        Some(age) => {
+            debcargo_info!(
+                "Using the local crates.io index, last updated {} ago; \
+                 run `debcargo update` or pass --update to refresh it",
+                format_age(age)
+            );
+            Ok(false)
+        }
+    }
+}
+
+fn explain_version_choice(matches: &ArgMatches, crate_info: &CrateInfo) -> Result<()> {
+    let version = match matches.value_of("version").filter(|v| !v.is_empty()) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let req = version_req_from_arg(version)?;
+    if req != version {
+        debcargo_info!(
+            "Interpreting version \"{}\" as \"{}\", i.e. exactly that version",
+            version,
+            req
+        );
+    }
+    let candidates = crate_info.version_candidates();
+    if !candidates.is_empty() {
+        debcargo_info!(
+            "Published versions matching \"{}\": {}",
+            req,
+            candidates.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+        );
+        debcargo_info!("Using the newest matching version: {}", crate_info.version());
+    }
+    Ok(())
+}
+
+/// Vendor the dependencies of a crate into vendor/ of its unpacked source,
+/// and write the matching orig tarball component.
+fn vendor_dependencies(
+    matches: &ArgMatches,
+    crate_name: &str,
+    pkg_srcdir: &Path,
+    orig_tar_gz: &Path,
+) -> Result<Vendoring> {
+    let shipped = pkg_srcdir.join("Cargo.lock");
+    let (lockfile, shipped) = match matches.value_of("locked") {
+        Some(l) => (fs::read_to_string(l)?, false),
+        None if shipped.is_file() => (fs::read_to_string(&shipped)?, true),
+        None => debcargo_bail!(
+            "{} has no Cargo.lock to vendor the dependencies from; give one with --locked",
+            crate_name
+        ),
+    };
+    let crates = vendor::locked_registry_crates(&lockfile, crate_name)?;
+    debcargo_info!("Vendoring {} crates", crates.len());
+    let vendor_dir = pkg_srcdir.join(vendor::COMPONENT);
+    let vendored = vendor::vendor_crates(&crates, &vendor_dir, false)?;
+    vendor::write_component_tarball(&vendor_dir, &vendor::component_tarball_path(orig_tar_gz, vendor::COMPONENT))?;
+    Ok(Vendoring {
+        crates: vendored,
+        lockfile: if shipped { None } else { Some(lockfile) },
+    })
+}
+
+/// Warn about tests, benchmarks and license files of the upstream repository
+/// that were left out of the crate, and with --add-upstream-files, ship them
+/// in an orig tarball component and under upstream/ in the source package.
+fn check_upstream_files(
+    matches: &ArgMatches,
+    crate_info: &CrateInfo,
+    pkg_srcdir: &Path,
+    orig_tar_gz: &Path,
+) -> Result<()> {
+    let repository = match crate_info.metadata().repository {
+        Some(ref r) => r.clone(),
+        None => {
+            debcargo_warn!("The crate has no repository, cannot check for files excluded from it");
+            return Ok(());
+        }
+    };
+    let crate_contents = verify::tarball_contents(fs::File::open(crate_info.crate_file().path())?)?;
+    let vcs = upstream::vcs_info(&crate_contents);
+    let name = crate_info.package_id().name();
+    let version = crate_info.version();
+    let from_head = matches.is_present("upstream-files-from-head");
+    let (revision, upstream_files) =
+        match upstream::fetch_upstream_files(&repository, &name, version, &vcs, from_head) {
+            Ok(found) => found,
+            // the files to add must be those of the release
+            Err(e) if matches.is_present("add-upstream-files") => return Err(e),
+            Err(e) => {
+                debcargo_warn!("Could not fetch {}, not checking for excluded files: {}", repository, e);
+                return Ok(());
+            }
+        };
+    let excluded = upstream::excluded_files(&crate_contents, &upstream_files);
+    if excluded.is_empty() {
+        debcargo_info!("The crate has all the tests and license files of {} at {}", repository, revision);
+        return Ok(());
+    }
+    debcargo_warn!("These files of {} at {} are not in the crate:", repository, revision);
+    for path in &excluded {
+        warn_with_code("upstream-file-missing", Some(path), None, &format!("\t •  {}", path.display()));
+    }
+    if !matches.is_present("add-upstream-files") {
+        debcargo_warn!("Pass --add-upstream-files to ship them as the {} component", upstream::COMPONENT);
+        return Ok(());
+    }
+    let files = excluded
+        .into_iter()
+        .map(|p| {
+            let data = upstream_files[&p].clone();
+            (p, data)
+        })
+        .collect();
+    let tarball = vendor::component_tarball_path(orig_tar_gz, upstream::COMPONENT);
+    upstream::write_component_tarball(&files, &tarball)?;
+    for (path, data) in &files {
+        let dest = pkg_srcdir.join(upstream::COMPONENT).join(path);
+        fs::create_dir_all(dest.parent().unwrap())?;
+        fs::write(dest, data)?;
+    }
+    debcargo_info!("Added them under {}/, from {}", upstream::COMPONENT, tarball.display());
+    Ok(())
+}
+
+/// Compare the generated Depends in d/control with what cargo resolves for
+/// each feature, since any difference is a bug in the translation.
+fn check_depends(crate_info: &CrateInfo, pkg_srcdir: &Path, naming: &PackageNaming, strict: bool) -> Result<()> {
+    let control = fs::read_to_string(pkg_srcdir.join("debian").join("control"))?;
+    let features_with_deps = crate_info.all_dependencies_and_features(&DependencySelection::default())?;
+    let discrepancies = crosscheck::cross_check(&features_with_deps, &control, naming)?;
+    if discrepancies.is_empty() {
+        debcargo_info!("The generated Depends match the dependencies cargo resolves");
+        return Ok(());
+    }
+    debcargo_warn!("The generated Depends differ from the dependencies cargo resolves:");
+    let control = Path::new("debian/control");
+    for d in &discrepancies {
+        warn_with_code("depends-mismatch", Some(control), None, &format!("\t •  {}", d));
+    }
+    if strict {
+        debcargo_bail!("{} discrepancies in the generated Depends", discrepancies.len());
+    }
+    Ok(())
+}
+
+/// Explain which cargo features each generated package stands for, and what
+/// they enable in the dependencies. `dest` is relative to the package source,
+/// or - for stdout.
+fn write_feature_report(crate_info: &CrateInfo, pkg_srcdir: &Path, naming: &PackageNaming, dest: &str) -> Result<()> {
+    let control = fs::read_to_string(pkg_srcdir.join("debian").join("control"))?;
+    let features_with_deps = crate_info.all_dependencies_and_features(&DependencySelection::default())?;
+    let report = crosscheck::feature_report(&features_with_deps, &control, naming)?;
+    if dest == "-" {
+        print!("{}", report);
+    } else {
+        let path = pkg_srcdir.join(dest);
+        fs::write(&path, report)?;
+        debcargo_info!("Wrote the feature report to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Fail on dependencies on git repositories or local paths that the patches
+/// in the overlay leave, as they can't be satisfied from the archive,
+/// suggesting registry versions to patch them to. Such dev-dependencies only keep the tests from running, so they
+/// are just warned about.
+fn check_source_dependencies(crate_info: &CrateInfo) -> Result<()> {
+    use cargo::core::dependency::Kind;
+    let (dev, needed): (Vec<_>, Vec<_>) = crate_info
+        .source_dependencies()
+        .into_iter()
+        .partition(|dep| dep.kind() == Kind::Development);
+    for dep in &dev {
+        debcargo_warn!(
+            "The dev-dependency {} is from {}, so the tests needing it can't run",
+            dep.name_in_toml(),
+            dep.source_id()
+        );
+    }
+    if needed.is_empty() {
+        return Ok(());
+    }
+    debcargo_warn!(
+        "{} has dependencies that can't be satisfied from the archive:",
+        crate_info.package_id().name()
+    );
+    for dep in &needed {
+        // a missing index only costs us the suggestion
+        match crate_info.newest_registry_version(dep).unwrap_or(None) {
+            Some(version) => debcargo_warn!(
+                "\t •  {} is from {}; patch Cargo.toml to use `{}`",
+                dep.name_in_toml(),
+                dep.source_id(),
+                registry_dependency_line(dep, &version)
+            ),
+            None => debcargo_warn!(
+                "\t •  {} is from {}, and no matching version is in the registry",
+                dep.name_in_toml(),
+                dep.source_id()
+            ),
+        }
+    }
+    debcargo_bail!(
+        "{} git or path dependencies; replace them with registry versions in a patch \
+         under debian/patches in the overlay",
+        needed.len()
+    );
+}
+
+/// Warn if upstream abandoned or renamed the crate, and what to package
+/// instead if known. With `lookup`, also ask crates.io.
+fn check_deprecated(crate_name: &str, lookup: bool) {
+    if let Some(successor) = deprecation::successor(crate_name) {
+        debcargo_warn!("{} is superseded by {} upstream; consider packaging that instead", crate_name, successor);
+    }
+    if !lookup {
+        return;
+    }
+    match deprecation::crates_io_deprecation_notices(crate_name) {
+        Ok(notices) => for notice in notices {
+            debcargo_warn!("{}", notice);
+        },
+        Err(e) => debcargo_warn!("Couldn't check whether {} is deprecated on crates.io: {}", crate_name, e),
+    }
+}
+
+/// The d/changelog item closing the ITP bug of a new package: the bug given
+/// with --itp, else the open one on the Debian BTS. Packagings that already
+/// have a d/changelog in their overlay were uploaded before, and close none.
+fn itp_closes(matches: &ArgMatches, config: &Config, source: &str) -> Result<Option<String>> {
+    let released = config
+        .overlay_dir()
+        .map_or(false, |o| o.join("changelog").exists());
+    if released && !matches.is_present("itp") {
+        return Ok(None);
+    }
+    if util::vec_opt_iter(config.changelog_entries.as_ref()).any(|e| e.contains("Closes: #")) {
+        return Ok(None);
+    }
+    let bug = match matches.value_of("itp") {
+        Some(bug) => match bug.trim_start_matches('#').parse() {
+            Ok(bug) => Some(bug),
+            Err(_) => debcargo_bail!("--itp takes a bug number, not {}", bug),
+        },
+        None if !matches.is_present("itp-lookup") => None,
+        None => match itp::lookup_itp(source) {
+            Ok(None) => {
+                debcargo_warn!("No open ITP bug for {} on the Debian BTS; file one before the upload", source);
+                None
+            }
+            Ok(bug) => bug,
+            Err(e) => {
+                debcargo_warn!(
+                    "Couldn't look up the ITP bug of {} on the Debian BTS: {}; give its number with --itp",
+                    source,
+                    e
+                );
+                None
+            }
+        },
+    };
+    Ok(bug.map(itp::closes_item))
+}
+
+/// Warn about what the build script of the crate does that the packaging may
+/// need to handle, and suggest the Build-Depends it seems to need that the
+/// config doesn't have yet.
+fn check_build_script(crate_info: &CrateInfo, config: &Config) -> Result<()> {
+    let report = match crate_info.analyze_build_script()? {
+        Some(report) => report,
+        None => return Ok(()),
+    };
+    for warning in report.warnings() {
+        debcargo_warn!("{}", warning);
+    }
+    let configured = config.build_depends().map_or(&[][..], |d| &d[..]);
+    let missing = report
+        .build_depends()
+        .into_iter()
+        .filter(|d| !configured.iter().any(|c| c.split_whitespace().next() == Some(d.as_str())))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        debcargo_info!(
+            "{} probably needs Build-Depends: {}; add them to build_depends under [source] \
+             in debcargo.toml",
+            report.path.display(),
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Look up the crates.io owners of the crate, which d/copyright falls back on
+/// without authors in Cargo.toml, and suggest those who are not Uploaders.
+fn load_owners(crate_info: &mut CrateInfo, uploaders: &[String]) -> Result<()> {
+    let name = crate_info.package_id().name().to_string();
+    let owners = match crate_info.load_crates_io_owners() {
+        Ok(owners) => owners,
+        Err(e) => {
+            debcargo_warn!("Couldn't look up the crates.io owners of {}: {}", name, e);
+            return Ok(());
+        }
+    };
+    let missing = owners
+        .iter()
+        .filter(|o| !uploaders.iter().any(|u| u.starts_with(o.as_str())))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        debcargo_info!(
+            "Owners of {} on crates.io who are not in Uploaders: {}. Add those who maintain \
+             the package to uploaders in debcargo.toml, as \"Full Name <email>\".",
+            name,
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The config files given with --config, in order.
+fn config_paths<'a>(matches: &'a ArgMatches) -> Vec<&'a Path> {
+    matches
+        .values_of("config")
+        .map_or_else(Vec::new, |v| v.map(Path::new).collect())
+}
+
+/// The packaging of the crate in the checkout of debcargo-conf given with
+/// --conf-dir, if any, started if there is none.
+fn conf_packaging(matches: &ArgMatches) -> Result<Option<ConfPackaging>> {
+    match matches.value_of("conf-dir") {
+        Some(dir) => Ok(Some(ConfLayout::new(Path::new(dir)).packaging_or_new(
+            matches.value_of("crate").unwrap(),
+            matches.value_of("version"),
+        )?)),
+        None => Ok(None),
+    }
+}
+
+/// How the packages of crates are named, by the templates of the config
+/// files given with --config, if any.
+fn package_naming(matches: &ArgMatches) -> Result<PackageNaming> {
+    let config_paths = config_paths(matches);
+    if config_paths.is_empty() {
+        return Ok(PackageNaming::default());
+    }
+    let config = load_config(&config_paths, matches.value_of("crate").unwrap(), &config_overrides()?)
+        .context("failed to parse debcargo.toml")?;
+    PackageNaming::from_config(&config)
+}
+
+/// The config overrides from --override and the environment, which must all
+/// be for known keys since a typo would otherwise go unnoticed.
+fn config_overrides() -> Result<Vec<String>> {
+    let overrides = env_overrides();
+    let unknown = unknown_override_keys(&overrides)?;
+    if !unknown.is_empty() {
+        debcargo_bail_kind!(ErrorKind::Config, "Unknown config keys in overrides: {}", unknown.join(", "));
+    }
+    Ok(overrides)
+}
+
 fn do_package(matches: &ArgMatches) -> Result<()> {
+    package(matches, |_| None).map(|_| ())
+}
+
+/// Package a crate as asked, returning the crate and the unpacked source
+/// package. `upstream_changes` gives an item for d/changelog about the crate,
+/// if any.
+fn package<F>(matches: &ArgMatches, upstream_changes: F) -> Result<(CrateInfo, PathBuf)>
+where
+    F: FnOnce(&CrateInfo) -> Option<String>,
+{
     let crate_name = matches.value_of("crate").unwrap();
-    let directory = matches.value_of("directory");
-    let (config_path, config) = match matches.value_of("config") {
-        Some(p) => {
-            let path = Path::new(p);
-            let config = parse_config(path).context("failed to parse debcargo.toml")?;
-            (Some(path), config)
+    let started = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    if let Some(dest) = matches.value_of("explain") {
+        if dest != "-" {
+            // a log of this run only
+            fs::File::create(dest)?;
         }
-        None => (None, Config::default()),
+        env::set_var(EXPLAIN_ENV, dest);
+    }
+    let conf = conf_packaging(matches)?;
+    let directory = matches
+        .value_of("directory")
+        .map(PathBuf::from)
+        .or_else(|| conf.as_ref().map(|c| c.build_dir.clone()));
+    let mut config_paths = config_paths(matches);
+    if let Some(ref c) = conf {
+        config_paths.push(&c.config);
+    }
+    // the last config is the most specific, i.e. the crate's own
+    let config_path = config_paths.last().cloned();
+    // parse the config before updating the index and downloading the crate,
+    // to report mistakes in it early; the hints of the crate come below it
+    let overrides = config_overrides()?;
+    let config = load_config(&config_paths, crate_name, &overrides).context("failed to parse debcargo.toml")?;
+    let mut crate_info = new_crate_info(matches, should_update_index(matches)?)?;
+    let upstream = if matches.is_present("ignore-upstream-metadata") {
+        Default::default()
+    } else {
+        crate_info.upstream_config()?
+    };
+    if !upstream.is_empty() {
+        debcargo_info!(
+            "Using the packaging hints in package.metadata.debian of Cargo.toml for {}, below those of \
+             debcargo.toml",
+            upstream.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+    let mut config = if upstream.is_empty() {
+        config
+    } else {
+        load_config_with_upstream(&config_paths, crate_name, &overrides, upstream)
+            .context("failed to parse debcargo.toml")?
     };
+    if let Some(revision) = matches.value_of("revision") {
+        config.initial_revision = Some(revision.to_string());
+    }
+    if let Some(vendor) = matches.value_of("vendor") {
+        config.vendor = Some(vendor.to_string());
+    }
+    if let Some(author) = matches.value_of("changelog-author") {
+        config.changelog_author = Some(author.to_string());
+    }
+    if matches.is_present("team-upload") {
+        config.team_upload = Some(true);
+    }
+    if let Some(entries) = matches.values_of("changelog-entry") {
+        config
+            .changelog_entries
+            .get_or_insert_with(Vec::new)
+            .extend(entries.map(str::to_string));
+    }
     let changelog_ready = matches.is_present("changelog-ready");
     let overlay_write_back = !matches.is_present("no-overlay-write-back");
-    let copyright_guess_harder = matches.is_present("copyright-guess-harder");
+    if let Some(mode) = matches.value_of("copyright-guess") {
+        config.copyright_guess = Some(CopyrightGuess::parse(mode)?);
+    } else if matches.is_present("copyright-guess-harder") {
+        config.copyright_guess = Some(CopyrightGuess::Harder);
+    }
+    let description_guess_harder = matches.is_present("description-guess-harder");
+    let strict = matches.is_present("strict");
+    let backport = match matches.value_of("backport") {
+        Some(suite) => Some(Backport::parse(suite)?),
+        None => None,
+    };
 
-    let mut crate_info = new_crate_info(matches, true)?;
+    check_deprecated(crate_name, matches.is_present("check-deprecated"));
+    check_build_script(&crate_info, &config)?;
+    if matches.is_present("crates-io-owners") {
+        load_owners(&mut crate_info, config.uploaders().map_or(&[][..], |u| &u[..]))?;
+    }
+    if let Some(origin) = crate_info.git_origin() {
+        config
+            .changelog_entries
+            .get_or_insert_with(Vec::new)
+            .push(origin.changelog_entry());
+    }
+    explain_version_choice(matches, &crate_info)?;
+    if let Some(item) = upstream_changes(&crate_info) {
+        config.changelog_entries.get_or_insert_with(Vec::new).push(item);
+    }
+    if let Some(lockfile) = matches.value_of("locked") {
+        crate_info
+            .set_lockfile(Path::new(lockfile))
+            .context(format!("failed to read lockfile {}", lockfile))?;
+    }
     let pkgbase = BaseInfo::new(
         crate_name,
         &crate_info,
         crate_version!(),
         config.semver_suffix,
+        config.repack_suffix(),
     );
 
-    let pkg_srcdir = Path::new(directory.unwrap_or(pkgbase.package_source_dir()));
+    if !changelog_ready {
+        let source = format!("rust-{}", pkgbase.package_name());
+        if let Some(item) = itp_closes(matches, &config, &source)? {
+            config.changelog_entries.get_or_insert_with(Vec::new).push(item);
+        }
+    }
+
+    let pkg_srcdir = directory.unwrap_or_else(|| PathBuf::from(pkgbase.package_source_dir()));
+    let pkg_srcdir = pkg_srcdir.as_path();
     let orig_tar_gz = pkg_srcdir
         .parent()
         .unwrap()
         .join(pkgbase.orig_tarball_path());
-    crate_info.set_includes_excludes(config.orig_tar_excludes(), config.orig_tar_whitelist());
+    crate_info.set_includes_excludes(config.orig_tar_excludes(), config.orig_tar_whitelist())?;
+    crate_info.set_allow_suspicious(matches.is_present("allow-suspicious"));
     let source_modified = crate_info.extract_crate(pkg_srcdir)?;
     debian::prepare_orig_tarball(&crate_info, &orig_tar_gz, source_modified, pkg_srcdir)?;
+    if matches.is_present("check-upstream-files") || matches.is_present("add-upstream-files") {
+        check_upstream_files(matches, &crate_info, pkg_srcdir, &orig_tar_gz)?;
+    }
+    let vendoring = if config.vendored || matches.is_present("vendored") {
+        Some(vendor_dependencies(matches, crate_name, pkg_srcdir, &orig_tar_gz)?)
+    } else {
+        None
+    };
     debian::prepare_debian_folder(
         &pkgbase,
         &mut crate_info,
         pkg_srcdir,
-        config_path,
         &config,
         changelog_ready,
-        copyright_guess_harder,
+        description_guess_harder,
         overlay_write_back,
+        strict,
+        backport.as_ref(),
+        vendoring.as_ref(),
     )?;
+    // only now that crate_info has the Cargo.toml patched by the overlay
+    check_source_dependencies(&crate_info)?;
+    if matches.is_present("verify-build") {
+        build::verify_build(pkg_srcdir, Path::new(build::DEBIAN_REGISTRY))?;
+        debcargo_info!("The crate builds with the chosen dependencies");
+    }
+    let naming = PackageNaming::from_config(&config)?;
+    if matches.is_present("check-depends") {
+        check_depends(&crate_info, pkg_srcdir, &naming, strict)?;
+    }
+    if let Some(dest) = matches.value_of("feature-report") {
+        write_feature_report(&crate_info, pkg_srcdir, &naming, dest)?;
+    }
 
     let curdir = env::current_dir()?;
     debcargo_info!(
@@ -115,15 +679,27 @@ fn do_package(matches: &ArgMatches) -> Result<()> {
         rel_p(pkg_srcdir, &curdir),
         rel_p(&orig_tar_gz, &curdir)
     );
-    let fixmes = lookup_fixmes(pkg_srcdir.join("debian").as_path());
+    // the FIXMEs are shown as warnings too, but only some of them fail --strict
+    let warnings = warning_count();
+    let fixmes = find_fixmes(pkg_srcdir.join("debian").as_path());
     if let Ok(fixmes) = fixmes {
-        if !fixmes.is_empty() {
-            debcargo_warn!("FIXME found in the following files.");
-            for f in fixmes {
-                if util::is_hint_file(&f) {
-                    debcargo_warn!("\t(•) {}", rel_p(&f, &curdir));
-                } else {
-                    debcargo_warn!("\t •  {}", rel_p(&f, &curdir));
+        if json_warnings() {
+            for f in &fixmes {
+                let code = if f.hint { "fixme-hint" } else { "fixme" };
+                let file = Path::new(rel_p(&f.file, &curdir));
+                eprintln!("{}", fixme_json(code, file, f.line, &f.text, f.kind.code(), f.kind.action()));
+            }
+        } else if !fixmes.is_empty() {
+            let mut by_kind: BTreeMap<FixmeKind, Vec<&Fixme>> = BTreeMap::new();
+            for f in &fixmes {
+                by_kind.entry(f.kind).or_default().push(f);
+            }
+            debcargo_warn!("FIXMEs left in the packaging, by kind; (•) marks those in hint files:");
+            for (kind, fixmes) in &by_kind {
+                debcargo_warn!("{}: {}", kind.code(), kind.action());
+                for f in fixmes {
+                    let mark = if f.hint { "(•)" } else { " • " };
+                    debcargo_warn!("\t{} {}:{}: {}", mark, rel_p(&f.file, &curdir), f.line, f.text);
                 }
             }
             debcargo_warn!("");
@@ -141,21 +717,192 @@ fn do_package(matches: &ArgMatches) -> Result<()> {
                 None => debcargo_warn!(format!("\t •  Create an overlay directory and add it to your config file with overlay = \"/path/to/overlay\"")),
                 Some(_) => {
                     debcargo_warn!(format!("\t •  Add or edit files in your overlay directory:"));
-                    debcargo_warn!(format!("\t    {}", rel_p(&config.overlay_dir(config_path).unwrap(), &curdir)));
+                    debcargo_warn!(format!("\t    {}", rel_p(&config.overlay_dir().unwrap(), &curdir)));
                 }
             }
         }
+        // hint files are expected to have them, as the things to review, and
+        // d/changelog is UNRELEASED-FIXME until the package is released
+        if strict && fixmes.iter().any(|f| !f.hint && f.kind != FixmeKind::Distribution) {
+            debcargo_bail_kind!(ErrorKind::Fixmes, "FIXMEs remain in the packaging");
+        }
+    }
+    if strict && warnings > 0 {
+        debcargo_bail_kind!(ErrorKind::Warnings, "{} warnings in generating the packaging", warnings);
+    }
+    if matches.is_present("source-package") {
+        let dsc = source::build_source_package(pkg_srcdir)?;
+        debcargo_info!("Source package: {}", rel_p(&dsc, &curdir));
+    }
+    if let Some(dest) = matches.value_of("provenance") {
+        let outdir = orig_tar_gz.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let source_name = format!("rust-{}", pkgbase.package_name());
+        let mut outputs = Vec::new();
+        for orig in source::orig_tarballs(outdir, &source_name, pkgbase.debian_version())? {
+            outputs.push(SourceFile::new(&orig)?);
+        }
+        let mut configs = Vec::new();
+        for path in &config_paths {
+            configs.push(SourceFile::new(path)?);
+        }
+        let overrides = config_overrides()?;
+        let attestation = Attestation {
+            crate_name: crate_name,
+            version: crate_info.version(),
+            crate_sha256: crate_info.checksum(),
+            download_url: crate_info.provenance().download_url.as_ref().map(String::as_str),
+            git_origin: crate_info.git_origin(),
+            configs: configs,
+            overrides: &overrides,
+            outputs: outputs,
+        };
+        fs::write(dest, attestation.to_statement(&started))?;
+        debcargo_info!("Provenance statement: {}", dest);
+    }
+
+    Ok((crate_info, pkg_srcdir.to_path_buf()))
+}
+
+fn do_new_upstream(matches: &ArgMatches) -> Result<()> {
+    let crate_name = matches.value_of("crate").unwrap();
+    let conf = matches.value_of("conf-dir").map(|dir| {
+        ConfLayout::new(Path::new(dir)).packaging(crate_name, matches.value_of("version"))
+    });
+    let mut config_paths = config_paths(matches);
+    if let Some(ref c) = conf {
+        if !c.config.is_file() {
+            debcargo_bail!("{} has no packaging of {} to update", c.config.display(), crate_name);
+        }
+        config_paths.push(&c.config);
+    }
+    let config_path = *config_paths.last().unwrap();
+    let config = load_config(&config_paths, crate_name, &config_overrides()?)
+        .context("failed to parse debcargo.toml")?;
+    let overlay = match config.overlay_dir() {
+        Some(o) => o,
+        None => debcargo_bail!("{} has no overlay directory, so there is no packaging to update",
+                               config_path.display()),
+    };
+    let previous = fs::read_to_string(overlay.join("changelog"))
+        .ok()
+        .and_then(|c| packaged_crate(&c))
+        .map(|(_, v)| v);
+
+    let mut diff = None;
+    let (crate_info, pkg_srcdir) = package(matches, |new: &CrateInfo| {
+        let old = previous.as_ref().filter(|old| *old < new.version())?;
+        let compared = CrateInfo::new_from_crates_io(crate_name, Some(&old.to_string()), false)
+            .and_then(|old_info| CrateDiff::new(&old_info, new));
+        match compared {
+            Ok(d) => {
+                let item = d.changelog_entry(old);
+                diff = Some(d);
+                Some(item)
+            }
+            Err(e) => {
+                debcargo_warn!("Couldn't compare with the previous version {}: {}", old, e);
+                None
+            }
+        }
+    })?;
+    let new = crate_info.version().clone();
+    match previous {
+        Some(ref old) if *old == new => debcargo_warn!("{} {} is already packaged", crate_name, new),
+        Some(ref old) if *old > new => debcargo_warn!("{} {} is older than the packaged {}", crate_name, new, old),
+        _ => (),
+    }
+
+    let results = patches::refresh_patches(&pkg_srcdir)?;
+    let write_back = !matches.is_present("no-overlay-write-back");
+    for &(ref patch, ref status) in &results {
+        debcargo_info!("Patch {}: {}", patch, status);
+        match *status {
+            patches::PatchStatus::Failed(ref output) => {
+                for line in output.lines() {
+                    debcargo_warn!("\t{}", line);
+                }
+            }
+            patches::PatchStatus::Refreshed if write_back => {
+                fs::copy(
+                    pkg_srcdir.join("debian/patches").join(patch),
+                    overlay.join("patches").join(patch),
+                )?;
+                debcargo_info!("Wrote back refreshed patch to overlay: patches/{}", patch);
+            }
+            _ => (),
+        }
+    }
+    let series_path = pkg_srcdir.join("debian/patches/series");
+    if series_path.exists() {
+        let series = fs::read_to_string(&series_path)?;
+        let marked = patches::mark_failed_patches(&series, &results, &new.to_string());
+        if marked != series {
+            fs::write(&series_path, &marked)?;
+            if write_back {
+                fs::write(overlay.join("patches/series"), &marked)?;
+                debcargo_warn!("Updated the patches that no longer apply in the overlay: patches/series");
+            }
+        }
     }
 
+    let summarized = diff.is_some();
+    if let (Some(old), Some(diff)) = (previous.as_ref(), diff) {
+        println!("Upstream changes {} -> {}:", old, new);
+        print!("{}", diff);
+    }
+
+    println!("Follow-ups:");
+    let failed = results
+        .iter()
+        .filter(|&&(_, ref s)| *s != patches::PatchStatus::Applied && *s != patches::PatchStatus::Refreshed)
+        .map(|&(ref p, _)| p.as_str())
+        .collect::<Vec<_>>();
+    if !failed.is_empty() {
+        println!("  - fix or drop the patches that no longer apply: {}", failed.join(", "));
+    }
+    if let Some(ref old) = previous {
+        if semver_suffix_of(old) != semver_suffix_of(&new) {
+            println!("  - the update crosses a semver boundary; check the reverse dependencies, \
+                      e.g. with `debcargo outdated`");
+        }
+    }
+    println!("  - review any FIXMEs and .debcargo.hint files listed above, and update the overlay");
+    if let Some(changes) = copyright_changes(&pkg_srcdir)? {
+        for change in changes {
+            println!("  - d/copyright: {}", change);
+        }
+    } else {
+        println!("  - check d/copyright against any license changes listed above");
+    }
+    println!("  - check that new or changed dependencies are in Debian, e.g. with `debcargo tree`");
+    if summarized {
+        println!("  - review the summary of the upstream changes in d/changelog");
+    }
+    println!("  - edit d/changelog to describe your other changes");
+    println!("  - build and test the package, e.g. with sbuild, before uploading");
     Ok(())
 }
 
+/// The changes to carry over to the d/copyright of the overlay, from the one
+/// generated now, if the overlay has one.
+fn copyright_changes(pkg_srcdir: &Path) -> Result<Option<Vec<String>>> {
+    let debian = pkg_srcdir.join("debian");
+    let hint = debian.join(format!("copyright{}", util::HINT_SUFFIX));
+    if !hint.exists() {
+        return Ok(None);
+    }
+    let parse = |p: &Path| -> Result<dep5::Copyright> {
+        Ok(fs::read_to_string(p)?.parse::<dep5::Copyright>().context(format!("failed to parse {}", p.display()))?)
+    };
+    Ok(Some(copyright::copyright_changes(&parse(&debian.join("copyright"))?, &parse(&hint)?)))
+}
+
 fn do_deb_src_name(matches: &ArgMatches) -> Result<()> {
     let crate_name = matches.value_of("crate").unwrap();
     let version = matches.value_of("version");
 
     let crate_info = new_crate_info(matches, false)?;
-    let pkgbase = BaseInfo::new(crate_name, &crate_info, crate_version!(), version.is_some());
+    let pkgbase = BaseInfo::new(crate_name, &crate_info, crate_version!(), version.is_some(), None);
 
     println!("{}", pkgbase.package_name());
     Ok(())
@@ -165,16 +912,442 @@ fn do_extract(matches: &ArgMatches) -> Result<()> {
     let crate_name = matches.value_of("crate").unwrap();
     let directory = matches.value_of("directory");
 
-    let crate_info = new_crate_info(matches, true)?;
-    let pkgbase = BaseInfo::new(crate_name, &crate_info, crate_version!(), false);
+    let mut crate_info = new_crate_info(matches, should_update_index(matches)?)?;
+    explain_version_choice(matches, &crate_info)?;
+    let pkgbase = BaseInfo::new(crate_name, &crate_info, crate_version!(), false, None);
     let pkg_srcdir = Path::new(directory.unwrap_or(pkgbase.package_source_dir()));
 
+    crate_info.set_allow_suspicious(matches.is_present("allow-suspicious"));
     crate_info.extract_crate(pkg_srcdir)?;
     Ok(())
 }
 
-fn do_update() -> Result<()> {
-    update_crates_io()
+fn do_verify(matches: &ArgMatches) -> Result<()> {
+    let config = load_config(
+        &config_paths(matches),
+        matches.value_of("crate").unwrap(),
+        &config_overrides()?,
+    ).context("failed to parse debcargo.toml")?;
+    let mut crate_info = new_crate_info(matches, should_update_index(matches)?)?;
+    crate_info.set_includes_excludes(config.orig_tar_excludes(), config.orig_tar_whitelist())?;
+    let pkgid = crate_info.package_id();
+    let crate_path = crate_info.crate_file().path().to_path_buf();
+
+    let checksum = verify::sha256_hex(&crate_path)?;
+    match crate_info.checksum() {
+        Some(expected) if expected == checksum => {
+            debcargo_info!("{} {}: .crate checksum matches the index", pkgid.name(), pkgid.version())
+        }
+        Some(expected) => debcargo_bail!(
+            "Checksum of {} is {}, but the index says {}",
+            crate_path.display(),
+            checksum,
+            expected
+        ),
+        None => debcargo_warn!("The index has no checksum for {} {}", pkgid.name(), pkgid.version()),
+    }
+
+    let expected = verify::tarball_contents(fs::File::open(&crate_path)?)?;
+    let mut ok = true;
+    let mut check = |what: &str, actual: verify::Contents| -> Result<()> {
+        let differences = verify::compare_contents(&crate_info, &expected, &actual)?;
+        if differences.is_empty() {
+            debcargo_info!("{}: corresponds to the crate", what);
+        } else {
+            ok = false;
+            debcargo_warn!("{}: {} differences from the crate", what, differences.len());
+            for d in differences {
+                debcargo_warn!("\t{}", d);
+            }
+        }
+        Ok(())
+    };
+    if let Some(tarball) = matches.value_of("orig-tarball") {
+        if verify::sha256_hex(Path::new(tarball))? == checksum {
+            debcargo_info!("{}: identical to the crate", tarball);
+        } else {
+            check(tarball, verify::tarball_contents(fs::File::open(tarball)?)?)?;
+        }
+    }
+    if let Some(dir) = matches.value_of("source-dir") {
+        check(dir, verify::dir_contents(Path::new(dir))?)?;
+    }
+    if !ok {
+        debcargo_bail!("Verification failed");
+    }
+    Ok(())
+}
+
+fn do_compare(matches: &ArgMatches) -> Result<()> {
+    let crate_name = matches.value_of("crate").unwrap();
+    let update = should_update_index(matches)?;
+    let old = CrateInfo::new_from_crates_io(crate_name, matches.value_of("old"), update)?;
+    let new = CrateInfo::new_from_crates_io(crate_name, matches.value_of("new"), false)?;
+    println!("Comparing {} {} -> {}", crate_name, old.version(), new.version());
+    print!("{}", CrateDiff::new(&old, &new)?);
+    Ok(())
+}
+
+fn do_tree(matches: &ArgMatches) -> Result<()> {
+    let config = cargo::Config::default()?;
+    let mut builder = TreeBuilder::new(
+        &config,
+        should_update_index(matches)?,
+        matches.is_present("optional"),
+    )?;
+    builder.set_naming(package_naming(matches)?);
+    let tree = builder.build(matches.value_of("crate").unwrap(), matches.value_of("version"))?;
+    print!("{}", tree);
+    Ok(())
+}
+
+fn do_graph(matches: &ArgMatches) -> Result<()> {
+    let config = cargo::Config::default()?;
+    let mut builder = TreeBuilder::new(
+        &config,
+        should_update_index(matches)?,
+        matches.is_present("optional"),
+    )?;
+    builder.set_naming(package_naming(matches)?);
+    let tree = builder.build(matches.value_of("crate").unwrap(), matches.value_of("version"))?;
+    let graph = dot_graph(&tree);
+    match matches.value_of("output") {
+        Some(file) => {
+            fs::write(file, graph)?;
+            debcargo_info!("Wrote the dependency graph of {} to {}", tree.name, file);
+        }
+        None => print!("{}", graph),
+    }
+    Ok(())
+}
+
+fn do_sbom(matches: &ArgMatches) -> Result<()> {
+    let crate_name = matches.value_of("crate").unwrap();
+    let version = matches.value_of("version");
+    let config = cargo::Config::default()?;
+    let update = should_update_index(matches)?;
+    let mut builder = TreeBuilder::new(&config, update, matches.is_present("optional"))?;
+    builder.set_archive_lookups(false);
+    let tree = builder.build(crate_name, version)?;
+
+    let crate_info = CrateInfo::new_from_crates_io(crate_name, Some(&tree.version.to_string()), false)?;
+    // the licenses are not in the index, only in the crates themselves
+    let lookup_license = |name: &str, version: &semver::Version| -> Result<Option<String>> {
+        if name == crate_name && *version == tree.version {
+            return Ok(crate_info.metadata().license.clone());
+        }
+        let dep_info = CrateInfo::new_from_crates_io(name, Some(&version.to_string()), false)?;
+        Ok(dep_info.metadata().license.clone())
+    };
+    let pkgbase = BaseInfo::new(crate_name, &crate_info, crate_version!(), version.is_some(), None);
+    let sbom = Sbom::from_tree(pkgbase.package_name(), &tree, lookup_license)?;
+    let created = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let document = match matches.value_of("format") {
+        Some("cyclonedx") => sbom.to_cyclonedx(&created),
+        _ => sbom.to_spdx(&created),
+    };
+    match matches.value_of("output") {
+        Some(file) => {
+            fs::write(file, document)?;
+            debcargo_info!("Wrote the SBOM of {} {} crates to {}", pkgbase.package_name(), sbom.packages.len(), file);
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+fn do_outdated(matches: &ArgMatches) -> Result<()> {
+    let packagings = find_packagings(Path::new(matches.value_of("dir").unwrap()))?;
+    if packagings.is_empty() {
+        debcargo_bail!("No packagings found under {}", matches.value_of("dir").unwrap());
+    }
+    let config = cargo::Config::default()?;
+    let mut checker = OutdatedChecker::new(&config, should_update_index(matches)?)?;
+    checker.set_transitions(matches.is_present("transitions"));
+    let outdated = checker.check(&packagings)?;
+    for o in &outdated {
+        print!("{}", o);
+    }
+    debcargo_info!("{} of {} packagings are outdated", outdated.len(), packagings.len());
+    Ok(())
+}
+
+fn do_versions(matches: &ArgMatches) -> Result<()> {
+    let crate_name = matches.value_of("crate").unwrap();
+    let config = cargo::Config::default()?;
+    let index = versions::index_versions(&config, crate_name, should_update_index(matches)?)?;
+    let crates_io = if matches.is_present("offline") {
+        None
+    } else {
+        match versions::crates_io_listed_versions(crate_name) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                debcargo_warn!("Couldn't ask crates.io for publish dates and yanked versions: {}", e);
+                None
+            }
+        }
+    };
+    let listed = versions::list_versions(&index, crates_io.as_ref().map(|v| &v[..]));
+    if listed.is_empty() {
+        debcargo_bail!("{} has no versions in the index", crate_name);
+    }
+    print!("{}", versions::format_versions(&listed));
+    Ok(())
+}
+
+fn do_transition(matches: &ArgMatches) -> Result<()> {
+    let crate_name = matches.value_of("crate").unwrap();
+    let parse = |v: &str| semver::Version::parse(v).context(format!("invalid version {}", v));
+    let to = parse(matches.value_of("version").unwrap())?;
+    let from = match matches.value_of("from") {
+        Some(v) => Some(parse(v)?),
+        None => None,
+    };
+    let naming = package_naming(matches)?;
+    match Transition::check(&mut Archive::default(), &naming, crate_name, from.as_ref(), &to)? {
+        Some(t) => print!("{}", t),
+        None => debcargo_info!("Updating {} to {} keeps its semver suffix", crate_name, to),
+    }
+    Ok(())
+}
+
+fn do_regenerate_all(matches: &ArgMatches) -> Result<()> {
+    let root = Path::new(matches.value_of("dir").unwrap());
+    let output_dir = Path::new(matches.value_of("output-dir").unwrap_or("build"));
+    let packagings = find_packagings(root)?
+        .into_iter()
+        .filter(|p| p.dir.join("debian/debcargo.toml").is_file())
+        .collect::<Vec<_>>();
+    if packagings.is_empty() {
+        debcargo_bail!("No packagings with a debian/debcargo.toml found under {}", root.display());
+    }
+    let debcargo = env::current_exe()?;
+    let mut update = matches.is_present("update");
+    let mut regenerated = Vec::new();
+    for packaging in packagings {
+        let dir = Regenerated::output_path(root, &packaging, output_dir);
+        debcargo_info!("Regenerating {} {} in {}", packaging.crate_name, packaging.version, dir.display());
+        let previous = if dir.join("debian").is_dir() {
+            Some(verify::dir_contents(&dir.join("debian"))?)
+        } else {
+            None
+        };
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.parent().unwrap())?;
+        // in another process, so that one failing doesn't stop the others
+        let mut package = Command::new(&debcargo);
+        package
+            .arg("package")
+            .arg("--config")
+            .arg(packaging.dir.join("debian/debcargo.toml"))
+            .arg("--directory")
+            .arg(&dir)
+            .arg("--changelog-ready");
+        if update {
+            // once is enough
+            package.arg("--update");
+            update = false;
+        } else if matches.is_present("no-update") {
+            package.arg("--no-update");
+        }
+        package.arg(&packaging.crate_name).arg(format!("={}", packaging.version));
+        let outcome = match package.status() {
+            Ok(ref status) if status.success() => {
+                Outcome::of(previous.as_ref(), &verify::dir_contents(&dir.join("debian"))?)
+            }
+            Ok(status) => Outcome::Failed(format!("debcargo package failed with {}, see its output above", status)),
+            Err(e) => Outcome::Failed(e.to_string()),
+        };
+        regenerated.push(Regenerated {
+            packaging: packaging,
+            dir: dir,
+            outcome: outcome,
+        });
+    }
+    for r in &regenerated {
+        print!("{}", r);
+    }
+    debcargo_info!("{}", regenerate::summary(&regenerated));
+    if regenerated.iter().any(|r| match r.outcome {
+        Outcome::Failed(_) => true,
+        _ => false,
+    }) {
+        debcargo_bail!("Some packagings failed to regenerate");
+    }
+    Ok(())
+}
+
+fn do_clean(matches: &ArgMatches) -> Result<()> {
+    let dir = Path::new(matches.value_of("directory").unwrap_or("."));
+    let dry_run = matches.is_present("dry-run");
+
+    // only what debcargo itself left; cargo's caches are shared with other
+    // builds, which may be using them right now
+    let mut paths = clean::leftover_tempdirs(dir)?;
+    paths.extend(clean::leftover_tempdirs(&env::temp_dir())?);
+    let outputs = clean::generated_outputs(dir)?;
+    for output in clean::stale_outputs(&outputs) {
+        paths.push(output.dir.clone());
+        paths.extend(clean::output_tarballs(output)?);
+    }
+    if paths.is_empty() {
+        debcargo_info!("Nothing to clean");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    for path in &paths {
+        let size = clean::disk_usage(path);
+        total += size;
+        if dry_run {
+            println!("Would remove {} ({})", path.display(), format_size(size));
+        } else {
+            clean::remove_path(path)?;
+            println!("Removed {} ({})", path.display(), format_size(size));
+        }
+    }
+    debcargo_info!(
+        "{} {} in total",
+        if dry_run { "Would free" } else { "Freed" },
+        format_size(total)
+    );
+    Ok(())
+}
+
+fn do_config_validate(matches: &ArgMatches) -> Result<()> {
+    let config_path = Path::new(matches.value_of("config").unwrap());
+    // the crate defaults to the one last packaged, per the changelog next to
+    // the config, as in debcargo-conf
+    let packaged = fs::read_to_string(config_path.with_file_name("changelog"))
+        .ok()
+        .and_then(|c| packaged_crate(&c));
+    let crate_info = match (matches.value_of("crate"), packaged) {
+        (Some(name), _) => Some(CrateInfo::new_from_crates_io(
+            name, matches.value_of("version"), should_update_index(matches)?)?),
+        (None, Some((name, version))) => {
+            debcargo_info!("Checking against {} {}, from the changelog", name, version);
+            Some(CrateInfo::new_from_crates_io(
+                &name, Some(&version.to_string()), should_update_index(matches)?)?)
+        }
+        (None, None) => {
+            debcargo_info!("No crate given, skipping the checks against the crate");
+            None
+        }
+    };
+    let issues = validate_config(config_path, crate_info.as_ref())?;
+    for issue in &issues {
+        if issue.is_error() {
+            debcargo_warn!("{}: {}", config_path.display(), issue);
+        } else {
+            debcargo_info!("{}: {}", config_path.display(), issue);
+        }
+    }
+    let errors = issues.iter().filter(|i| i.is_error()).count();
+    if errors > 0 {
+        debcargo_bail_kind!(ErrorKind::Config, "{} has {} errors", config_path.display(), errors);
+    }
+    debcargo_info!("{} is valid, with {} warnings", config_path.display(), issues.len());
+    Ok(())
+}
+
+fn do_init_config(matches: &ArgMatches) -> Result<()> {
+    let crate_info = CrateInfo::new_from_crates_io(
+        matches.value_of("crate").unwrap(),
+        matches.value_of("version"),
+        should_update_index(matches)?,
+    )?;
+    let skeleton = config_skeleton(&crate_info)?;
+    match matches.value_of("output") {
+        Some(file) => {
+            if Path::new(file).exists() {
+                debcargo_bail!("{} exists already, not overwriting it", file);
+            }
+            fs::write(file, skeleton)?;
+            debcargo_info!("Wrote a debcargo.toml for {} {} to {}", crate_info.package_id().name(),
+                           crate_info.version(), file);
+        }
+        None => print!("{}", skeleton),
+    }
+    Ok(())
+}
+
+fn do_selftest(matches: &ArgMatches) -> Result<()> {
+    let cases_dir = Path::new(matches.value_of("cases").unwrap_or("tests/golden"));
+    let bless = matches.is_present("bless");
+    let cases = selftest::find_cases(cases_dir)?;
+    if cases.is_empty() {
+        debcargo_bail!("No test crates found in {}", cases_dir.display());
+    }
+    let mut failed = 0;
+    for case in &cases {
+        let differences = selftest::run_case(case, bless)?;
+        if bless {
+            debcargo_info!("{}: updated the expected output", case.name);
+        } else if differences.is_empty() {
+            debcargo_info!("{}: ok", case.name);
+        } else {
+            failed += 1;
+            debcargo_warn!("{}: {} differences", case.name, differences.len());
+            for d in differences {
+                debcargo_warn!("\t{}", d);
+            }
+        }
+    }
+    if failed > 0 {
+        debcargo_bail!(
+            "{} of {} cases differ; if that is intended, update them with --bless",
+            failed,
+            cases.len()
+        );
+    }
+    Ok(())
+}
+
+fn do_update(matches: &ArgMatches) -> Result<()> {
+    let registry = matches.value_of("registry");
+    let registry_desc = registry.unwrap_or("crates.io");
+    let age = registry_index_age(registry)?;
+    if matches.is_present("status") {
+        match age {
+            Some(age) => println!("{}: last updated {} ago", registry_desc, format_age(age)),
+            None => println!("{}: never fetched", registry_desc),
+        }
+        return Ok(());
+    }
+    if let (Some(max_age), Some(age)) = (matches.value_of("max-age"), age) {
+        let max_age = max_age
+            .parse::<u64>()
+            .map_err(|e| format_err!("Invalid --max-age \"{}\": {}", max_age, e))?;
+        if age < Duration::from_secs(max_age * 60 * 60) {
+            debcargo_info!(
+                "The {} index was updated {} ago, not updating",
+                registry_desc,
+                format_age(age)
+            );
+            return Ok(());
+        }
+    }
+    update_registry(registry)
+}
+
+/// --update and --no-update, for the subcommands that read the crates.io index.
+fn update_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::from_usage("--update 'Update the crates.io index first, \
+                         even if there is a local copy.'")
+            .conflicts_with("no-update"),
+        Arg::from_usage("--no-update 'Never update the crates.io index, \
+                         fail if there is no local copy.'"),
+    ]
+}
+
+/// --allow-suspicious, for the subcommands that extract a crate.
+fn allow_suspicious_arg() -> Arg<'static, 'static> {
+    Arg::from_usage("--allow-suspicious 'Only warn about suspicious files in the crate, \
+                     e.g. C sources, instead of aborting, and report them \
+                     next to the output directory.'")
 }
 
 fn real_main() -> Result<()> {
@@ -184,18 +1357,169 @@ fn real_main() -> Result<()> {
         .global_setting(AppSettings::ColoredHelp)
         .global_setting(AppSettings::UnifiedHelpMessage)
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg_from_usage("--local-registry [dir] 'Use a local registry directory, as made by \
+                         `cargo local-registry`, instead of crates.io. Can also be set with \
+                         DEBCARGO_LOCAL_REGISTRY.'")
+        .arg_from_usage("--index-url [url] 'Use the registry index at this URL instead of crates.io \
+                         and any mirror in the cargo config, e.g. that of a caching proxy. Can also \
+                         be set with DEBCARGO_INDEX_URL.'")
+        .arg_from_usage("--dl-url [url] 'Download crates from this URL instead of where the index \
+                         says, e.g. a caching proxy; {crate} and {version} are filled in, else \
+                         /{crate}/{version}/download is appended. Can also be set with \
+                         DEBCARGO_DL_URL.'")
+        .arg(Arg::from_usage("--warnings-format [format] 'How to show warnings: as text, or as \
+                              JSON lines on stderr with a stable code, the file and the message. \
+                              Can also be set with DEBCARGO_WARNINGS_FORMAT.'")
+             .possible_values(&["text", "json"]))
+        .arg(Arg::from_usage("--override [key=value]... 'Override a key of the config, e.g. \
+                              source.section=utils or packages.\"lib+std\".test_is_broken=true; \
+                              values are TOML, or else strings. Can also be set with \
+                              DEBCARGO_OVERRIDES, one per line.'")
+             .number_of_values(1))
         .subcommands(vec![SubCommand::with_name("package")
                               .about("Package a crate from crates.io or path")
                               .arg_from_usage("<crate> 'Name of the crate to package'")
                               .arg_from_usage("[version] 'Version of the crate to package; may \
                                                include dependency operators'")
                               .arg_from_usage("--path [path] 'If local package, path to the crate to package'")
+                              .arg(Arg::from_usage("--vendor-dir [dir] 'Package the crate from a directory \
+                                                    made by `cargo vendor`, without the network or the \
+                                                    crates.io index.'")
+                                   .conflicts_with("path"))
+                              .arg(Arg::from_usage("--git [url] 'Package the crate from a git repository, \
+                                                    possibly from a workspace there, instead of crates.io. \
+                                                    The commit is recorded in d/changelog and \
+                                                    d/upstream/metadata.'")
+                                   .conflicts_with_all(&["path", "vendor-dir"]))
+                              .arg(Arg::from_usage("--crate-file [file] 'Package the crate from a .crate \
+                                                    file downloaded before, e.g. to reproduce an old \
+                                                    packaging, without the network or the crates.io index.'")
+                                   .conflicts_with_all(&["path", "vendor-dir", "git"]))
+                              .arg(Arg::from_usage("--rev [rev] 'With --git, the commit to package'")
+                                   .requires("git")
+                                   .conflicts_with_all(&["tag", "branch"]))
+                              .arg(Arg::from_usage("--tag [tag] 'With --git, the tag to package'")
+                                   .requires("git")
+                                   .conflicts_with("branch"))
+                              .arg(Arg::from_usage("--branch [branch] 'With --git, the branch to package, \
+                                                    instead of the default one'")
+                                   .requires("git"))
                               .arg_from_usage("--directory [directory] 'Output directory.'")
+                              .arg_from_usage("--conf-dir [dir] 'Checkout of debcargo-conf to take the \
+                                               packaging from, in src/<crate>/debian, starting it if there \
+                                               is none, and to generate the source package in, in \
+                                               build/<crate>. The debcargo.toml there comes after any \
+                                               given with --config.'")
                               .arg_from_usage("--changelog-ready 'Assume the changelog is already bumped, and leave it alone.'")
-                              .arg_from_usage("--copyright-guess-harder 'Guess extra values for d/copyright. Might be slow.'")
+                              .arg_from_usage("--copyright-guess-harder 'Guess extra values for d/copyright. Might be slow. \
+                                               Same as --copyright-guess harder.'")
+                              .arg(Arg::from_usage("--copyright-guess [mode] 'How much to guess for d/copyright: \
+                                                    off for only a skeleton from Cargo.toml, normal to also \
+                                                    scan the sources for copyright notices, or harder to also \
+                                                    look at the upstream git history. Overrides copyright_guess \
+                                                    in the config.'")
+                                   .possible_values(&["off", "normal", "harder"])
+                                   .conflicts_with("copyright-guess-harder"))
+                              .arg_from_usage("--crates-io-owners 'Look up the owners of the crate on \
+                                               crates.io, to name them as copyright holders if Cargo.toml \
+                                               has no authors, and suggest them as Uploaders. Needs network \
+                                               access.'")
+                              .arg_from_usage("--check-deprecated 'Check on crates.io whether upstream \
+                                               abandoned or renamed the crate: all its versions are yanked, \
+                                               its description says so, or it had no release for years. \
+                                               Needs network access.'")
+                              .arg_from_usage("--description-guess-harder 'If the crate has no description, \
+                                               try its GitHub repository. Needs network access.'")
+                              .arg_from_usage("--strict 'Fail instead of generating placeholder values, \
+                                               e.g. for a missing description, when d/copyright \
+                                               does not cover every file, when FIXMEs are left, \
+                                               and when anything else was warned about.'")
+                              .arg(allow_suspicious_arg())
                               .arg_from_usage("--no-overlay-write-back 'Don\'t write back hint files or d/changelog to the source overlay directory.'")
-                              .arg_from_usage("--config [file] 'TOML file providing additional \
-                                               package-specific options.'")
+                              .arg(Arg::from_usage("--config [file]... 'TOML file providing additional \
+                                                    package-specific options. Can be given several times, \
+                                                    e.g. team defaults then the crate\'s own config; \
+                                                    later files override earlier ones.'")
+                                   .number_of_values(1))
+                              .arg_from_usage("--locked [lockfile] 'Cargo.lock whose versions to pin \
+                                               dependencies to, instead of any semver-compatible version.'")
+                              .arg_from_usage("--vendored 'Package an application together with all its \
+                                               dependencies from its Cargo.lock, vendored under vendor/, \
+                                               like vendored = true in the config.'")
+                              .arg_from_usage("--check-upstream-files 'Warn about tests, benchmarks and \
+                                               license files in the upstream repository that are not in \
+                                               the crate. Needs network access.'")
+                              .arg_from_usage("--add-upstream-files 'Like --check-upstream-files, and add \
+                                               the missing files as the upstream orig tarball component.'")
+                              .arg_from_usage("--upstream-files-from-head 'For --check-upstream-files \
+                                               and --add-upstream-files, take the files from HEAD when \
+                                               the release has no commit or tag in the repository.'")
+                              .arg_from_usage("--verify-build 'Check that the crate builds with `cargo build \
+                                               --offline` against the crates installed in \
+                                               /usr/share/cargo/registry, or the vendored ones.'")
+                              .arg_from_usage("--source-package 'Also build the 3.0 (quilt) source \
+                                               package, the debian tarball and the .dsc, without \
+                                               dpkg-source, e.g. on hosts that are not Debian.'")
+                              .arg_from_usage("--provenance [file] 'Write an in-toto provenance statement \
+                                               linking the .crate or git commit, the debcargo version \
+                                               and config to the digests of the orig tarballs, to this \
+                                               file.'")
+                              .arg_from_usage("--check-depends 'Cross-check the generated Depends against \
+                                               the dependencies cargo resolves for each feature.'")
+                              .arg_from_usage("--explain [file] 'Record the decisions taken in generating \
+                                               the packaging, e.g. why a feature has no package of its own \
+                                               or where the summary comes from, in this file, or - to print \
+                                               them. Can also be set with DEBCARGO_EXPLAIN.'")
+                              .arg_from_usage("--feature-report [file] 'Write a report of the cargo \
+                                               features each package enables in its dependencies, e.g. to \
+                                               debian/feature-report.md, or - for stdout.'")
+                              .arg_from_usage("--backport [suite] 'Prepare a backport to the given suite, \
+                                               e.g. bookworm-backports, adjusting d/changelog accordingly.'")
+                              .arg_from_usage("--revision [revision] 'Debian revision for a new upstream \
+                                               version, e.g. 1~exp1. Overrides initial_revision in the config.'")
+                              .arg_from_usage("--vendor [vendor] 'Vendor whose revision suffix to use, \
+                                               e.g. ubuntu for 0ubuntu1. Overrides vendor in the config.'")
+                              .arg_from_usage("--changelog-author [author] 'Author of the d/changelog entry, \
+                                               as \"Full Name <email>\", instead of $DEBFULLNAME and $DEBEMAIL. \
+                                               Overrides changelog_author in the config.'")
+                              .arg_from_usage("--team-upload 'Mark the d/changelog entry as a team upload, \
+                                               even if its author is in Uploaders.'")
+                              .arg(Arg::from_usage("--changelog-entry [text]... 'Extra item for the \
+                                                    d/changelog entry, after those in the config.'")
+                                   .number_of_values(1))
+                              .arg(Arg::from_usage("--itp [bug] 'ITP bug to close in the initial \
+                                                    d/changelog entry.'")
+                                   .conflicts_with("itp-lookup"))
+                              .arg_from_usage("--itp-lookup 'Look up the ITP bug to close in the \
+                                               initial d/changelog entry of a new package on the \
+                                               Debian BTS. Needs network access.'")
+                              .arg_from_usage("--ignore-upstream-metadata 'Ignore the packaging hints \
+                                               in package.metadata.debian of the Cargo.toml of the crate.'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("new-upstream")
+                              .about("Update an existing packaging to a new upstream version: \
+                                      regenerate it, refresh its patches, and list what to check.")
+                              .arg_from_usage("<crate> 'Name of the crate to package'")
+                              .arg_from_usage("[version] 'Version of the crate to package; may \
+                                               include dependency operators. Defaults to the newest.'")
+                              .arg(Arg::from_usage("--config [file]... 'debcargo.toml of the existing \
+                                                    packaging, with an overlay directory. Can be given \
+                                                    several times, as for package; the last is the \
+                                                    crate\'s own.'")
+                                   .number_of_values(1)
+                                   .required_unless("conf-dir"))
+                              .arg_from_usage("--directory [directory] 'Output directory.'")
+                              .arg_from_usage("--conf-dir [dir] 'Checkout of debcargo-conf with the \
+                                               existing packaging, in src/<crate>/debian, to generate the \
+                                               source package in, in build/<crate>.'")
+                              .arg_from_usage("--no-overlay-write-back 'Don\'t write back hint files, \
+                                               refreshed patches or d/changelog to the overlay directory.'")
+                              .arg_from_usage("--revision [revision] 'Debian revision for the new upstream \
+                                               version, e.g. 1~exp1. Overrides initial_revision in the config.'")
+                              .arg_from_usage("--vendor [vendor] 'Vendor whose revision suffix to use, \
+                                               e.g. ubuntu for 0ubuntu1. Overrides vendor in the config.'")
+                              .args(&update_args())
                      ])
         .subcommands(vec![SubCommand::with_name("deb-src-name")
                               .about("Prints the Debian package name for a crate")
@@ -210,23 +1534,226 @@ fn real_main() -> Result<()> {
                               .arg_from_usage("[version] 'Version of the crate to package; may \
                                                include dependency operators'")
                               .arg_from_usage("--directory [directory] 'Output directory.'")
+                              .args(&update_args())
+                              .arg(allow_suspicious_arg())
+                     ])
+        .subcommands(vec![SubCommand::with_name("verify")
+                              .about("Verify that an orig tarball or unpacked source corresponds \
+                                      to a crate as released on crates.io.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("<version> 'Version of the crate'")
+                              .arg_from_usage("--orig-tarball [file] 'Orig tarball to verify.'")
+                              .arg_from_usage("--source-dir [directory] 'Unpacked source to verify; \
+                                               debian/ and .pc/ are ignored.'")
+                              .arg(Arg::from_usage("--config [file]... 'TOML file providing the excludes \
+                                                    that were applied. Can be given several times.'")
+                                   .number_of_values(1))
+                              .arg_from_usage("--path [path] 'If local package, path to the crate'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("compare")
+                              .about("Summarize the differences between two versions of a crate, \
+                                      to help review a version bump.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("<old> 'Old version of the crate'")
+                              .arg_from_usage("<new> 'New version of the crate'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("tree")
+                              .about("Print the dependency tree of a crate, with the Debian \
+                                      packages that provide each dependency.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("[version] 'Version of the crate; may \
+                                               include dependency operators'")
+                              .arg_from_usage("--optional 'Include all optional dependencies, not only those \
+                                               that the default features enable.'")
+                              .arg(Arg::from_usage("--config [file]... 'TOML file providing the package \
+                                                    name templates. Can be given several times.'")
+                                   .number_of_values(1))
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("graph")
+                              .about("Write the dependency tree of a crate as a Graphviz dot \
+                                      graph of crates and the features enabled on them, colored \
+                                      by whether Debian has them.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("[version] 'Version of the crate; may \
+                                               include dependency operators'")
+                              .arg_from_usage("-o, --output [file] 'Write the graph to this file \
+                                               instead of stdout.'")
+                              .arg_from_usage("--optional 'Include all optional dependencies, not only those \
+                                               that the default features enable.'")
+                              .arg(Arg::from_usage("--config [file]... 'TOML file providing the package \
+                                                    name templates. Can be given several times.'")
+                                   .number_of_values(1))
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("sbom")
+                              .about("Write a software bill of materials for the source package \
+                                      of a crate: the crate and its dependency tree, with their \
+                                      versions, checksums and licenses.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("[version] 'Version of the crate; may \
+                                               include dependency operators'")
+                              .arg(Arg::from_usage("--format [format] 'Format of the SBOM: SPDX tag-value \
+                                                    or CycloneDX JSON.'")
+                                   .possible_values(&["spdx", "cyclonedx"])
+                                   .default_value("spdx"))
+                              .arg_from_usage("-o, --output [file] 'Write the SBOM to this file \
+                                               instead of stdout.'")
+                              .arg_from_usage("--optional 'Include all optional dependencies, not only those \
+                                               that the default features enable.'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("outdated")
+                              .about("Report packagings in a checkout like debcargo-conf that \
+                                      have newer upstream versions on crates.io.")
+                              .arg_from_usage("<dir> 'Directory containing the packagings, \
+                                               e.g. the src/ directory of debcargo-conf'")
+                              .arg_from_usage("--transitions 'For updates that cross a semver-suffix \
+                                               boundary, list the reverse dependencies in the archive \
+                                               that need sourceful rebuilds. Needs apt.'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("versions")
+                              .about("List the versions of a crate, newest first, with the semver \
+                                      suffix each would be packaged with, when it was published, \
+                                      and whether it is yanked.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("--offline 'Only list the versions in the index, without \
+                                               asking crates.io for publish dates and yanked versions.'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("transition")
+                              .about("Check whether updating a crate changes its semver suffix, and \
+                                      so the names of its binary packages, and list the reverse \
+                                      dependencies in the archive that need sourceful rebuilds.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("<version> 'Version to update to'")
+                              .arg_from_usage("--from [version] 'Version to update from; defaults to \
+                                               the one in the archive.'")
+                              .arg(Arg::from_usage("--config [file]... 'TOML file providing the package \
+                                                    name templates. Can be given several times.'")
+                                   .number_of_values(1))
+                     ])
+        .subcommands(vec![SubCommand::with_name("regenerate-all")
+                              .about("Regenerate every packaging with a debcargo.toml in a checkout \
+                                      like debcargo-conf, at its packaged version, e.g. after a \
+                                      debcargo release, and summarize what changed since the last \
+                                      time. The overlays get the usual write-backs.")
+                              .arg_from_usage("<dir> 'Directory containing the packagings, \
+                                               e.g. the src/ directory of debcargo-conf'")
+                              .arg_from_usage("-o, --output-dir [dir] 'Where to generate the packagings, \
+                                               under their paths relative to <dir>, and where the last \
+                                               ones are compared with. Defaults to build.'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("config")
+                              .about("Work with debcargo.toml files.")
+                              .setting(AppSettings::SubcommandRequiredElseHelp)
+                              .subcommand(SubCommand::with_name("validate")
+                                          .about("Check a debcargo.toml for errors, unknown keys and \
+                                                  settings that would have no effect, without \
+                                                  generating anything.")
+                                          .arg_from_usage("<config> 'The debcargo.toml to check'")
+                                          .arg_from_usage("[crate] 'Crate to check it against; defaults \
+                                                           to the one in the changelog next to it, if any'")
+                                          .arg_from_usage("[version] 'Version of the crate; may \
+                                                           include dependency operators'")
+                                          .args(&update_args()))
+                     ])
+        .subcommands(vec![SubCommand::with_name("init-config")
+                              .about("Write a commented debcargo.toml for a crate, with stubs for \
+                                      the overrides of its features, optional dependencies and \
+                                      binaries.")
+                              .arg_from_usage("<crate> 'Name of the crate'")
+                              .arg_from_usage("[version] 'Version of the crate; may \
+                                               include dependency operators'")
+                              .arg_from_usage("-o, --output [file] 'Write it to this file, which must \
+                                               not exist yet, instead of stdout.'")
+                              .args(&update_args())
+                     ])
+        .subcommands(vec![SubCommand::with_name("clean")
+                              .about("Remove leftover debcargo temporary directories, and source \
+                                      packages superseded by newer versions.")
+                              .arg_from_usage("--directory [directory] 'Where to look for temporary \
+                                               directories, besides the system one, and source packages, \
+                                               instead of the current one.'")
+                              .arg_from_usage("--dry-run 'Only list what would be removed.'")
+                     ])
+        .subcommands(vec![SubCommand::with_name("selftest")
+                              .about("Regenerate the packaging of a set of test crates, and compare \
+                                      it with the expected output.")
+                              .arg_from_usage("[cases] 'Directory of test crates, by default \
+                                               tests/golden of the debcargo source tree'")
+                              .arg_from_usage("--bless 'Replace the expected output with the \
+                                               generated one, after intended changes.'")
                      ])
         .subcommands(vec![SubCommand::with_name("update")
                               .about("Update the crates.io index, outside of a workspace.")
+                              .arg_from_usage("--registry [url] 'Index URL of the registry to \
+                                               update, instead of crates.io.'")
+                              .arg_from_usage("--status 'Only show when the index was last \
+                                               updated, without updating it.'")
+                              .arg_from_usage("--max-age [hours] 'Only update the index if it is \
+                                               older than this.'")
                      ])
         .get_matches();
+    if let Some(dir) = m.value_of("local-registry") {
+        env::set_var(LOCAL_REGISTRY_ENV, dir);
+    }
+    if let Some(url) = m.value_of("index-url") {
+        env::set_var(INDEX_URL_ENV, url);
+    }
+    if let Some(url) = m.value_of("dl-url") {
+        env::set_var(DL_URL_ENV, url);
+    }
+    if let Some(format) = m.value_of("warnings-format") {
+        env::set_var(WARNINGS_FORMAT_ENV, format);
+    }
+    if let Some(overrides) = m.values_of("override") {
+        // after those from the environment, so that they take precedence
+        let mut all = env::var(OVERRIDES_ENV).unwrap_or_default();
+        for o in overrides {
+            all.push('\n');
+            all.push_str(o);
+        }
+        env::set_var(OVERRIDES_ENV, all);
+    }
     match m.subcommand() {
         ("package", Some(sm)) => do_package(sm),
+        ("new-upstream", Some(sm)) => do_new_upstream(sm),
         ("deb-src-name", Some(sm)) => do_deb_src_name(sm),
         ("extract", Some(sm)) => do_extract(sm),
-        ("update", Some(_)) => do_update(),
+        ("verify", Some(sm)) => do_verify(sm),
+        ("compare", Some(sm)) => do_compare(sm),
+        ("tree", Some(sm)) => do_tree(sm),
+        ("graph", Some(sm)) => do_graph(sm),
+        ("sbom", Some(sm)) => do_sbom(sm),
+        ("outdated", Some(sm)) => do_outdated(sm),
+        ("transition", Some(sm)) => do_transition(sm),
+        ("versions", Some(sm)) => do_versions(sm),
+        ("regenerate-all", Some(sm)) => do_regenerate_all(sm),
+        ("config", Some(sm)) => match sm.subcommand() {
+            ("validate", Some(ssm)) => do_config_validate(ssm),
+            _ => unreachable!(),
+        },
+        ("init-config", Some(sm)) => do_init_config(sm),
+        ("clean", Some(sm)) => do_clean(sm),
+        ("selftest", Some(sm)) => do_selftest(sm),
+        ("update", Some(sm)) => do_update(sm),
         _ => unreachable!(),
     }
 }
 
 fn main() {
     if let Err(e) = real_main() {
-        eprintln!("{}", Red.bold().paint(format!("Something failed: {:?}", e)));
-        std::process::exit(1);
+        if json_warnings() {
+            // as the last line of the stream
+            warn_with_code("error", None, None, &e.to_string());
+        } else {
+            eprintln!("{}", Red.bold().paint(format!("Something failed: {:?}", e)));
+        }
+        std::process::exit(ErrorKind::of(&e).map_or(1, ErrorKind::exit_code));
     }
 }
@@ -0,0 +1,84 @@
+use tempfile;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use errors::*;
+use util::copy_tree;
+use vendor;
+
+/// Where Debian's librust-*-dev packages install their crate sources, as a
+/// cargo directory source.
+pub const DEBIAN_REGISTRY: &str = "/usr/share/cargo/registry";
+
+/// How many lines of cargo's output to show when the build fails.
+const ERROR_LINES: usize = 30;
+
+/// Cargo config that replaces crates.io with a directory source, so that an
+/// offline build only sees the crates that are in it.
+pub fn cargo_config(registry: &Path) -> String {
+    format!(
+        "[source.crates-io]\nreplace-with = \"local-crates\"\n\n\
+         [source.local-crates]\ndirectory = \"{}\"\n",
+        registry.display()
+    )
+}
+
+fn last_lines(text: &str, n: usize) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+/// Run `cargo build --offline` on a copy of an unpacked source package, with
+/// its dependencies taken from the vendored crates if it has them, or else from
+/// `registry`; typically the crates installed in DEBIAN_REGISTRY. The source
+/// package itself is left alone.
+pub fn verify_build(pkg_srcdir: &Path, registry: &Path) -> Result<()> {
+    let vendored = pkg_srcdir.join(vendor::COMPONENT);
+    let registry = if vendored.is_dir() { vendored.as_path() } else { registry };
+    if !registry.is_dir() {
+        debcargo_bail!(
+            "Cannot verify the build, {} does not exist; install the librust-*-dev \
+             packages of the dependencies first",
+            registry.display()
+        );
+    }
+
+    let work_dir = tempfile::Builder::new().prefix("debcargo").tempdir()?;
+    let build_dir = work_dir.path().join("source");
+    fs::create_dir(&build_dir)?;
+    copy_tree(pkg_srcdir, &build_dir)?;
+    for generated in &["debian", ".pc", "target"] {
+        let path = build_dir.join(generated);
+        if path.exists() {
+            fs::remove_dir_all(path)?;
+        }
+    }
+    // vendoring mode ships the lockfile in debian/ if upstream doesn't
+    let lockfile = pkg_srcdir.join("debian").join("Cargo.lock");
+    if lockfile.is_file() && !build_dir.join("Cargo.lock").exists() {
+        fs::copy(&lockfile, build_dir.join("Cargo.lock"))?;
+    }
+    let registry = registry.canonicalize()?;
+    let cargo_home = work_dir.path().join("cargo_home");
+    fs::create_dir(&cargo_home)?;
+    fs::write(cargo_home.join("config.toml"), cargo_config(&registry))?;
+
+    debcargo_info!("Verifying that the crate builds against {}", registry.display());
+    let output = Command::new("cargo")
+        .args(&["build", "--offline"])
+        .current_dir(&build_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .env("CARGO_TARGET_DIR", work_dir.path().join("target"))
+        .output()?;
+    if !output.status.success() {
+        debcargo_bail!(
+            "cargo build failed against {}; the dependency versions debcargo chose \
+             may not be available there, or not work with the crate:\n{}",
+            registry.display(),
+            last_lines(&String::from_utf8_lossy(&output.stderr), ERROR_LINES)
+        );
+    }
+    Ok(())
+}
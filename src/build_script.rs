@@ -0,0 +1,160 @@
+use regex::Regex;
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Debian packages with the development files of native libraries, by their
+/// pkg-config or `links` name, where it isn't simply lib<name>-dev.
+pub const NATIVE_LIBRARY_PACKAGES: &'static [(&'static str, &'static str)] = &[
+    ("curl", "libcurl4-openssl-dev"),
+    ("libcurl", "libcurl4-openssl-dev"),
+    ("git2", "libgit2-dev"),
+    ("libgit2", "libgit2-dev"),
+    ("openssl", "libssl-dev"),
+    ("ssl", "libssl-dev"),
+    ("libssh2", "libssh2-1-dev"),
+    ("ssh2", "libssh2-1-dev"),
+    ("z", "zlib1g-dev"),
+    ("zlib", "zlib1g-dev"),
+    ("bz2", "libbz2-dev"),
+    ("lzma", "liblzma-dev"),
+    ("liblzma", "liblzma-dev"),
+    ("zstd", "libzstd-dev"),
+    ("libzstd", "libzstd-dev"),
+    ("sqlite3", "libsqlite3-dev"),
+    ("dbus-1", "libdbus-1-dev"),
+    ("dbus", "libdbus-1-dev"),
+    ("libudev", "libudev-dev"),
+    ("udev", "libudev-dev"),
+    ("libpcre2-8", "libpcre2-dev"),
+    ("pcre2", "libpcre2-dev"),
+];
+
+/// Environment variables that cargo sets for build scripts, which are no
+/// concern for the packaging.
+const CARGO_ENV: &'static [&'static str] = &["OUT_DIR", "TARGET", "HOST", "NUM_JOBS", "OPT_LEVEL", "DEBUG", "PROFILE"];
+
+/// What a build script seems to do, from a look at its source: native
+/// libraries it builds or links to, and what else the build needs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BuildScriptReport {
+    /// The build script, relative to the root of the crate.
+    pub path: PathBuf,
+    /// The `links` key of Cargo.toml, i.e. the native library linked to.
+    pub links: Option<String>,
+    /// It compiles C or C++ with the cc crate, or its predecessor gcc.
+    pub uses_cc: bool,
+    pub uses_cmake: bool,
+    /// The libraries it looks up with pkg-config.
+    pub pkg_config: Vec<String>,
+    /// It generates bindings with bindgen, which needs libclang.
+    pub uses_bindgen: bool,
+    /// The environment variables it reads, besides those cargo sets.
+    pub env_vars: Vec<String>,
+    /// It looks like it downloads something.
+    pub network: bool,
+    /// It writes files into OUT_DIR, e.g. generated code.
+    pub codegen: bool,
+}
+
+fn captures(re: &str, source: &str) -> Vec<String> {
+    let re = Regex::new(re).unwrap();
+    let found = re
+        .captures_iter(source)
+        .map(|c| c[1].to_string())
+        .collect::<BTreeSet<_>>();
+    found.into_iter().collect()
+}
+
+/// The Debian package with the development files of a native library.
+pub fn native_library_package(name: &str) -> String {
+    NATIVE_LIBRARY_PACKAGES
+        .iter()
+        .find(|&&(n, _)| n == name)
+        .map(|&(_, p)| p.to_string())
+        .unwrap_or_else(|| format!("lib{}-dev", name.trim_start_matches("lib")))
+}
+
+impl BuildScriptReport {
+    /// Analyze the source of a build script. This is a heuristic: it looks
+    /// for the usual crates and calls, not at what the code actually does.
+    pub fn analyze(path: PathBuf, source: &str, links: Option<&str>) -> BuildScriptReport {
+        let uses = |re: &str| Regex::new(re).unwrap().is_match(source);
+        let env_vars = captures(r#"\benv::var(?:_os)?\(\s*"([A-Za-z0-9_]+)"\s*\)"#, source)
+            .into_iter()
+            .chain(captures(r"rerun-if-env-changed=([A-Za-z0-9_]+)", source))
+            .filter(|v| !v.starts_with("CARGO") && !v.starts_with("RUSTC") && !CARGO_ENV.contains(&v.as_str()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        BuildScriptReport {
+            path: path,
+            links: links.map(str::to_string),
+            uses_cc: uses(r"\b(cc|gcc)::(Build|Config)\b"),
+            uses_cmake: uses(r"\bcmake::(Config|build)\b"),
+            pkg_config: captures(r#"\bprobe(?:_library)?\(\s*"([^"]+)""#, source),
+            uses_bindgen: uses(r"\bbindgen::"),
+            env_vars: env_vars,
+            network: uses(r#"\b(reqwest|ureq|curl|attohttpc|minreq)::|\bTcpStream::connect|Command::new\(\s*"(curl|wget|git)""#),
+            codegen: source.contains("OUT_DIR") && uses(r"\b(File::create|fs::write|write_to_file)\b"),
+        }
+    }
+
+    /// The Build-Depends that the packages of the crate probably need, besides
+    /// the Rust ones.
+    pub fn build_depends(&self) -> Vec<String> {
+        let mut deps = BTreeSet::new();
+        if !self.pkg_config.is_empty() {
+            deps.insert("pkg-config".to_string());
+        }
+        for lib in &self.pkg_config {
+            deps.insert(native_library_package(lib));
+        }
+        // a library built from bundled sources needs no -dev package
+        if let Some(ref links) = self.links {
+            if self.pkg_config.is_empty() && !self.uses_cc && !self.uses_cmake {
+                deps.insert(native_library_package(links));
+            }
+        }
+        if self.uses_cmake {
+            deps.insert("cmake".to_string());
+        }
+        if self.uses_bindgen {
+            deps.insert("libclang-dev".to_string());
+        }
+        deps.into_iter().collect()
+    }
+
+    /// What needs a closer look before packaging the crate.
+    pub fn warnings(&self) -> Vec<String> {
+        let path = self.path.display();
+        let mut warnings = Vec::new();
+        if self.network {
+            warnings.push(format!(
+                "{} seems to access the network, which Debian builds can't; patch it out",
+                path
+            ));
+        }
+        if self.uses_cc || self.uses_cmake {
+            warnings.push(format!(
+                "{} compiles native code; if it is bundled, build against the Debian package \
+                 of the library instead, and exclude the copy",
+                path
+            ));
+        }
+        if self.codegen || self.uses_bindgen {
+            warnings.push(format!(
+                "{} generates code at build time; check that it needs nothing outside the crate",
+                path
+            ));
+        }
+        if !self.env_vars.is_empty() {
+            warnings.push(format!(
+                "{} reads the environment variables {}, which may change the build",
+                path,
+                self.env_vars.join(", ")
+            ));
+        }
+        warnings
+    }
+}
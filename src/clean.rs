@@ -0,0 +1,118 @@
+use regex::Regex;
+use semver::Version;
+use walkdir;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use errors::*;
+
+/// A source package directory that debcargo generated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub source: String,
+    pub version: Version,
+    pub dir: PathBuf,
+}
+
+/// Directories left behind by our tempfile::Builder::prefix("debcargo")
+/// tempdirs, when debcargo was interrupted.
+pub fn leftover_tempdirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let re = Regex::new(r"^debcargo[[:alnum:]]{6}$").unwrap();
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && re.is_match(&entry.file_name().to_string_lossy()) {
+            found.push(entry.path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Split a source package directory name like rust-foo-1.2.3~beta1 into the
+/// source package and the upstream version.
+pub fn parse_output_dir_name(name: &str) -> Option<(String, Version)> {
+    let mut parts = name.rsplitn(2, '-');
+    let version = parts.next()?;
+    let source = parts.next()?;
+    if !source.starts_with("rust-") {
+        return None;
+    }
+    let version = Version::parse(&version.replacen('~', "-", 1)).ok()?;
+    Some((source.to_string(), version))
+}
+
+/// Source package directories generated by debcargo, recognised by the
+/// debian/cargo-checksum.json that it writes.
+pub fn generated_outputs(dir: &Path) -> Result<Vec<Output>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.path().join("debian/cargo-checksum.json").is_file() {
+            continue;
+        }
+        if let Some((source, version)) = parse_output_dir_name(&entry.file_name().to_string_lossy()) {
+            found.push(Output {
+                source: source,
+                version: version,
+                dir: entry.path(),
+            });
+        }
+    }
+    found.sort_by(|a, b| (&a.source, &a.version).cmp(&(&b.source, &b.version)));
+    Ok(found)
+}
+
+/// The outputs superseded by a newer version of the same source package.
+pub fn stale_outputs(outputs: &[Output]) -> Vec<&Output> {
+    let mut newest: BTreeMap<&str, &Version> = BTreeMap::new();
+    for o in outputs {
+        let v = newest.entry(&o.source).or_insert(&o.version);
+        if o.version > **v {
+            *v = &o.version;
+        }
+    }
+    outputs
+        .iter()
+        .filter(|o| o.version < *newest[o.source.as_str()])
+        .collect()
+}
+
+/// The orig tarballs next to an output directory, including components.
+pub fn output_tarballs(output: &Output) -> Result<Vec<PathBuf>> {
+    let dir_name = output.dir.file_name().unwrap().to_string_lossy().into_owned();
+    let prefix = format!("{}_{}.orig", output.source, &dir_name[output.source.len() + 1..]);
+    let parent = output.dir.parent().unwrap();
+    let mut found = Vec::new();
+    for entry in fs::read_dir(if parent.as_os_str().is_empty() { Path::new(".") } else { parent })? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(&prefix) && name.contains(".tar.") {
+            found.push(entry.path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// The total size of the files under a path.
+pub fn disk_usage(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+pub fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use cargo::core::dependency::Kind;
+use cargo::core::{Dependency, Summary};
+use regex::Regex;
+use semver::Version;
+use textwrap::Wrapper;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crates::CrateInfo;
+use errors::*;
+use verify::{tarball_contents, Contents};
+
+/// A summary of the differences between two versions of a crate, of the kind
+/// that matter when reviewing a version bump of its Debian package.
+#[derive(Debug, Default)]
+pub struct CrateDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub license: Option<(String, String)>,
+    pub license_files: Vec<PathBuf>,
+    pub dependencies: Vec<String>,
+    pub features: Vec<String>,
+    pub build_scripts: Vec<PathBuf>,
+    /// The items of the upstream changelog for the versions after the old
+    /// one, up to the new one.
+    pub changelog: Vec<String>,
+}
+
+/// Upstream changelogs, as they are usually named, lowercased.
+const CHANGELOG_FILES: &[&str] = &[
+    "changelog.md", "changes.md", "history.md", "release-notes.md", "changelog", "changes", "news.md",
+];
+
+/// At most this many items of the upstream changelog go into d/changelog.
+const MAX_CHANGELOG_ITEMS: usize = 10;
+
+impl CrateDiff {
+    pub fn new(old: &CrateInfo, new: &CrateInfo) -> Result<Self> {
+        let old_contents = tarball_contents(fs::File::open(old.crate_file().path())?)?;
+        let new_contents = tarball_contents(fs::File::open(new.crate_file().path())?)?;
+        let (added, removed, modified) = diff_contents(&old_contents, &new_contents);
+
+        let license_of = |c: &CrateInfo| {
+            let meta = c.metadata();
+            meta.license
+                .clone()
+                .or_else(|| meta.license_file.as_ref().map(|f| format!("file {}", f)))
+                .unwrap_or_else(|| "(none)".to_string())
+        };
+        let (old_license, new_license) = (license_of(old), license_of(new));
+        let is_license_file = |p: &&PathBuf| {
+            let name = p.to_string_lossy().to_lowercase();
+            name.starts_with("license") || name.starts_with("copying")
+        };
+        let build_scripts = build_script_paths(old)
+            .union(&build_script_paths(new))
+            .filter(|p| {
+                added.contains(p) || removed.contains(p) || modified.contains(p)
+            })
+            .cloned()
+            .collect();
+
+        Ok(CrateDiff {
+            license: if old_license != new_license {
+                Some((old_license, new_license))
+            } else {
+                None
+            },
+            license_files: added
+                .iter()
+                .chain(removed.iter())
+                .chain(modified.iter())
+                .filter(is_license_file)
+                .cloned()
+                .collect(),
+            dependencies: diff_dependencies(old.summary().dependencies(), new.summary().dependencies()),
+            features: diff_features(old.summary(), new.summary()),
+            build_scripts: build_scripts,
+            changelog: new_contents
+                .iter()
+                .find(|&(p, _)| CHANGELOG_FILES.contains(&p.to_string_lossy().to_lowercase().as_str()))
+                .map_or_else(Vec::new, |(_, data)| {
+                    changelog_excerpt(&String::from_utf8_lossy(data), old.version(), new.version())
+                }),
+            added: added,
+            removed: removed,
+            modified: modified,
+        })
+    }
+
+    /// An item for d/changelog summarizing the upstream changes since `old`:
+    /// from the upstream changelog if it has any for these versions, and
+    /// otherwise from the changes to dependencies, features and files.
+    pub fn changelog_entry(&self, old: &Version) -> String {
+        let mut items = if self.changelog.is_empty() {
+            let mut items = Vec::new();
+            if let Some((ref old, ref new)) = self.license {
+                items.push(format!("License changed: {} -> {}", old, new));
+            }
+            items.extend(self.dependencies.iter().map(|d| format!("Dependencies: {}", d)));
+            items.extend(self.features.iter().map(|f| format!("Features: {}", f)));
+            items.push(format!(
+                "{} files added, {} removed, {} modified",
+                self.added.len(),
+                self.removed.len(),
+                self.modified.len()
+            ));
+            items
+        } else {
+            self.changelog.clone()
+        };
+        if items.len() > MAX_CHANGELOG_ITEMS {
+            let more = items.len() - MAX_CHANGELOG_ITEMS + 1;
+            items.truncate(MAX_CHANGELOG_ITEMS - 1);
+            items.push(format!("and {} more", more));
+        }
+        let wrapper = Wrapper::new(79).initial_indent("    - ").subsequent_indent("      ");
+        let mut entry = format!("Upstream changes since {}:", old);
+        for item in &items {
+            entry.push('\n');
+            entry.push_str(&wrapper.fill(item));
+        }
+        entry
+    }
+}
+
+/// The items of a markdown changelog for the versions after `old`, up to
+/// `new`: the top-level list items under headings with those versions, e.g.
+/// "## [1.2.0] - 2019-01-01" or "1.2.0 (2019-01-01)", including under their
+/// subheadings like "### Fixed".
+pub fn changelog_excerpt(changelog: &str, old: &Version, new: &Version) -> Vec<String> {
+    let heading = Regex::new(r"(?i)^(?:#+\s*)?(?:version\s+)?\[?v?(\d+\.\d+(?:\.\d+)?(?:-[0-9A-Za-z.-]+)?)\b").unwrap();
+    let version_of = |v: &str| {
+        let v = if v.matches('.').count() == 1 { format!("{}.0", v) } else { v.to_string() };
+        Version::parse(&v).ok()
+    };
+    let mut items: Vec<String> = Vec::new();
+    let mut in_range = false;
+    let mut in_item = false;
+    for line in changelog.lines() {
+        let trimmed = line.trim();
+        if line.starts_with('#') || heading.is_match(line) {
+            in_range = heading
+                .captures(line)
+                .and_then(|c| version_of(&c[1]))
+                .map_or(in_range && !line.starts_with("# ") && !line.starts_with("## "), |v| {
+                    v > *old && v <= *new
+                });
+            in_item = false;
+            continue;
+        }
+        if !in_range || trimmed.is_empty() {
+            in_item = false;
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let bullet = trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ");
+        if bullet && indent < 2 {
+            items.push(trimmed[2..].trim().to_string());
+            in_item = true;
+        } else if in_item && !bullet {
+            let last = items.last_mut().unwrap();
+            last.push(' ');
+            last.push_str(trimmed);
+        } else {
+            // nested items are details of the one above
+            in_item = false;
+        }
+    }
+    items
+}
+
+fn build_script_paths(crate_info: &CrateInfo) -> BTreeSet<PathBuf> {
+    let root = crate_info.package().root().to_path_buf();
+    let mut paths: BTreeSet<PathBuf> = crate_info
+        .targets()
+        .iter()
+        .filter(|t| t.is_custom_build())
+        .filter_map(|t| t.src_path().path().and_then(|p| p.strip_prefix(&root).ok()))
+        .map(Path::to_path_buf)
+        .collect();
+    // also catch a build.rs that appears or disappears without Cargo.toml
+    // mentioning it
+    paths.insert(PathBuf::from("build.rs"));
+    paths
+}
+
+/// Files added, removed and modified between two versions of a crate.
+pub fn diff_contents(old: &Contents, new: &Contents) -> (Vec<PathBuf>, Vec<PathBuf>, Vec<PathBuf>) {
+    let added = new.keys().filter(|p| !old.contains_key(*p)).cloned().collect();
+    let removed = old.keys().filter(|p| !new.contains_key(*p)).cloned().collect();
+    let modified = old
+        .iter()
+        .filter(|&(p, d)| new.get(p).map_or(false, |n| n != d))
+        .map(|(p, _)| p.clone())
+        .collect();
+    (added, removed, modified)
+}
+
+fn dependency_key(dep: &Dependency) -> String {
+    let kind = match dep.kind() {
+        Kind::Normal => "",
+        Kind::Development => " (dev)",
+        Kind::Build => " (build)",
+    };
+    match dep.platform() {
+        Some(p) => format!("{}{} [{}]", dep.name_in_toml(), kind, p),
+        None => format!("{}{}", dep.name_in_toml(), kind),
+    }
+}
+
+/// Human-readable changes to the dependencies of a crate.
+pub fn diff_dependencies(old: &[Dependency], new: &[Dependency]) -> Vec<String> {
+    let by_key = |deps: &[Dependency]| {
+        deps.iter()
+            .map(|d| (dependency_key(d), d.version_req().to_string()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let (old, new) = (by_key(old), by_key(new));
+    let mut changes = Vec::new();
+    for (key, req) in &old {
+        match new.get(key) {
+            None => changes.push(format!("removed {} {}", key, req)),
+            Some(new_req) if new_req != req => {
+                changes.push(format!("changed {} {} -> {}", key, req, new_req))
+            }
+            Some(_) => (),
+        }
+    }
+    for (key, req) in &new {
+        if !old.contains_key(key) {
+            changes.push(format!("added {} {}", key, req));
+        }
+    }
+    changes
+}
+
+/// Human-readable changes to the features of a crate.
+pub fn diff_features(old: &Summary, new: &Summary) -> Vec<String> {
+    let by_name = |s: &Summary| {
+        s.features()
+            .iter()
+            .map(|(f, values)| {
+                let values = values.iter().map(|v| v.to_string(s)).collect::<Vec<_>>();
+                (f.to_string(), values.join(", "))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let (old, new) = (by_name(old), by_name(new));
+    let mut changes = Vec::new();
+    for (feature, values) in &old {
+        match new.get(feature) {
+            None => changes.push(format!("removed {} = [{}]", feature, values)),
+            Some(new_values) if new_values != values => changes.push(format!(
+                "changed {} = [{}] -> [{}]",
+                feature, values, new_values
+            )),
+            Some(_) => (),
+        }
+    }
+    for (feature, values) in &new {
+        if !old.contains_key(feature) {
+            changes.push(format!("added {} = [{}]", feature, values));
+        }
+    }
+    changes
+}
+
+fn write_paths(f: &mut fmt::Formatter, title: &str, paths: &[PathBuf]) -> fmt::Result {
+    if !paths.is_empty() {
+        writeln!(f, "{} ({}):", title, paths.len())?;
+        for p in paths {
+            writeln!(f, "  {}", p.display())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_lines(f: &mut fmt::Formatter, title: &str, lines: &[String]) -> fmt::Result {
+    if !lines.is_empty() {
+        writeln!(f, "{}:", title)?;
+        for l in lines {
+            writeln!(f, "  {}", l)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for CrateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((ref old, ref new)) = self.license {
+            writeln!(f, "License changed: {} -> {}", old, new)?;
+        }
+        write_paths(f, "License files changed", &self.license_files)?;
+        write_paths(f, "Build scripts changed", &self.build_scripts)?;
+        write_lines(f, "Dependencies", &self.dependencies)?;
+        write_lines(f, "Features", &self.features)?;
+        write_lines(f, "Upstream changelog", &self.changelog)?;
+        write_paths(f, "Files added", &self.added)?;
+        write_paths(f, "Files removed", &self.removed)?;
+        writeln!(f, "Files modified: {}", self.modified.len())
+    }
+}
@@ -1,43 +1,92 @@
+use cargo::util::Sha256;
 use toml;
 
+use std::env;
 use std::io::Read;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::fs::File;
 use errors::*;
+use debian::copyright::CopyrightGuess;
 use util::vec_opt_iter;
 
+/// Suffix for the upstream version of repacked orig tarballs, as is usual for
+/// repacks that are not for DFSG reasons.
+pub const DEFAULT_REPACK_SUFFIX: &str = "+ds";
+
+/// Environment variable with overrides of config keys, as by --override.
+pub const OVERRIDES_ENV: &str = "DEBCARGO_OVERRIDES";
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Config {
     pub bin: Option<bool>,
     pub bin_name: String,
     pub semver_suffix: bool,
+    pub epoch: Option<u32>,
+    pub initial_revision: Option<String>,
+    pub vendor: Option<String>,
+    pub vendored: bool,
+    pub single_package: bool,
+    pub provide_zero_dep_features: bool,
+    pub max_feature_packages: Option<usize>,
+    pub all_features_package: bool,
+    pub optional_deps_as_suggests: bool,
+    pub unpackageable_features: Option<Vec<String>>,
+    pub package_name_template: Option<String>,
+    pub feature_package_name_template: Option<String>,
     pub overlay: Option<PathBuf>,
     pub excludes: Option<Vec<String>>,
     pub whitelist: Option<Vec<String>>,
+    pub repack_suffix: Option<String>,
     pub allow_prerelease_deps: bool,
+    pub drop_unavailable_dev_deps: bool,
+    pub relax: Option<Vec<String>>,
     pub summary: String,
     pub description: String,
+    pub description_rules_disabled: Option<Vec<String>>,
+    pub description_strip_patterns: Option<Vec<String>>,
     pub uploaders: Option<Vec<String>>,
+    pub uploaders_file: Option<PathBuf>,
+    pub changelog_author: Option<String>,
+    pub team_upload: Option<bool>,
+    pub changelog_entries: Option<Vec<String>>,
+    pub news_entries: Option<Vec<String>>,
+    pub copyright_guess: Option<CopyrightGuess>,
 
     pub source: Option<SourceOverride>,
     pub packages: Option<HashMap<String, PackageOverride>>,
+    pub backports: Option<HashMap<String, BackportOverride>>,
+
+    /// The SHA-256 of the settings the config was parsed from, to tell
+    /// whether they changed between runs.
+    #[serde(skip)]
+    pub digest: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct SourceOverride {
     section: Option<String>,
     policy: Option<String>,
+    requires_root: Option<String>,
+    testsuite: Option<bool>,
+    test_examples: Option<bool>,
+    dh_cargo_version: Option<String>,
+    debhelper_compat: Option<u32>,
+    cargo_version: Option<String>,
+    rustc_version: Option<String>,
     homepage: Option<String>,
     vcs_git: Option<String>,
     vcs_browser: Option<String>,
     build_depends: Option<Vec<String>>,
     build_depends_excludes: Option<Vec<String>>,
+    extra_lines: Option<Vec<String>>,
+    rules_extra_lines: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct PackageOverride {
+    name: Option<String>,
     section: Option<String>,
     summary: Option<String>,
     description: Option<String>,
@@ -49,21 +98,51 @@ pub struct PackageOverride {
     test_is_broken: Option<bool>,
 }
 
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct BackportOverride {
+    build_depends_versions: Option<HashMap<String, String>>,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
             bin: None,
             bin_name: "<default>".to_string(),
             semver_suffix: false,
+            epoch: None,
+            initial_revision: None,
+            vendor: None,
+            vendored: false,
+            single_package: false,
+            provide_zero_dep_features: false,
+            max_feature_packages: None,
+            all_features_package: false,
+            optional_deps_as_suggests: false,
+            unpackageable_features: None,
+            package_name_template: None,
+            feature_package_name_template: None,
             overlay: None,
             excludes: None,
             whitelist: None,
+            repack_suffix: None,
             allow_prerelease_deps: false,
+            drop_unavailable_dev_deps: false,
+            relax: None,
             summary: "".to_string(),
             description: "".to_string(),
+            description_rules_disabled: None,
+            description_strip_patterns: None,
             uploaders: None,
+            uploaders_file: None,
+            changelog_author: None,
+            team_upload: None,
+            changelog_entries: None,
+            news_entries: None,
+            copyright_guess: None,
             source: None,
             packages: None,
+            backports: None,
+            digest: None,
         }
     }
 }
@@ -76,9 +155,69 @@ impl Config {
         }
     }
 
-    pub fn overlay_dir(&self, config_path: Option<&Path>) -> Option<PathBuf> {
-        self.overlay.as_ref()
-            .map(|p| config_path.unwrap().parent().unwrap().join(p))
+    /// The overlay directory, already relative to the config file setting it.
+    pub fn overlay_dir(&self) -> Option<PathBuf> {
+        self.overlay.clone()
+    }
+
+    /// The uploaders file, already relative to the config file setting it.
+    pub fn uploaders_file_path(&self) -> Option<PathBuf> {
+        self.uploaders_file.clone()
+    }
+
+    /// Add the uploaders listed in uploaders_file to the ones given directly.
+    pub fn expand_uploaders_file(&mut self) -> Result<()> {
+        let path = match self.uploaders_file_path() {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        let from_file = read_uploaders_file(&path)
+            .map_err(|e| format_err!("failed to read uploaders_file {}: {}", path.display(), e))?;
+        let uploaders = self.uploaders.get_or_insert_with(Vec::new);
+        for u in from_file {
+            if !uploaders.contains(&u) {
+                uploaders.push(u);
+            }
+        }
+        Ok(())
+    }
+
+    /// A copy of the config with template variables like `$crate` filled in,
+    /// in the values that are free text or URLs: summaries and descriptions,
+    /// homepage, Vcs fields and extra lines.
+    pub fn with_templates_expanded(&self, vars: &[(&str, &str)]) -> Config {
+        // longest first, in case one variable is a prefix of another
+        let mut vars = vars.to_vec();
+        vars.sort_by_key(|&(name, _)| ::std::cmp::Reverse(name.len()));
+        let expand = |s: &mut String| {
+            for &(name, value) in &vars {
+                if s.contains(name) {
+                    *s = s.replace(name, value);
+                }
+            }
+        };
+        let expand_opt = |s: &mut Option<String>| s.as_mut().map(|s| expand(s));
+        let expand_vec = |v: &mut Option<Vec<String>>| {
+            for s in v.iter_mut().flat_map(|v| v.iter_mut()) {
+                expand(s);
+            }
+        };
+
+        let mut config = self.clone();
+        expand(&mut config.summary);
+        expand(&mut config.description);
+        if let Some(ref mut source) = config.source {
+            expand_opt(&mut source.homepage);
+            expand_opt(&mut source.vcs_git);
+            expand_opt(&mut source.vcs_browser);
+            expand_vec(&mut source.extra_lines);
+        }
+        for package in config.packages.iter_mut().flat_map(|p| p.values_mut()) {
+            expand_opt(&mut package.summary);
+            expand_opt(&mut package.description);
+            expand_vec(&mut package.extra_lines);
+        }
+        config
     }
 
     pub fn is_source_present(&self) -> bool {
@@ -97,6 +236,19 @@ impl Config {
         self.whitelist.as_ref()
     }
 
+    /// The suffix for the upstream version of an orig tarball repacked
+    /// without the excluded files, if there are any.
+    pub fn repack_suffix(&self) -> Option<&str> {
+        if self.excludes.as_ref().map_or(true, |e| e.is_empty()) {
+            return None;
+        }
+        match self.repack_suffix {
+            Some(ref s) if s.is_empty() => None,
+            Some(ref s) => Some(s),
+            None => Some(DEFAULT_REPACK_SUFFIX),
+        }
+    }
+
     pub fn policy_version(&self) -> Option<&str> {
         if let Some(ref s) = self.source {
             if let Some(ref policy) = s.policy {
@@ -106,6 +258,53 @@ impl Config {
         None
     }
 
+    pub fn requires_root(&self) -> Option<&str> {
+        if let Some(ref s) = self.source {
+            if let Some(ref requires_root) = s.requires_root {
+                return Some(requires_root);
+            }
+        }
+        None
+    }
+
+    pub fn testsuite(&self) -> Option<bool> {
+        self.source.as_ref().and_then(|s| s.testsuite)
+    }
+
+    pub fn test_examples(&self) -> bool {
+        self.source.as_ref().and_then(|s| s.test_examples).unwrap_or(false)
+    }
+
+    pub fn dh_cargo_version(&self) -> Option<&str> {
+        self.source.as_ref().and_then(|s| s.dh_cargo_version.as_ref().map(String::as_str))
+    }
+
+    pub fn debhelper_compat(&self) -> Option<u32> {
+        self.source.as_ref().and_then(|s| s.debhelper_compat)
+    }
+
+    pub fn cargo_version(&self) -> Option<&str> {
+        self.source.as_ref().and_then(|s| s.cargo_version.as_ref().map(String::as_str))
+    }
+
+    pub fn rustc_version(&self) -> Option<&str> {
+        self.source.as_ref().and_then(|s| s.rustc_version.as_ref().map(String::as_str))
+    }
+
+    pub fn backport_build_depends_versions(&self, codename: &str) -> Option<&HashMap<String, String>> {
+        self.backports.as_ref().and_then(|b| {
+            b.get(codename).and_then(|b| b.build_depends_versions.as_ref())
+        })
+    }
+
+    pub fn rules_extra_lines(&self) -> Option<&Vec<String>> {
+        self.source.as_ref().and_then(|s| s.rules_extra_lines.as_ref())
+    }
+
+    pub fn source_extra_lines(&self) -> Option<&Vec<String>> {
+        self.source.as_ref().and_then(|s| s.extra_lines.as_ref())
+    }
+
     pub fn homepage(&self) -> Option<&str> {
         if let Some(ref s) = self.source {
             if let Some(ref homepage) = s.homepage {
@@ -121,6 +320,14 @@ impl Config {
         })
     }
 
+    pub fn description_rules_disabled(&self) -> Option<&Vec<String>> {
+        self.description_rules_disabled.as_ref()
+    }
+
+    pub fn description_strip_patterns(&self) -> Option<&Vec<String>> {
+        self.description_strip_patterns.as_ref()
+    }
+
     pub fn uploaders(&self) -> Option<&Vec<String>> {
         self.uploaders.as_ref()
     }
@@ -140,6 +347,14 @@ impl Config {
         None
     }
 
+    pub fn package_name(&self, key: PackageKey) -> Option<&str> {
+        self.packages.as_ref().and_then(|pkg| {
+            pkg.get(&package_key_string(key)).and_then(|package| {
+                package.name.as_ref().map(|s| s.as_str())
+            })
+        })
+    }
+
     pub fn package_section(&self, key: PackageKey) -> Option<&str> {
         self.packages.as_ref().and_then(|pkg| {
             pkg.get(&package_key_string(key)).and_then(|package| {
@@ -231,12 +446,337 @@ impl Config {
     }
 }
 
-pub fn parse_config(src: &Path) -> Result<Config> {
+/// Whether a key of the `packages` table is for a binary package of the
+/// crate, i.e. `lib`, `bin` or `lib+feature`, rather than for another crate.
+pub fn is_package_key(key: &str) -> bool {
+    key == "lib" || key == "bin" || key.starts_with("lib+")
+}
+
+fn read_config_value(src: &Path) -> Result<toml::value::Table> {
     let mut config_file = File::open(src)?;
     let mut content = String::new();
     config_file.read_to_string(&mut content)?;
 
-    Ok(toml::from_str(&content)?)
+    match toml::from_str(&content)? {
+        toml::Value::Table(t) => Ok(t),
+        _ => debcargo_bail!("{} is not a TOML table", src.display()),
+    }
+}
+
+/// Keys that only the sections of binary packages have, so that a section
+/// with them that isn't named like one is a typo rather than a crate section.
+const PACKAGE_ONLY_KEYS: &[&str] = &[
+    "name", "section", "depends", "recommends", "suggests", "provides", "extra_lines",
+    "test_is_broken",
+];
+
+/// Split the `[packages."crate"]` sections of other crates off a config,
+/// leaving the settings shared by all crates.
+fn split_crate_sections(config: &mut toml::value::Table) -> Result<BTreeMap<String, toml::value::Table>> {
+    let mut sections = BTreeMap::new();
+    let mut emptied = false;
+    if let Some(&mut toml::Value::Table(ref mut packages)) = config.get_mut("packages") {
+        let crates = packages
+            .keys()
+            .filter(|k| !is_package_key(k))
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in crates {
+            if let Some(toml::Value::Table(t)) = packages.remove(&name) {
+                if let Some(key) = t.keys().find(|k| PACKAGE_ONLY_KEYS.contains(&k.as_str())) {
+                    debcargo_bail!(
+                        "[packages.\"{}\"] has {}, like the section of a package, but packages \
+                         are named lib, bin or lib+feature; other names are for crate sections",
+                        name,
+                        key
+                    );
+                }
+                sections.insert(name, t);
+            }
+        }
+        emptied = packages.is_empty() && !sections.is_empty();
+    }
+    if emptied {
+        config.remove("packages");
+    }
+    Ok(sections)
+}
+
+/// Merge the settings of `over` into `base`, recursing into tables so that
+/// e.g. a crate section can override one field of `[source]`.
+fn merge_tables(base: &mut toml::value::Table, over: toml::value::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(&mut toml::Value::Table(ref mut b)), toml::Value::Table(o)) => merge_tables(b, o),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Keys that crates may set in the `[package.metadata.debian]` table of their
+/// Cargo.toml, as hints for their packagers, and the TOML type of each.
+/// `build_depends` goes to `[source]`; the others are as in debcargo.toml.
+pub const UPSTREAM_KEYS: &'static [(&'static str, &'static str)] = &[
+    ("summary", "string"),
+    ("description", "string"),
+    ("build_depends", "array"),
+    ("single_package", "boolean"),
+    ("provide_zero_dep_features", "boolean"),
+    ("max_feature_packages", "integer"),
+    ("unpackageable_features", "array"),
+];
+
+/// The config settings from the `[package.metadata.debian]` table of a
+/// crate's Cargo.toml, if any. Since upstream is not in charge of the
+/// packaging, other keys and values of the wrong type are only warned about.
+pub fn upstream_config_table(manifest: &str) -> Result<toml::value::Table> {
+    let manifest: toml::Value = toml::from_str(manifest)?;
+    let hints = match manifest.get("package").and_then(|p| p.get("metadata")).and_then(|m| m.get("debian")) {
+        Some(&toml::Value::Table(ref t)) => t.clone(),
+        Some(_) => {
+            debcargo_warn!("Ignoring package.metadata.debian in Cargo.toml, which is not a table");
+            return Ok(toml::value::Table::new());
+        }
+        None => return Ok(toml::value::Table::new()),
+    };
+    let mut table = toml::value::Table::new();
+    for (key, value) in hints {
+        match UPSTREAM_KEYS.iter().find(|&&(k, _)| k == key) {
+            None => debcargo_warn!("Ignoring package.metadata.debian.{} in Cargo.toml, which debcargo doesn't take from upstream", key),
+            Some(&(_, ty)) if value.type_str() != ty => debcargo_warn!(
+                "Ignoring package.metadata.debian.{} in Cargo.toml, which is a {} rather than a {}",
+                key,
+                value.type_str(),
+                ty
+            ),
+            Some(_) if key == "build_depends" => {
+                let mut source = toml::value::Table::new();
+                source.insert(key, value);
+                table.insert("source".to_string(), toml::Value::Table(source));
+            }
+            Some(_) => {
+                table.insert(key, value);
+            }
+        }
+    }
+    Ok(table)
+}
+
+/// A config from its settings, with their digest. Paths are resolved only
+/// after taking the digest, which is then the same from any directory.
+fn config_from_table(table: toml::value::Table, resolved: toml::value::Table) -> Result<Config> {
+    let mut hasher = Sha256::new();
+    hasher.update(toml::to_string(&toml::Value::Table(table))?.as_bytes());
+    let mut config: Config = toml::Value::Table(resolved).try_into()?;
+    config.digest = Some(hasher.finish().iter().map(|b| format!("{:02x}", b)).collect());
+    Ok(config)
+}
+
+/// Parse a config, ignoring any sections for other crates.
+pub fn parse_config(src: &Path) -> Result<Config> {
+    let mut table = read_config_value(src)?;
+    split_crate_sections(&mut table)?;
+    let mut resolved = table.clone();
+    resolve_paths(&mut resolved, src);
+    config_from_table(table, resolved)
+}
+
+fn crate_config_table(src: &Path, crate_name: &str) -> Result<toml::value::Table> {
+    let mut table = read_config_value(src)?;
+    let mut sections = split_crate_sections(&mut table)?;
+    if let Some(mut section) = sections.remove(crate_name) {
+        if !split_crate_sections(&mut section)?.is_empty() {
+            debcargo_bail!("{}: the section for {} has sections for other crates",
+                           src.display(), crate_name);
+        }
+        merge_tables(&mut table, section);
+    }
+    Ok(table)
+}
+
+/// Parse the config of a crate from a file that may have sections for
+/// several crates: its `[packages."crate"]` section, if any, overrides the
+/// settings shared by all crates at the top level.
+pub fn parse_config_for_crate(src: &Path, crate_name: &str) -> Result<Config> {
+    let table = crate_config_table(src, crate_name)?;
+    let mut resolved = table.clone();
+    resolve_paths(&mut resolved, src);
+    config_from_table(table, resolved)
+}
+
+/// Split a dotted key like `packages."lib+std".test_is_broken` into its parts.
+fn key_path(key: &str) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut rest = key.trim();
+    while !rest.is_empty() {
+        let (part, after) = if rest.starts_with('"') {
+            match rest[1..].find('"') {
+                Some(end) => (&rest[1..end + 1], &rest[end + 2..]),
+                None => debcargo_bail!("unterminated quote in key {}", key),
+            }
+        } else {
+            match rest.find('.') {
+                Some(dot) => (&rest[..dot], &rest[dot..]),
+                None => (rest, ""),
+            }
+        };
+        if part.is_empty() {
+            debcargo_bail!("empty part in key {}", key);
+        }
+        parts.push(part.to_string());
+        rest = if after.is_empty() {
+            after
+        } else if after.starts_with('.') && after.len() > 1 {
+            &after[1..]
+        } else {
+            debcargo_bail!("bad key {}", key)
+        };
+    }
+    if parts.is_empty() {
+        debcargo_bail!("empty key");
+    }
+    Ok(parts)
+}
+
+/// Nest a value in tables along a key path.
+fn nested_value(path: &[String], value: toml::Value) -> toml::Value {
+    path.iter().rev().fold(value, |inner, part| {
+        let mut outer = toml::value::Table::new();
+        outer.insert(part.clone(), inner);
+        toml::Value::Table(outer)
+    })
+}
+
+/// Whether a config with only this value, at this key path, parses; i.e.
+/// whether the value has the type that the key takes.
+fn fits_config(path: &[String], value: &toml::Value) -> bool {
+    // the sections of crates take the keys of the top level
+    let path = if path.len() > 2 && path[0] == "packages" && !is_package_key(&path[1]) {
+        &path[2..]
+    } else {
+        path
+    };
+    nested_value(path, value.clone()).try_into::<Config>().is_ok()
+}
+
+/// A table of the config keys set by overrides like `source.section=utils`.
+/// Values are TOML, e.g. `semver_suffix=true` or `excludes=["data/*"]`; if
+/// they are not valid TOML, or the key takes a string, e.g. for
+/// `source.rustc_version=1.41`, they are taken as a string.
+pub fn overrides_table(overrides: &[String]) -> Result<toml::value::Table> {
+    let mut table = toml::value::Table::new();
+    for o in overrides {
+        let mut split = o.splitn(2, '=');
+        let key = split.next().unwrap();
+        let value = match split.next() {
+            Some(v) => v.trim(),
+            None => debcargo_bail!("override {} is not of the form key=value", o),
+        };
+        let path = key_path(key)?;
+        if path.is_empty() {
+            debcargo_bail!("override {} has an empty key", o);
+        }
+        let string = toml::Value::String(value.to_string());
+        let value = match toml::from_str::<toml::value::Table>(&format!("v = {}", value)) {
+            Ok(mut t) => {
+                let value = t.remove("v").unwrap();
+                if !value.is_str() && !fits_config(&path, &value) && fits_config(&path, &string) {
+                    string
+                } else {
+                    value
+                }
+            }
+            Err(_) => string,
+        };
+        match nested_value(&path, value) {
+            toml::Value::Table(t) => merge_tables(&mut table, t),
+            _ => unreachable!(),
+        }
+    }
+    Ok(table)
+}
+
+/// Overrides of config keys from the environment, one `key=value` per line.
+pub fn env_overrides() -> Vec<String> {
+    env::var(OVERRIDES_ENV)
+        .unwrap_or_default()
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A shared roster of uploaders: either a TOML file with an `uploaders` list
+/// like in debcargo.toml, or one uploader per line, with # comments.
+pub fn read_uploaders_file(path: &Path) -> Result<Vec<String>> {
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    if let Ok(table) = toml::from_str::<toml::value::Table>(&content) {
+        if let Some(uploaders) = table.get("uploaders") {
+            return Ok(uploaders.clone().try_into()?);
+        }
+    }
+    Ok(content
+        .lines()
+        .map(|l| l.splitn(2, '#').next().unwrap().trim())
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Make the paths in a config table relative to the config file itself, as
+/// they are meant, rather than to the current directory.
+fn resolve_paths(table: &mut toml::value::Table, src: &Path) {
+    let dir = src.parent().unwrap_or_else(|| Path::new(""));
+    for key in &["overlay", "uploaders_file"] {
+        if let Some(&mut toml::Value::String(ref mut p)) = table.get_mut(*key) {
+            *p = dir.join(&p).to_string_lossy().into_owned();
+        }
+    }
+}
+
+/// The config of a crate from layered config files, e.g. team defaults, then
+/// local settings, then the crate's own config, with later files overriding
+/// earlier ones key by key, and finally the given overrides.
+pub fn load_config(srcs: &[&Path], crate_name: &str, overrides: &[String]) -> Result<Config> {
+    load_config_with_upstream(srcs, crate_name, overrides, toml::value::Table::new())
+}
+
+/// Like `load_config`, below which come the settings that the crate gives
+/// in its Cargo.toml; see `upstream_config_table`.
+pub fn load_config_with_upstream(
+    srcs: &[&Path],
+    crate_name: &str,
+    overrides: &[String],
+    upstream: toml::value::Table,
+) -> Result<Config> {
+    merge_configs(srcs, crate_name, overrides, upstream).kind(ErrorKind::Config)
+}
+
+fn merge_configs(srcs: &[&Path], crate_name: &str, overrides: &[String], upstream: toml::value::Table) -> Result<Config> {
+    let mut table = upstream.clone();
+    let mut resolved = upstream;
+    for src in srcs {
+        let mut layer = crate_config_table(src, crate_name)
+            .map_err(|e| format_err!("{}: {}", src.display(), e))?;
+        merge_tables(&mut table, layer.clone());
+        resolve_paths(&mut layer, src);
+        merge_tables(&mut resolved, layer);
+    }
+    let overrides = overrides_table(overrides)?;
+    merge_tables(&mut table, overrides.clone());
+    merge_tables(&mut resolved, overrides);
+    let mut config = config_from_table(table, resolved)?;
+    config.expand_uploaders_file()?;
+    Ok(config)
+}
+
+/// The crates that a config has `[packages."crate"]` sections for.
+pub fn config_crates(src: &Path) -> Result<Vec<String>> {
+    let mut table = read_config_value(src)?;
+    Ok(split_crate_sections(&mut table)?.into_iter().map(|(k, _)| k).collect())
 }
 
 pub fn package_field_for_feature<'a>(
@@ -6,27 +6,41 @@ use cargo::{
         SourceId, Summary, Target, TargetKind,
     },
     sources::{path::PathSource, registry::RegistrySource},
-    util::{toml::read_manifest, FileLock, Filesystem},
+    util::{toml::read_manifest, FileLock, Filesystem, ToUrl},
     Config,
 };
+use curl::easy::{Easy, List};
 use failure::Error;
 use filetime::{set_file_times, FileTime};
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use git2::build::{CheckoutBuilder, RepoBuilder};
 use glob::Pattern;
 use regex::Regex;
-use semver::Version;
-use tar::Archive;
+use semver::{Version, VersionReq};
+use serde_json;
+use tar::{Archive, Builder, EntryType, Header};
 use tempfile;
+use toml;
+use walkdir;
 
 use std;
-use std::collections::{BTreeMap, HashSet};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use build_script::BuildScriptReport;
+use config::upstream_config_table;
+use debian::description::DescriptionRules;
 use errors::*;
-use util::vec_opt_iter;
+use util::{copy_tree, vec_opt_iter};
+use verify::sha256_hex;
 
 pub struct CrateInfo {
     package: Package,
@@ -36,6 +50,27 @@ pub struct CrateInfo {
     source_id: SourceId,
     excludes: Vec<Pattern>,
     includes: Vec<Pattern>,
+    allow_suspicious: bool,
+    version_candidates: Vec<Version>,
+    locked_versions: BTreeMap<String, Vec<Version>>,
+    new_feature_syntax: NewFeatureSyntax,
+    git_origin: Option<GitOrigin>,
+    provenance: Provenance,
+    owners: Vec<String>,
+    // hold crate_file when we packed it ourselves, and the git clone it
+    // was packed from
+    _scratch_dirs: Vec<tempfile::TempDir>,
+}
+
+#[derive(Deserialize)]
+struct LockFile {
+    package: Option<Vec<LockedPackage>>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
 }
 
 fn hash<H: Hash>(hashable: &H) -> u64 {
@@ -45,29 +80,1482 @@ fn hash<H: Hash>(hashable: &H) -> u64 {
     hasher.finish()
 }
 
-fn traverse_depth<'a>(map: &BTreeMap<&'a str, Vec<&'a str>>, key: &'a str) -> Vec<&'a str> {
-    let mut x = Vec::new();
-    if let Some(pp) = (*map).get(key) {
-        x.extend(pp);
-        for p in pp {
-            x.extend(traverse_depth(map, p));
+fn traverse_depth<'a>(map: &BTreeMap<&'a str, Vec<&'a str>>, key: &'a str) -> Vec<&'a str> {
+    let mut x = Vec::new();
+    if let Some(pp) = (*map).get(key) {
+        x.extend(pp);
+        for p in pp {
+            x.extend(traverse_depth(map, p));
+        }
+    }
+    x
+}
+
+/// Summaries of the versions of a crate matching a dependency, newest first.
+pub fn fetch_candidates(registry: &mut PackageRegistry, dep: &Dependency) -> Result<Vec<Summary>> {
+    // querying may fetch the index
+    let mut summaries = registry.query_vec(dep, false).kind(ErrorKind::Network)?;
+    summaries.sort_by(|a, b| b.package_id().partial_cmp(&a.package_id()).unwrap());
+    Ok(summaries)
+}
+
+/// Environment variable naming a local registry directory to use in place of
+/// crates.io, laid out like `cargo local-registry` makes them: the index under
+/// `index/` and the .crate files at the top level. This allows packaging
+/// without network access, e.g. in tests or air-gapped environments.
+pub const LOCAL_REGISTRY_ENV: &str = "DEBCARGO_LOCAL_REGISTRY";
+
+/// The local registry set with DEBCARGO_LOCAL_REGISTRY, if any.
+pub fn local_registry() -> Result<Option<PathBuf>> {
+    let path = match std::env::var_os(LOCAL_REGISTRY_ENV) {
+        Some(ref p) if !p.is_empty() => PathBuf::from(p),
+        _ => return Ok(None),
+    };
+    if !path.join("index").is_dir() {
+        debcargo_bail!(
+            "{} is set to {}, which is not a local registry: it has no index directory",
+            LOCAL_REGISTRY_ENV,
+            path.display()
+        );
+    }
+    Ok(Some(path.canonicalize()?))
+}
+
+/// The source id of crates.io, or of the local registry standing in for it.
+pub fn crates_io_source_id(config: &Config) -> Result<SourceId> {
+    match (local_registry()?, index_url()) {
+        (Some(path), _) => SourceId::for_local_registry(&path),
+        (None, Some(url)) => SourceId::for_registry(&url.to_url()?),
+        (None, None) => SourceId::crates_io(config),
+    }
+}
+
+/// Environment variable giving the URL of a registry index to use in place of
+/// crates.io, whatever the cargo config says, e.g. that of a caching proxy.
+pub const INDEX_URL_ENV: &str = "DEBCARGO_INDEX_URL";
+
+/// Environment variable giving where to download crates from instead of what
+/// the index says, as a URL or a template with {crate} and {version} like the
+/// `dl` of a registry index.
+pub const DL_URL_ENV: &str = "DEBCARGO_DL_URL";
+
+fn env_url(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|url| !url.is_empty())
+}
+
+/// The registry index set with DEBCARGO_INDEX_URL, if any.
+pub fn index_url() -> Option<String> {
+    env_url(INDEX_URL_ENV)
+}
+
+/// The download URL set with DEBCARGO_DL_URL, if any.
+pub fn dl_url() -> Option<String> {
+    env_url(DL_URL_ENV)
+}
+
+/// Where cargo's source replacement configuration, i.e. `replace-with` under
+/// `[source.crates-io]` in .cargo/config, redirects crates.io to.
+pub enum Mirror {
+    Registry(SourceId),
+    LocalRegistry(PathBuf),
+    /// Crates unpacked like `cargo vendor` does
+    Directory(PathBuf),
+}
+
+impl Mirror {
+    pub fn source_id(&self) -> Result<SourceId> {
+        match *self {
+            Mirror::Registry(id) => Ok(id),
+            Mirror::LocalRegistry(ref path) => SourceId::for_local_registry(path),
+            Mirror::Directory(ref path) => SourceId::for_directory(path),
+        }
+    }
+}
+
+/// The source that replaces crates.io in the cargo config, if any, following
+/// chains of `replace-with`, and its name there. Cargo fetches the index and
+/// crates from it instead of crates.io. This is ignored if debcargo was given
+/// a registry to use itself.
+pub fn crates_io_mirror(config: &Config) -> Result<Option<(String, Mirror)>> {
+    // registries given to debcargo itself take precedence
+    if local_registry()?.is_some() || index_url().is_some() {
+        return Ok(None);
+    }
+    let mut name = "crates-io".to_string();
+    let mut seen = BTreeSet::new();
+    while let Some(next) = config.get_string(&format!("source.{}.replace-with", name))? {
+        if !seen.insert(next.val.clone()) {
+            debcargo_bail!("The cargo config replaces source {} with itself", next.val);
+        }
+        name = next.val;
+    }
+    if seen.is_empty() {
+        return Ok(None);
+    }
+    let key = |k: &str| format!("source.{}.{}", name, k);
+    // like cargo, take paths relative to the directory holding .cargo/
+    let path = |k: &str| -> Result<Option<PathBuf>> {
+        Ok(config
+            .get_string(&key(k))?
+            .map(|v| v.definition.root(config).join(v.val)))
+    };
+    let mirror = if let Some(url) = config.get_string(&key("registry"))? {
+        Mirror::Registry(SourceId::for_registry(&url.val.to_url()?)?)
+    } else if let Some(path) = path("local-registry")? {
+        Mirror::LocalRegistry(path)
+    } else if let Some(path) = path("directory")? {
+        Mirror::Directory(path)
+    } else {
+        debcargo_bail!(
+            "The source {} replacing crates.io in the cargo config has no registry, \
+             local-registry or directory",
+            name
+        );
+    };
+    Ok(Some((name, mirror)))
+}
+
+/// The token for a registry named in the cargo config, from cargo's
+/// credentials file or CARGO_REGISTRIES_<NAME>_TOKEN.
+pub fn registry_token(config: &Config, name: &str) -> Result<Option<String>> {
+    Ok(config
+        .get_string(&format!("registries.{}.token", name))?
+        .map(|token| token.val))
+}
+
+/// The URL to download a crate from, filling in the `dl` template from the
+/// config.json of a registry index like cargo does.
+pub fn crate_download_url(dl: &str, name: &str, version: &Version) -> String {
+    let mut url = dl.to_string();
+    if !url.contains("{crate}") && !url.contains("{version}") {
+        url.push_str("/{crate}/{version}/download");
+    }
+    url.replace("{crate}", name)
+        .replace("{version}", &version.to_string())
+}
+
+/// The `dl` template of a registry, from its index as cargo last fetched it.
+fn registry_dl(config: &Config, source_id: &SourceId) -> Result<String> {
+    let index = config
+        .registry_index_path()
+        .join(registry_name(source_id))
+        .into_path_unlocked();
+    let repo = git2::Repository::open(&index)?;
+    let head = repo.find_commit(repo.refname_to_id("refs/remotes/origin/master")?)?;
+    let blob = head
+        .tree()?
+        .get_path(Path::new("config.json"))?
+        .to_object(&repo)?
+        .peel_to_blob()?;
+    let json: serde_json::Value = serde_json::from_slice(blob.content())?;
+    match json["dl"].as_str() {
+        Some(dl) => Ok(dl.to_string()),
+        None => debcargo_bail!("The index of {} has no download URL in config.json", source_id),
+    }
+}
+
+/// Download a URL to `dest`, resuming from what an interrupted download left
+/// there, if the server supports it. `headers` are sent along.
+pub fn download_resumable(url: &str, headers: &[String], dest: &Path) -> Result<()> {
+    let offset = fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(dest)?;
+    let mut handle = Easy::new();
+    handle.url(url)?;
+    handle.useragent(concat!("debcargo/", env!("CARGO_PKG_VERSION")))?;
+    handle.follow_location(true)?;
+    handle.fail_on_error(false)?;
+    let mut list = List::new();
+    for header in headers {
+        list.append(header)?;
+    }
+    handle.http_headers(list)?;
+    if offset > 0 {
+        // not resume_from(), which fails outright if the server ignores it
+        handle.range(&format!("{}-", offset))?;
+    }
+
+    // the status of the last response, after any redirects; 0 for non-HTTP
+    // URLs, e.g. file://
+    let status = Cell::new(0);
+    let restarted = Cell::new(false);
+    let mut write_error = None;
+    {
+        let mut transfer = handle.transfer();
+        transfer.header_function(|h| {
+            let line = String::from_utf8_lossy(h);
+            if line.starts_with("HTTP/") {
+                let code = line.split_whitespace().nth(1).and_then(|c| c.parse().ok());
+                status.set(code.unwrap_or(0));
+            }
+            true
+        })?;
+        transfer.write_function(|d| {
+            let result = match status.get() {
+                // the server ignored the range, so start over
+                200 if offset > 0 && !restarted.get() => {
+                    restarted.set(true);
+                    file.set_len(0).and_then(|_| file.write_all(d))
+                }
+                0 | 200 | 206 => file.write_all(d),
+                // an error page
+                _ => Ok(()),
+            };
+            match result {
+                Ok(()) => Ok(d.len()),
+                Err(e) => {
+                    write_error = Some(e);
+                    Ok(0)
+                }
+            }
+        })?;
+        let performed = transfer.perform();
+        drop(transfer);
+        if let Some(e) = write_error {
+            return Err(e.into());
+        }
+        performed.kind(ErrorKind::Network)?;
+    }
+    match handle.response_code()? {
+        0 | 200 | 206 => Ok(()),
+        // nothing left to fetch; the checksum tells whether it is whole
+        416 if offset > 0 => Ok(()),
+        code => debcargo_bail_kind!(ErrorKind::Network, "Downloading {} failed with HTTP status {}", url, code),
+    }
+}
+
+/// Download a .crate into cargo's cache, from `dl` if given, with `token` if
+/// given, rather than leaving it to our cargo, which can do neither and does
+/// not say much when a download fails. Interrupted downloads are resumed, and
+/// the .crate, even one already in the cache, must match the checksum in the
+/// index. Returns the URL of the .crate, if known.
+fn fetch_crate(
+    config: &Config,
+    source_id: &SourceId,
+    dl: Option<&str>,
+    token: Option<&str>,
+    summary: &Summary,
+    cache: &Filesystem,
+) -> Result<Option<String>> {
+    let pkgid = summary.package_id();
+    let filename = format!("{}-{}.crate", pkgid.name(), pkgid.version());
+    let dest = cache.join(&filename).into_path_unlocked();
+    let url = match dl {
+        Some(dl) => Ok(dl.to_string()),
+        None => registry_dl(config, source_id),
+    }.map(|dl| crate_download_url(&dl, &pkgid.name(), pkgid.version()));
+    let checksum_matches = |path: &Path| -> Result<bool> {
+        match summary.checksum() {
+            Some(expected) => Ok(sha256_hex(path)? == expected),
+            None => Ok(true),
+        }
+    };
+    if fs::metadata(&dest).map(|m| m.len() > 0).unwrap_or(false) {
+        if checksum_matches(&dest)? {
+            return Ok(url.ok());
+        }
+        debcargo_warn!(
+            "{} does not match the checksum in the index, downloading it again",
+            dest.display()
+        );
+        fs::remove_file(&dest)?;
+    }
+    let url = url?;
+    let headers = token.map_or_else(Vec::new, |t| vec![format!("Authorization: {}", t)]);
+
+    fs::create_dir_all(dest.parent().unwrap())?;
+    let partial = dest.with_extension("crate.part");
+    download_resumable(&url, &headers, &partial)?;
+    if !checksum_matches(&partial)? {
+        fs::remove_file(&partial)?;
+        debcargo_bail!(
+            "{} does not match the checksum of {} {} in the index",
+            url,
+            pkgid.name(),
+            pkgid.version()
+        );
+    }
+    fs::rename(&partial, &dest)?;
+    Ok(Some(url))
+}
+
+/// The body of a GET request to a web API, which must succeed.
+pub fn http_get(url: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut handle = Easy::new();
+    handle.url(url)?;
+    handle.useragent(concat!("debcargo/", env!("CARGO_PKG_VERSION")))?;
+    handle.timeout(Duration::from_secs(30))?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|d| {
+            data.extend_from_slice(d);
+            Ok(d.len())
+        })?;
+        transfer.perform().kind(ErrorKind::Network)?;
+    }
+    let code = handle.response_code()?;
+    if code != 200 {
+        debcargo_bail_kind!(ErrorKind::Network, "Request {} failed with HTTP status {}", url, code);
+    }
+    Ok(data)
+}
+
+/// A GET request to the crates.io API, e.g. for "crates/foo/owners".
+pub fn crates_io_api(path: &str) -> Result<Vec<u8>> {
+    http_get(&format!("https://crates.io/api/v1/{}", path))
+}
+
+/// When a crate version was published on crates.io, per its API.
+pub fn crates_io_publish_date(name: &str, version: &Version) -> Result<Option<String>> {
+    let data = crates_io_api(&format!("crates/{}/{}", name, version))?;
+    let info: serde_json::Value = serde_json::from_slice(&data)?;
+    Ok(info["version"]["created_at"].as_str().map(str::to_string))
+}
+
+/// A registry for querying crates.io, and the source id to use in queries.
+/// Unless `update` is given, queries are answered from the local copy of
+/// the index, without updating it.
+pub fn crates_io_registry<'cfg>(
+    config: &'cfg Config,
+    update: bool,
+) -> Result<(PackageRegistry<'cfg>, SourceId)> {
+    let source_id = crates_io_source_id(config)?;
+    let source_id = if update {
+        source_id
+    } else {
+        // see CrateInfo::new_from_crates_io
+        source_id.with_precise(Some("locked".to_string()))
+    };
+    let mut registry = PackageRegistry::new(config)?;
+    registry.lock_patches();
+    Ok((registry, source_id))
+}
+
+fn registry_source_id(config: &Config, registry: Option<&str>) -> Result<SourceId> {
+    match registry {
+        Some(url) => SourceId::for_registry(&url.to_url()?),
+        None => crates_io_source_id(config),
+    }
+}
+
+fn registry_name(source_id: &SourceId) -> String {
+    format!(
+        "{}-{:016x}",
+        source_id.url().host_str().unwrap_or(""),
+        hash(source_id).swap_bytes()
+    )
+}
+
+/// Update the local copy of a registry index; crates.io if none is given.
+pub fn update_registry(registry: Option<&str>) -> Result<()> {
+    let config = Config::default()?;
+    if registry.is_none() {
+        if let Some(path) = local_registry()? {
+            debcargo_info!("Using the local registry {}, nothing to update", path.display());
+            return Ok(());
+        }
+    }
+    let mut source_id = registry_source_id(&config, registry)?;
+    if registry.is_none() {
+        match crates_io_mirror(&config)? {
+            Some((name, Mirror::Registry(id))) => {
+                debcargo_info!("Updating the index of {}, which replaces crates.io", name);
+                source_id = id;
+            }
+            Some((name, _)) => {
+                debcargo_info!("Using {} in place of crates.io, nothing to update", name);
+                return Ok(());
+            }
+            None => (),
+        }
+    }
+    let yanked_whitelist = HashSet::new();
+    let mut r = RegistrySource::remote(source_id, &yanked_whitelist, &config);
+    r.update().kind(ErrorKind::Network)
+}
+
+pub fn update_crates_io() -> Result<()> {
+    update_registry(None)
+}
+
+/// How long ago the local copy of a registry index was last updated, or None
+/// if it was never fetched.
+pub fn registry_index_age(registry: Option<&str>) -> Result<Option<Duration>> {
+    let config = Config::default()?;
+    if registry.is_none() && local_registry()?.is_some() {
+        // a local registry is as fresh as it is ever going to be
+        return Ok(Some(Duration::from_secs(0)));
+    }
+    let mut source_id = registry_source_id(&config, registry)?;
+    if registry.is_none() {
+        match crates_io_mirror(&config)? {
+            Some((_, Mirror::Registry(id))) => source_id = id,
+            // local mirrors are never fetched
+            Some(_) => return Ok(Some(Duration::from_secs(0))),
+            None => (),
+        }
+    }
+    let index = config
+        .registry_index_path()
+        .join(registry_name(&source_id))
+        .into_path_unlocked();
+    // cargo touches FETCH_HEAD whenever it fetches the index
+    let stamp = [index.join(".git").join("FETCH_HEAD"), index.join(".git")]
+        .iter()
+        .filter_map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+        .next();
+    Ok(stamp.map(|t| SystemTime::now().duration_since(t).unwrap_or_default()))
+}
+
+/// Where extracting a crate to `srcdir` with --allow-suspicious reports the
+/// suspicious files in it: next to `srcdir`, so not in the source package.
+pub fn suspicious_files_report(srcdir: &Path) -> PathBuf {
+    let mut name = srcdir.file_name().unwrap_or_default().to_os_string();
+    name.push(".suspicious-files");
+    srcdir.with_file_name(name)
+}
+
+/// The names of all crates in a registry index as cargo last fetched it, or
+/// in the local registry standing in for crates.io.
+fn index_crate_names(config: &Config, registry_name: &str) -> Result<Vec<String>> {
+    let is_name = |n: &str| n != "config.json" && !n.starts_with('.');
+    if let Some(path) = local_registry()? {
+        return Ok(walkdir::WalkDir::new(path.join("index"))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|n| is_name(n))
+            .collect());
+    }
+    let index = config
+        .registry_index_path()
+        .join(registry_name)
+        .into_path_unlocked();
+    let repo = git2::Repository::open(&index)?;
+    let head = repo.find_commit(repo.refname_to_id("refs/remotes/origin/master")?)?;
+    let mut names = Vec::new();
+    head.tree()?.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            if let Some(name) = entry.name().filter(|n| is_name(n)) {
+                names.push(name.to_string());
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    Ok(names)
+}
+
+/// The number of insertions, deletions, substitutions and transpositions of
+/// adjacent characters that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..=a.len() {
+        d[i][0] = i;
+    }
+    for j in 0..=b.len() {
+        d[0][j] = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Of `names`, those that `name` is likely a misspelling of, most similar
+/// first: the same but for case and `-`/`_`, or a few edits away.
+pub fn similar_crate_names<'a, I>(name: &str, names: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let normalize = |n: &str| n.to_lowercase().replace('_', "-");
+    let wanted = normalize(name);
+    let max_distance = (wanted.len() / 4).max(1).min(3);
+    let mut similar = names
+        .into_iter()
+        .filter(|&n| n != name)
+        .map(|n| (edit_distance(&wanted, &normalize(n)), n))
+        .filter(|&(d, _)| d <= max_distance)
+        .collect::<Vec<_>>();
+    similar.sort();
+    similar.into_iter().take(5).map(|(_, n)| n.to_string()).collect()
+}
+
+/// Interpret a version argument given on the command line as a cargo version
+/// requirement. A bare version like "1.2.3" means exactly that version, i.e.
+/// "=1.2.3", unlike in Cargo.toml where it would mean "^1.2.3".
+pub fn version_req_from_arg(version: &str) -> Result<String> {
+    let req = if version.starts_with(|c: char| c.is_digit(10)) {
+        ["=", version].concat()
+    } else {
+        version.to_string()
+    };
+    if let Err(e) = VersionReq::parse(&req) {
+        debcargo_bail!(
+            concat!(
+                "Invalid version requirement \"{}\": {}\n",
+                "Give either an exact version like 1.2.3, or a requirement with ",
+                "operators like \">= 1.2, < 2\", quoted so that it is one argument."
+            ),
+            version,
+            e
+        );
+    }
+    Ok(req)
+}
+
+/// The first paragraph of text in a README, skipping headings, badges, HTML
+/// and code blocks, joined into one line.
+pub fn readme_first_paragraph(content: &str) -> Option<String> {
+    let mut para: Vec<&str> = Vec::new();
+    let mut in_code = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if !line.is_empty() && line.chars().all(|c| c == '=' || c == '-') {
+            // setext-style heading underline, the previous line was a title
+            para.clear();
+            continue;
+        }
+        let skip = line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with('<')
+            || line.starts_with("![")
+            || line.starts_with("[![")
+            || (line.starts_with('[') && line.contains("]:"));
+        if skip {
+            if !para.is_empty() {
+                break;
+            }
+            continue;
+        }
+        para.push(line);
+    }
+    if para.is_empty() {
+        None
+    } else {
+        Some(para.join(" "))
+    }
+}
+
+/// Look up the description of a GitHub repository, if the given repository
+/// URL points to one.
+pub fn github_description(repository: &str) -> Result<Option<String>> {
+    let re = Regex::new(r"^https?://github\.com/([^/]+)/([^/]+?)(?:\.git)?(?:/.*)?$").unwrap();
+    let caps = match re.captures(repository.trim()) {
+        Some(caps) => caps,
+        None => return Ok(None),
+    };
+    let url = format!("https://api.github.com/repos/{}/{}", &caps[1], &caps[2]);
+
+    let data = http_get(&url)?;
+    let repo: serde_json::Value = serde_json::from_slice(&data)?;
+    Ok(repo["description"]
+        .as_str()
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty()))
+}
+
+// Hosts that are known to serve everything over https, so plain http links
+// to them can safely be upgraded.
+const HTTPS_HOSTS: &'static [&'static str] = &[
+    "github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "codeberg.org",
+    "git.sr.ht",
+    "sr.ht",
+    "salsa.debian.org",
+    "docs.rs",
+    "crates.io",
+];
+
+/// Normalize an upstream URL for use as a Homepage: ssh and git:// clone URLs
+/// of well-known hosts are turned into https web URLs, and trailing `.git` and
+/// `/` are stripped. Returns None if the result doesn't look like a web URL.
+pub fn normalize_homepage(url: &str) -> Option<String> {
+    let mut url = url.trim().to_string();
+    let scp_like = Regex::new(r"^(?:ssh://)?git@([^:/]+)[:/](.*)$").unwrap();
+    if let Some(caps) = scp_like.captures(&url.clone()) {
+        url = format!("https://{}/{}", &caps[1], &caps[2]);
+    }
+    if url.starts_with("git://") || url.starts_with("git+https://") {
+        url = format!("https://{}", &url[url.find("://").unwrap() + 3..]);
+    }
+    if url.starts_with("http://") {
+        let host = url[7..].split('/').next().unwrap_or("").to_lowercase();
+        let host = host.trim_start_matches("www.");
+        if HTTPS_HOSTS.contains(&host) {
+            url = format!("https://{}", &url[7..]);
+        }
+    }
+    while url.ends_with('/') {
+        url.pop();
+    }
+    if url.ends_with(".git") {
+        let len = url.len();
+        url.truncate(len - 4);
+    }
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Some(url)
+    } else {
+        None
+    }
+}
+
+// Uses of network APIs that suggest a test needs network access, which is
+// not available when running autopkgtests on the Debian infrastructure. URLs
+// alone are not among them, tests of parsers and formatters have plenty.
+const NETWORK_PATTERNS: &'static [&'static str] = &[
+    "TcpStream::connect",
+    "UdpSocket::bind",
+    "to_socket_addrs",
+    "reqwest::",
+    "hyper::Client",
+    "ureq::",
+    "curl::easy",
+];
+
+/// Heuristically decide whether the crate unpacked at `srcdir` has tests
+/// that can run without network access. Test code is the contents of the
+/// `tests` directory, plus everything after `#[cfg(test)]` in other source
+/// files; if none of it exists, or any of it looks like it uses the network,
+/// the crate is considered not to have usable tests.
+pub fn has_offline_tests(srcdir: &Path) -> Result<bool> {
+    let mut found = false;
+    for entry in walkdir::WalkDir::new(srcdir) {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().map_or(true, |e| e != "rs") {
+            continue;
+        }
+        let mut content = String::new();
+        // skip over non-UTF-8 files, they are not going to be tests anyway
+        if fs::File::open(path)?.read_to_string(&mut content).is_err() {
+            continue;
+        }
+        let in_tests_dir = path
+            .strip_prefix(srcdir)
+            .map(|p| p.starts_with("tests"))
+            .unwrap_or(false);
+        let test_code = if in_tests_dir {
+            &content[..]
+        } else {
+            match content.find("#[cfg(test)]") {
+                Some(p) => &content[p..],
+                None => continue,
+            }
+        };
+        if !test_code.contains("#[test]") {
+            continue;
+        }
+        if NETWORK_PATTERNS.iter().any(|p| test_code.contains(p)) {
+            debcargo_info!(
+                "Tests in {} look like they need network access",
+                path.display()
+            );
+            return Ok(false);
+        }
+        found = true;
+    }
+    Ok(found)
+}
+
+/// The suffix for semver-compatible versions of a crate, as used in package
+/// names: "-0.<minor>" for 0.x versions and "-<major>" otherwise.
+pub fn semver_suffix_of(version: &Version) -> String {
+    match *version {
+        Version {
+            major: 0, minor, ..
+        } => format!("-0.{}", minor),
+        Version { major, .. } => format!("-{}", major),
+    }
+}
+
+/// Feature syntax from newer versions of cargo, that our version of cargo does
+/// not understand and that had to be rewritten; see `rewrite_new_features`.
+#[derive(Default, Debug)]
+pub struct NewFeatureSyntax {
+    /// Optional dependencies referred to with `dep:`. These don't get an
+    /// implicit feature of the same name.
+    pub dep_only: BTreeSet<String>,
+    /// Weak dependency features `dep?/feature`, as (dep, feature) pairs for
+    /// each feature that has them. These only apply if the optional
+    /// dependency is enabled by something else.
+    pub weak: BTreeMap<String, Vec<(String, String)>>,
+    /// Features named after the optional dependency that they enable with
+    /// `dep:`, with what else they enable. Our cargo doesn't allow a feature
+    /// and a dependency of the same name, so these were taken out of the
+    /// features, leaving the implicit feature of the dependency.
+    pub same_name: BTreeMap<String, Vec<String>>,
+}
+
+impl NewFeatureSyntax {
+    fn is_empty(&self) -> bool {
+        self.dep_only.is_empty() && self.weak.is_empty() && self.same_name.is_empty()
+    }
+}
+
+/// Whether a dependency is on a git repository or a local path, which can't
+/// be satisfied from the archive.
+pub fn is_source_dependency(dep: &Dependency) -> bool {
+    let source = dep.source_id();
+    source.is_git() || source.is_path()
+}
+
+/// The Cargo.toml line taking a dependency from the registry at `version`,
+/// to patch in place of a git or path dependency.
+pub fn registry_dependency_line(dep: &Dependency, version: &Version) -> String {
+    let mut fields = vec![];
+    if dep.name_in_toml() != dep.package_name() {
+        fields.push(format!("package = \"{}\"", dep.package_name()));
+    }
+    fields.push(format!("version = \"{}\"", version));
+    if dep.is_optional() {
+        fields.push("optional = true".to_string());
+    }
+    if !dep.uses_default_features() {
+        fields.push("default-features = false".to_string());
+    }
+    if !dep.features().is_empty() {
+        let features = dep
+            .features()
+            .iter()
+            .map(|f| format!("\"{}\"", f))
+            .collect::<Vec<_>>();
+        fields.push(format!("features = [{}]", features.join(", ")));
+    }
+    if fields.len() == 1 {
+        format!("{} = \"{}\"", dep.name_in_toml(), version)
+    } else {
+        format!("{} = {{ {} }}", dep.name_in_toml(), fields.join(", "))
+    }
+}
+
+/// Which of the features of a package to take the dependencies of; the
+/// default is the merged view that d/control uses, of all features.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DependencySelection {
+    /// Only the features that these enable, and the base package "", e.g.
+    /// ["default"] for a default build, instead of all of them.
+    pub features: Option<Vec<String>>,
+}
+
+impl DependencySelection {
+    /// Restrict the dependencies of each feature, as calculated by
+    /// `summary_dependencies_and_features`, to the selection. Fails if it
+    /// names features that the crate doesn't have.
+    pub fn apply(&self, features_with_deps: &mut BTreeMap<&str, (Vec<&str>, Vec<Dependency>)>) -> Result<()> {
+        if let Some(ref selected) = self.features {
+            let unknown = selected
+                .iter()
+                .filter(|f| !features_with_deps.contains_key(f.as_str()))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                debcargo_bail!("The crate has no features {}", unknown.join(", "));
+            }
+            let mut enabled = BTreeSet::new();
+            let mut todo = vec![""];
+            todo.extend(selected.iter().map(String::as_str));
+            while let Some(f) = todo.pop() {
+                if let Some(&(ref ff, _)) = features_with_deps.get(f) {
+                    if enabled.insert(f.to_string()) {
+                        todo.extend(ff.iter().cloned());
+                    }
+                }
+            }
+            features_with_deps.retain(|f, _| enabled.contains(*f));
+        }
+        Ok(())
+    }
+}
+
+/// Calculate the dependencies of each feature of a package, given its summary
+/// and its (possibly modified) dependencies, plus any newer feature syntax
+/// that was rewritten before cargo read the summary. See
+/// `CrateInfo::all_dependencies_and_features` for the format of the result.
+pub fn summary_dependencies_and_features<'a>(
+    summary: &'a Summary,
+    dependencies: &[Dependency],
+    new_syntax: &NewFeatureSyntax,
+) -> BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)> {
+    use cargo::core::dependency::Kind;
+
+    let dep_only = &new_syntax.dep_only;
+
+    // features refer to dependencies by the name they are given in Cargo.toml,
+    // which is not the package name if the dependency is renamed
+    let mut deps_by_name: BTreeMap<&str, Vec<&Dependency>> = BTreeMap::new();
+    for dep in dependencies {
+        // we treat build-dependencies also as dependencies in Debian
+        if dep.kind() != Kind::Development {
+            let s = dep.name_in_toml().as_str();
+            deps_by_name.entry(s).or_default().push(dep);
+        }
+    }
+    let deps_by_name = deps_by_name;
+
+    let mut features_with_deps = BTreeMap::new();
+
+    // calculate dependencies of this crate's features
+    for (feature, deps) in summary.features() {
+        let feature = feature.as_str();
+        let mut feature_deps = vec![""];
+        // always need "", because in dh-cargo we symlink /usr/share/doc/{$feature => $main} pkg
+        let mut other_deps: Vec<Dependency> = Vec::new();
+        for dep in deps {
+            use self::FeatureValue::*;
+            match dep {
+                // another feature is a dependency
+                Feature(dep_feature) => feature_deps.push(dep_feature.as_str()),
+                // an optional dependency, which is a feature in its own right
+                // unless it was referred to with dep: or namespaced features
+                // gave it an explicit feature of the same name, i.e. this one
+                Crate(dep_name) => {
+                    if dep_name.as_str() == feature || dep_only.contains(dep_name.as_str()) {
+                        // valid Cargo.toml files must have this, but it may
+                        // only be a dev-dependency, which we don't depend on
+                        for &dep in vec_opt_iter(deps_by_name.get(dep_name.as_str())) {
+                            other_deps.push(dep.clone());
+                        }
+                    } else {
+                        feature_deps.push(dep_name.as_str());
+                    }
+                }
+                // a feature of another package is a dependency; if that
+                // package is optional, this also enables it as it is declared,
+                // i.e. usually with its default features
+                CrateFeature(dep_name, dep_feature) => {
+                    // as above, valid Cargo.toml files must have this
+                    let deps = deps_by_name.get(dep_name.as_str()).map_or(&[][..], |d| &d[..]);
+                    if deps.iter().any(|d| d.is_optional()) {
+                        if dep_name.as_str() == feature || dep_only.contains(dep_name.as_str()) {
+                            other_deps.extend(deps.iter().map(|&d| d.clone()));
+                        } else {
+                            feature_deps.push(dep_name.as_str());
+                        }
+                    }
+                    for &dep in deps {
+                        let mut dep = dep.clone();
+                        dep.set_features(vec![dep_feature.to_string()]);
+                        dep.set_default_features(false);
+                        other_deps.push(dep);
+                    }
+                }
+            }
+        }
+        // e.g. both "foo" and "foo/bar" enable the optional dependency foo
+        let mut seen = BTreeSet::new();
+        feature_deps.retain(|f| seen.insert(*f));
+        let mut unique_deps = Vec::new();
+        for dep in other_deps {
+            if !unique_deps.contains(&dep) {
+                unique_deps.push(dep);
+            }
+        }
+        features_with_deps.insert(feature, (feature_deps, unique_deps));
+    }
+
+    // calculate dependencies of this crate's "optional dependencies", since they are also features
+    let mut deps_optional: BTreeMap<&str, Vec<Dependency>> = BTreeMap::new();
+    let mut deps_required: Vec<Dependency> = Vec::new();
+    for (&name, deps) in deps_by_name.iter() {
+        for &dep in deps {
+            if dep.is_optional() && dep_only.contains(name) {
+                // no implicit feature, only reachable via the features that
+                // refer to it with dep:
+                continue;
+            } else if dep.is_optional() {
+                // the same crate may be depended on several times, e.g. for
+                // different targets, so collect all of them
+                deps_optional.entry(name).or_default().push(dep.clone());
+            } else {
+                deps_required.push(dep.clone())
+            }
+        }
+    }
+    for (name, deps) in deps_optional {
+        features_with_deps.entry(name).or_insert((vec![""], deps));
+    }
+
+    // features named after their optional dependency, which the summary only
+    // has as the implicit feature of the dependency, enable more than it
+    for (feature, values) in new_syntax.same_name.iter() {
+        let mut feature_deps = Vec::new();
+        let mut other_deps = Vec::new();
+        for value in values {
+            let (dep_name, dep_feature) = match value.find('/') {
+                Some(p) => (&value[..p], Some(&value[p + 1..])),
+                None => (value.as_str(), None),
+            };
+            if let Some((&f, _)) = summary.features().iter().find(|&(f, _)| f.as_str() == dep_name) {
+                // another feature
+                feature_deps.push(f.as_str());
+                continue;
+            }
+            let deps = match deps_by_name.get_key_value(dep_name) {
+                Some((&name, deps)) => {
+                    if deps.iter().any(|d| d.is_optional()) && name != feature.as_str() {
+                        if dep_only.contains(name) {
+                            other_deps.extend(deps.iter().map(|&d| d.clone()));
+                        } else {
+                            feature_deps.push(name);
+                        }
+                    }
+                    deps
+                }
+                None => continue,
+            };
+            if let Some(dep_feature) = dep_feature {
+                for &dep in deps {
+                    let mut dep = dep.clone();
+                    dep.set_features(vec![dep_feature.to_string()]);
+                    dep.set_default_features(false);
+                    other_deps.push(dep);
+                }
+            }
+        }
+        if let Some(&mut (ref mut ff, ref mut dd)) = features_with_deps.get_mut(feature.as_str()) {
+            for f in feature_deps {
+                if !ff.contains(&f) {
+                    ff.push(f);
+                }
+            }
+            for dep in other_deps {
+                if !dd.contains(&dep) {
+                    dd.push(dep);
+                }
+            }
+        }
+    }
+
+    // a weak dependency feature dep?/feat applies as soon as anything enables
+    // the optional dependency, not only the feature that declares it; Debian
+    // can't express "both of these features", so every feature that enables
+    // the dependency also depends on its feature
+    let mut weak_features: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for weak in new_syntax.weak.values() {
+        for &(ref dep_name, ref dep_feature) in weak {
+            weak_features.entry(dep_name).or_default().insert(dep_feature);
+        }
+    }
+    let mut weak_deps: Vec<(&str, Vec<Dependency>)> = Vec::new();
+    for &feature in features_with_deps.keys() {
+        let mut closure = vec![feature];
+        closure.extend(traverse_depth_2(&features_with_deps, feature));
+        let enabled = |dep_name: &str| {
+            closure.iter().any(|f| {
+                features_with_deps.get(f).map_or(false, |&(_, ref deps)| {
+                    deps.iter().any(|d| d.name_in_toml().as_str() == dep_name)
+                })
+            })
+        };
+        let mut deps = Vec::new();
+        for (&dep_name, dep_features) in weak_features.iter() {
+            if !enabled(dep_name) {
+                continue;
+            }
+            for &dep in vec_opt_iter(deps_by_name.get(dep_name)) {
+                for &dep_feature in dep_features {
+                    let mut dep = dep.clone();
+                    dep.set_features(vec![dep_feature.to_string()]);
+                    dep.set_default_features(false);
+                    deps.push(dep);
+                }
+            }
+        }
+        if !deps.is_empty() {
+            weak_deps.push((feature, deps));
+        }
+    }
+    // dep?/feat of a required dependency is just dep/feat, since nothing
+    // needs to enable the dependency
+    for (feature, weak) in new_syntax.weak.iter() {
+        let mut deps = Vec::new();
+        for &(ref dep_name, ref dep_feature) in weak {
+            for &dep in vec_opt_iter(deps_by_name.get(dep_name.as_str())) {
+                if !dep.is_optional() {
+                    let mut dep = dep.clone();
+                    dep.set_features(vec![dep_feature.to_string()]);
+                    dep.set_default_features(false);
+                    deps.push(dep);
+                }
+            }
+        }
+        if let Some((&feature, _)) = features_with_deps.get_key_value(feature.as_str()) {
+            if !deps.is_empty() {
+                weak_deps.push((feature, deps));
+            }
+        }
+    }
+    for (feature, deps) in weak_deps {
+        let feature_deps = &mut features_with_deps.get_mut(feature).unwrap().1;
+        for dep in deps {
+            if !feature_deps.contains(&dep) {
+                feature_deps.push(dep);
+            }
+        }
+    }
+
+    // implicit no-default-features
+    features_with_deps.insert("", (vec![], deps_required));
+
+    // implicit default feature
+    features_with_deps.entry("default").or_insert((vec![""], vec![]));
+
+    features_with_deps
+}
+
+pub fn traverse_depth_2<'a, T>(map: &BTreeMap<&'a str, (Vec<&'a str>, T)>, key: &'a str) -> Vec<&'a str> {
+    let mut x = Vec::new();
+    if let Some((pp, _)) = (*map).get(key) {
+        x.extend(pp);
+        for p in pp {
+            x.extend(traverse_depth_2(map, p));
+        }
+    }
+    x
+}
+
+/// Features that need no dependencies beyond the crate itself, not even
+/// through the features they enable.
+pub fn zero_dependency_features<'a, T>(map: &BTreeMap<&'a str, (Vec<&'a str>, Vec<T>)>) -> Vec<&'a str> {
+    map.keys()
+        .cloned()
+        .filter(|&f| f != "")
+        .filter(|&f| {
+            let mut closure = traverse_depth_2(map, f);
+            closure.push(f);
+            closure.iter().all(|&g| g == "" || map.get(g).map_or(true, |&(_, ref dd)| dd.is_empty()))
+        })
+        .collect()
+}
+
+/// The feature packages that `feature` needs, itself included, following
+/// features that are provided by other packages to those packages.
+fn feature_package_closure<'a, T>(
+    features_with_deps: &BTreeMap<&'a str, (Vec<&'a str>, Vec<T>)>,
+    provider: &BTreeMap<&'a str, &'a str>,
+    feature: &'a str,
+) -> Vec<&'a str> {
+    let mut closure = Vec::new();
+    let mut todo = vec![feature];
+    while let Some(f) = todo.pop() {
+        let f = *provider.get(f).unwrap_or(&f);
+        if f == "" || closure.contains(&f) {
+            continue;
+        }
+        if let Some(&(ref ff, _)) = features_with_deps.get(f) {
+            closure.push(f);
+            todo.extend(ff.iter().cloned());
+        }
+    }
+    closure.sort();
+    closure
+}
+
+/// Fold feature packages into the main package until there are no more than
+/// `max` library packages, starting with the features that need the fewest
+/// dependencies. Each feature goes together with the features it enables, so
+/// that the main package never depends on a feature package. To be run on the
+/// output of calculate_provides; returns the features that the main package
+/// now provides as a result.
+pub fn cap_feature_packages<'a, T>(
+    features_with_deps: &mut BTreeMap<&'a str, (Vec<&'a str>, Vec<T>)>,
+    provides: &mut BTreeMap<&'a str, Vec<&'a str>>,
+    max: usize,
+) -> Vec<&'a str> {
+    let mut provider = BTreeMap::new();
+    for (&p, pp) in provides.iter() {
+        for &f in pp {
+            provider.insert(f, p);
+        }
+    }
+    let mut folded = Vec::new();
+    while features_with_deps.len() > std::cmp::max(max, 1) {
+        let next = features_with_deps
+            .keys()
+            .cloned()
+            .filter(|&f| f != "")
+            .map(|f| feature_package_closure(features_with_deps, &provider, f))
+            .min_by_key(|c| c.iter().map(|f| features_with_deps[f].1.len()).sum::<usize>())
+            .unwrap();
+        for f in next {
+            let (_, o_deps) = features_with_deps.remove(f).unwrap();
+            features_with_deps.get_mut("").unwrap().1.extend(o_deps);
+            let mut pp = provides.remove(f).unwrap_or_default();
+            pp.push(f);
+            folded.extend(pp.iter().cloned());
+            provides.get_mut("").unwrap().extend(pp);
+        }
+    }
+    provides.get_mut("").unwrap().sort();
+    folded.sort();
+    folded
+}
+
+/// Remove features that cannot be packaged, together with the features that
+/// enable them, which are returned. The base package and the default features
+/// must not need any of them.
+pub fn remove_unpackageable_features<'a, T>(
+    features_with_deps: &mut BTreeMap<&'a str, (Vec<&'a str>, Vec<T>)>,
+    unpackageable: &[String],
+) -> Result<Vec<&'a str>> {
+    let mut removed = Vec::new();
+    for f in unpackageable {
+        match features_with_deps.keys().find(|&&k| k == f) {
+            Some(&k) if removed.contains(&k) => (),
+            Some(&k) => removed.push(k),
+            None => debcargo_bail!("unpackageable_features: there is no feature or optional dependency \"{}\"", f),
+        }
+    }
+    let listed = removed.len();
+    loop {
+        let enabling = features_with_deps
+            .iter()
+            .filter(|&(f, &(ref ff, _))| !removed.contains(f) && ff.iter().any(|g| removed.contains(g)))
+            .map(|(&f, _)| f)
+            .collect::<Vec<_>>();
+        if enabling.is_empty() {
+            break;
+        }
+        removed.extend(enabling);
+    }
+    if removed.contains(&"default") {
+        debcargo_bail!(
+            "The default features need some of unpackageable_features {:?}; patch them out of the \
+             default features in Cargo.toml instead",
+            unpackageable
+        );
+    }
+    for f in &removed {
+        features_with_deps.remove(f);
+    }
+    let mut enabling = removed.split_off(listed);
+    enabling.sort();
+    Ok(enabling)
+}
+
+/// Take the features of optional dependencies out of the feature packages:
+/// features that enable one depend on the dependency directly instead. Returns
+/// the dependencies of the features that were taken out.
+pub fn inline_optional_dependencies<'a, T: Clone>(
+    features_with_deps: &mut BTreeMap<&'a str, (Vec<&'a str>, Vec<T>)>,
+    optional: &[&str],
+) -> BTreeMap<&'a str, Vec<T>> {
+    let inlined = features_with_deps
+        .iter()
+        .filter(|&(f, &(ref ff, _))| optional.contains(f) && ff.iter().all(|&g| g == ""))
+        .map(|(&f, &(_, ref dd))| (f, dd.clone()))
+        .collect::<BTreeMap<_, _>>();
+    for f in inlined.keys() {
+        features_with_deps.remove(f);
+    }
+    for (&f, &mut (ref mut ff, ref mut dd)) in features_with_deps.iter_mut() {
+        for g in ff.iter() {
+            if let Some(deps) = inlined.get(g) {
+                dd.extend(deps.iter().cloned());
+            }
+        }
+        ff.retain(|g| !inlined.contains_key(g));
+        if f != "" && ff.is_empty() {
+            ff.push("");
         }
     }
-    x
+    inlined
 }
 
-fn fetch_candidates(registry: &mut PackageRegistry, dep: &Dependency) -> Result<Vec<Summary>> {
-    let mut summaries = registry.query_vec(dep, false)?;
-    summaries.sort_by(|a, b| b.package_id().partial_cmp(&a.package_id()).unwrap());
-    Ok(summaries)
+/// Rewrite the features of a Cargo.toml that use syntax our version of cargo
+/// does not understand into something it does: `dep:foo` becomes `foo`, weak
+/// dependency features `foo?/bar` are dropped, and a feature `foo` that enables
+/// `dep:foo` is dropped in favour of the implicit feature of the dependency.
+/// Returns None if nothing needed rewriting, otherwise the rewritten Cargo.toml
+/// and a record of what was rewritten, to be taken into account when
+/// calculating features.
+pub fn rewrite_new_features(manifest: &str) -> Result<Option<(String, NewFeatureSyntax)>> {
+    let mut value: toml::Value = toml::from_str(manifest)?;
+    let mut new_syntax = NewFeatureSyntax::default();
+    {
+        let features = match value.get_mut("features").and_then(|f| f.as_table_mut()) {
+            Some(features) => features,
+            None => return Ok(None),
+        };
+        let feature_names = features.keys().cloned().collect::<BTreeSet<_>>();
+        let same_name = features
+            .iter()
+            .filter(|&(f, values)| {
+                let dep = toml::Value::String(format!("dep:{}", f));
+                values.as_array().map_or(false, |values| values.contains(&dep))
+            })
+            .map(|(f, _)| f.clone())
+            .collect::<BTreeSet<_>>();
+        for (feature, values) in features.iter_mut() {
+            let values = match values.as_array_mut() {
+                Some(values) => values,
+                None => continue,
+            };
+            let mut weak = Vec::new();
+            for v in values.iter_mut() {
+                let s = match v.as_str() {
+                    Some(s) => s.to_string(),
+                    None => continue,
+                };
+                if s.starts_with("dep:") {
+                    let dep = s[4..].to_string();
+                    if feature_names.contains(&dep) && !same_name.contains(&dep) {
+                        debcargo_bail!(
+                            "Feature \"{}\" refers to dep:{}, which has the same name as a \
+                             feature that doesn't enable it; this is not supported yet",
+                            feature,
+                            dep
+                        );
+                    }
+                    *v = toml::Value::String(dep.clone());
+                    if !same_name.contains(&dep) {
+                        new_syntax.dep_only.insert(dep);
+                    }
+                } else if let Some(p) = s.find("?/") {
+                    weak.push((s[..p].to_string(), s[p + 2..].to_string()));
+                }
+            }
+            if !weak.is_empty() {
+                values.retain(|v| v.as_str().map_or(true, |s| !s.contains("?/")));
+                new_syntax.weak.insert(feature.to_string(), weak);
+            }
+        }
+        for feature in same_name {
+            let values = features.remove(&feature).unwrap();
+            let values = values
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|v| v.as_str())
+                .filter(|&v| v != feature)
+                .map(|v| v.to_string())
+                .collect();
+            new_syntax.same_name.insert(feature, values);
+        }
+    }
+    if new_syntax.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((toml::to_string(&value)?, new_syntax)))
 }
 
-pub fn update_crates_io() -> Result<()> {
-    let config = Config::default()?;
-    let source_id = SourceId::crates_io(&config)?;
-    let yanked_whitelist = HashSet::new();
-    let mut r = RegistrySource::remote(source_id, &yanked_whitelist, &config);
-    r.update()
+/// Pack the files of a package into a .crate like `cargo package` does, with
+/// the manifest normalized for publishing and the original kept alongside,
+/// but without verifying that it builds. A vendored copy was unpacked from a
+/// .crate already, so it is packed as is, minus the checksums `cargo vendor`
+/// added.
+fn pack_crate(
+    package: &Package,
+    files: &[PathBuf],
+    config: &Config,
+    vendored: bool,
+    dest: &Path,
+) -> Result<()> {
+    let top = Path::new(&format!("{}-{}", package.name(), package.version())).to_path_buf();
+    let mut builder = Builder::new(GzEncoder::new(fs::File::create(dest)?, Compression::best()));
+    for file in files {
+        let relative = file.strip_prefix(package.root())?;
+        if vendored && relative == Path::new(".cargo-checksum.json") {
+            continue;
+        }
+        if !vendored && relative == Path::new("Cargo.toml") {
+            builder.append_path_with_name(file, top.join("Cargo.toml.orig"))?;
+            let toml = package.to_registry_toml(config)?;
+            let mut header = Header::new_ustar();
+            header.set_path(top.join("Cargo.toml"))?;
+            header.set_entry_type(EntryType::file());
+            header.set_mode(0o644);
+            let mtime = fs::metadata(file)?.modified()?;
+            header.set_mtime(mtime.duration_since(std::time::UNIX_EPOCH)?.as_secs());
+            header.set_size(toml.len() as u64);
+            header.set_cksum();
+            builder.append(&header, toml.as_bytes())?;
+        } else {
+            builder.append_path_with_name(file, top.join(relative))?;
+        }
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Pack the source directory of a package into a .crate in a new temporary
+/// directory, which must be kept for as long as the .crate is used.
+fn pack_in_scratch_dir(
+    package: &Package,
+    config: &Config,
+    vendored: bool,
+) -> Result<(FileLock, tempfile::TempDir)> {
+    let root = package.root();
+    let mut source = PathSource::new(root, SourceId::for_path(root)?, config);
+    source.update()?;
+    let files = source.list_files(package)?;
+    let filename = format!("{}-{}.crate", package.name(), package.version());
+    let scratch_dir = tempfile::Builder::new().prefix("debcargo").tempdir()?;
+    pack_crate(package, &files, config, vendored, &scratch_dir.path().join(&filename))?;
+    let crate_file = Filesystem::new(scratch_dir.path().to_path_buf())
+        .open_ro(&filename, config, &filename)?;
+    Ok((crate_file, scratch_dir))
+}
+
+/// Open a .crate in a registry cache or a local registry. If it is not there,
+/// say where it was expected and `how_to_fetch` it, rather than leave it to
+/// the I/O error from cargo.
+fn open_crate_file(dir: &Filesystem, filename: &str, config: &Config, how_to_fetch: &str) -> Result<FileLock> {
+    let path = dir.join(filename).into_path_unlocked();
+    if !path.is_file() {
+        debcargo_bail_kind!(
+            ErrorKind::CrateNotFound,
+            "{} was expected at {}, but is not there.\n{}",
+            filename,
+            path.display(),
+            how_to_fetch
+        );
+    }
+    Ok(dir.open_ro(filename, config, filename).context(format!("Couldn't open {}", path.display()))?)
+}
+
+/// Read the top-level Cargo.toml out of a .crate file.
+fn read_crate_manifest(crate_file: &Path) -> Result<String> {
+    let mut archive = Archive::new(GzDecoder::new(fs::File::open(crate_file)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let is_manifest = {
+            let path = entry.path()?;
+            path.ends_with("Cargo.toml") && path.iter().count() == 2
+        };
+        if is_manifest {
+            let mut manifest = String::new();
+            entry.read_to_string(&mut manifest)?;
+            return Ok(manifest);
+        }
+    }
+    debcargo_bail!("{} does not contain a Cargo.toml", crate_file.display())
+}
+
+/// The newer feature syntax used by a .crate file's Cargo.toml.
+fn crate_new_feature_syntax(crate_file: &Path) -> Result<NewFeatureSyntax> {
+    let manifest = read_crate_manifest(crate_file)?;
+    Ok(rewrite_new_features(&manifest)?
+        .map(|(_, new_syntax)| new_syntax)
+        .unwrap_or_default())
+}
+
+/// The names of the users owning a crate on crates.io, from the JSON of its
+/// owners API. Users without a display name are named by their login; teams
+/// are left out, as they are neither copyright holders nor uploaders.
+pub fn owner_names(json: &str) -> Result<Vec<String>> {
+    let owners: serde_json::Value = serde_json::from_str(json)?;
+    let users = match owners["users"].as_array() {
+        Some(users) => users,
+        None => debcargo_bail!("Unexpected reply from the crates.io owners API: {}", json),
+    };
+    Ok(users
+        .iter()
+        .filter(|u| u["kind"].as_str().map_or(true, |k| k == "user"))
+        .filter_map(|u| u["name"].as_str().or_else(|| u["login"].as_str()))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+/// The names of the users owning a crate on crates.io.
+pub fn crates_io_owners(name: &str) -> Result<Vec<String>> {
+    let data = crates_io_api(&format!("crates/{}/owners", name))?;
+    owner_names(&String::from_utf8_lossy(&data))
+}
+
+/// Where the .crate of a crate was downloaded from, to record it.
+#[derive(Default)]
+pub struct Provenance {
+    pub download_url: Option<String>,
+    /// When it was published, for crates from crates.io
+    pub published: Option<String>,
+}
+
+/// Which commit of a git repository to package.
+pub enum GitSelector {
+    Rev(String),
+    Tag(String),
+    Branch(String),
+    DefaultBranch,
+}
+
+impl GitSelector {
+    fn revspec(&self) -> String {
+        match *self {
+            GitSelector::Rev(ref rev) => rev.clone(),
+            GitSelector::Tag(ref tag) => format!("refs/tags/{}", tag),
+            GitSelector::Branch(ref branch) => format!("refs/remotes/origin/{}", branch),
+            GitSelector::DefaultBranch => "HEAD".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GitSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GitSelector::Rev(ref rev) => write!(f, "rev {}", rev),
+            GitSelector::Tag(ref tag) => write!(f, "tag {}", tag),
+            GitSelector::Branch(ref branch) => write!(f, "branch {}", branch),
+            GitSelector::DefaultBranch => write!(f, "the default branch"),
+        }
+    }
+}
+
+/// Where a crate packaged from git came from.
+pub struct GitOrigin {
+    pub url: String,
+    pub selector: GitSelector,
+    pub commit: String,
+}
+
+impl GitOrigin {
+    /// A line recording the origin, for d/changelog.
+    pub fn changelog_entry(&self) -> String {
+        format!(
+            "Package {} from git, {} at commit {}.",
+            self.url, self.selector, self.commit
+        )
+    }
+}
+
+/// The directory of the package named `crate_name` in a source tree, either
+/// at its top or one of the members of a workspace there.
+fn find_crate_dir(tree: &Path, crate_name: &str) -> Result<PathBuf> {
+    let manifests = walkdir::WalkDir::new(tree)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git" && e.file_name() != "target")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == "Cargo.toml");
+    for entry in manifests {
+        let manifest = fs::read_to_string(entry.path())?.parse::<toml::Value>()?;
+        let name = manifest
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str());
+        if name == Some(crate_name) {
+            return Ok(entry.path().parent().unwrap().to_path_buf());
+        }
+    }
+    debcargo_bail_kind!(ErrorKind::CrateNotFound, "There is no crate {} in the repository", crate_name)
 }
 
 pub enum CrateSource {
@@ -84,7 +1572,7 @@ impl CrateInfo {
     ) -> Result<CrateInfo> {
         let config = Config::default()?;
         let source_id = {
-            let source_id = SourceId::crates_io(&config)?;
+            let source_id = crates_io_source_id(&config)?;
             if update {
                 source_id
             } else {
@@ -95,13 +1583,10 @@ impl CrateInfo {
             }
         };
 
-        let version = version.map(|v| {
-            if v.starts_with(|c: char| c.is_digit(10)) {
-                ["=", v].concat()
-            } else {
-                v.to_string()
-            }
-        });
+        let version = match version.filter(|v| !v.is_empty()) {
+            Some(v) => Some(version_req_from_arg(v)?),
+            None => None,
+        };
 
         let dependency = Dependency::parse_no_deprecated(
             crate_name,
@@ -109,40 +1594,181 @@ impl CrateInfo {
             source_id,
         )?;
 
-        let registry_name = format!(
-            "{}-{:016x}",
-            source_id.url().host_str().unwrap_or(""),
-            hash(&source_id).swap_bytes()
-        );
+        let mirror = crates_io_mirror(&config)?;
+        // cargo keeps what it fetches from a mirror under the mirror's name
+        let registry_name = match mirror {
+            Some((_, ref mirror)) => registry_name(&mirror.source_id()?),
+            None => registry_name(&source_id),
+        };
 
-        let (package, manifest, crate_file) = {
+        let (package, manifest, crate_file, version_candidates, scratch_dirs, provenance) = {
             let mut registry = PackageRegistry::new(&config)?;
             registry.lock_patches();
             let summaries = fetch_candidates(&mut registry, &dependency)?;
             let pkgids = summaries
-                .into_iter()
+                .iter()
                 .map(|s| s.package_id().clone())
                 .collect::<Vec<_>>();
-            let pkgid = pkgids.iter().max().ok_or_else(|| {
-                format_err!(
-                    concat!(
-                        "Couldn't find any crate matching {} {}\n ",
-                        "Try `debcargo update` to update the crates.io index."
-                    ),
-                    dependency.package_name(),
-                    dependency.version_req()
-                )
-            })?;
-            let pkgset = registry.get(pkgids.as_slice())?;
-            let package = pkgset.get_one(*pkgid)?;
-            let manifest = package.manifest();
+            let pkgid = match pkgids.iter().max() {
+                Some(pkgid) => pkgid,
+                None => {
+                    let any_version =
+                        Dependency::parse_no_deprecated(crate_name, None, source_id)?;
+                    let published = fetch_candidates(&mut registry, &any_version)?
+                        .iter()
+                        .map(|s| s.version().to_string())
+                        .collect::<Vec<_>>();
+                    // no such crate at all, so maybe a typo; the index is
+                    // just a hint here, so not having one is no error
+                    let similar = if published.is_empty() {
+                        let names = index_crate_names(&config, &registry_name).unwrap_or_default();
+                        similar_crate_names(crate_name, names.iter().map(String::as_str))
+                    } else {
+                        Vec::new()
+                    };
+                    debcargo_bail_kind!(
+                        ErrorKind::CrateNotFound,
+                        concat!(
+                            "Couldn't find any crate matching {} {}\n",
+                            "Published versions: {}\n",
+                            "{}",
+                            "Try `debcargo update` to update the crates.io index."
+                        ),
+                        dependency.package_name(),
+                        dependency.version_req(),
+                        if published.is_empty() {
+                            "(none)".to_string()
+                        } else {
+                            published.join(", ")
+                        },
+                        if similar.is_empty() {
+                            String::new()
+                        } else {
+                            format!("Did you mean: {}?\n", similar.join(", "))
+                        }
+                    )
+                }
+            };
+            let mut version_candidates = pkgids.iter().map(|p| p.version().clone()).collect::<Vec<_>>();
+            version_candidates.sort_by(|a, b| b.cmp(a));
+            // download the crate ourselves, see fetch_crate
+            let (remote_id, token) = match mirror {
+                Some((ref name, Mirror::Registry(id))) => (Some(id), registry_token(&config, name)?),
+                Some(_) => (None, None),
+                None if local_registry()?.is_some() => (None, None),
+                None => (Some(source_id), None),
+            };
+            let mut provenance = Provenance::default();
+            if let Some(id) = remote_id {
+                let summary = summaries.iter().find(|s| s.package_id() == *pkgid).unwrap();
+                let cache = config.registry_cache_path().join(&registry_name);
+                provenance.download_url = fetch_crate(
+                    &config,
+                    &id,
+                    dl_url().as_ref().map(String::as_str),
+                    token.as_ref().map(String::as_str),
+                    summary,
+                    &cache,
+                )?;
+                if id.is_default_registry() {
+                    provenance.published = crates_io_publish_date(&pkgid.name(), pkgid.version())
+                        .unwrap_or_else(|e| {
+                            debcargo_warn!("Couldn't look up when {} was published: {}", pkgid, e);
+                            None
+                        });
+                }
+            }
             let filename = format!("{}-{}.crate", pkgid.name(), pkgid.version());
-            let crate_file = config
-                .registry_cache_path()
-                .join(&registry_name)
-                .open_ro(&filename, &config, &filename)?;
-            (package.clone(), manifest.clone(), crate_file)
+            // before cargo looks for it, so as to say what is missing if it is not there
+            let crate_file = match (local_registry()?, mirror.map(|(_, m)| m)) {
+                // local registries are their own cache
+                (Some(path), _) | (None, Some(Mirror::LocalRegistry(path))) => {
+                    let how_to_fetch = format!(
+                        concat!(
+                            "The index of the local registry {} lists {} {}, but not its .crate; ",
+                            "add it there, e.g. with `cargo local-registry --sync`."
+                        ),
+                        path.display(),
+                        pkgid.name(),
+                        pkgid.version()
+                    );
+                    Some(open_crate_file(&Filesystem::new(path), &filename, &config, &how_to_fetch)?)
+                }
+                // only the unpacked crate is there
+                (None, Some(Mirror::Directory(_))) => None,
+                (None, Some(Mirror::Registry(_))) | (None, None) => {
+                    let cache = config.registry_cache_path().join(&registry_name);
+                    // fetch_crate should have just put it there
+                    let how_to_fetch = match provenance.download_url {
+                        Some(ref url) => format!(
+                            "Check that the directory is writable, or download it there yourself, from {}",
+                            url
+                        ),
+                        None => "Check that the directory is writable, and try again.".to_string(),
+                    };
+                    Some(open_crate_file(&cache, &filename, &config, &how_to_fetch)?)
+                }
+            };
+            let mut scratch_dirs = Vec::new();
+            let pkgset = registry.get(pkgids.as_slice())?;
+            let package = match pkgset.get_one(*pkgid) {
+                Ok(package) => package.clone(),
+                Err(e) => {
+                    // The crate might use Cargo.toml syntax that is too new
+                    // for us; if so, rewrite a scratch copy of cargo's
+                    // unpacked sources, which other builds share, and load
+                    // the package from there.
+                    let unpacked = config
+                        .registry_source_path()
+                        .join(&registry_name)
+                        .into_path_unlocked()
+                        .join(format!("{}-{}", pkgid.name(), pkgid.version()));
+                    let manifest = match fs::read_to_string(unpacked.join("Cargo.toml")) {
+                        Ok(manifest) => manifest,
+                        Err(_) => return Err(e.into()),
+                    };
+                    match rewrite_new_features(&manifest)? {
+                        Some((rewritten, _)) => {
+                            debcargo_info!(
+                                "Rewriting newer feature syntax of {} {} for our cargo library",
+                                pkgid.name(),
+                                pkgid.version()
+                            );
+                            let scratch_dir = tempfile::Builder::new().prefix("debcargo").tempdir()?;
+                            let root = scratch_dir
+                                .path()
+                                .join(format!("{}-{}", pkgid.name(), pkgid.version()));
+                            fs::create_dir(&root)?;
+                            copy_tree(&unpacked, &root)?;
+                            fs::write(root.join("Cargo.toml"), rewritten)?;
+                            let source_id = SourceId::for_path(&root)?;
+                            let package = PathSource::new(&root, source_id, &config).root_package()?;
+                            scratch_dirs.push(scratch_dir);
+                            package
+                        }
+                        None => return Err(e.into()),
+                    }
+                }
+            };
+            let manifest = package.manifest().clone();
+            let crate_file = match crate_file {
+                Some(crate_file) => crate_file,
+                None => {
+                    let (crate_file, scratch_dir) = pack_in_scratch_dir(&package, &config, true)?;
+                    scratch_dirs.push(scratch_dir);
+                    crate_file
+                }
+            };
+            (
+                package,
+                manifest,
+                crate_file,
+                version_candidates,
+                scratch_dirs,
+                provenance,
+            )
         };
+        let new_feature_syntax = crate_new_feature_syntax(crate_file.path())?;
 
         Ok(CrateInfo {
             package: package,
@@ -151,11 +1777,184 @@ impl CrateInfo {
             config: config,
             source_id: source_id,
             excludes: vec![],
+            allow_suspicious: false,
             includes: vec![],
+            version_candidates: version_candidates,
+            locked_versions: BTreeMap::new(),
+            new_feature_syntax: new_feature_syntax,
+            git_origin: None,
+            provenance: provenance,
+            owners: vec![],
+            _scratch_dirs: scratch_dirs,
         })
     }
 
+    /// Load a crate from a source checkout. This uses the .crate left by
+    /// `cargo package` if there is one, and otherwise packs the checkout
+    /// into one in a temporary directory, so unreleased trees can be
+    /// packaged directly.
     pub fn new_from_path(path: &Path, version: Option<&str>, update: bool) -> Result<CrateInfo> {
+        CrateInfo::new_from_dir(path, false)
+    }
+
+    /// Load a crate from its copy in a directory made by `cargo vendor`,
+    /// taking the newest vendored version that matches `version`. This needs
+    /// neither the network nor the crates.io index.
+    pub fn new_from_vendor_dir(
+        vendor_dir: &Path,
+        crate_name: &str,
+        version: Option<&str>,
+    ) -> Result<CrateInfo> {
+        let config = Config::default()?;
+        let req = match version.filter(|v| !v.is_empty()) {
+            Some(v) => VersionReq::parse(&version_req_from_arg(v)?)?,
+            None => VersionReq::any(),
+        };
+        let mut candidates = vec![];
+        for entry in fs::read_dir(vendor_dir)? {
+            let path = entry?.path();
+            // `cargo vendor --versioned-dirs` appends the version
+            let dirname = path.file_name().unwrap().to_string_lossy().to_string();
+            if dirname != crate_name && !dirname.starts_with(&format!("{}-", crate_name)) {
+                continue;
+            }
+            if !path.join("Cargo.toml").is_file() {
+                continue;
+            }
+            let mut source = PathSource::new(&path, SourceId::for_path(&path)?, &config);
+            let package = source.root_package()?;
+            if package.name().as_str() == crate_name && req.matches(package.version()) {
+                candidates.push((package.version().clone(), path));
+            }
+        }
+        candidates.sort();
+        let path = match candidates.last() {
+            Some(&(_, ref path)) => path.clone(),
+            None => debcargo_bail_kind!(
+                ErrorKind::CrateNotFound,
+                "Couldn't find any vendored crate matching {} {} in {}",
+                crate_name,
+                req,
+                vendor_dir.display()
+            ),
+        };
+        let mut crate_info = CrateInfo::new_from_dir(&path, true)?;
+        crate_info.version_candidates = candidates.into_iter().rev().map(|(v, _)| v).collect();
+        Ok(crate_info)
+    }
+
+    /// Load a crate from a .crate file downloaded before, e.g. one archived
+    /// with an old packaging, to reproduce it. The manifest is read from the
+    /// .crate itself, with neither the network nor the index, so the crate
+    /// has no provenance nor other version candidates. It must be
+    /// `crate_name`, at a version matching `version` if given.
+    pub fn new_with_local_crate(crate_name: &str, version: Option<&str>, crate_path: &Path) -> Result<CrateInfo> {
+        let config = Config::default()?;
+        if !crate_path.is_file() {
+            debcargo_bail_kind!(ErrorKind::CrateNotFound, "{} is not a .crate file", crate_path.display());
+        }
+        let crate_path = crate_path.canonicalize()?;
+        let manifest = read_crate_manifest(&crate_path)?;
+        let (name, crate_version) = {
+            let table: toml::Value = toml::from_str(&manifest)?;
+            let field = |f: &str| table.get("package").and_then(|p| p.get(f)).and_then(|v| v.as_str()).map(str::to_string);
+            match (field("name"), field("version")) {
+                (Some(name), Some(v)) => (name, Version::parse(&v)?),
+                _ => debcargo_bail!("The Cargo.toml in {} has no package name or version", crate_path.display()),
+            }
+        };
+        if name != crate_name {
+            debcargo_bail!("{} is a .crate of {}, not of {}", crate_path.display(), name, crate_name);
+        }
+        if let Some(v) = version.filter(|v| !v.is_empty()) {
+            let req = VersionReq::parse(&version_req_from_arg(v)?)?;
+            if !req.matches(&crate_version) {
+                debcargo_bail!("{} is {} {}, which does not match {}", crate_path.display(), name, crate_version, req);
+            }
+        }
+
+        // cargo only reads packages from directories, where it also finds
+        // the targets that the manifest doesn't list
+        let scratch_dir = tempfile::Builder::new().prefix("debcargo").tempdir()?;
+        Archive::new(GzDecoder::new(fs::File::open(&crate_path)?)).unpack(scratch_dir.path())?;
+        let root = scratch_dir.path().join(format!("{}-{}", name, crate_version));
+        if let Some((rewritten, _)) = rewrite_new_features(&manifest)? {
+            debcargo_info!("Rewriting newer feature syntax of {} {} for our cargo library", name, crate_version);
+            fs::write(root.join("Cargo.toml"), rewritten)?;
+        }
+        let source_id = SourceId::for_path(&root)?;
+        let package = PathSource::new(&root, source_id, &config).root_package()?;
+        let manifest = package.manifest().clone();
+
+        let filename = crate_path.file_name().unwrap().to_string_lossy().to_string();
+        let crate_file = Filesystem::new(crate_path.parent().unwrap().to_path_buf())
+            .open_ro(&filename, &config, &filename)?;
+        let new_feature_syntax = crate_new_feature_syntax(&crate_path)?;
+
+        Ok(CrateInfo {
+            package: package,
+            manifest: manifest,
+            crate_file: crate_file,
+            config: config,
+            source_id: source_id,
+            excludes: vec![],
+            allow_suspicious: false,
+            includes: vec![],
+            version_candidates: vec![],
+            locked_versions: BTreeMap::new(),
+            new_feature_syntax: new_feature_syntax,
+            git_origin: None,
+            provenance: Provenance::default(),
+            owners: vec![],
+            _scratch_dirs: vec![scratch_dir],
+        })
+    }
+
+    /// Load a crate from a git repository, at the commit `selector` picks.
+    /// The crate may be a member of a workspace there. A `version`, if given,
+    /// must match that of the crate at that commit.
+    pub fn new_from_git(
+        url: &str,
+        selector: GitSelector,
+        crate_name: &str,
+        version: Option<&str>,
+    ) -> Result<CrateInfo> {
+        let clone_dir = tempfile::Builder::new().prefix("debcargo").tempdir()?;
+        debcargo_info!("Cloning {}", url);
+        let repo = RepoBuilder::new()
+            .clone(url, clone_dir.path())
+            .kind(ErrorKind::Network)?;
+        let commit = match repo.revparse_single(&selector.revspec()) {
+            Ok(object) => object.peel_to_commit()?,
+            Err(e) => debcargo_bail_kind!(ErrorKind::CrateNotFound, "Couldn't find {} in {}: {}", selector, url, e),
+        };
+        repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().force()))?;
+        repo.set_head_detached(commit.id())?;
+
+        let path = find_crate_dir(clone_dir.path(), crate_name)?;
+        let mut crate_info = CrateInfo::new_from_dir(&path, false)?;
+        if let Some(v) = version.filter(|v| !v.is_empty()) {
+            let req = VersionReq::parse(&version_req_from_arg(v)?)?;
+            if !req.matches(crate_info.version()) {
+                debcargo_bail!(
+                    "{} at {} is version {}, which does not match {}",
+                    crate_name,
+                    selector,
+                    crate_info.version(),
+                    req
+                );
+            }
+        }
+        crate_info.git_origin = Some(GitOrigin {
+            url: url.to_string(),
+            selector: selector,
+            commit: commit.id().to_string(),
+        });
+        crate_info._scratch_dirs.push(clone_dir);
+        Ok(crate_info)
+    }
+
+    fn new_from_dir(path: &Path, vendored: bool) -> Result<CrateInfo> {
         let config = Config::default()?;
         let source_id = SourceId::for_path(path)?;
         let mut source = PathSource::new(path, source_id, &config);
@@ -163,10 +1962,23 @@ impl CrateInfo {
         let manifest = package.manifest();
 
         let crate_filename = format!("{}-{}.crate", package.name(), package.version());
-        let crate_file = Filesystem::new(package.root().to_path_buf())
-            .join("target")
-            .join("package")
-            .open_ro(&crate_filename, &config, &crate_filename)?;
+        let package_dir = package.root().join("target").join("package");
+        let (crate_file, scratch_dir) = if !vendored && package_dir.join(&crate_filename).is_file() {
+            let crate_file =
+                Filesystem::new(package_dir).open_ro(&crate_filename, &config, &crate_filename)?;
+            (crate_file, None)
+        } else {
+            if !vendored {
+                debcargo_info!(
+                    "No {} from `cargo package` in {}, packing the source directory as is",
+                    crate_filename,
+                    package_dir.display()
+                );
+            }
+            let (crate_file, scratch_dir) = pack_in_scratch_dir(&package, &config, vendored)?;
+            (crate_file, Some(scratch_dir))
+        };
+        let new_feature_syntax = crate_new_feature_syntax(crate_file.path())?;
 
         Ok(CrateInfo {
             package: package.clone(),
@@ -175,7 +1987,15 @@ impl CrateInfo {
             config: Config::default()?,
             source_id: source_id,
             excludes: vec![],
+            allow_suspicious: false,
             includes: vec![],
+            version_candidates: vec![],
+            locked_versions: BTreeMap::new(),
+            new_feature_syntax: new_feature_syntax,
+            git_origin: None,
+            provenance: Provenance::default(),
+            owners: vec![],
+            _scratch_dirs: scratch_dir.into_iter().collect(),
         })
     }
 
@@ -187,12 +2007,47 @@ impl CrateInfo {
         self.manifest.summary().package_id().version()
     }
 
+    /// Where the .crate was downloaded from and when it was published, as
+    /// far as we know; empty for crates that weren't downloaded.
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
+    /// The repository and commit the crate was packaged from, if it was
+    /// packaged from git.
+    pub fn git_origin(&self) -> Option<&GitOrigin> {
+        self.git_origin.as_ref()
+    }
+
+    /// Published versions that matched the requested version, newest first.
+    /// Empty if the crate did not come from a registry.
+    pub fn version_candidates(&self) -> &[Version] {
+        &self.version_candidates
+    }
+
     pub fn manifest(&self) -> &Manifest {
         &self.manifest
     }
 
     pub fn replace_manifest(&mut self, path: &PathBuf) -> Result<&Self> {
-        if let (EitherManifest::Real(v), _) = read_manifest(path, self.source_id, &self.config)? {
+        let manifest = fs::read_to_string(path)?;
+        let read = match rewrite_new_features(&manifest)? {
+            None => {
+                self.new_feature_syntax = NewFeatureSyntax::default();
+                read_manifest(path, self.source_id, &self.config)?
+            }
+            Some((rewritten, new_syntax)) => {
+                // cargo infers targets relative to the manifest, so this
+                // needs to be in the same directory
+                let rewritten_path = path.with_file_name(".Cargo.toml.debcargo");
+                fs::write(&rewritten_path, rewritten)?;
+                let read = read_manifest(&rewritten_path, self.source_id, &self.config);
+                fs::remove_file(&rewritten_path)?;
+                self.new_feature_syntax = new_syntax;
+                read?
+            }
+        };
+        if let (EitherManifest::Real(v), _) = read {
             self.manifest = v;
         }
         Ok(self)
@@ -210,6 +2065,12 @@ impl CrateInfo {
         self.manifest.metadata()
     }
 
+    /// The packaging hints of the crate in its Cargo.toml, as config
+    /// settings; see `upstream_config_table`.
+    pub fn upstream_config(&self) -> Result<toml::value::Table> {
+        upstream_config_table(&fs::read_to_string(self.package.manifest_path())?)
+    }
+
     pub fn summary(&self) -> &Summary {
         self.manifest.summary()
     }
@@ -218,6 +2079,17 @@ impl CrateInfo {
         &self.package
     }
 
+    /// The best guess at the crate's homepage: `homepage` if set, otherwise
+    /// `repository`, otherwise `documentation`, normalized with
+    /// `normalize_homepage`.
+    pub fn homepage(&self) -> Option<String> {
+        let meta = self.metadata();
+        vec![&meta.homepage, &meta.repository, &meta.documentation]
+            .into_iter()
+            .filter_map(|u| u.as_ref().and_then(|u| normalize_homepage(u)))
+            .next()
+    }
+
     pub fn crate_file(&self) -> &FileLock {
         &self.crate_file
     }
@@ -226,12 +2098,96 @@ impl CrateInfo {
         self.manifest.dependencies()
     }
 
+    /// Look up the owners of the crate on crates.io, to fall back on when
+    /// Cargo.toml names no authors.
+    pub fn load_crates_io_owners(&mut self) -> Result<&[String]> {
+        self.owners = crates_io_owners(&self.package_id().name())?;
+        Ok(&self.owners)
+    }
+
+    /// The crates.io owners of the crate, if they were looked up.
+    pub fn crates_io_owners(&self) -> &[String] {
+        &self.owners
+    }
+
+    /// Pin dependencies to the versions recorded in the given Cargo.lock,
+    /// instead of allowing any semver-compatible version.
+    pub fn set_lockfile(&mut self, lockfile: &Path) -> Result<()> {
+        let mut content = String::new();
+        fs::File::open(lockfile)?.read_to_string(&mut content)?;
+        let lock: LockFile = toml::from_str(&content)?;
+        let mut locked_versions: BTreeMap<String, Vec<Version>> = BTreeMap::new();
+        for p in lock.package.unwrap_or_default() {
+            let version = Version::parse(&p.version).map_err(|e| {
+                format_err!(
+                    "{}: bad version {} for {}: {}",
+                    lockfile.display(),
+                    p.version,
+                    p.name,
+                    e
+                )
+            })?;
+            locked_versions.entry(p.name).or_default().push(version);
+        }
+        self.locked_versions = locked_versions;
+        Ok(())
+    }
+
+    /// Restrict a dependency to its locked version, if a lockfile was given
+    /// and it contains a version satisfying the dependency.
+    fn pin_locked(&self, dep: &Dependency) -> Dependency {
+        let mut dep = dep.clone();
+        let locked = self
+            .locked_versions
+            .get(dep.package_name().as_str())
+            .and_then(|vv| vv.iter().filter(|v| dep.version_req().matches(v)).max())
+            .cloned();
+        if let Some(v) = locked {
+            dep.set_version_req(VersionReq::exact(&v));
+        }
+        dep
+    }
+
+    /// The dependencies of the crate as packaged, i.e. pinned to the
+    /// lockfile versions if one was given.
+    pub fn packaged_dependencies(&self) -> Vec<Dependency> {
+        self.dependencies()
+            .iter()
+            .map(|dep| self.pin_locked(dep))
+            .collect()
+    }
+
+    /// The dependencies on git repositories or local paths, which must be
+    /// patched to registry versions before the crate can be packaged.
+    pub fn source_dependencies(&self) -> Vec<&Dependency> {
+        let mut deps = self
+            .dependencies()
+            .iter()
+            .filter(|dep| is_source_dependency(dep))
+            .collect::<Vec<_>>();
+        // the same dependency may appear for several targets
+        deps.dedup_by(|a, b| a.name_in_toml() == b.name_in_toml() && a.kind() == b.kind());
+        deps
+    }
+
+    /// The newest version in the registry that could replace a git or path
+    /// dependency, honouring its version requirement if it has one.
+    pub fn newest_registry_version(&self, dep: &Dependency) -> Result<Option<Version>> {
+        let (mut registry, source_id) = crates_io_registry(&self.config, false)?;
+        let req = dep.version_req().to_string();
+        let query = Dependency::parse_no_deprecated(&dep.package_name(), Some(&req), source_id)?;
+        Ok(fetch_candidates(&mut registry, &query)?
+            .into_iter()
+            .next()
+            .map(|s| s.version().clone()))
+    }
+
     pub fn dev_dependencies(&self) -> Vec<Dependency> {
         use cargo::core::dependency::Kind;
         let mut deps = vec![];
         for dep in self.dependencies() {
             if dep.kind() == Kind::Development {
-                deps.push(dep.clone())
+                deps.push(self.pin_locked(dep))
             }
         }
         deps
@@ -239,83 +2195,24 @@ impl CrateInfo {
 
     pub fn all_dependencies_and_features(
         &self,
-    ) -> BTreeMap<
+        selection: &DependencySelection,
+    ) -> Result<BTreeMap<
         &str, // name of feature / optional dependency,
         // or "" for the base package w/ no default features, guaranteed to be in the map
         (
             Vec<&str>, // dependencies: other features (of the current package)
             Vec<Dependency>,
         ),
-    > // dependencies: other packages
+    >> // dependencies: other packages
     {
-        use cargo::core::dependency::Kind;
-
-        let mut deps_by_name: BTreeMap<&str, Vec<&Dependency>> = BTreeMap::new();
-        for dep in self.dependencies() {
-            // we treat build-dependencies also as dependencies in Debian
-            if dep.kind() != Kind::Development {
-                let s = dep.package_name().as_str();
-                deps_by_name.entry(s).or_default().push(dep);
-            }
-        }
-        let deps_by_name = deps_by_name;
-
-        let mut features_with_deps = BTreeMap::new();
-
-        // calculate dependencies of this crate's features
-        for (feature, deps) in self.manifest.summary().features() {
-            let mut feature_deps = vec![""];
-            // always need "", because in dh-cargo we symlink /usr/share/doc/{$feature => $main} pkg
-            let mut other_deps: Vec<Dependency> = Vec::new();
-            for dep in deps {
-                use self::FeatureValue::*;
-                match dep {
-                    // another feature is a dependency
-                    Feature(dep_feature) => feature_deps.push(dep_feature),
-                    // another package is a dependency
-                    Crate(dep_name) => {
-                        // unwrap is ok, valid Cargo.toml files must have this
-                        for &dep in deps_by_name.get(dep_name.as_str()).unwrap() {
-                            other_deps.push(dep.clone());
-                        }
-                    }
-                    // another package is a dependency
-                    CrateFeature(dep_name, dep_feature) => {
-                        // unwrap is ok, valid Cargo.toml files must have this
-                        for &dep in deps_by_name.get(dep_name.as_str()).unwrap() {
-                            let mut dep = dep.clone();
-                            dep.set_features(vec![dep_feature.to_string()]);
-                            dep.set_default_features(false);
-                            other_deps.push(dep);
-                        }
-                    }
-                }
-            }
-            features_with_deps.insert(feature.as_str(), (feature_deps, other_deps));
-        }
-
-        // calculate dependencies of this crate's "optional dependencies", since they are also features
-        let mut deps_required: Vec<Dependency> = Vec::new();
-        for deps in deps_by_name.values() {
-            for &dep in deps {
-                if dep.is_optional() {
-                    features_with_deps
-                        .insert(&dep.package_name().as_str(), (vec![""], vec![dep.clone()]));
-                } else {
-                    deps_required.push(dep.clone())
-                }
-            }
-        }
-
-        // implicit no-default-features
-        features_with_deps.insert("", (vec![], deps_required));
-
-        // implicit default feature
-        if !features_with_deps.contains_key("default") {
-            features_with_deps.insert("default", (vec![""], vec![]));
-        }
-
-        features_with_deps
+        let deps = self.packaged_dependencies();
+        let mut features_with_deps = summary_dependencies_and_features(
+            self.manifest.summary(),
+            &deps,
+            &self.new_feature_syntax,
+        );
+        selection.apply(&mut features_with_deps)?;
+        Ok(features_with_deps)
     }
 
     pub fn feature_all_deps<'a>(
@@ -338,19 +2235,35 @@ impl CrateInfo {
 
     // Note: this mutates features_with_deps so you need to run e.g.
     // feature_all_deps before calling this.
+    //
+    // With zero_dep_provides, every feature in zero_dependency_features is
+    // provided by the main package, however it is reached.
     pub fn calculate_provides<'a>(
         &self,
         features_with_deps: &mut BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)>,
+        zero_dep_provides: bool,
     ) -> BTreeMap<&'a str, Vec<&'a str>> {
         let mut provides = BTreeMap::new();
         let mut provided = Vec::new();
+        if zero_dep_provides {
+            let zero_dep = zero_dependency_features(features_with_deps);
+            for f in &zero_dep {
+                debcargo_explain!(
+                    "Feature {} needs no dependencies, so the main package provides it, as \
+                     provide_zero_dep_features is set",
+                    f
+                );
+            }
+            provided.extend(zero_dep.iter().cloned());
+            provides.insert("", zero_dep);
+        }
         // the below is very simple and incomplete. e.g. it does not,
         // but could be improved to, simplify things like:
         // f1 depends on f2, f3
         // f2 depends on f4
         // f3 depends on f4
         for (&f, &(ref ff, ref dd)) in features_with_deps.iter() {
-            if !dd.is_empty() {
+            if !dd.is_empty() || provided.contains(&f) {
                 continue;
             }
             assert!(!ff.is_empty() || f == "");
@@ -361,6 +2274,12 @@ impl CrateInfo {
             } else {
                 continue;
             };
+            debcargo_explain!(
+                "Feature {} adds no dependencies and enables just {}, so it is provided by the \
+                 package of that rather than packaged on its own",
+                f,
+                if k == "" { "the main library".to_string() } else { format!("feature {}", k) }
+            );
             if !provides.contains_key(k) {
                 provides.insert(k, vec![]);
             }
@@ -411,16 +2330,45 @@ impl CrateInfo {
         bins
     }
 
+    pub fn get_example_targets(&self) -> Vec<&str> {
+        let mut examples = self
+            .manifest
+            .targets()
+            .iter()
+            .filter(|t| t.is_example())
+            .map(|t| t.name())
+            .collect::<Vec<_>>();
+        examples.sort();
+        examples
+    }
+
+    /// What the build script of the crate seems to do, if it has one; see
+    /// `BuildScriptReport::analyze`.
+    pub fn analyze_build_script(&self) -> Result<Option<BuildScriptReport>> {
+        let root = self.package.root();
+        let script = match self.targets().iter().find(|t| t.is_custom_build()) {
+            Some(t) => match t.src_path().path() {
+                Some(p) => p.to_path_buf(),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        if !script.is_file() {
+            return Ok(None);
+        }
+        let source = fs::read_to_string(&script)?;
+        let path = script.strip_prefix(root).unwrap_or(&script).to_path_buf();
+        Ok(Some(BuildScriptReport::analyze(path, &source, self.manifest.links())))
+    }
+
     pub fn semver_suffix(&self) -> String {
         let lib = self.is_lib();
         let bins = self.get_binary_targets();
 
-        match *self.package_id().version() {
-            _ if !lib && !bins.is_empty() => "".to_string(),
-            Version {
-                major: 0, minor, ..
-            } => format!("-0.{}", minor),
-            Version { major, .. } => format!("-{}", major),
+        if !lib && !bins.is_empty() {
+            "".to_string()
+        } else {
+            semver_suffix_of(self.package_id().version())
         }
     }
 
@@ -435,72 +2383,63 @@ impl CrateInfo {
         }
     }
 
-    pub fn get_summary_description(&self) -> (Option<String>, Option<String>) {
-        let (summary, description) = if let Some(ref description) = self.metadata().description {
-            // Convention these days seems to be to do manual text
-            // wrapping in crate descriptions, boo. \n\n is a real line break.
-            let mut description = description
-                .replace("\n\n", "\r")
-                .replace("\n", " ")
-                .replace("\r", "\n")
-                .trim()
-                .to_string();
-            // Trim off common prefixes
-            let re = Regex::new(&format!(
-                r"^(?i)({}|This(\s+\w+)?)(\s*,|\s+is|\s+provides)\s+",
-                self.package_id().name()
-            ))
-            .unwrap();
-            description = re.replace(&description, "").to_string();
-            let re = Regex::new(r"^(?i)(a|an|the)\s+").unwrap();
-            description = re.replace(&description, "").to_string();
-            let re =
-                Regex::new(r"^(?i)(rust\s+)?(implementation|library|tool|crate)\s+(of|to|for)\s+")
-                    .unwrap();
-            description = re.replace(&description, "").to_string();
-
-            // https://stackoverflow.com/questions/38406793/why-is-capitalizing-the-first-letter-of-a-string-so-convoluted-in-rust
-            description = {
-                let mut d = description.chars();
-                match d.next() {
-                    None => String::new(),
-                    Some(f) => f.to_uppercase().chain(d).collect::<String>(),
-                }
-            };
+    pub fn get_summary_description(
+        &self,
+        rules: &DescriptionRules,
+    ) -> (Option<String>, Option<String>) {
+        match self.metadata().description {
+            Some(ref description) => self.summarize_description(description, rules),
+            None => (None, None),
+        }
+    }
 
-            // Use the first sentence or first line, whichever comes first, as the summary.
-            let p1 = description.find('\n');
-            let p2 = description.find(". ");
-            match p1.into_iter().chain(p2.into_iter()).min() {
-                Some(p) => {
-                    let s = description[..p].trim_right_matches('.').to_string();
-                    let d = description[p + 1..].trim();
-                    if d.is_empty() {
-                        (Some(s), None)
-                    } else {
-                        (Some(s), Some(d.to_string()))
-                    }
-                }
-                None => (Some(description.trim_right_matches('.').to_string()), None),
-            }
-        } else {
-            (None, None)
-        };
+    /// Split a free-form description into a summary and the remaining
+    /// description, applying some cleanups along the way.
+    pub fn summarize_description(
+        &self,
+        description: &str,
+        rules: &DescriptionRules,
+    ) -> (Option<String>, Option<String>) {
+        rules.summarize(self.package_id().name().as_str(), description)
+    }
 
-        (summary, description)
+    /// The first paragraph of the crate's README, for use as a description
+    /// when Cargo.toml does not provide one.
+    pub fn readme_description(&self, srcdir: &Path) -> Option<String> {
+        let readme = match self.metadata().readme {
+            Some(ref r) => srcdir.join(r),
+            None => ["README.md", "README"]
+                .iter()
+                .map(|f| srcdir.join(f))
+                .find(|p| p.exists())?,
+        };
+        readme_first_paragraph(&fs::read_to_string(readme).ok()?)
     }
 
     pub fn set_includes_excludes(
         &mut self,
         excludes: Option<&Vec<String>>,
         includes: Option<&Vec<String>>,
-    ) {
-        self.excludes = vec_opt_iter(excludes)
-            .map(|x| Pattern::new(&("*/".to_owned() + x)).unwrap())
-            .collect::<Vec<_>>();
-        self.includes = vec_opt_iter(includes)
-            .map(|x| Pattern::new(&("*/".to_owned() + x)).unwrap())
-            .collect::<Vec<_>>();
+    ) -> Result<()> {
+        fn patterns(key: &str, globs: Option<&Vec<String>>) -> Result<Vec<Pattern>> {
+            vec_opt_iter(globs)
+                .map(|x| {
+                    Pattern::new(&("*/".to_owned() + x)).map_err(|e| {
+                        ErrorKind::Config.mark(format_err!(
+                            "{} has an invalid glob \"{}\": {}", key, x, e))
+                    })
+                })
+                .collect()
+        }
+        self.excludes = patterns("excludes", excludes)?;
+        self.includes = patterns("whitelist", includes)?;
+        Ok(())
+    }
+
+    /// Only warn about suspicious files when extracting the crate, for when
+    /// they were already reviewed, rather than refusing to extract it.
+    pub fn set_allow_suspicious(&mut self, allow: bool) {
+        self.allow_suspicious = allow;
     }
 
     pub fn filter_path(&self, path: &Path) -> ::std::result::Result<bool, String> {
@@ -532,6 +2471,31 @@ impl CrateInfo {
         }
     }
 
+    fn suspicious_files_report_text(&self, suspicious: &[PathBuf]) -> String {
+        let pkgid = self.package_id();
+        // relative to the top of the crate, as for excludes
+        let files = suspicious
+            .iter()
+            .map(|p| p.components().skip(1).collect::<PathBuf>().display().to_string())
+            .collect::<Vec<_>>();
+        let mut text = format!(
+            concat!(
+                "# Suspicious files in {} {}, extracted with --allow-suspicious.\n",
+                "# They are in the orig tarball as is. To leave them out of it, add to\n",
+                "# debcargo.toml:\n",
+                "excludes = [\n"
+            ),
+            pkgid.name(),
+            pkgid.version()
+        );
+        for f in &files {
+            text.push_str(&format!("    \"{}\",\n", f));
+        }
+        text.push_str("]\n# or to Files-Excluded in d/copyright, for a repacked orig tarball:\n");
+        text.push_str(&format!("# Files-Excluded: {}\n", files.join(" ")));
+        text
+    }
+
     pub fn extract_crate(&self, path: &Path) -> Result<bool> {
         let mut archive = Archive::new(GzDecoder::new(self.crate_file.file()));
         let tempdir = tempfile::Builder::new()
@@ -540,11 +2504,15 @@ impl CrateInfo {
         let mut source_modified = false;
         let mut last_mtime = 0;
         let mut err = vec![];
+        let mut suspicious = vec![];
 
         for entry in archive.entries()? {
             let mut entry = entry?;
             match self.filter_path(&(entry.path()?)) {
-                Err(e) => err.push(e),
+                Err(e) => {
+                    err.push(e);
+                    suspicious.push(entry.path()?.to_path_buf());
+                }
                 Ok(r) => {
                     if r {
                         source_modified = true;
@@ -564,12 +2532,24 @@ impl CrateInfo {
             }
         }
         if !err.is_empty() {
-            for e in err {
-                debcargo_warn!("{}", e);
+            for (e, p) in err.iter().zip(&suspicious) {
+                warn_with_code("suspicious-file", Some(p), None, e);
             }
-            debcargo_bail!(
-                "Suspicious files detected, aborting. Ask on #debian-rust if you are stuck."
-            )
+            if !self.allow_suspicious {
+                debcargo_bail_kind!(
+                    ErrorKind::SuspiciousFiles,
+                    concat!(
+                        "Suspicious files detected, aborting. Ask on #debian-rust if you are stuck.\n",
+                        "If you already reviewed them, pass --allow-suspicious to go on anyway."
+                    )
+                )
+            }
+            let report = suspicious_files_report(path);
+            fs::write(&report, self.suspicious_files_report_text(&suspicious))?;
+            debcargo_warn!(
+                "Going on despite the suspicious files, as allowed; see {} for how to exclude them",
+                report.display()
+            );
         }
 
         let entries = tempdir.path().read_dir()?.collect::<io::Result<Vec<_>>>()?;
@@ -1,6 +1,7 @@
 use chrono::{DateTime, FixedOffset, Local, TimeZone};
 use failure;
 use regex::Regex;
+use semver::Version;
 
 use std::fmt;
 use std::str;
@@ -8,6 +9,69 @@ use std::str;
 pub const DEFAULT_DIST: &'static str = "UNRELEASED-FIXME-AUTOGENERATED-DEBCARGO";
 pub const COMMENT_TEAM_UPLOAD: &'static str = "  * Team upload.";
 
+// Debian release codenames and their version numbers, for ~bpoXX suffixes.
+const RELEASES: &'static [(&'static str, u32)] = &[
+    ("stretch", 9),
+    ("buster", 10),
+    ("bullseye", 11),
+    ("bookworm", 12),
+    ("trixie", 13),
+    ("forky", 14),
+    ("duke", 15),
+];
+
+/// A target for a backport, e.g. bookworm-backports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backport {
+    pub codename: String,
+    pub release: u32,
+}
+
+impl Backport {
+    /// Parse a suite given as either a codename ("bookworm") or a backports
+    /// suite ("bookworm-backports").
+    pub fn parse(suite: &str) -> Result<Self, failure::Error> {
+        let codename = suite.trim_end_matches("-backports");
+        match RELEASES.iter().find(|&&(c, _)| c == codename) {
+            Some(&(c, release)) => Ok(Backport {
+                codename: c.to_string(),
+                release: release,
+            }),
+            None => Err(format_err!(
+                "Unknown backport suite \"{}\", expected one of: {}",
+                suite,
+                RELEASES.iter().map(|&(c, _)| c).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    pub fn distribution(&self) -> String {
+        format!("{}-backports", self.codename)
+    }
+
+    pub fn changelog_item(&self) -> String {
+        format!("  * Rebuild for {}.", self.distribution())
+    }
+
+    /// Turn a Debian revision into the revision for this backport, e.g. "2"
+    /// into "2~bpo12+1". Revisions that are already for this backport are
+    /// bumped instead, e.g. "2~bpo12+1" into "2~bpo12+2".
+    pub fn revision(&self, revision: &str) -> String {
+        let marker = format!("~bpo{}+", self.release);
+        match revision.find(&marker) {
+            Some(p) => {
+                let n = revision[p + marker.len()..].parse::<u64>().unwrap_or(0);
+                format!("{}{}{}", &revision[..p], marker, n + 1)
+            }
+            None => {
+                // drop any backport suffix for another release
+                let base = revision.split("~bpo").next().unwrap();
+                format!("{}{}1", base, marker)
+            }
+        }
+    }
+}
+
 pub struct ChangelogEntry {
     pub source: String,
     pub version: String,
@@ -56,18 +120,26 @@ impl str::FromStr for ChangelogEntry {
         // see https://manpages.debian.org/testing/dpkg-dev/deb-changelog.5.en.html
         // regexes adapted from /usr/share/perl5/Dpkg/Changelog/Entry/Debian.pm
 
-        let firstline = lines[0];
+        let firstline = lines.first().cloned().unwrap_or("");
         let re1 = Regex::new(r"(?i)^(\w[-+0-9a-z.]*) \(([^\(\) \t]+)\)((?:\s+[-+0-9a-z.]+)+);(.*?)\s*$").unwrap();
-        let matches1 = re1.captures(firstline).unwrap();
-        let mut i = 1;
-        while line_is_blank(lines[i]) { i += 1; }
-        lines = lines.split_off(i);
-
-        while line_is_blank(lines.last().unwrap()) { lines.pop(); }
-        let lastline = lines.pop().unwrap();
-        while line_is_blank(lines.last().unwrap()) { lines.pop(); }
+        let matches1 = re1.captures(firstline).ok_or_else(|| format_err!(
+            "Bad changelog entry heading: \"{}\"", firstline))?;
+        if !matches1[2].contains('-') {
+            return Err(format_err!(
+                "Changelog entry version {} has no Debian revision, but debcargo \
+                 packages are not native packages", &matches1[2]));
+        }
+        lines.remove(0);
+        while lines.first().map_or(false, |l| line_is_blank(l)) { lines.remove(0); }
+        while lines.last().map_or(false, |l| line_is_blank(l)) { lines.pop(); }
+        let lastline = lines.pop().ok_or_else(|| format_err!(
+            "Changelog entry for {} has no trailer line", &matches1[2]))?;
+        while lines.last().map_or(false, |l| line_is_blank(l)) { lines.pop(); }
         let re2 = Regex::new(r"^ \-\- ((?:.*) <(?:.*)>)  ?(\w.*\S)\s*$").unwrap();
-        let matches2 = re2.captures(lastline).unwrap();
+        let matches2 = re2.captures(lastline).ok_or_else(|| format_err!(
+            "Bad changelog entry trailer: \"{}\"", lastline))?;
+        let date = DateTime::parse_from_rfc2822(&matches2[2]).map_err(|e| format_err!(
+            "Bad changelog entry date \"{}\": {}", &matches2[2], e))?;
 
         Ok(Self::new(
             matches1[1].to_string(),
@@ -75,7 +147,7 @@ impl str::FromStr for ChangelogEntry {
             matches1[3].to_string(),
             matches1[4].to_string(),
             matches2[1].to_string(),
-            DateTime::parse_from_rfc2822(&matches2[2])?,
+            date,
             lines.iter().map(|s| s.to_string()).collect()))
     }
 }
@@ -102,9 +174,11 @@ impl ChangelogEntry {
     }
 
     pub fn maintainer_name(self: &ChangelogEntry) -> String {
-        let re = Regex::new(r"^\s*(\S.*\S)\s*<.*>\s*$").unwrap();
-        let matches = re.captures(&self.maintainer).unwrap();
-        matches[1].to_string()
+        let re = Regex::new(r"^\s*(\S.*?)\s*<.*>\s*$").unwrap();
+        match re.captures(&self.maintainer) {
+            Some(matches) => matches[1].to_string(),
+            None => self.maintainer.trim().to_string(),
+        }
     }
 
     pub fn version_parts(self: &ChangelogEntry) -> (String, String) {
@@ -118,16 +192,36 @@ impl ChangelogEntry {
         re.captures(&self.version).unwrap()[1].to_string()
     }
 
-    pub fn deb_version_suffix_bump(self: &ChangelogEntry) -> String {
-        let suf = self.deb_version_suffix();
-        let re = Regex::new(r"^((?:.*\D)?)(\d*)$").unwrap();
-        let matches = re.captures(&suf).unwrap();
-        if matches[2].is_empty() {
-            format!("{}.1", &matches[1])
-        } else {
-            format!("{}{}", &matches[1], (matches[2].parse::<u64>().unwrap() + 1))
+    pub fn deb_version_suffix_bump(self: &ChangelogEntry, vendor: Option<&str>) -> String {
+        bump_revision(&self.deb_version_suffix(), vendor)
+    }
+}
+
+/// The Debian revision to use for the first upload of a new upstream version.
+/// Defaults to "1", or "0<vendor>1" for a vendor such as ubuntu.
+pub fn initial_revision(initial: Option<&str>, vendor: Option<&str>) -> String {
+    match (initial, vendor) {
+        (Some(initial), _) => initial.to_string(),
+        (None, Some(vendor)) => format!("0{}1", vendor),
+        (None, None) => "1".to_string(),
+    }
+}
+
+/// Bump a Debian revision for the next upload of the same upstream version,
+/// e.g. "1" to "2" or "1~exp1" to "1~exp2". For a vendor, a revision that
+/// doesn't have the vendor suffix yet gets it added, e.g. "1" to "1ubuntu1".
+pub fn bump_revision(revision: &str, vendor: Option<&str>) -> String {
+    if let Some(vendor) = vendor {
+        if !revision.contains(vendor) {
+            return format!("{}{}1", revision, vendor);
         }
     }
+    let re = Regex::new(r"^((?:.*\D)?)(\d*)$").unwrap();
+    let matches = re.captures(revision).unwrap();
+    match matches[2].parse::<u64>().ok().and_then(|n| n.checked_add(1)) {
+        Some(n) => format!("{}{}", &matches[1], n),
+        None => format!("{}.1", revision),
+    }
 }
 
 pub struct ChangelogIterator<'a> {
@@ -170,3 +264,26 @@ impl<'a> Iterator for ChangelogIterator<'a> {
         Some(str::from_utf8(&result).unwrap())
     }
 }
+
+/// The upstream version of the most recent released entry in a changelog
+/// that is for a different upstream version than `current` (a Debian version
+/// without epoch or revision), if any.
+pub fn previous_upstream_version(changelog: &str, current: &str) -> Option<Version> {
+    for x in ChangelogIterator::from(changelog) {
+        if x.contains(DEFAULT_DIST) {
+            continue;
+        }
+        let e = match x.parse::<ChangelogEntry>() {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        let (ups, _) = e.version_parts();
+        // strip any epoch, and undo deb_version's ~ for prereleases
+        let ups = ups.rsplit(':').next().unwrap();
+        if ups == current {
+            continue;
+        }
+        return Version::parse(&ups.replacen('~', "-", 1)).ok();
+    }
+    None
+}
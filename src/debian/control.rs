@@ -1,29 +1,43 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Write};
 use std::env::{self, VarError};
 
 use failure::Error;
 use itertools::Itertools;
+use regex::{self, Regex};
 use semver::Version;
 use textwrap::fill;
 
 use config::{Config, PackageKey, package_field_for_feature};
 use errors::*;
-use util::vec_opt_iter;
+use util::{normalize_text, vec_opt_iter};
 
 pub const RUST_MAINT: &'static str = "Debian Rust Maintainers <pkg-rust-maintainers@alioth-lists.debian.net>";
 
+/// Default templates for the Vcs-* fields, following the layout of the Debian
+/// Rust team's debcargo-conf repository. `$crate` is replaced with the source
+/// package name without the "rust-" prefix.
+pub const VCS_GIT_TEMPLATE: &'static str =
+    "https://salsa.debian.org/rust-team/debcargo-conf.git [src/$crate]";
+pub const VCS_BROWSER_TEMPLATE: &'static str =
+    "https://salsa.debian.org/rust-team/debcargo-conf/tree/master/src/$crate";
+
 pub struct Source {
     name: String,
+    pkgbase: String,
     section: String,
     priority: String,
     maintainer: String,
     uploaders: Vec<String>,
     standards: String,
+    requires_root: String,
     build_deps: Vec<String>,
     vcs_git: String,
     vcs_browser: String,
     homepage: String,
+    testsuite: String,
     x_cargo: String,
+    extra_lines: Vec<String>,
 }
 
 pub struct Package {
@@ -45,6 +59,8 @@ pub struct PkgTest {
     name: String,
     crate_name: String,
     version: Version,
+    dh_cargo_version: String,
+    targets: String,
     extra_test_args: Vec<String>,
     depends: Vec<String>,
     extra_restricts: Vec<String>,
@@ -61,6 +77,9 @@ impl fmt::Display for Source {
             writeln!(f, "Uploaders:\n {}", self.uploaders.join(",\n "))?;
         }
         writeln!(f, "Standards-Version: {}", self.standards)?;
+        if !self.requires_root.is_empty() {
+            writeln!(f, "Rules-Requires-Root: {}", self.requires_root)?;
+        }
         writeln!(f, "Vcs-Git: {}", self.vcs_git)?;
         writeln!(f, "Vcs-Browser: {}", self.vcs_browser)?;
 
@@ -68,10 +87,18 @@ impl fmt::Display for Source {
             writeln!(f, "Homepage: {}", self.homepage)?;
         }
 
+        if !self.testsuite.is_empty() {
+            writeln!(f, "Testsuite: {}", self.testsuite)?;
+        }
+
         if !self.x_cargo.is_empty() {
             writeln!(f, "X-Cargo-Crate: {}", self.x_cargo)?;
         }
 
+        for line in &self.extra_lines {
+            writeln!(f, "{}", line)?;
+        }
+
         Ok(())
     }
 }
@@ -108,19 +135,24 @@ impl fmt::Display for Package {
 
 impl fmt::Display for PkgTest {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(
+        write!(
             f,
-            "Test-Command: /usr/share/cargo/bin/cargo-auto-test {} {} --all-targets {}",
+            "Test-Command: /usr/share/cargo/bin/cargo-auto-test {} {} {}",
             self.crate_name,
             self.version,
-            self.extra_test_args.join(" ")
+            self.targets,
         )?;
+        for arg in &self.extra_test_args {
+            write!(f, " {}", arg)?;
+        }
+        writeln!(f)?;
         if self.depends.is_empty() {
-            writeln!(f, "Depends: dh-cargo (>= 18), {}", &self.name)?;
+            writeln!(f, "Depends: dh-cargo (>= {}), {}", self.dh_cargo_version, &self.name)?;
         } else {
             writeln!(
                 f,
-                "Depends: dh-cargo (>= 18), {}, {}",
+                "Depends: dh-cargo (>= {}), {}, {}",
+                self.dh_cargo_version,
                 self.depends.join(", "),
                 &self.name
             )?;
@@ -145,6 +177,7 @@ impl Source {
         upstream_name: &str,
         home: &str,
         lib: bool,
+        testsuite: bool,
         uploaders: Vec<String>,
         build_deps: Vec<String>,
     ) -> Result<Source> {
@@ -155,10 +188,8 @@ impl Source {
         let section = if lib { "rust" } else { "FIXME-(source.section)" };
         let priority = "optional".to_string();
         let maintainer = RUST_MAINT.to_string();
-        let vcs_browser = format!(
-            "https://salsa.debian.org/rust-team/debcargo-conf/tree/master/src/{}", pkgbase);
-        let vcs_git = format!(
-            "https://salsa.debian.org/rust-team/debcargo-conf.git [src/{}]", pkgbase);
+        let vcs_browser = vcs_from_template(VCS_BROWSER_TEMPLATE, &pkgbase);
+        let vcs_git = vcs_from_template(VCS_GIT_TEMPLATE, &pkgbase);
 
         let cargo_crate = if upstream_name != upstream_name.replace('_', "-") {
             upstream_name.to_string()
@@ -167,16 +198,20 @@ impl Source {
         };
         Ok(Source {
             name: format!("rust-{}", pkgbase),
+            pkgbase: pkgbase,
             section: section.to_string(),
             priority: priority,
             maintainer: maintainer,
             uploaders: uploaders,
             standards: "4.2.0".to_string(),
+            requires_root: "no".to_string(),
             build_deps: build_deps,
             vcs_git: vcs_git,
             vcs_browser: vcs_browser,
             homepage: home.to_string(),
+            testsuite: if testsuite { "autopkgtest-pkg-rust".to_string() } else { "".to_string() },
             x_cargo: cargo_crate,
+            extra_lines: vec![],
         })
     }
 
@@ -184,6 +219,24 @@ impl Source {
         self.name.as_str()
     }
 
+    /// Replace the version constraints of the given Build-Depends, keeping
+    /// any architecture or profile restrictions. An empty constraint drops
+    /// the version constraint entirely.
+    pub fn set_build_depends_versions(&mut self, versions: &HashMap<String, String>) {
+        let re = Regex::new(r"^(\S+)(?:\s*\([^)]*\))?(.*)$").unwrap();
+        for dep in self.build_deps.iter_mut() {
+            let new_dep = match re.captures(dep) {
+                Some(caps) => match versions.get(&caps[1]) {
+                    Some(v) if v.is_empty() => format!("{}{}", &caps[1], &caps[2]),
+                    Some(v) => format!("{} ({}){}", &caps[1], v, &caps[2]),
+                    None => continue,
+                },
+                None => continue,
+            };
+            *dep = new_dep;
+        }
+    }
+
     pub fn apply_overrides(&mut self, config: &Config) {
         if let Some(section) = config.section() {
             self.section = section.to_string();
@@ -193,6 +246,10 @@ impl Source {
             self.standards = policy.to_string();
         }
 
+        if let Some(requires_root) = config.requires_root() {
+            self.requires_root = requires_root.to_string();
+        }
+
         self.build_deps.extend(vec_opt_iter(config.build_depends()).map(String::to_string));
         let bdeps_ex = config.build_depends_excludes().map(Vec::as_slice).unwrap_or(&[]);
         self.build_deps.retain(|x| !bdeps_ex.contains(x));
@@ -202,12 +259,14 @@ impl Source {
         }
 
         if let Some(vcs_git) = config.vcs_git() {
-            self.vcs_git = vcs_git.to_string();
+            self.vcs_git = vcs_from_template(vcs_git, &self.pkgbase);
         }
 
         if let Some(vcs_browser) = config.vcs_browser() {
-            self.vcs_browser = vcs_browser.to_string();
+            self.vcs_browser = vcs_from_template(vcs_browser, &self.pkgbase);
         }
+
+        self.extra_lines.extend(vec_opt_iter(config.source_extra_lines()).map(String::to_string));
     }
 }
 
@@ -216,6 +275,7 @@ impl Package {
         basename: &str,
         name_suffix: Option<&str>,
         version: &Version,
+        epoch: Option<u32>,
         upstream_name: &str,
         summary: Option<&str>,
         description: Option<&str>,
@@ -225,16 +285,14 @@ impl Package {
         f_provides: Vec<&str>,
         f_recommends: Vec<&str>,
         f_suggests: Vec<&str>,
+        naming: &PackageNaming,
     ) -> Result<Package> {
         let pkgbase = match name_suffix {
             None => format!("{}", basename),
             Some(suf) => format!("{}{}", basename, suf),
         };
         let deb_feature2 = &|p: &str, f: &str| {
-            format!("{} (= ${{binary:Version}})", match f {
-                "" => deb_name(p),
-                _ => deb_feature_name(p, f),
-            })
+            format!("{} (= ${{binary:Version}})", naming.name_for(p, f))
         };
         let deb_feature = &|f: &str| deb_feature2(&pkgbase, &f);
 
@@ -270,13 +328,13 @@ impl Package {
         depends.extend(o_deps);
 
         let summary_default = format!("Rust crate \"{}\"", upstream_name);
-        let summary = summary.unwrap_or(&summary_default);
+        let summary = normalize_text(summary.unwrap_or(&summary_default));
         let short_desc = match feature {
             Some(f) => format!("{} - feature \"{}\"", summary, f),
             None => format!("{} - Rust source code", summary),
         };
 
-        let long_desc = description.unwrap_or("");
+        let long_desc = normalize_text(description.unwrap_or(""));
         let boilerplate = match feature {
             None => format!(
                 concat!(
@@ -298,10 +356,7 @@ impl Package {
         };
 
         Ok(Package {
-            name: match feature {
-                None => deb_name(&pkgbase),
-                Some(f) => deb_feature_name(&pkgbase, f),
-            },
+            name: naming.name_for(&pkgbase, feature.unwrap_or("")),
             arch: "any".to_string(),
             // This is the best but not ideal option for us.
             //
@@ -336,9 +391,10 @@ impl Package {
                 (Some(_), None) => {
                     let mut v = version.clone();
                     v.increment_patch();
+                    let v = with_epoch(epoch, &deb_version(&v));
                     vec![
-                        format!("Replaces: {} (<< {}-~~)", deb_name(basename), deb_version(&v)),
-                        format!("Breaks: {} (<< {}-~~)", deb_name(basename), deb_version(&v)),
+                        format!("Replaces: {} (<< {}-~~)", naming.name(basename), v),
+                        format!("Breaks: {} (<< {}-~~)", naming.name(basename), v),
                     ]
                 },
                 (_, _) => vec![],
@@ -346,6 +402,33 @@ impl Package {
         })
     }
 
+    /// A metapackage depending on all the packages of a library, given by
+    /// their features.
+    pub fn new_all_features(
+        basename: &str,
+        name_suffix: Option<&str>,
+        version: &Version,
+        epoch: Option<u32>,
+        upstream_name: &str,
+        summary: Option<&str>,
+        features: Vec<&str>,
+        naming: &PackageNaming,
+    ) -> Result<Package> {
+        let mut package = Package::new(
+            basename, name_suffix, version, epoch, upstream_name, summary, None,
+            Some(ALL_FEATURES), features, vec![], vec![], vec![], vec![], naming)?;
+        let summary_default = format!("Rust crate \"{}\"", upstream_name);
+        package.summary = format!("{} - all features", normalize_text(summary.unwrap_or(&summary_default)));
+        package.boilerplate = fill(&format!(
+            concat!(
+                "This metapackage enables all features of the Rust {} crate, ",
+                "by pulling in all of its packages."
+            ),
+            upstream_name
+        ), 79);
+        Ok(package)
+    }
+
     pub fn new_bin(
         basename: &str,
         name_suffix: Option<&str>,
@@ -362,12 +445,12 @@ impl Package {
         };
         let short_desc = match summary {
             None => format!("Binaries built from the Rust {} crate", upstream_name),
-            Some(s) => s.to_string(),
+            Some(s) => normalize_text(s),
         };
 
         let long_desc = match description {
             None => "".to_string(),
-            Some(s) => s.to_string(),
+            Some(s) => fill(&normalize_text(s), 79),
         };
 
         provides.push("${cargo:Provides}".to_string());
@@ -402,6 +485,29 @@ impl Package {
         self.name.as_str()
     }
 
+    /// Recommend and suggest other packages, beyond the generated relations.
+    pub fn add_relations(&mut self, recommends: Vec<String>, suggests: Vec<String>) {
+        self.recommends.extend(recommends);
+        self.suggests.extend(suggests);
+    }
+
+    /// Point relations on other packages from the same source at their new
+    /// names, for packages that were renamed in the config.
+    pub fn rename_relations(&mut self, renames: &BTreeMap<String, String>) {
+        for rel in self.depends.iter_mut()
+            .chain(self.recommends.iter_mut())
+            .chain(self.suggests.iter_mut()) {
+            let renamed = {
+                let (name, rest) = rel.split_at(rel.find(' ').unwrap_or(rel.len()));
+                match renames.get(name) {
+                    Some(new_name) => format!("{}{}", new_name, rest),
+                    None => continue,
+                }
+            };
+            *rel = renamed;
+        }
+    }
+
     fn write_description(&self, out: &mut fmt::Formatter) -> fmt::Result {
         writeln!(out, "Description: {}", self.summary)?;
         let description = [&self.description, &self.boilerplate].iter().filter_map(|x| {
@@ -422,17 +528,33 @@ impl Package {
     }
 
     pub fn apply_overrides(&mut self, config: &Config, key: PackageKey, f_provides: Vec<&str>) {
+        if let Some(name) = config.package_name(key) {
+            if name != self.name {
+                match key {
+                    PackageKey::Bin => (),
+                    // other crates still depend on the usual name
+                    _ => self.provides.push(format!("{} (= ${{binary:Version}})", self.name)),
+                }
+                self.name = name.to_string();
+            }
+        }
+
         if let Some(section) = config.package_section(key) {
             self.section = Some(section.to_string());
         }
 
         if let Some((s, d)) = config.package_summary(key) {
             if !s.is_empty() {
-                self.summary = s.to_string();
+                self.summary = normalize_text(s);
             }
 
             if !d.is_empty() {
-                self.description = d.to_string();
+                match key {
+                    // says what the feature is for, instead of the generic
+                    // boilerplate, below the description of the crate
+                    PackageKey::FeatureLib(_) => self.boilerplate = normalize_text(d),
+                    _ => self.description = normalize_text(d),
+                }
             }
         }
 
@@ -454,6 +576,7 @@ impl PkgTest {
         name: &str,
         crate_name: &str,
         version: &Version,
+        dh_cargo_version: &str,
         extra_test_args: Vec<&str>,
         depends: &Vec<String>,
         extra_restricts: Vec<&str>,
@@ -462,11 +585,41 @@ impl PkgTest {
             name: name.to_string(),
             crate_name: crate_name.to_string(),
             version: version.clone(),
+            dh_cargo_version: dh_cargo_version.to_string(),
+            targets: "--all-targets".to_string(),
             extra_test_args: extra_test_args.iter().map(|x| x.to_string()).collect(),
             depends: depends.clone(),
             extra_restricts: extra_restricts.iter().map(|x| x.to_string()).collect(),
         })
     }
+
+    /// A test that only builds the examples, with all features, against the
+    /// installed library packages; i.e. uses the crate the way users would.
+    pub fn examples(
+        crate_name: &str,
+        version: &Version,
+        dh_cargo_version: &str,
+        all_features_args: Vec<&str>,
+        depends: &Vec<String>,
+        extra_restricts: Vec<&str>,
+    ) -> Result<PkgTest> {
+        let mut test = PkgTest::new(
+            "@",
+            crate_name,
+            version,
+            dh_cargo_version,
+            all_features_args,
+            depends,
+            extra_restricts,
+        )?;
+        test.targets = "--examples".to_string();
+        Ok(test)
+    }
+}
+
+/// Fills in a Vcs-* template, see `VCS_GIT_TEMPLATE`.
+pub fn vcs_from_template(template: &str, pkgbase: &str) -> String {
+    template.replace("$crate", pkgbase)
 }
 
 /// Translates a semver into a Debian version. Omits the build metadata, and uses a ~ before the
@@ -479,16 +632,142 @@ pub fn deb_version(v: &Version) -> String {
     s
 }
 
-fn deb_name(name: &str) -> String {
-    format!("librust-{}-dev", name.replace('_', "-"))
+/// Prefixes a Debian version with an epoch, if there is one.
+pub fn with_epoch(epoch: Option<u32>, version: &str) -> String {
+    match epoch {
+        Some(e) => format!("{}:{}", e, version),
+        None => version.to_string(),
+    }
+}
+
+/// Default templates for the names of library packages. `$crate` is replaced
+/// with the crate name with dashes, followed by any semver suffix, and
+/// `$feature` with the feature name as mangled by `deb_feature`.
+pub const PACKAGE_NAME_TEMPLATE: &'static str = "librust-$crate-dev";
+pub const FEATURE_PACKAGE_NAME_TEMPLATE: &'static str = "librust-$crate+$feature-dev";
+
+/// How library packages are named, both ours and those of our dependencies.
+#[derive(Debug, Clone)]
+pub struct PackageNaming {
+    base: String,
+    feature: String,
+}
+
+impl Default for PackageNaming {
+    fn default() -> Self {
+        PackageNaming {
+            base: PACKAGE_NAME_TEMPLATE.to_string(),
+            feature: FEATURE_PACKAGE_NAME_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl PackageNaming {
+    pub fn new(base: &str, feature: &str) -> Result<Self> {
+        if !base.contains("$crate") || base.contains("$feature") {
+            debcargo_bail!("Package name template {:?} must contain $crate and not $feature", base);
+        }
+        if !feature.contains("$crate") || !feature.contains("$feature") {
+            debcargo_bail!("Feature package name template {:?} must contain $crate and $feature", feature);
+        }
+        Ok(PackageNaming { base: base.to_string(), feature: feature.to_string() })
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        PackageNaming::new(
+            config.package_name_template.as_ref().map_or(PACKAGE_NAME_TEMPLATE, String::as_str),
+            config.feature_package_name_template.as_ref().map_or(FEATURE_PACKAGE_NAME_TEMPLATE, String::as_str),
+        )
+    }
+
+    /// The package for `name`, which may end with a semver suffix.
+    pub fn name(&self, name: &str) -> String {
+        self.base.replace("$crate", &name.replace('_', "-"))
+    }
+
+    pub fn feature_name(&self, name: &str, feature: &str) -> String {
+        self.feature
+            .replace("$feature", &deb_feature(feature))
+            .replace("$crate", &name.replace('_', "-"))
+    }
+
+    /// The feature packages of `name` in general, for messages.
+    pub fn feature_name_pattern(&self, name: &str) -> String {
+        self.feature
+            .replace("$feature", "FEATURE")
+            .replace("$crate", &name.replace('_', "-"))
+    }
+
+    /// `name` for the empty feature, otherwise `feature_name`.
+    pub fn name_for(&self, name: &str, feature: &str) -> String {
+        match feature {
+            "" => self.name(name),
+            f => self.feature_name(name, f),
+        }
+    }
+
+    /// The crate, with dashes and any semver suffix, and the mangled feature
+    /// of a package named by these templates, e.g. ("foo-1", Some("std")) for
+    /// librust-foo-1+std-dev; None if the package isn't named by them.
+    pub fn parse(&self, package: &str) -> Option<(String, Option<String>)> {
+        let pattern = |template: &str| {
+            let re = regex::escape(template)
+                .replace(r"\$crate", "(?P<crate>[a-z0-9.-]+)")
+                .replace(r"\$feature", "(?P<feature>[a-z0-9.-]+)");
+            Regex::new(&format!("^{}$", re)).unwrap()
+        };
+        // the feature template first, as the base one may match its names too
+        if let Some(c) = pattern(&self.feature).captures(package) {
+            return Some((c["crate"].to_string(), Some(c["feature"].to_string())));
+        }
+        pattern(&self.base).captures(package).map(|c| (c["crate"].to_string(), None))
+    }
 }
 
 pub fn deb_feature_name(name: &str, feature: &str) -> String {
-    format!(
-        "librust-{}+{}-dev",
-        name.replace('_', "-"),
-        feature.replace('_', "-").to_lowercase()
-    )
+    PackageNaming::default().feature_name(name, feature)
+}
+
+/// Mangles a cargo feature name into something usable as part of a Debian
+/// package name. Debian package names may only contain lowercase letters,
+/// digits, and the characters `+ - .`; we also can't allow `+` since it
+/// separates the crate name from the feature name. Everything else becomes
+/// `-`, so e.g. `Foo_bar+baz` becomes `foo-bar-baz`.
+pub fn deb_feature(feature: &str) -> String {
+    feature
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '.' => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+/// The pseudo-feature of the metapackage for all features, which no real
+/// feature may be mangled into.
+pub const ALL_FEATURES: &'static str = "all-features";
+
+/// Checks that no two features of a crate are mangled by `deb_feature` into
+/// the same name, which would give us two binary packages with the same name.
+pub fn check_deb_feature_collisions<'a, I>(features: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut seen: BTreeMap<String, &str> = BTreeMap::new();
+    for feature in features {
+        let mangled = deb_feature(feature);
+        if let Some(other) = seen.insert(mangled.clone(), feature) {
+            debcargo_bail!(
+                "Features \"{}\" and \"{}\" would both be packaged as \"{}\"; \
+                 this crate cannot be packaged as-is",
+                other,
+                feature,
+                mangled
+            );
+        }
+    }
+    Ok(())
 }
 
 /// Retrieve one of a series of environment variables, and provide a friendly error message for
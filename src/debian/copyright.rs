@@ -6,172 +6,45 @@ use tempfile;
 use textwrap::fill;
 use git2::Repository;
 
-use std::fmt;
 use std::fs;
 use std::env;
 use std::cmp::Ordering;
-use std::path::Path;
-use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{BufRead, BufReader, Read};
 
 use errors::*;
 use debian::control::RUST_MAINT;
-
-const DEB_COPYRIGHT_FORMAT: &'static str = "\
-    https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/";
-
-macro_rules! format_para {
-    ($fmt: expr, $text:expr) => {
-        {
-            for line in $text.lines() {
-                let line = line.trim_right();
-                if line.is_empty() {
-                    writeln!($fmt, " .")?;
-                } else {
-                    writeln!($fmt, " {}", line)?;
-                }
-            }
-            write!($fmt, "")
-        }
-    }
-}
-
-struct UpstreamInfo {
-    name: String,
-    contacts: Vec<String>,
-    source: String,
-}
-
-#[derive(Clone)]
-pub struct Files {
-    files: String,
-    copyright: Vec<String>,
-    license: String,
-    comment: String,
-}
-
-#[derive(Clone)]
-struct License {
-    name: String,
-    text: String,
-}
-
-pub struct DebCopyright {
-    format: String,
-    upstream: UpstreamInfo,
-    files: Vec<Files>,
-    licenses: Vec<License>,
-}
-
-impl fmt::Display for DebCopyright {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Format: {}\n", self.format)?;
-        write!(f, "{}", self.upstream)?;
-
-        for file in &self.files {
-            write!(f, "\n{}", file)?;
-        }
-
-        for license in &self.licenses {
-            write!(f, "\n{}", license)?;
-        }
-
-        Ok(())
-    }
+use debian::dep5::{Copyright, Files, Header, License};
+use debian::syntax::parse_deb822;
+use util::normalize_text;
+use vendor::VendoredCrate;
+
+/// How much d/copyright guesses from the sources, beyond what Cargo.toml says.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyrightGuess {
+    /// Only a skeleton from Cargo.toml, to be filled in by hand.
+    Off,
+    /// Also the copyright notices found in the source files.
+    Normal,
+    /// Also the copyright years from the upstream git history. Might be slow.
+    Harder,
 }
 
-impl DebCopyright {
-    fn new(u: UpstreamInfo, f: &[Files], l: &[License]) -> DebCopyright {
-        DebCopyright {
-            format: DEB_COPYRIGHT_FORMAT.to_string(),
-            upstream: u,
-            files: f.to_vec(),
-            licenses: l.to_vec(),
-        }
-    }
-}
-
-impl fmt::Display for UpstreamInfo {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Upstream-Name: {}\n", self.name)?;
-        write!(f, "Upstream-Contact:")?;
-        if self.contacts.len() > 1 {
-            write!(f, "\n")?;
-        }
-        for contact in &self.contacts {
-            write!(f, " {}\n", contact)?;
-        }
-        if !self.source.is_empty() {
-            write!(f, "Source: {}\n", self.source)?;
-        }
-
-        Ok(())
+impl Default for CopyrightGuess {
+    fn default() -> Self {
+        CopyrightGuess::Normal
     }
 }
 
-impl UpstreamInfo {
-    fn new(name: String, authors: &[String], repo: &str) -> UpstreamInfo {
-        assert!(authors.len() > 0);
-        UpstreamInfo {
-            name: name,
-            contacts: authors.to_vec(),
-            source: repo.to_string(),
-        }
-    }
-}
-
-impl fmt::Display for Files {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Files: {}\n", self.files)?;
-        write!(f, "Copyright:")?;
-        if self.copyright.len() > 1 {
-            write!(f, "\n")?;
-        }
-        for copyright in &self.copyright {
-            write!(f, " {}\n", copyright)?;
-        }
-        write!(f, "License: {}\n", self.license)?;
-        if !self.comment.is_empty() {
-            write!(f, "Comment:\n")?;
-            format_para!(f, &self.comment)?;
-        }
-        Ok(())
-    }
-}
-
-impl Files {
-    pub fn new<T: ToString>(name: &str, notice: &[T], license: &str, comment: &str) -> Files {
-        assert!(notice.len() > 0);
-        Files {
-            files: name.to_string(),
-            copyright: notice.iter().map(|s| s.to_string()).collect(),
-            license: license.to_string(),
-            comment: comment.to_string(),
-        }
-    }
-
-    pub fn files(&self) -> &str {
-        &self.files
-    }
-
-    pub fn license(&self) -> &str {
-        &self.license
-    }
-}
-
-impl fmt::Display for License {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "License: {}\n", self.name)?;
-        format_para!(f, &self.text)?;
-        Ok(())
-    }
-}
-
-impl License {
-    fn new(name: String, text: String) -> License {
-        License {
-            name: name,
-            text: text,
+impl CopyrightGuess {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(CopyrightGuess::Off),
+            "normal" => Ok(CopyrightGuess::Normal),
+            "harder" => Ok(CopyrightGuess::Harder),
+            _ => debcargo_bail!("copyright guess mode {} is not one of off, normal or harder", s),
         }
     }
 }
@@ -188,7 +61,7 @@ macro_rules! default_files {
     }}
 }
 
-fn gen_files(debsrcdir: &Path) -> Result<Vec<Files>> {
+fn gen_files(debsrcdir: &Path, skip_vendor: bool) -> Result<Vec<Files>> {
     let mut copyright_notices = BTreeMap::new();
 
     let copyright_notice_re = try!(regex::Regex::new(
@@ -205,10 +78,15 @@ fn gen_files(debsrcdir: &Path) -> Result<Vec<Files>> {
     // current_dir then we end up having absolute path from user home directory,
     // which again messes debian/copyright.
     // Use of . creates paths in format ./src/ which is acceptable.
-    for entry in walkdir::WalkDir::new(".").sort_by(|a,b| a.file_name().cmp(b.file_name())) {
+    // Vendored crates get a paragraph each instead, see vendored_files.
+    let walker = walkdir::WalkDir::new(".")
+        .sort_by(|a,b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| !(skip_vendor && e.depth() == 1 && e.file_name() == "vendor"));
+    for entry in walker {
         let entry = try!(entry);
         if entry.file_type().is_file() {
-            let copyright_file = entry.path().to_str().unwrap().to_string();
+            let copyright_file = entry.path().to_string_lossy().into_owned();
             let file = try!(fs::File::open(entry.path()));
             let reader = BufReader::new(file);
             for line in reader.lines() {
@@ -245,9 +123,19 @@ fn gen_files(debsrcdir: &Path) -> Result<Vec<Files>> {
     Ok(notices)
 }
 
+/// The names of the licenses in a Cargo.toml license expression, e.g. "MIT"
+/// and "Apache-2.0" in "MIT OR Apache-2.0" or "MIT/Apache-2.0". Both the
+/// generated d/copyright and its check split licenses this way.
+pub fn license_names(license: &str) -> Vec<&str> {
+    let sep = regex::Regex::new(r"(?i)\s+(?:or|and)\s+|/|,").unwrap();
+    sep.split(license)
+        .map(|l| l.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace()))
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
 fn get_licenses(license: &str) -> Result<Vec<License>> {
     let mut licenses = BTreeMap::new();
-    let sep = regex::Regex::new(r"(?i:(or|/|and))")?;
     let known_licenses = vec![
         ("agpl-3.0", include_str!("licenses/AGPL-3.0")),
         ("apache-2.0", include_str!("licenses/Apache-2.0")),
@@ -267,28 +155,72 @@ fn get_licenses(license: &str) -> Result<Vec<License>> {
         ("zlib", include_str!("licenses/Zlib")),
     ].into_iter().collect::<BTreeMap<_, _>>();
 
-    let lses: Vec<&str> = sep.split(license).filter(|s| s.len() != 0).collect();
-    for ls in lses {
-        let lname = ls.trim().to_lowercase().trim_right_matches('+').to_string();
-        let text = match known_licenses.get(lname.as_str()) {
+    for ls in license_names(license) {
+        let lname = ls.to_lowercase();
+        // the SPDX forms for "this version or any later one" and "only this
+        // version" have the same text
+        let lname = lname
+            .trim_end_matches('+')
+            .trim_end_matches("-or-later")
+            .trim_end_matches("-only");
+        let text = match known_licenses.get(lname) {
             Some(s) => s.to_string(),
             None => "FIXME (overlay): Unrecognized crate license, please find the \
                 full license text in the rest of the crate source code and \
                 copy-paste it here".to_string()
         };
-        licenses.insert(ls.trim().to_string(), text);
+        licenses.insert(ls.to_string(), text);
     }
 
     let mut lblocks: Vec<License> = Vec::new();
     if !licenses.is_empty() {
         for (l, t) in licenses {
-            lblocks.push(License::new(l, t));
+            lblocks.push(License::new(&l, Some(&t)));
         }
     }
 
     Ok(lblocks)
 }
 
+fn normalize_license(license: &str) -> String {
+    license.trim()
+        .replace("/", " or ")
+        .replace(" OR ", " or ")
+        .replace(" AND ", " and ")
+}
+
+/// Paragraphs for the crates vendored into an application's source package,
+/// and the texts of their licenses.
+fn vendored_files(crates: &[VendoredCrate]) -> Result<(Vec<Files>, Vec<License>)> {
+    let mut files = Vec::new();
+    let mut licenses = Vec::new();
+    for c in crates {
+        let license = match (&c.license, &c.license_file) {
+            (&Some(ref l), _) => {
+                licenses.extend(get_licenses(l)?);
+                normalize_license(l)
+            }
+            (&None, &Some(ref f)) => format!("UNKNOWN-LICENSE; FIXME (overlay): see vendor/{}/{}", c.dir, f),
+            (&None, &None) => "UNKNOWN-LICENSE; FIXME (overlay)".to_string(),
+        };
+        let notice = if c.authors.is_empty() {
+            vec!["FIXME (overlay) UNKNOWN-YEARS UNKNOWN-AUTHORS".to_string()]
+        } else {
+            c.authors
+                .iter()
+                .map(|a| format!("FIXME (overlay) UNKNOWN-YEARS {}", normalize_text(a)))
+                .collect()
+        };
+        files.push(Files::new(
+            &format!("vendor/{}/*", c.dir),
+            &notice,
+            &license,
+            &format!("Vendored crate {} {}", c.name, c.version),
+        ));
+    }
+    Ok((files, licenses))
+}
+
 fn copyright_fromgit(repo_url: &str) -> Result<String> {
     let tempdir = tempfile::Builder::new().prefix("debcargo").tempdir_in(".")?;
     let repo = Repository::clone(repo_url, tempdir.path())?;
@@ -321,21 +253,107 @@ fn copyright_fromgit(repo_url: &str) -> Result<String> {
     Ok(notice)
 }
 
+/// Whether a pattern of a Files field matches a path relative to the source
+/// root; `*` matches any characters including `/`, `?` a single one, and a
+/// backslash escapes them.
+pub fn files_pattern_matches(pattern: &str, path: &Path) -> bool {
+    // tolerated by lintian, and what gen_files writes
+    let pattern = pattern.trim_start_matches("./");
+    let mut re = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '\\' => match chars.next() {
+                Some(e) => re.push_str(&regex::escape(&e.to_string())),
+                None => re.push_str(r"\\"),
+            },
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    regex::Regex::new(&re)
+        .map(|re| re.is_match(&path.to_string_lossy()))
+        .unwrap_or(false)
+}
+
+/// The files, relative to the source root, that no Files paragraph of a
+/// d/copyright covers. A copyright file that doesn't parse covers nothing,
+/// but its syntax errors are reported elsewhere.
+pub fn unmatched_files(copyright: &str, files: &[PathBuf]) -> Vec<PathBuf> {
+    let patterns = match parse_deb822("copyright", copyright) {
+        Ok(paragraphs) => paragraphs
+            .iter()
+            .filter_map(|p| p.get("Files"))
+            .flat_map(|f| f.value.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        Err(_) => return Vec::new(),
+    };
+    files
+        .iter()
+        .filter(|f| !patterns.iter().any(|p| files_pattern_matches(p, f)))
+        .cloned()
+        .collect()
+}
+
+/// What changed between the d/copyright of an overlay and the one debcargo
+/// generates now, e.g. for a new upstream version, that the maintainer should
+/// carry over: the license of the crate, the licenses that are given in
+/// full, and the excluded files.
+pub fn copyright_changes(old: &Copyright, new: &Copyright) -> Vec<String> {
+    let mut changes = Vec::new();
+    let crate_license = |c: &Copyright| {
+        c.files.iter().find(|f| f.files == ["*"]).map(|f| f.license.name.clone())
+    };
+    if let (Some(o), Some(n)) = (crate_license(old), crate_license(new)) {
+        if o != n {
+            changes.push(format!("the license of the crate is now {}, was {}", n, o));
+        }
+    }
+    let names = |c: &Copyright| c.licenses.iter().map(|l| l.name.clone()).collect::<BTreeSet<_>>();
+    let (old_names, new_names) = (names(old), names(new));
+    for l in new_names.difference(&old_names) {
+        changes.push(format!("add a License paragraph for {}", l));
+    }
+    for l in old_names.difference(&new_names) {
+        changes.push(format!("check whether the License paragraph for {} is still needed", l));
+    }
+    if old.header.files_excluded != new.header.files_excluded {
+        changes.push(format!("set Files-Excluded to: {}", new.header.files_excluded.join(" ")));
+    }
+    changes
+}
+
 pub fn debian_copyright(
     package: &package::Package,
     srcdir: &Path,
     manifest: &manifest::Manifest,
     uploaders: &Vec<&str>,
     year_range: (i32, i32),
-    guess_harder: bool,
-) -> Result<DebCopyright> {
+    guess: CopyrightGuess,
+    vendored: &[VendoredCrate],
+    files_excluded: &[String],
+    owners: &[String],
+) -> Result<Copyright> {
     let meta = manifest.metadata().clone();
+    // without authors in Cargo.toml, the crates.io owners are the best guess
+    let authors = if meta.authors.is_empty() {
+        owners.to_vec()
+    } else {
+        meta.authors.clone()
+    };
     let repository = match meta.repository {
         None => "",
         Some(ref r) => r,
     };
 
-    let upstream = UpstreamInfo::new(manifest.name().to_string(), &meta.authors, repository);
+    let mut header = Header::new(&manifest.name());
+    header.upstream_contact = authors.iter().map(|a| normalize_text(a)).collect();
+    if !repository.is_empty() {
+        header.source = Some(repository.to_string());
+    }
+    header.files_excluded = files_excluded.to_vec();
 
     let mut licenses: Vec<License> = Vec::new();
     let mut crate_license: String = "".to_string();
@@ -345,19 +363,37 @@ pub fn debian_copyright(
         let mut text = Vec::new();
         fs::File::open(license_file)?.read_to_end(&mut text)?;
         licenses.reserve(1);
-        let stext = String::from_utf8(text)?;
-        licenses.push(License::new("UNKNOWN-LICENSE; FIXME (overlay)".to_string(), stext));
+        let stext = match String::from_utf8(text) {
+            Ok(s) => s,
+            Err(e) => {
+                debcargo_warn!(
+                    "License file {} is not valid UTF-8, replacing invalid bytes",
+                    license_file_name
+                );
+                String::from_utf8_lossy(e.as_bytes()).into_owned()
+            }
+        };
+        let stext = normalize_text(&stext);
+        licenses.push(License::new("UNKNOWN-LICENSE; FIXME (overlay)", Some(&stext)));
     } else if let Some(ref license) = meta.license {
-        licenses = get_licenses(license).unwrap();
-        crate_license = license.trim()
-            .replace("/", " or ")
-            .replace(" OR ", " or ")
-            .replace(" AND ", " and ");
+        licenses = get_licenses(license)?;
+        crate_license = normalize_license(license);
     } else {
         debcargo_bail!("Crate has no license or license_file");
     }
 
-    let mut files = gen_files(srcdir)?;
+    let mut files = if guess == CopyrightGuess::Off {
+        Vec::new()
+    } else {
+        gen_files(srcdir, !vendored.is_empty())?
+    };
+    let (vendored_files, vendored_licenses) = vendored_files(vendored)?;
+    files.extend(vendored_files);
+    for l in vendored_licenses {
+        if !licenses.iter().any(|k| k.name == l.name) {
+            licenses.push(l);
+        }
+    }
 
     let (y0, y1) = year_range;
     let years = if y0 == y1 {
@@ -372,7 +408,7 @@ pub fn debian_copyright(
 
     // Insert catch all block as the first block of copyright file. Capture
     // copyright notice from git log of the upstream repository.
-    let years = if guess_harder && !repository.is_empty() {
+    let years = if guess == CopyrightGuess::Harder && !repository.is_empty() {
         match copyright_fromgit(repository) {
             Ok(x) => x,
             Err(e) => {
@@ -387,12 +423,13 @@ pub fn debian_copyright(
     } else {
         "FIXME (overlay) UNKNOWN-YEARS".to_string()
     };
-    let notice = match meta.authors.len() {
-        1 => vec![format!("{} {}", years, &meta.authors[0])],
+    let notice = match authors.len() {
+        0 => vec![format!("{} UNKNOWN-AUTHORS", years)],
+        1 => vec![format!("{} {}", years, normalize_text(&authors[0]))],
         _ => {
-            meta.authors
+            authors
                 .iter()
-                .map(|s| format!("{} {}", years, s))
+                .map(|s| format!("{} {}", years, normalize_text(s)))
                 .collect()
         }
     };
@@ -407,5 +444,9 @@ pub fn debian_copyright(
         Files::new("*", notice.as_slice(), &crate_license, &fill(comment, 79)),
     );
 
-    Ok(DebCopyright::new(upstream, &files, &licenses))
+    Ok(Copyright {
+        header: header,
+        files: files,
+        licenses: licenses,
+    })
 }
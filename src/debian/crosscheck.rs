@@ -0,0 +1,445 @@
+use cargo::core::Dependency;
+use semver::{Version, VersionReq};
+use semver_parser;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crates::traverse_depth_2;
+use errors::*;
+use debian::control::{deb_feature, PackageNaming};
+use debian::syntax::parse_deb822;
+
+/// A dependency on another crate, as cargo would resolve it when building
+/// with some feature of the crate enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    pub crate_name: String,
+    pub req: VersionReq,
+    /// Features of the other crate, including "default" if its default
+    /// features are used.
+    pub features: BTreeSet<String>,
+}
+
+/// A way in which the Debian relations of a package don't express what cargo
+/// requires for the corresponding feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub package: String,
+    pub message: String,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.package, self.message)
+    }
+}
+
+/// The dependencies cargo enables for a feature of a crate ("" for none,
+/// i.e. --no-default-features), given its features as resolved by
+/// `summary_dependencies_and_features`, so that `dep:` and `dep?/feature`
+/// count the same here as in the generated packages. A dependency enabled
+/// several times is listed once, with all the features enabled on it.
+pub fn cargo_requirements<'a>(
+    features_with_deps: &BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)>,
+    feature: &'a str,
+) -> Vec<Requirement> {
+    let mut closure = vec!["", feature];
+    closure.extend(traverse_depth_2(features_with_deps, feature));
+    let mut seen = BTreeSet::new();
+    let mut requirements: Vec<Requirement> = Vec::new();
+    for f in closure {
+        if !seen.insert(f) {
+            continue;
+        }
+        let deps = match features_with_deps.get(f) {
+            Some(&(_, ref deps)) => deps,
+            None => continue,
+        };
+        for dep in deps {
+            let mut features = dep
+                .features()
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<BTreeSet<_>>();
+            if dep.uses_default_features() {
+                features.insert("default".to_string());
+            }
+            let crate_name = dep.package_name().to_string();
+            match requirements
+                .iter_mut()
+                .find(|r| r.crate_name == crate_name && r.req == *dep.version_req())
+            {
+                Some(r) => r.features.extend(features),
+                None => requirements.push(Requirement {
+                    crate_name: crate_name,
+                    req: dep.version_req().clone(),
+                    features: features,
+                }),
+            }
+        }
+    }
+    requirements
+}
+
+#[derive(Debug, Clone)]
+struct Relation {
+    name: String,
+    constraint: Option<(String, String)>,
+}
+
+/// Parse a relation like "librust-foo-1+std-dev (>= 1.2-~~) <!nocheck>",
+/// ignoring architecture restrictions and build profiles.
+fn parse_relation(s: &str) -> Relation {
+    let s = s.trim();
+    let name_end = s.find(|c: char| c.is_whitespace() || c == '(' || c == '[' || c == '<');
+    let name = match name_end {
+        Some(i) => &s[..i],
+        None => s,
+    };
+    let name = name.splitn(2, ':').next().unwrap();
+    let constraint = s.find('(').and_then(|start| {
+        let end = s[start..].find(')')? + start;
+        let mut parts = s[start + 1..end].split_whitespace();
+        Some((parts.next()?.to_string(), parts.next()?.to_string()))
+    });
+    Relation {
+        name: name.to_string(),
+        constraint: constraint,
+    }
+}
+
+fn parse_relations(value: &str) -> Vec<Vec<Relation>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| c.split('|').map(parse_relation).collect())
+        .collect()
+}
+
+struct DebPackage {
+    name: String,
+    provides: Vec<String>,
+    depends: Vec<Vec<Relation>>,
+}
+
+/// The version prefix that a package of crate `base` is limited to, e.g. [1, 2]
+/// for librust-foo-1.2-dev; None if it's not for that crate.
+fn version_prefix(head: &str, base: &str) -> Option<Vec<u64>> {
+    if head == base {
+        return Some(vec![]);
+    }
+    if !head.starts_with(base) || !head[base.len()..].starts_with('-') {
+        return None;
+    }
+    let version = &head[base.len() + 1..];
+    let parts = version.split('.').map(|p| p.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    Some(parts)
+}
+
+/// Compare a release version with a Debian version bound like "1.2-~~"; None
+/// if the bound can't be interpreted.
+fn compare_bound(v: &Version, bound: &str) -> Option<Ordering> {
+    let mut split = bound.splitn(2, '-');
+    let upstream = split.next()?;
+    let tilde = split.next().map_or(false, |r| r.starts_with('~'));
+    let mut nums = upstream.split('.').map(|p| p.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+    if nums.is_empty() || nums.len() > 3 {
+        return None;
+    }
+    nums.resize(3, 0);
+    let ord = (v.major, v.minor, v.patch).cmp(&(nums[0], nums[1], nums[2]));
+    // X-~~ sorts before X and all its Debian revisions
+    Some(if tilde && ord == Ordering::Equal { Ordering::Greater } else { ord })
+}
+
+/// Whether a relation on crate `base` admits a version, or None if we can't
+/// tell.
+fn admits(naming: &PackageNaming, relation: &Relation, base: &str, v: &Version) -> Option<bool> {
+    let (head, _) = naming.parse(&relation.name)?;
+    let prefix = version_prefix(&head, base)?;
+    let components = [v.major, v.minor, v.patch];
+    if prefix.iter().zip(components.iter()).any(|(a, b)| a != b) {
+        return Some(false);
+    }
+    Some(match relation.constraint {
+        None => true,
+        Some((ref op, ref bound)) => {
+            let ord = compare_bound(v, bound)?;
+            match op.as_str() {
+                ">=" => ord != Ordering::Less,
+                ">>" => ord == Ordering::Greater,
+                "<=" => ord != Ordering::Greater,
+                "<<" => ord == Ordering::Less,
+                "=" => ord == Ordering::Equal,
+                _ => return None,
+            }
+        }
+    })
+}
+
+/// Versions around the bounds of a requirement, where a translation that is
+/// too strict or too lax would show.
+fn probe_versions(req: &VersionReq) -> Vec<Version> {
+    let mut points = vec![(0, 0, 0), (0, 0, 1), (0, 1, 0), (0, 1, 1), (1, 0, 0), (1, 1, 0), (2, 0, 0)];
+    if let Ok(range) = semver_parser::range::parse(&req.to_string()) {
+        for p in range.predicates {
+            let (ma, mi, pa) = (p.major, p.minor.unwrap_or(0), p.patch.unwrap_or(0));
+            points.extend(&[
+                (ma, mi, pa),
+                (ma, mi, pa + 1),
+                (ma, mi + 1, 0),
+                (ma + 1, 0, 0),
+                (ma + 2, 0, 0),
+            ]);
+            if pa > 0 {
+                points.push((ma, mi, pa - 1));
+            }
+            if mi > 0 {
+                points.push((ma, mi - 1, 0));
+                points.push((ma, mi - 1, 99));
+            }
+            if ma > 0 {
+                points.push((ma - 1, 0, 0));
+                points.push((ma - 1, 99, 0));
+            }
+        }
+    }
+    points.sort();
+    points.dedup();
+    points
+        .into_iter()
+        .map(|(ma, mi, pa)| Version::new(ma, mi, pa))
+        .collect()
+}
+
+/// The first version where an OR-clause of relations and a cargo requirement
+/// disagree, and whether it is the clause that allows it.
+fn first_mismatch(naming: &PackageNaming, clause: &[Relation], base: &str, req: &VersionReq) -> Option<(Version, bool)> {
+    for v in probe_versions(req) {
+        let mut deb = Some(false);
+        for relation in clause {
+            match admits(naming, relation, base, &v) {
+                Some(true) => deb = Some(true),
+                Some(false) => (),
+                None => deb = None,
+            }
+            if deb != Some(false) {
+                break;
+            }
+        }
+        match deb {
+            Some(deb) if deb != req.matches(&v) => return Some((v, deb)),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn control_packages(control: &str) -> Result<(String, Vec<DebPackage>)> {
+    let paragraphs = match parse_deb822("control", control) {
+        Ok(p) => p,
+        Err(errors) => debcargo_bail!("Cannot parse d/control: {}", errors[0]),
+    };
+    let source = match paragraphs.first().and_then(|p| p.get("Source")) {
+        Some(s) => s.value.clone(),
+        None => debcargo_bail!("d/control has no Source"),
+    };
+    let packages = paragraphs[1..]
+        .iter()
+        .filter_map(|p| {
+            let field = |name: &str| p.get(name).map(|f| parse_relations(&f.value)).unwrap_or_default();
+            Some(DebPackage {
+                name: p.get("Package")?.value.clone(),
+                provides: field("Provides")
+                    .into_iter()
+                    .flat_map(|c| c.into_iter().map(|r| r.name))
+                    .collect(),
+                depends: field("Depends"),
+            })
+        })
+        .collect();
+    Ok((source, packages))
+}
+
+/// The relations of a package on packages outside this source package,
+/// including those it gets through its dependencies within it.
+fn external_depends<'a>(packages: &'a [DebPackage], start: usize) -> Vec<&'a [Relation]> {
+    let internal = |name: &str| {
+        packages
+            .iter()
+            .position(|p| p.name == name || p.provides.iter().any(|v| v == name))
+    };
+    let mut visited = BTreeSet::new();
+    let mut todo = vec![start];
+    let mut depends = Vec::new();
+    while let Some(i) = todo.pop() {
+        if !visited.insert(i) {
+            continue;
+        }
+        for clause in &packages[i].depends {
+            if clause.iter().any(|r| r.name.starts_with("${")) {
+                continue;
+            }
+            match (clause.len(), internal(&clause[0].name)) {
+                (1, Some(j)) => todo.push(j),
+                _ => depends.push(&clause[..]),
+            }
+        }
+    }
+    depends
+}
+
+/// The package that is or provides `package_name`.
+fn feature_package(packages: &[DebPackage], package_name: &str) -> Option<usize> {
+    packages
+        .iter()
+        .position(|p| p.name == package_name || p.provides.iter().any(|v| v == package_name))
+}
+
+/// Compare the Depends that debcargo generated in d/control against the
+/// dependencies cargo enables for each feature of the crate, flagging those
+/// that are missing, or whose version bounds allow or exclude versions that
+/// the cargo requirement doesn't. Such discrepancies are bugs in the
+/// translation, or need to be explained in the config.
+pub fn cross_check<'a>(
+    features_with_deps: &BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)>,
+    control: &str,
+    naming: &PackageNaming,
+) -> Result<Vec<Discrepancy>> {
+    let (source, packages) = control_packages(control)?;
+    let base = source.trim_start_matches("rust-");
+
+    let mut discrepancies: Vec<Discrepancy> = Vec::new();
+    let mut reported = BTreeSet::new();
+    for &feature in features_with_deps.keys() {
+        let package_name = naming.name_for(base, feature);
+        let index = match feature_package(&packages, &package_name) {
+            Some(i) => i,
+            None => {
+                discrepancies.push(Discrepancy {
+                    package: package_name,
+                    message: format!("no package provides feature \"{}\"", feature),
+                });
+                continue;
+            }
+        };
+        let depends = external_depends(&packages, index);
+        for requirement in cargo_requirements(features_with_deps, feature) {
+            let dep_base = requirement.crate_name.replace('_', "-").to_lowercase();
+            let wanted = if requirement.features.is_empty() {
+                vec![None]
+            } else {
+                requirement.features.iter().map(|f| Some(deb_feature(f))).collect()
+            };
+            for dep_feature in wanted {
+                let clauses = depends
+                    .iter()
+                    .filter(|clause| {
+                        clause.iter().all(|r| match naming.parse(&r.name) {
+                            Some((head, f)) => {
+                                f == dep_feature && version_prefix(&head, &dep_base).is_some()
+                            }
+                            None => false,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let what = match dep_feature {
+                    Some(ref f) => format!("{} with feature \"{}\"", requirement.crate_name, f),
+                    None => format!("{} without default features", requirement.crate_name),
+                };
+                let message = if clauses.is_empty() {
+                    Some(format!("no dependency on {} {}", what, requirement.req))
+                } else if clauses
+                    .iter()
+                    .any(|c| first_mismatch(naming, c, &dep_base, &requirement.req).is_none())
+                {
+                    None
+                } else {
+                    let (v, allowed) = first_mismatch(naming, clauses[0], &dep_base, &requirement.req).unwrap();
+                    Some(if allowed {
+                        format!("too lax, the dependency on {} allows {} but cargo requires {}",
+                                what, v, requirement.req)
+                    } else {
+                        format!("too strict, the dependency on {} excludes {} which cargo allows with {}",
+                                what, v, requirement.req)
+                    })
+                };
+                if let Some(message) = message {
+                    if reported.insert(message.clone()) {
+                        discrepancies.push(Discrepancy {
+                            package: packages[index].name.clone(),
+                            message: message,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(discrepancies)
+}
+
+/// A Markdown report of what each library package in d/control stands for:
+/// the cargo features of the crate that it provides, and the dependencies
+/// that cargo then enables, with the features enabled in each.
+pub fn feature_report<'a>(
+    features_with_deps: &BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)>,
+    control: &str,
+    naming: &PackageNaming,
+) -> Result<String> {
+    let (source, packages) = control_packages(control)?;
+    let base = source.trim_start_matches("rust-");
+
+    let mut by_package: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    let mut missing = Vec::new();
+    for &feature in features_with_deps.keys() {
+        match feature_package(&packages, &naming.name_for(base, feature)) {
+            Some(i) => by_package.entry(i).or_default().push(feature),
+            None => missing.push(feature),
+        }
+    }
+
+    let mut report = format!("# Cargo features of the packages of {}\n", source);
+    for (i, features) in by_package {
+        report.push_str(&format!("\n## {}\n\n", packages[i].name));
+        let names = features
+            .iter()
+            .map(|f| if f.is_empty() { "(none)".to_string() } else { format!("`{}`", f) })
+            .collect::<Vec<_>>();
+        report.push_str(&format!("Features: {}\n\n", names.join(", ")));
+
+        // what installing the package gives, i.e. for all of its features
+        let mut requirements: Vec<Requirement> = Vec::new();
+        for feature in &features {
+            for r in cargo_requirements(features_with_deps, feature) {
+                match requirements.iter_mut().find(|q| q.crate_name == r.crate_name && q.req == r.req) {
+                    Some(q) => q.features.extend(r.features),
+                    None => requirements.push(r),
+                }
+            }
+        }
+        if requirements.is_empty() {
+            report.push_str("No dependencies.\n");
+        }
+        for r in &requirements {
+            let enabled = if r.features.is_empty() {
+                "no default features".to_string()
+            } else {
+                r.features.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>().join(", ")
+            };
+            report.push_str(&format!("- {} {}: {}\n", r.crate_name, r.req, enabled));
+        }
+    }
+    if !missing.is_empty() {
+        report.push_str("\n## Not packaged\n\n");
+        for f in &missing {
+            report.push_str(&format!("- `{}`\n", f));
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,316 @@
+use failure;
+
+use std::fmt;
+use std::str;
+
+use debian::syntax::{parse_deb822, Paragraph};
+
+pub const FORMAT: &'static str = "https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/";
+
+/// A machine-readable d/copyright, as in
+/// https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/.
+/// It is written normalized: fields in the usual order, lists one item per
+/// line, and empty lines of text as " .", so that it always parses back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Copyright {
+    pub header: Header,
+    pub files: Vec<Files>,
+    pub licenses: Vec<License>,
+}
+
+/// The header paragraph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub format: String,
+    pub upstream_name: Option<String>,
+    pub upstream_contact: Vec<String>,
+    pub source: Option<String>,
+    pub files_excluded: Vec<String>,
+    pub comment: Option<String>,
+    /// Any other fields, e.g. Disclaimer, kept as they are.
+    pub other: Vec<(String, String)>,
+}
+
+/// A Files paragraph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Files {
+    pub files: Vec<String>,
+    pub copyright: Vec<String>,
+    pub license: License,
+    pub comment: Option<String>,
+    pub other: Vec<(String, String)>,
+}
+
+/// A License field: the license names, e.g. "MIT or Apache-2.0", and the
+/// text if it is given there. On its own, it is a standalone License paragraph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct License {
+    pub name: String,
+    pub text: Option<String>,
+}
+
+impl Header {
+    pub fn new(upstream_name: &str) -> Header {
+        Header {
+            format: FORMAT.to_string(),
+            upstream_name: Some(upstream_name.to_string()),
+            upstream_contact: Vec::new(),
+            source: None,
+            files_excluded: Vec::new(),
+            comment: None,
+            other: Vec::new(),
+        }
+    }
+}
+
+impl Files {
+    pub fn new<T: ToString>(files: &str, copyright: &[T], license: &str, comment: &str) -> Files {
+        Files {
+            files: files.split_whitespace().map(str::to_string).collect(),
+            copyright: copyright.iter().map(|c| c.to_string()).collect(),
+            license: License::new(license, None),
+            comment: if comment.is_empty() { None } else { Some(comment.to_string()) },
+            other: Vec::new(),
+        }
+    }
+}
+
+impl License {
+    pub fn new(name: &str, text: Option<&str>) -> License {
+        License {
+            name: name.to_string(),
+            text: text.map(str::to_string),
+        }
+    }
+}
+
+/// A single-line value, e.g. of Format.
+fn line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A value that is a list of lines, e.g. of Copyright: on the field line if
+/// there is only one, otherwise each on a continuation line.
+fn lines(items: &[String]) -> String {
+    let items = items
+        .iter()
+        .flat_map(|i| i.lines())
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .collect::<Vec<_>>();
+    match items.len() {
+        1 => items[0].to_string(),
+        _ => items.iter().map(|i| format!("\n{}", i)).collect(),
+    }
+}
+
+/// A value that is formatted text, e.g. of Comment, all on continuation lines,
+/// after the optional `first` line.
+fn text(first: &str, text: &str) -> String {
+    let mut value = line(first);
+    let body = text.lines().map(str::trim_end).collect::<Vec<_>>();
+    let start = body.iter().position(|l| !l.is_empty()).unwrap_or(body.len());
+    let end = body.iter().rposition(|l| !l.is_empty()).map_or(start, |e| e + 1);
+    for l in &body[start..end] {
+        value.push('\n');
+        value.push_str(l);
+    }
+    value
+}
+
+/// Write a field, with the first line of its value on the field line and the
+/// rest on continuation lines, empty ones as " .".
+fn write_field(f: &mut fmt::Formatter, name: &str, value: &str) -> fmt::Result {
+    let mut value_lines = value.split('\n');
+    let first = value_lines.next().unwrap_or("").trim();
+    if first.is_empty() {
+        writeln!(f, "{}:", name)?;
+    } else {
+        writeln!(f, "{}: {}", name, first)?;
+    }
+    for l in value_lines {
+        let l = l.trim_end();
+        if l.is_empty() {
+            writeln!(f, " .")?;
+        } else {
+            writeln!(f, " {}", l)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_other(f: &mut fmt::Formatter, other: &[(String, String)]) -> fmt::Result {
+    for &(ref name, ref value) in other {
+        write_field(f, name, value)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_field(f, "Format", &line(&self.format))?;
+        if let Some(ref name) = self.upstream_name {
+            write_field(f, "Upstream-Name", &line(name))?;
+        }
+        if !self.upstream_contact.is_empty() {
+            write_field(f, "Upstream-Contact", &lines(&self.upstream_contact))?;
+        }
+        if let Some(ref source) = self.source {
+            write_field(f, "Source", &text("", source).trim_start())?;
+        }
+        if !self.files_excluded.is_empty() {
+            let patterns = self.files_excluded
+                .iter()
+                .flat_map(|p| p.split_whitespace())
+                .map(|p| format!("\n{}", p))
+                .collect::<String>();
+            write_field(f, "Files-Excluded", &patterns)?;
+        }
+        if let Some(ref comment) = self.comment {
+            write_field(f, "Comment", &text("", comment))?;
+        }
+        write_other(f, &self.other)
+    }
+}
+
+impl fmt::Display for Files {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_field(f, "Files", &line(&self.files.join(" ")))?;
+        write_field(f, "Copyright", &lines(&self.copyright))?;
+        write!(f, "{}", self.license)?;
+        if let Some(ref comment) = self.comment {
+            write_field(f, "Comment", &text("", comment))?;
+        }
+        write_other(f, &self.other)
+    }
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_field(f, "License", &text(&self.name, self.text.as_ref().map_or("", String::as_str)))
+    }
+}
+
+impl fmt::Display for Copyright {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.header)?;
+        for files in &self.files {
+            write!(f, "\n{}", files)?;
+        }
+        for license in &self.licenses {
+            write!(f, "\n{}", license)?;
+        }
+        Ok(())
+    }
+}
+
+/// The text of a value after its first line, with " ." lines as empty ones.
+fn value_text(value: &str) -> Option<String> {
+    let mut value_lines = value.split('\n');
+    value_lines.next();
+    let body = value_lines
+        .map(|l| if l.trim() == "." { "" } else { l })
+        .collect::<Vec<_>>();
+    if body.is_empty() {
+        None
+    } else {
+        Some(body.join("\n"))
+    }
+}
+
+/// All the text of a value, with " ." lines as empty ones.
+fn value_all_text(value: &str) -> Option<String> {
+    let body = value
+        .split('\n')
+        .map(|l| if l.trim() == "." { "" } else { l.trim_end() })
+        .collect::<Vec<_>>();
+    let start = body.iter().position(|l| !l.is_empty())?;
+    let end = body.iter().rposition(|l| !l.is_empty()).unwrap() + 1;
+    Some(body[start..end].join("\n"))
+}
+
+fn value_first_line(value: &str) -> &str {
+    value.split('\n').next().unwrap_or("").trim()
+}
+
+/// The items of a value that is a list of lines, e.g. of Copyright.
+fn value_lines(value: &str) -> Vec<String> {
+    value
+        .split('\n')
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && *l != ".")
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_license(value: &str) -> License {
+    License {
+        name: value_first_line(value).to_string(),
+        text: value_text(value),
+    }
+}
+
+/// The known fields of a paragraph, and the other ones in order.
+fn split_fields(paragraph: &Paragraph, known: &[&str]) -> Vec<(String, String)> {
+    paragraph
+        .fields
+        .iter()
+        .filter(|f| !known.iter().any(|k| k.eq_ignore_ascii_case(&f.name)))
+        .map(|f| (f.name.clone(), f.value.clone()))
+        .collect()
+}
+
+impl str::FromStr for Copyright {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let paragraphs = parse_deb822("copyright", s).map_err(|errors| {
+            format_err!("{}", errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))
+        })?;
+        let mut paragraphs = paragraphs.iter();
+        let header = match paragraphs.next() {
+            Some(h) if h.get("Format").is_some() => h,
+            Some(h) => return Err(format_err!("debian/copyright:{}: header paragraph has no Format field", h.line)),
+            None => return Err(format_err!("debian/copyright: no paragraphs")),
+        };
+        let get = |p: &Paragraph, name: &str| p.get(name).map(|f| f.value.clone());
+        let header = Header {
+            format: value_first_line(&get(header, "Format").unwrap_or_default()).to_string(),
+            upstream_name: get(header, "Upstream-Name").map(|v| line(&v)),
+            upstream_contact: get(header, "Upstream-Contact").map_or_else(Vec::new, |v| value_lines(&v)),
+            source: get(header, "Source").map(|v| value_lines(&v).join("\n")),
+            files_excluded: get(header, "Files-Excluded")
+                .map_or_else(Vec::new, |v| v.split_whitespace().map(str::to_string).collect()),
+            comment: get(header, "Comment").and_then(|v| value_all_text(&v)),
+            other: split_fields(header, &[
+                "Format", "Upstream-Name", "Upstream-Contact", "Source", "Files-Excluded", "Comment",
+            ]),
+        };
+        let mut files = Vec::new();
+        let mut licenses = Vec::new();
+        for p in paragraphs {
+            let license = p.get("License").map(|l| parse_license(&l.value));
+            match (get(p, "Files"), license) {
+                (Some(f), Some(license)) => files.push(Files {
+                    files: f.split_whitespace().map(str::to_string).collect(),
+                    copyright: get(p, "Copyright").map_or_else(Vec::new, |v| value_lines(&v)),
+                    license: license,
+                    comment: get(p, "Comment").and_then(|v| value_all_text(&v)),
+                    other: split_fields(p, &["Files", "Copyright", "License", "Comment"]),
+                }),
+                (Some(_), None) => {
+                    return Err(format_err!("debian/copyright:{}: Files paragraph has no License field", p.line))
+                }
+                (None, Some(license)) => licenses.push(license),
+                (None, None) => {
+                    return Err(format_err!("debian/copyright:{}: paragraph has neither Files nor License", p.line))
+                }
+            }
+        }
+        Ok(Copyright {
+            header: header,
+            files: files,
+            licenses: licenses,
+        })
+    }
+}
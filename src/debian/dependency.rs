@@ -9,6 +9,7 @@ use std::fmt;
 
 use errors::*;
 use config::Config;
+use debian::control::PackageNaming;
 
 #[derive(Eq, Clone)]
 enum V {
@@ -127,12 +128,24 @@ impl VRange {
         self
     }
 
-    fn to_deb_or_clause(&self, base: &str, suffix: &str) -> Result<String> {
+    /// The range in Debian versions, for explaining it.
+    fn describe(&self) -> String {
+        match (&self.ge, &self.lt) {
+            (None, None) => "any version".to_string(),
+            (Some(ge), None) => format!(">= {}", ge),
+            (None, Some(lt)) => format!("<< {}", lt),
+            (Some(ge), Some(lt)) => format!(">= {} and << {}", ge, lt),
+        }
+    }
+
+    /// `name` gives the package for the crate `base`, with any version
+    /// suffix appended.
+    fn to_deb_or_clause<F: Fn(&str) -> String>(&self, base: &str, name: F) -> Result<String> {
         use debian::dependency::V::*;
         match (&self.ge, &self.lt) {
-            (None, None) => Ok(format!("{}{}", base, suffix)),
-            (Some(ge), None) => Ok(format!("{}{} (>= {}-~~)", base, suffix, ge)),
-            (None, Some(lt)) => Ok(format!("{}{} (<< {}-~~)", base, suffix, lt)),
+            (None, None) => Ok(name(base)),
+            (Some(ge), None) => Ok(format!("{} (>= {}-~~)", name(base), ge)),
+            (None, Some(lt)) => Ok(format!("{} (<< {}-~~)", name(base), lt)),
             (Some(ge), Some(lt)) => {
                 if ge >= lt {
                     debcargo_bail!("bad version range: >= {}, << {}", ge, lt);
@@ -160,18 +173,18 @@ impl VRange {
                 // reverse the order so higher versions go first
                 // this helps sbuild find build-deps, it does not resolve alternatives by default
                 Ok(ranges.iter().rev().filter_map(|(ver, cons)| match cons {
-                    None => Some(format!("{}-{}{}", base, ver, suffix)),
+                    None => Some(name(&format!("{}-{}", base, ver))),
                     Some((true, c)) => if c == &ver {
                         // A-x >= x is redundant, drop the >=
-                        Some(format!("{}-{}{}", base, ver, suffix))
+                        Some(name(&format!("{}-{}", base, ver)))
                     } else {
-                        Some(format!("{}-{}{} (>= {}-~~)", base, ver, suffix, c))
+                        Some(format!("{} (>= {}-~~)", name(&format!("{}-{}", base, ver)), c))
                     },
                     Some((false, c)) => if c == &ver {
                         // A-x << x is unsatisfiable, drop it
                         None
                     } else {
-                        Some(format!("{}-{}{} (<< {}-~~)", base, ver, suffix, c))
+                        Some(format!("{} (<< {}-~~)", name(&format!("{}-{}", base, ver)), c))
                     },
                 }).join(" | "))
             }
@@ -296,28 +309,37 @@ fn generate_version_constraints(
 /// Translates a Cargo dependency into a Debian package dependency.
 pub fn deb_dep(config: &Config, dep: &Dependency) -> Result<Vec<String>> // result is a AND-clause
 {
-    let dep_dashed = dep.package_name().replace('_', "-").to_lowercase();
-    let mut suffixes = Vec::new();
+    let naming = PackageNaming::from_config(config)?;
+    let base = dep.package_name().replace('_', "-").to_lowercase();
+    let mut features = Vec::new();
     if dep.uses_default_features() {
-        suffixes.push("+default-dev".to_string());
+        features.push("default");
     }
-    for feature in dep.features() {
-        suffixes.push(format!("+{}-dev", feature.replace('_', "-").to_lowercase()));
+    features.extend(dep.features().iter().map(|f| f.as_str()));
+    if features.is_empty() {
+        features.push("");
     }
-    if suffixes.is_empty() {
-        suffixes.push("-dev".to_string());
+    let req = semver_parser::range::parse(&dep.version_req().to_string()).map_err(|e| {
+        format_err!("Couldn't parse the version requirement {} of {}: {}",
+                    dep.version_req(), dep.package_name(), e)
+    })?;
+    let mut vr = VRange::new();
+    for p in &req.predicates {
+        let op = coerce_unacceptable_predicate(dep, &p, config.allow_prerelease_deps)?;
+        generate_version_constraints(&mut vr, dep, &p, op)?;
     }
-    let req = semver_parser::range::parse(&dep.version_req().to_string()).unwrap();
     let mut deps = Vec::new();
-    for suffix in suffixes {
-        let base = format!("librust-{}", dep_dashed);
-        let mut vr = VRange::new();
-        for p in &req.predicates {
-            let op = coerce_unacceptable_predicate(dep, &p, config.allow_prerelease_deps)?;
-            generate_version_constraints(&mut vr, dep, &p, op)?;
-        }
-        deps.push(vr.to_deb_or_clause(&base, &suffix)?);
+    for feature in features {
+        deps.push(vr.to_deb_or_clause(&base, |b| naming.name_for(b, feature))?);
     }
+    debcargo_explain!(
+        "{} {} is {} in Debian versions{}, so the dependency is on {}",
+        dep.package_name(),
+        dep.version_req(),
+        vr.describe(),
+        if dep.uses_default_features() { ", with its default features" } else { "" },
+        deps.join(", ")
+    );
     Ok(deps)
 }
 
@@ -0,0 +1,177 @@
+use regex::{self, Regex};
+
+use config::Config;
+use errors::*;
+use util::{normalize_text, vec_opt_iter};
+
+/// Names of the built-in rules, in the order they are applied.
+pub const RULES: &'static [&'static str] = &[
+    "strip-name-prefix",
+    "strip-article",
+    "strip-library-of",
+    "capitalize",
+];
+
+// Abbreviations that end in a full stop but don't end a sentence.
+const ABBREVIATIONS: &'static [&'static str] = &["e.g", "i.e", "etc", "vs", "cf", "approx"];
+
+/// Heuristics for turning a crate description into a Debian package summary
+/// and long description. Each built-in rule can be disabled, and extra strip
+/// patterns can be supplied; these are applied before the built-in rules.
+pub struct DescriptionRules {
+    disabled: Vec<String>,
+    strip_patterns: Vec<Regex>,
+}
+
+impl Default for DescriptionRules {
+    fn default() -> Self {
+        DescriptionRules {
+            disabled: vec![],
+            strip_patterns: vec![],
+        }
+    }
+}
+
+impl DescriptionRules {
+    pub fn new(disabled: &[String], strip_patterns: &[String]) -> Result<Self> {
+        for rule in disabled {
+            if !RULES.contains(&rule.as_str()) {
+                debcargo_bail!(
+                    "Unknown description rule \"{}\", expected one of: {}",
+                    rule,
+                    RULES.join(", ")
+                );
+            }
+        }
+        let mut patterns = Vec::new();
+        for p in strip_patterns {
+            let re = Regex::new(p)
+                .map_err(|e| format_err!("Bad description strip pattern \"{}\": {}", p, e))?;
+            patterns.push(re);
+        }
+        Ok(DescriptionRules {
+            disabled: disabled.to_vec(),
+            strip_patterns: patterns,
+        })
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let disabled: Vec<String> = vec_opt_iter(config.description_rules_disabled())
+            .cloned()
+            .collect();
+        let patterns: Vec<String> = vec_opt_iter(config.description_strip_patterns())
+            .cloned()
+            .collect();
+        DescriptionRules::new(&disabled, &patterns)
+    }
+
+    fn enabled(&self, rule: &str) -> bool {
+        !self.disabled.iter().any(|r| r == rule)
+    }
+
+    /// Split a free-form crate description into a summary and the remaining
+    /// description, applying the enabled cleanups along the way.
+    pub fn summarize(&self, crate_name: &str, description: &str) -> (Option<String>, Option<String>) {
+        // Convention these days seems to be to do manual text
+        // wrapping in crate descriptions, boo. \n\n is a real line break.
+        let mut description = normalize_text(description)
+            .replace("\n\n", "\r")
+            .replace("\n", " ")
+            .replace("\r", "\n")
+            .trim()
+            .to_string();
+
+        let strip = |rule: &str, re: &Regex, description: &mut String| {
+            let stripped = re.replace(description, "").to_string();
+            if stripped != *description {
+                debcargo_explain!("Description rule {} turned {:?} into {:?}", rule, description, stripped);
+                *description = stripped;
+            }
+        };
+        for re in &self.strip_patterns {
+            strip(&format!("strip_patterns {:?}", re.as_str()), re, &mut description);
+        }
+
+        if self.enabled("strip-name-prefix") {
+            // crates are often referred to with - and _ interchangeably
+            let name = regex::escape(crate_name).replace("_", "[-_]").replace("\\-", "[-_]");
+            let re = Regex::new(&format!(
+                r"^(?i)({}|This(\s+\w+)?)(\s*,|\s+is|\s+provides)\s+",
+                name
+            ))
+            .unwrap();
+            strip("strip-name-prefix", &re, &mut description);
+        }
+        if self.enabled("strip-article") {
+            let re = Regex::new(r"^(?i)(a|an|the)\s+").unwrap();
+            strip("strip-article", &re, &mut description);
+        }
+        if self.enabled("strip-library-of") {
+            let re =
+                Regex::new(r"^(?i)(rust\s+)?(implementation|library|tool|crate)\s+(of|to|for)\s+")
+                    .unwrap();
+            strip("strip-library-of", &re, &mut description);
+        }
+        if self.enabled("capitalize") {
+            description = capitalize(&description);
+        }
+
+        // Use the first sentence or first line, whichever comes first, as the summary.
+        let p1 = description.find('\n');
+        let p2 = first_sentence_end(&description);
+        match p1.into_iter().chain(p2.into_iter()).min() {
+            Some(p) => {
+                debcargo_explain!(
+                    "The summary is the first {} of the description",
+                    if Some(p) == p1 { "line" } else { "sentence" }
+                );
+                let s = description[..p].trim_right_matches('.').to_string();
+                let d = description[p + 1..].trim();
+                if d.is_empty() {
+                    (Some(s), None)
+                } else {
+                    (Some(s), Some(d.to_string()))
+                }
+            }
+            None => (Some(description.trim_right_matches('.').to_string()), None),
+        }
+    }
+}
+
+/// Uppercase the first letter, unless the first word looks like it has
+/// deliberate casing (e.g. "iOS", "macOS") or is a code identifier.
+fn capitalize(description: &str) -> String {
+    let first_word = description.split_whitespace().next().unwrap_or("");
+    if first_word.chars().skip(1).any(char::is_uppercase)
+        || first_word.contains('_')
+        || first_word.contains("::")
+    {
+        return description.to_string();
+    }
+    // https://stackoverflow.com/questions/38406793/why-is-capitalizing-the-first-letter-of-a-string-so-convoluted-in-rust
+    let mut d = description.chars();
+    match d.next() {
+        None => String::new(),
+        Some(f) => f.to_uppercase().chain(d).collect::<String>(),
+    }
+}
+
+/// Position of the full stop that ends the first sentence, skipping over
+/// common abbreviations.
+fn first_sentence_end(description: &str) -> Option<usize> {
+    let mut start = 0;
+    while let Some(p) = description[start..].find(". ") {
+        let p = start + p;
+        let word = description[..p]
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or("")
+            .trim_start_matches('(')
+            .to_lowercase();
+        if !ABBREVIATIONS.contains(&word.as_str()) {
+            return Some(p);
+        }
+        start = p + 2;
+    }
+    None
+}
@@ -0,0 +1,49 @@
+use regex::Regex;
+
+use crates::http_get;
+use errors::*;
+
+/// The open wnpp bugs with the source package in their title, as listed by
+/// the Debian BTS.
+pub fn wnpp_query_url(source: &str) -> String {
+    format!(
+        "https://bugs.debian.org/cgi-bin/pkgreport.cgi?package=wnpp;include=subject:{};pend-exc=done",
+        source
+    )
+}
+
+/// The ITP bugs for a source package, from a BTS bug listing in HTML, e.g.
+/// `<a href="bugreport.cgi?bug=912345">#912345: ITP: rust-foo -- ...</a>`.
+/// Other wnpp bugs, and ITPs of packages whose name merely contains the
+/// source package's, are left out.
+pub fn itp_bugs(html: &str, source: &str) -> Vec<u32> {
+    let re = Regex::new(r#"bugreport\.cgi\?bug=(\d+)"[^>]*>\s*#\d+:\s*ITP:\s*([^\s<]+)"#).unwrap();
+    let mut bugs = re
+        .captures_iter(html)
+        .filter(|c| c[2].eq_ignore_ascii_case(source))
+        .filter_map(|c| c[1].parse().ok())
+        .collect::<Vec<u32>>();
+    bugs.sort();
+    bugs.dedup();
+    bugs
+}
+
+/// The open ITP bug for a source package on the Debian BTS, if any. With
+/// several, the oldest is taken, as later ones are usually duplicates.
+pub fn lookup_itp(source: &str) -> Result<Option<u32>> {
+    let html = http_get(&wnpp_query_url(source))?;
+    let bugs = itp_bugs(&String::from_utf8_lossy(&html), source);
+    if bugs.len() > 1 {
+        debcargo_warn!(
+            "Several ITP bugs are open for {}: {}; closing the oldest",
+            source,
+            bugs.iter().map(|b| format!("#{}", b)).collect::<Vec<_>>().join(", ")
+        );
+    }
+    Ok(bugs.first().cloned())
+}
+
+/// The d/changelog item closing the ITP bug of a new package.
+pub fn closes_item(bug: u32) -> String {
+    format!("Initial release (Closes: #{})", bug)
+}
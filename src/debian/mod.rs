@@ -1,7 +1,9 @@
 use std::fs;
 use std::collections::BTreeMap;
+use std::iter;
+use std::fmt::Write as FmtWrite;
 use std::io::{self, ErrorKind, Read, Seek, Write as IoWrite};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::os::unix::fs::PermissionsExt;
 use std::str::FromStr;
 use std::process::Command;
@@ -11,30 +13,52 @@ use tempfile;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
-use regex::Regex;
+use regex::{self, Regex};
 use tar::{Archive, Builder};
+use walkdir;
 
-use crates::CrateInfo;
+use crates::{cap_feature_packages, github_description, has_offline_tests, inline_optional_dependencies,
+             remove_unpackageable_features, semver_suffix_of, traverse_depth_2, CrateInfo, DependencySelection,
+             GitOrigin, Provenance};
 use errors::*;
 use config::{Config, PackageKey, package_field_for_feature};
 use util::{self, copy_tree, vec_opt_iter};
+use tree::{self, DebianStatus};
+use vendor::{self, Vendoring};
 
-use self::control::deb_version;
-use self::control::{Package, PkgTest, Source};
+use self::control::{check_deb_feature_collisions, deb_feature, deb_version, with_epoch, ALL_FEATURES};
+use self::control::{Package, PackageNaming, PkgTest, Source};
 use self::copyright::debian_copyright;
-use self::changelog::{ChangelogEntry, ChangelogIterator};
+use self::description::DescriptionRules;
+use self::changelog::{Backport, ChangelogEntry, ChangelogIterator};
+use self::patches::{parse_series, DROP_DEV_DEPS_PATCH, RELAX_DEPS_PATCH};
+use self::stamp::{Stamp, STAMP_FILE};
 pub use self::dependency::{deb_deps, deb_dep_add_nocheck};
 
 pub mod control;
+pub mod crosscheck;
 mod dependency;
 pub mod copyright;
 pub mod changelog;
+pub mod dep5;
+pub mod itp;
+pub mod description;
+pub mod patches;
+pub mod source;
+pub mod stamp;
+pub mod syntax;
+
+/// debhelper compat level and minimum dh-cargo version to build with, unless
+/// overridden in the config.
+pub const DEFAULT_DEBHELPER_COMPAT: u32 = 11;
+pub const DEFAULT_DH_CARGO_VERSION: &str = "18";
 
 pub struct BaseInfo {
     upstream_name: String,
     base_package_name: String,
     name_suffix: Option<String>,
     uscan_version_pattern: Option<String>,
+    repack_suffix: Option<String>,
     package_name: String,
     debian_version: String,
     debcargo_version: String,
@@ -44,7 +68,7 @@ pub struct BaseInfo {
 
 impl BaseInfo {
     pub fn new(name: &str, crate_info: &CrateInfo,
-               debcargo_version: &str, semver_suffix: bool) -> Self {
+               debcargo_version: &str, semver_suffix: bool, repack_suffix: Option<&str>) -> Self {
         let upstream = name.to_string();
         let name_dashed = upstream.replace('_', "-");
         let base_package_name = name_dashed.to_lowercase();
@@ -55,7 +79,7 @@ impl BaseInfo {
         } else {
             (None, None, base_package_name.clone())
         };
-        let debian_version = deb_version(crate_info.version());
+        let debian_version = format!("{}{}", deb_version(crate_info.version()), repack_suffix.unwrap_or(""));
         let debian_source = match name_suffix {
             Some(ref suf) => format!("rust-{}{}", base_package_name, suf),
             None => format!("rust-{}", base_package_name),
@@ -68,6 +92,7 @@ impl BaseInfo {
             base_package_name: base_package_name,
             name_suffix: name_suffix,
             uscan_version_pattern: uscan_version_pattern,
+            repack_suffix: repack_suffix.map(str::to_string),
             package_name: package_name,
             debian_version: debian_version,
             debcargo_version: debcargo_version.to_string(),
@@ -110,17 +135,6 @@ impl BaseInfo {
 
 }
 
-fn traverse_depth_2<'a, T>(map: &BTreeMap<&'a str, (Vec<&'a str>, T)>, key: &'a str) -> Vec<&'a str> {
-    let mut x = Vec::new();
-    if let Some((pp, _)) = (*map).get(key) {
-        x.extend(pp);
-        for p in pp {
-            x.extend(traverse_depth_2(map, p));
-        }
-    }
-    x
-}
-
 pub fn prepare_orig_tarball(
     crate_info: &CrateInfo,
     tarball: &Path,
@@ -192,29 +206,124 @@ pub fn prepare_debian_folder(
     pkgbase: &BaseInfo,
     crate_info: &mut CrateInfo,
     pkg_srcdir: &Path,
-    config_path: Option<&Path>,
     config: &Config,
     changelog_ready: bool,
-    copyright_guess_harder: bool,
+    description_guess_harder: bool,
     overlay_write_back: bool,
+    strict: bool,
+    backport: Option<&Backport>,
+    vendoring: Option<&Vendoring>,
 ) -> Result<()> {
+    let upstream_version = crate_info.version().to_string();
+    let config = &config.with_templates_expanded(&[
+        ("$crate", pkgbase.package_name()),
+        ("$version", pkgbase.debian_version()),
+        ("$upstream_version", &upstream_version),
+        ("$semver_suffix", pkgbase.name_suffix().unwrap_or("")),
+    ]);
     let tempdir = tempfile::Builder::new().prefix("debcargo").tempdir_in(".")?;
-    let overlay = config.overlay_dir(config_path);
-    overlay.as_ref().map(|p| {
-        copy_tree(p.as_path(), tempdir.path()).unwrap();
-    });
+    let overlay = config.overlay_dir();
+    if let Some(ref p) = overlay {
+        copy_tree(p.as_path(), tempdir.path())
+            .map_err(|e| format_err!("Couldn't copy the overlay {}: {}", p.display(), e))?;
+    }
     if tempdir.path().join("control").exists() {
         debcargo_warn!("Most of the time you shouldn't overlay debian/control, \
                         it's a maintenance burden. Use debcargo.toml instead.")
     }
-    if tempdir.path().join("patches").join("series").exists() {
+    let series_path = tempdir.path().join("patches").join("series");
+    let relax = vec_opt_iter(config.relax.as_ref())
+        .map(|r| patches::parse_relax_rule(r))
+        .collect::<Result<Vec<_>>>()?;
+    let mut regenerated = vec![];
+    if config.drop_unavailable_dev_deps {
+        regenerated.push(DROP_DEV_DEPS_PATCH);
+    }
+    if !relax.is_empty() {
+        regenerated.push(RELAX_DEPS_PATCH);
+    }
+    let mut generated_patches = vec![];
+    if let Ok(series) = fs::read_to_string(&series_path) {
+        // regenerated each time, so that they only do what is still needed
+        let kept = series
+            .lines()
+            .filter(|l| !parse_series(l).iter().any(|p| regenerated.contains(&p.as_str())))
+            .map(|l| format!("{}\n", l))
+            .collect::<String>();
+        if kept != series {
+            fs::write(&series_path, kept)?;
+            for patch in &regenerated {
+                fs::remove_file(series_path.with_file_name(patch)).ok();
+            }
+            generated_patches.push("patches/series".to_string());
+        }
+    }
+    let unavailable = if config.drop_unavailable_dev_deps {
+        unavailable_dev_dependencies(crate_info, &PackageNaming::from_config(config)?)
+    } else {
+        vec![]
+    };
+    if series_path.exists() || !regenerated.is_empty() {
         // apply patches to Cargo.toml in case they exist, and re-read it
         let pkg_srcdir = &fs::canonicalize(&pkg_srcdir)?;
-        Command::new("quilt")
-                .current_dir(&pkg_srcdir)
-                .env("QUILT_PATCHES", tempdir.path().join("patches"))
-                .args(&["push", "--quiltrc=-", "-a"])
-                .status().expect("failed to apply patches");
+        if series_path.exists() {
+            let pushed = Command::new("quilt")
+                    .current_dir(&pkg_srcdir)
+                    .env("QUILT_PATCHES", tempdir.path().join("patches"))
+                    .args(&["push", "--quiltrc=-", "-a"])
+                    .status().expect("failed to apply patches");
+            // quilt exits with 2 when there is nothing to do
+            if pushed.code() == Some(1) {
+                debcargo_warn!("Some patches in the overlay don't apply to {} {}, \
+                                rebase or drop them, e.g. after `debcargo new-upstream`",
+                               crate_info.package_id().name(), crate_info.version());
+            }
+        }
+        // on top of the other patches, which may change Cargo.toml too
+        let manifest = fs::read_to_string(pkg_srcdir.join("Cargo.toml"))?;
+        let names = unavailable.iter().map(String::as_str).collect::<Vec<_>>();
+        let dropped = patches::dev_dependency_lines(&manifest, &names);
+        if !dropped.is_empty() {
+            let description = format!(
+                "Description: Drop dev-dependencies that are not in Debian\n \
+                 Generated by debcargo, so that the tests that don't need them can run:\n \
+                 {}.\nForwarded: not-needed\n---",
+                unavailable.join(", ")
+            );
+            let edits = dropped.into_iter().map(|l| (l, None)).collect::<Vec<_>>();
+            let patch = patches::line_patch("Cargo.toml", &manifest, &edits, &description);
+            push_generated_patch(pkg_srcdir, &series_path, DROP_DEV_DEPS_PATCH, &patch)?;
+            debcargo_info!("Dropped the dev-dependencies that are not in Debian from Cargo.toml, \
+                            in debian/patches/{}: {}", DROP_DEV_DEPS_PATCH, unavailable.join(", "));
+            generated_patches.push(format!("patches/{}", DROP_DEV_DEPS_PATCH));
+        }
+        let manifest = fs::read_to_string(pkg_srcdir.join("Cargo.toml"))?;
+        let mut edits = vec![];
+        for &(ref name, ref req) in &relax {
+            let relaxed = patches::relaxed_dependency_lines(&manifest, name, req);
+            if relaxed.is_empty() {
+                debcargo_warn!("The relax rule for {} changes nothing, as Cargo.toml has no such dependency \
+                                or it already requires {}; remove it from debcargo.toml", name, req);
+            }
+            edits.extend(relaxed.into_iter().map(|(l, new)| (l, Some(new))));
+        }
+        if !edits.is_empty() {
+            edits.sort();
+            let rules = relax.iter().map(|&(ref n, ref r)| format!("{} {}", n, r)).collect::<Vec<_>>();
+            let description = format!(
+                "Description: Relax the dependencies to the versions in Debian\n \
+                 Generated by debcargo from the relax rules in debcargo.toml:\n \
+                 {}.\nForwarded: not-needed\n---",
+                rules.join(", ")
+            );
+            let patch = patches::line_patch("Cargo.toml", &manifest, &edits, &description);
+            push_generated_patch(pkg_srcdir, &series_path, RELAX_DEPS_PATCH, &patch)?;
+            debcargo_info!("Relaxed the dependencies in debian/patches/{}: {}", RELAX_DEPS_PATCH, rules.join(", "));
+            generated_patches.push(format!("patches/{}", RELAX_DEPS_PATCH));
+        }
+        if !generated_patches.is_empty() && !generated_patches.iter().any(|p| p == "patches/series") {
+            generated_patches.insert(0, "patches/series".to_string());
+        }
         crate_info.replace_manifest(&pkg_srcdir.join("Cargo.toml"))?;
         Command::new("quilt")
                 .current_dir(&pkg_srcdir)
@@ -223,9 +332,14 @@ pub fn prepare_debian_folder(
                 .status().expect("failed to unapply patches");
     }
 
-    let lib = crate_info.is_lib();
+    // an application with vendored dependencies provides no library packages
+    let lib = crate_info.is_lib() && vendoring.is_none();
     let mut bins = crate_info.get_binary_targets();
     let meta = crate_info.metadata();
+    if vendoring.is_some() && bins.is_empty() {
+        debcargo_bail!("Vendoring is only for crates with binaries, but {} has none",
+                       crate_info.package_id().name());
+    }
 
     if lib && !bins.is_empty() && !config.build_bin_package() {
         bins.clear();
@@ -243,7 +357,32 @@ pub fn prepare_debian_folder(
         &config.bin_name
     };
 
-    let mut features_with_deps = crate_info.all_dependencies_and_features();
+    let mut features_with_deps = crate_info.all_dependencies_and_features(&DependencySelection::default())?;
+    let unpackageable = vec_opt_iter(config.unpackageable_features.as_ref()).cloned().collect::<Vec<_>>();
+    let unpackageable_enabling = remove_unpackageable_features(&mut features_with_deps, &unpackageable)?;
+    // optional dependencies as Recommends or Suggests of the main package,
+    // depending on whether the default features enable them
+    let optional_inlined = if config.optional_deps_as_suggests && !config.single_package {
+        let default_features = traverse_depth_2(&features_with_deps, "default");
+        let optional = crate_info.dependencies().iter()
+            .filter(|d| d.is_optional())
+            .map(|d| d.name_in_toml().as_str())
+            .collect::<Vec<_>>();
+        inline_optional_dependencies(&mut features_with_deps, &optional)
+            .into_iter()
+            .map(|(f, deps)| (f, deps, default_features.contains(&f)))
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+    check_deb_feature_collisions(features_with_deps.keys().cloned())?;
+    if config.all_features_package {
+        if let Some(f) = features_with_deps.keys().find(|&f| deb_feature(f) == ALL_FEATURES) {
+            debcargo_bail!(
+                "Feature \"{}\" would be packaged as the all-features metapackage; \
+                 unset all_features_package", f);
+        }
+    }
     let dev_depends = deb_deps(config, &crate_info.dev_dependencies())?;
     /*debcargo_info!("features_with_deps: {:?}", features_with_deps
         .iter()
@@ -256,11 +395,12 @@ pub fn prepare_debian_folder(
 
     let crate_name = crate_info.package_id().name();
     let crate_version = crate_info.package_id().version();
+    let dh_cargo_version = config.dh_cargo_version().unwrap_or(DEFAULT_DH_CARGO_VERSION);
     let base_pkgname = pkgbase.base_package_name();
     let name_suffix = pkgbase.name_suffix();
     let upstream_name = pkgbase.upstream_name();
 
-    let mut new_hints = vec![];
+    let mut new_hints = generated_patches;
     {
         let mut file = |name: &str| {
             let path = tempdir.path();
@@ -291,9 +431,11 @@ pub fn prepare_debian_folder(
             checksum
         )?;
 
-        // debian/compat
-        let mut compat = file("compat")?;
-        writeln!(compat, "11")?;
+        // debian/compat, unless the compat level is given in Build-Depends
+        if config.debhelper_compat().is_none() {
+            let mut compat = file("compat")?;
+            writeln!(compat, "{}", DEFAULT_DEBHELPER_COMPAT)?;
+        }
 
         // debian/copyright
         let uploaders: Vec<&str> = vec_opt_iter(config.uploaders()).map(String::as_str).collect();
@@ -315,7 +457,10 @@ pub fn prepare_debian_folder(
             crate_info.manifest(),
             &uploaders,
             year_range,
-            copyright_guess_harder,
+            config.copyright_guess.unwrap_or_default(),
+            vendoring.map_or(&[][..], |v| &v.crates[..]),
+            config.orig_tar_excludes().map_or(&[][..], |e| &e[..]),
+            crate_info.crates_io_owners(),
         )?;
         write!(copyright, "{}", dep5_copyright)?;
 
@@ -325,11 +470,17 @@ pub fn prepare_debian_folder(
             .uscan_version_pattern
             .as_ref()
             .map_or_else(|| "@ANY_VERSION@".to_string(), |ref s| s.to_string());
+        // with excludes, uscan repacks the tarball according to Files-Excluded
+        let repack_opts = pkgbase.repack_suffix.as_ref().map_or_else(String::new, |s| format!(
+            "repack,compression=gz,repacksuffix={},dversionmangle=s/{}$//,\\\n",
+            s,
+            regex::escape(s)));
         writeln!(
             watch,
-            "{}\n{}\n{}\n{}\n",
+            "{}\n{}\n{}{}\n{}\n",
             r"version=4",
             format!(r"opts=filenamemangle=s/.*\/(.*)\/download/{name}-$1\.tar\.gz/g,\", name = upstream_name),
+            repack_opts,
             r"uversionmangle=s/(\d)[_\.\-\+]?((RC|rc|pre|dev|beta|alpha)\d*)$/$1~$2/ \",
             format!("https://qa.debian.org/cgi-bin/fakeupstream.cgi?upstream=crates.io/{name} \
                      .*/crates/{name}/{version_pattern}/download",
@@ -337,6 +488,15 @@ pub fn prepare_debian_folder(
                     version_pattern = uscan_version_pattern)
         )?;
 
+        // debian/upstream/metadata, recording what exactly we packaged
+        if let Some(metadata) = upstream_metadata(
+            crate_info.git_origin(),
+            crate_info.checksum(),
+            crate_info.provenance(),
+        ) {
+            write!(file("upstream/metadata")?, "{}", metadata)?;
+        }
+
         // debian/source/format
         fs::create_dir_all(tempdir.path().join("source"))?;
         let mut source_format = file("source/format")?;
@@ -355,14 +515,83 @@ pub fn prepare_debian_folder(
                 broken_tests.insert(
                     feature, broken || all_deps.iter().any(|f| is_broken(f)));
             }
+            for &(feature, _, _) in &optional_inlined {
+                broken_tests.insert(feature, is_broken(feature));
+            }
             (is_broken("@") || any_test_broken, broken_tests)
         };
         let test_is_broken_for = |f: &str| *broken_tests.get(f).unwrap();
 
+        // debian/README.source, explaining what is changed from upstream
+        let series = tempdir.path().join("patches").join("series");
+        let changes = SourceChanges {
+            excludes: config.orig_tar_excludes().map_or(&[][..], |e| &e[..]),
+            repack_suffix: config.repack_suffix(),
+            whitelist: config.orig_tar_whitelist().map_or(&[][..], |w| &w[..]),
+            unpackageable: &unpackageable,
+            unpackageable_enabling: &unpackageable_enabling,
+            broken_tests: iter::once("@")
+                .chain(features_with_deps.keys().cloned())
+                .chain(optional_inlined.iter().map(|&(f, _, _)| f))
+                .filter(|&f| config.package_test_is_broken(PackageKey::feature(f)).unwrap_or(false))
+                .map(|f| if f.is_empty() { "lib".to_string() } else { format!("lib+{}", f) })
+                .collect(),
+            patches: if series.exists() {
+                patches::parse_series(&fs::read_to_string(&series)?)
+            } else {
+                vec![]
+            },
+        };
+        if let Some(readme) = readme_source(&changes) {
+            write!(file("README.source")?, "{}", readme)?;
+        }
+
         // debian/rules
-        let mut rules = file("rules")?;
-        rules.set_permissions(fs::Permissions::from_mode(0o777))?;
-        if !dev_depends.is_empty() {
+        let mut rules = String::new();
+        if let Some(v) = vendoring {
+            let mut cargo_config = file("cargo-config.toml")?;
+            write!(cargo_config, "{}", vendor::CARGO_CONFIG)?;
+            let copy_lockfile = match v.lockfile {
+                Some(ref lockfile) => {
+                    let mut cargo_lock = file("Cargo.lock")?;
+                    write!(cargo_lock, "{}", lockfile)?;
+                    "\tcp debian/Cargo.lock Cargo.lock\n"
+                }
+                None => "",
+            };
+            write!(
+                rules,
+                concat!(
+                    "#!/usr/bin/make -f\n",
+                    "export CARGO_HOME = $(CURDIR)/debian/cargo_home\n",
+                    "\n",
+                    "%:\n",
+                    "\tdh $@\n",
+                    "\n",
+                    "override_dh_auto_configure:\n",
+                    "\tmkdir -p $(CARGO_HOME)\n",
+                    "\tcp debian/cargo-config.toml $(CARGO_HOME)/config.toml\n",
+                    "{}",
+                    "\n",
+                    "override_dh_auto_build:\n",
+                    "\tcargo build --release --frozen\n",
+                    "\n",
+                    "override_dh_auto_test:\n",
+                    "\tcargo test --release --frozen\n",
+                    "\n",
+                    "override_dh_auto_install:\n",
+                    "\tcargo install --frozen --path . --root debian/tmp/usr\n",
+                    "\n",
+                    "override_dh_auto_clean:\n",
+                    "\tdh_auto_clean\n",
+                    "\trm -rf $(CARGO_HOME) target\n",
+                ),
+                copy_lockfile,
+            )?;
+            // the binary package comes first, as there are no lib packages
+            let mut install = file("install")?;
+            writeln!(install, "usr/bin")?;
+        } else if !dev_depends.is_empty() {
             write!(
                 rules,
                 "{}",
@@ -394,39 +623,123 @@ pub fn prepare_debian_folder(
                 },
             )?;
         }
+        let extra_rules = vec_opt_iter(config.rules_extra_lines()).cloned().collect::<Vec<_>>();
+        let mut rules_file = file("rules")?;
+        rules_file.set_permissions(fs::Permissions::from_mode(0o777))?;
+        rules_file.write_all(with_extra_rules(&rules, &extra_rules).as_bytes())?;
 
-        // debian/tests/control
-        let mut testctl = io::BufWriter::new(file("tests/control")?);
-        write!(
-            testctl,
-            "{}",
-            PkgTest::new(
-                "@",
-                &crate_name,
-                &crate_version,
-                vec!["--all-features"],
-                &dev_depends,
-                if all_features_test_broken { vec!["flaky"] } else { vec![] },
-            )?
-        )?;
+        // debian/tests/control, only if there are tests we can run
+        let testsuite = match config.testsuite() {
+            // the autopkgtests test the library packages against each other
+            _ if vendoring.is_some() => false,
+            Some(testsuite) => testsuite,
+            None => {
+                let testsuite = has_offline_tests(pkg_srcdir)?;
+                if !testsuite {
+                    debcargo_info!("No tests that can run offline found, not generating autopkgtests; \
+                        set source.testsuite = true to override.");
+                }
+                testsuite
+            }
+        };
+        let mut testctl = if testsuite {
+            Some(io::BufWriter::new(file("tests/control")?))
+        } else {
+            None
+        };
+        let naming = PackageNaming::from_config(config)?;
+        let all_features_package = if lib && config.all_features_package && !config.single_package {
+            Some(config.package_name(PackageKey::feature(ALL_FEATURES)).map(str::to_string).unwrap_or_else(
+                || naming.feature_name(pkgbase.package_name(), ALL_FEATURES)))
+        } else {
+            None
+        };
+        // with a single package, nothing installs what the other features
+        // need; nor what optional dependencies need, without their packages
+        let all_features_depends = {
+            let mut depends = dev_depends.clone();
+            let mut unpulled = optional_inlined.iter().map(|&(_, ref deps, _)| deps).collect::<Vec<_>>();
+            if lib && config.single_package {
+                unpulled.extend(features_with_deps.values().map(|&(_, ref o_deps)| o_deps));
+            }
+            for o_deps in unpulled {
+                for dep in deb_deps(config, o_deps)? {
+                    if !depends.contains(&dep) {
+                        depends.push(dep);
+                    }
+                }
+            }
+            depends
+        };
+        // all the features that are packaged, which may not be all of them
+        let mut all_features = features_with_deps.keys().cloned().filter(|&f| f != "").collect::<Vec<_>>();
+        all_features.extend(optional_inlined.iter().map(|&(f, _, _)| f));
+        all_features.sort();
+        let all_features = all_features.join(",");
+        let all_features_args = if unpackageable.is_empty() {
+            vec!["--all-features"]
+        } else {
+            vec!["--features", all_features.as_str()]
+        };
+        if let Some(ref mut testctl) = testctl {
+            write!(
+                testctl,
+                "{}",
+                PkgTest::new(
+                    all_features_package.as_ref().map_or("@", String::as_str),
+                    &crate_name,
+                    &crate_version,
+                    dh_cargo_version,
+                    all_features_args.clone(),
+                    &all_features_depends,
+                    if all_features_test_broken { vec!["flaky"] } else { vec![] },
+                )?
+            )?;
+            if lib && config.test_examples() {
+                if crate_info.get_example_targets().is_empty() {
+                    debcargo_warn!("test_examples is set, but the crate has no examples");
+                } else {
+                    write!(
+                        testctl,
+                        "\n{}",
+                        PkgTest::examples(
+                            &crate_name,
+                            &crate_version,
+                            dh_cargo_version,
+                            all_features_args,
+                            &dev_depends,
+                            if all_features_test_broken { vec!["flaky"] } else { vec![] },
+                        )?
+                    )?;
+                }
+            }
+        }
 
         // debian/control
         let build_deps = {
-            let build_deps = [
-                "debhelper (>= 11)",
-                "dh-cargo (>= 18)"
-                ].iter().map(|x| x.to_string());
+            let debhelper = match config.debhelper_compat() {
+                Some(level) => format!("debhelper-compat (= {})", level),
+                None => format!("debhelper (>= {})", DEFAULT_DEBHELPER_COMPAT),
+            };
+            let build_deps = vec![
+                debhelper,
+                format!("dh-cargo (>= {})", dh_cargo_version),
+                ].into_iter();
             let (default_features, default_deps) = crate_info.feature_all_deps(&features_with_deps, "default");
             //debcargo_info!("default_features: {:?}", default_features);
             //debcargo_info!("default_deps: {:?}", deb_deps(config, &default_deps)?);
             let extra_override_deps =
                 package_field_for_feature(&|x| config.package_depends(x), PackageKey::feature("default"), &default_features);
-            let build_deps_extra = [
-                "cargo:native",
-                "rustc:native",
-                "libstd-rust-dev",
-                ].iter().map(|s| s.to_string())
-                .chain(deb_deps(config, &default_deps)?)
+            let with_version = |pkg: &str, version: Option<&str>| match version {
+                Some(v) => format!("{} (>= {})", pkg, v),
+                None => pkg.to_string(),
+            };
+            let build_deps_extra = vec![
+                with_version("cargo:native", config.cargo_version()),
+                with_version("rustc:native", config.rustc_version()),
+                "libstd-rust-dev".to_string(),
+                ].into_iter()
+                .chain(if vendoring.is_some() { vec![] } else { deb_deps(config, &default_deps)? })
                 .chain(extra_override_deps);
             if !bins.is_empty() {
                 build_deps.chain(build_deps_extra).collect()
@@ -439,25 +752,54 @@ pub fn prepare_debian_folder(
             base_pkgname,
             name_suffix,
             upstream_name,
-            if let Some(ref home) = meta.homepage { home } else { "" },
+            &crate_info.homepage().unwrap_or_default(),
             lib,
+            testsuite,
             uploaders.iter().map(|s| s.to_string()).collect(),
             build_deps
         )?;
 
         // If source overrides are present update related parts.
         source.apply_overrides(config);
+        if let Some(b) = backport {
+            if let Some(versions) = config.backport_build_depends_versions(&b.codename) {
+                source.set_build_depends_versions(versions);
+            }
+        }
 
         let mut control = io::BufWriter::new(file("control")?);
         write!(control, "{}", source)?;
 
         // Summary and description generated from Cargo.toml
-        let (summary, description) = crate_info.get_summary_description();
+        let description_rules = DescriptionRules::from_config(config)?;
+        let (summary, description) = if meta.description.is_some() || !config.summary.is_empty() {
+            if meta.description.is_some() {
+                debcargo_explain!("The summary and description are from the description in Cargo.toml");
+            }
+            crate_info.get_summary_description(&description_rules)
+        } else {
+            match fallback_description(crate_info, pkg_srcdir, description_guess_harder) {
+                Some(d) => crate_info.summarize_description(&d, &description_rules),
+                None => {
+                    if strict {
+                        debcargo_bail!(concat!(
+                            "Crate has no description, and none could be found elsewhere. ",
+                            "Set summary and description in debcargo.toml."));
+                    }
+                    debcargo_warn!("Crate has no description, using a generic one");
+                    writeln!(control, "\n{}", concat!(
+                        "# FIXME (summary) crate has no description, ",
+                        "set summary and description in debcargo.toml"))?;
+                    (None, None)
+                }
+            }
+        };
         let summary = if !config.summary.is_empty() {
+            debcargo_explain!("The summary is from debcargo.toml");
             Some(config.summary.as_str())
         } else {
             if let Some(summary) = summary.as_ref() {
-                if summary.len() > 80 {
+                if util::display_width(summary) > 80 {
                     writeln!(control, "\n{}", concat!(
                         "# FIXME (packages.\"(name)\".section) debcargo ",
                         "auto-generated summaries are very long, consider overriding"))?;
@@ -468,11 +810,61 @@ pub fn prepare_debian_folder(
         let description = if config.description.is_empty() {
             description.as_ref().map(String::as_str)
         } else {
+            debcargo_explain!("The description is from debcargo.toml");
             Some(config.description.as_str())
         };
 
-        if lib {
-            let mut provides = crate_info.calculate_provides(&mut features_with_deps);
+        if lib && config.single_package {
+            // just the base package, depending on everything the default
+            // features need, which is also what its default test runs with;
+            // other features are provided but not pulled in
+            let mut default_build = features_with_deps.clone();
+            DependencySelection { features: Some(vec!["default".to_string()]) }.apply(&mut default_build)?;
+            let all_deps = default_build.values().flat_map(|&(_, ref deps)| deps.iter().cloned()).collect::<Vec<_>>();
+            let mut o_deps = vec![];
+            for dep in deb_deps(config, &all_deps)? {
+                if !o_deps.contains(&dep) {
+                    o_deps.push(dep);
+                }
+            }
+            let f_provides: Vec<&str> = features_with_deps.keys().cloned().filter(|&f| f != "").collect();
+            let mut package =
+                Package::new(base_pkgname, name_suffix, &crate_info.version(), config.epoch, upstream_name,
+                    summary, description, None, vec![], o_deps, f_provides.clone(), vec![], vec![], &naming)?;
+            package.apply_overrides(config, PackageKey::feature(""), f_provides);
+            write!(control, "\n{}", package)?;
+
+            if let Some(ref mut testctl) = testctl {
+                let pkgtest = PkgTest::new(
+                    package.name(),
+                    &crate_name,
+                    &crate_version,
+                    dh_cargo_version,
+                    vec![],
+                    &dev_depends,
+                    if test_is_broken_for("") || test_is_broken_for("default") { vec!["flaky"] } else { vec![] },
+                )?;
+                write!(testctl, "\n{}", pkgtest)?;
+            }
+        } else if lib {
+            let mut provides = crate_info.calculate_provides(&mut features_with_deps, config.provide_zero_dep_features);
+            if !optional_inlined.is_empty() {
+                let base_provides = provides.get_mut("").unwrap();
+                base_provides.extend(optional_inlined.iter().map(|&(f, _, _)| f));
+                base_provides.sort();
+            }
+            if let Some(max) = config.max_feature_packages {
+                let packages = features_with_deps.len();
+                let folded = cap_feature_packages(&mut features_with_deps, &mut provides, max);
+                if !folded.is_empty() {
+                    debcargo_warn!(
+                        "{} library packages are more than max_feature_packages = {}; the main package \
+                         now provides {} more features, and depends on everything that they need \
+                         even when they are not used: {}",
+                        packages, max, folded.len(), folded.join(", ")
+                    );
+                }
+            }
             //debcargo_info!("provides: {:?}", provides);
             let mut recommends = vec![];
             let mut suggests = vec![];
@@ -485,38 +877,95 @@ pub fn prepare_debian_folder(
                     suggests.push(feature);
                 }
             }
+            // packages renamed in the config, so we can update references
+            // to them from other packages of this crate
+            let renames: BTreeMap<String, String> = features_with_deps.keys().filter_map(|&f| {
+                config.package_name(PackageKey::feature(f)).map(|name| {
+                    (naming.name_for(pkgbase.package_name(), f), name.to_string())
+                })
+            }).collect();
+            let feature_packages: Vec<&str> = features_with_deps.keys().cloned().collect();
             for (feature, (f_deps, o_deps)) in features_with_deps.into_iter() {
                 let f_provides = provides.remove(feature).unwrap();
                 let mut package =
-                    Package::new(base_pkgname, name_suffix, &crate_info.version(), upstream_name,
+                    Package::new(base_pkgname, name_suffix, &crate_info.version(), config.epoch, upstream_name,
                         summary, description,
                         if feature == "" { None } else { Some(feature) },
                         f_deps, deb_deps(config, &o_deps)?,
                         f_provides.clone(),
                         if feature == "" { recommends.clone() } else { vec![] },
-                        if feature == "" { suggests.clone() } else { vec![] })?;
+                        if feature == "" { suggests.clone() } else { vec![] },
+                        &naming)?;
+                if feature == "" {
+                    let (mut optional_recommends, mut optional_suggests) = (vec![], vec![]);
+                    for &(_, ref deps, by_default) in &optional_inlined {
+                        if by_default {
+                            optional_recommends.extend(deb_deps(config, deps)?);
+                        } else {
+                            optional_suggests.extend(deb_deps(config, deps)?);
+                        }
+                    }
+                    package.add_relations(optional_recommends, optional_suggests);
+                }
 
                 let test_is_broken = test_is_broken_for(feature) || f_provides.iter().any(|f| test_is_broken_for(f));
+                let provides_default = feature != "default" && f_provides.contains(&"default");
 
+                for &f in &f_provides {
+                    match config.package_summary(PackageKey::feature(f)) {
+                        Some((s, d)) if !s.is_empty() || !d.is_empty() => debcargo_warn!(
+                            "The summary and description of packages.\"lib+{}\" are not used, since \
+                             that feature is provided by {}; set them there instead",
+                            f, package.name()),
+                        _ => (),
+                    }
+                }
                 // If any overrides present for this package it will be taken care.
                 package.apply_overrides(config, PackageKey::feature(feature), f_provides);
+                package.rename_relations(&renames);
                 write!(control, "\n{}", package)?;
 
-                let mut pkgtest = PkgTest::new(
-                    package.name(),
-                    &crate_name,
-                    &crate_version,
-                    if feature == "" {
-                        vec!["--no-default-features"]
-                    } else {
-                        vec!["--features", feature]
-                    },
-                    &dev_depends,
-                    if test_is_broken { vec!["flaky"] } else { vec![] },
-                )?;
-                write!(testctl, "\n{}", pkgtest)?;
+                if let Some(ref mut testctl) = testctl {
+                    let pkgtest = PkgTest::new(
+                        package.name(),
+                        &crate_name,
+                        &crate_version,
+                        dh_cargo_version,
+                        if feature == "" {
+                            vec!["--no-default-features"]
+                        } else {
+                            vec!["--features", feature]
+                        },
+                        &dev_depends,
+                        if test_is_broken { vec!["flaky"] } else { vec![] },
+                    )?;
+                    write!(testctl, "\n{}", pkgtest)?;
+                    // what dh_auto_test runs at build time, so the feature
+                    // matrix covers none, default and all features
+                    if provides_default {
+                        let pkgtest = PkgTest::new(
+                            package.name(),
+                            &crate_name,
+                            &crate_version,
+                            dh_cargo_version,
+                            vec![],
+                            &dev_depends,
+                            if test_is_broken_for("default") { vec!["flaky"] } else { vec![] },
+                        )?;
+                        write!(testctl, "\n{}", pkgtest)?;
+                    }
+                }
             }
             assert!(provides.is_empty());
+
+            if all_features_package.is_some() {
+                let mut package = Package::new_all_features(
+                    base_pkgname, name_suffix, &crate_info.version(), config.epoch, upstream_name,
+                    summary, feature_packages, &naming)?;
+                package.apply_overrides(config, PackageKey::feature(ALL_FEATURES), vec![]);
+                package.rename_relations(&renames);
+                write!(control, "\n{}", package)?;
+            }
             // features_with_deps consumed by into_iter, no longer usable
         }
 
@@ -546,9 +995,33 @@ pub fn prepare_debian_folder(
             write!(control, "\n{}", bin_pkg)?;
         }
 
+        // warn about package names that go away when crossing a semver boundary
+        if lib && name_suffix.is_none() {
+            if let Ok(data) = fs::read_to_string(tempdir.path().join("changelog")) {
+                let new = crate_info.version();
+                match changelog::previous_upstream_version(&data, pkgbase.debian_version()) {
+                    Some(ref old) if old < new && semver_suffix_of(old) != semver_suffix_of(new) => {
+                        let old_base = format!("{}{}", base_pkgname, semver_suffix_of(old));
+                        debcargo_warn!(
+                            "Upstream version {} crosses a semver boundary from the previous {}. \
+                             {} and {} will no longer be provided, so their reverse \
+                             dependencies need to be updated, or a rust-{} package (with \
+                             semver_suffix = true) uploaded first to keep providing them.",
+                            new, old, naming.name(&old_base), naming.feature_name_pattern(&old_base),
+                            old_base
+                        );
+                    }
+                    _ => (),
+                }
+            }
+        }
+
         // debian/changelog
         if !changelog_ready {
-            let author = control::get_deb_author()?;
+            let author = match config.changelog_author {
+                Some(ref a) => a.clone(),
+                None => control::get_deb_author()?,
+            };
             let autogenerated_item = format!(
                 "  * Package {} {} from crates.io using debcargo {}",
                 &crate_name,
@@ -563,6 +1036,10 @@ pub fn prepare_debian_folder(
             // - Always prepend to any existing file from the overlay.
             // - If the first entry is changelog::DEFAULT_DIST then write over that, smartly
             let (mut changelog, changelog_data) = changelog_or_new(tempdir.path())?;
+            let upstream_deb_version = with_epoch(config.epoch, pkgbase.debian_version());
+            let vendor = config.vendor.as_ref().map(String::as_str);
+            let initial = changelog::initial_revision(
+                config.initial_revision.as_ref().map(String::as_str), vendor);
             let (changelog_old, mut changelog_items, deb_version_suffix) = match ChangelogIterator::from(&changelog_data).next() {
                 Some(x) => if x.contains(changelog::DEFAULT_DIST) {
                     let mut e = ChangelogEntry::from_str(x)?;
@@ -580,34 +1057,113 @@ pub fn prepare_debian_folder(
                         let ename = e.maintainer_name();
                         e.items.insert(2, format!("  [ {} ]", ename));
                     }
+                    let suf = if ups == upstream_deb_version { suf } else { initial };
                     (&changelog_data[x.len()..],
                      e.items,
-                     if ups == pkgbase.debian_version() { suf } else { "1".to_string() })
+                     match backport {
+                         Some(b) if !suf.contains(&format!("~bpo{}+", b.release)) => b.revision(&suf),
+                         _ => suf,
+                     })
                 } else {
                     let e = ChangelogEntry::from_str(x)?;
-                    let (ups, _suf) = e.version_parts();
+                    let (ups, suf) = e.version_parts();
                     (changelog_data.as_str(),
                      vec![autogenerated_item],
-                     if ups == pkgbase.debian_version() { e.deb_version_suffix_bump() } else { "1".to_string() })
+                     match (ups == upstream_deb_version, backport) {
+                         (true, Some(b)) => b.revision(&suf),
+                         (true, None) => e.deb_version_suffix_bump(vendor),
+                         (false, Some(b)) => b.revision(&initial),
+                         (false, None) => initial,
+                     })
                 },
                 None => {
                     (changelog_data.as_str(),
                      vec![autogenerated_item],
-                     "1".to_string())
+                     match backport {
+                         Some(b) => b.revision(&initial),
+                         None => initial,
+                     })
                 }
             };
+            if let Some(b) = backport {
+                if !changelog_items.contains(&b.changelog_item()) {
+                    changelog_items.insert(0, b.changelog_item());
+                }
+            }
+            if let Some(item) = unpackageable_changelog_item(&unpackageable) {
+                if !changelog_items.contains(&item) {
+                    changelog_items.push(item);
+                }
+            }
+            for entry in vec_opt_iter(config.changelog_entries.as_ref()) {
+                let item = format!("  * {}", entry);
+                if !changelog_items.contains(&item) {
+                    changelog_items.push(item);
+                }
+            }
 
-            let source_deb_version = format!("{}-{}", pkgbase.debian_version(), &deb_version_suffix);
-            if !uploaders.contains(&author.as_str()) {
-                debcargo_warn!("You ({}) are not in Uploaders; adding \"Team upload\" to d/changelog", author);
-                if !changelog_items.contains(&changelog::COMMENT_TEAM_UPLOAD.to_string()) {
-                    changelog_items.insert(0, changelog::COMMENT_TEAM_UPLOAD.to_string());
+            let source_deb_version = format!("{}-{}", upstream_deb_version, &deb_version_suffix);
+            let team_upload = match config.team_upload {
+                Some(t) => t,
+                None if !uploaders.contains(&author.as_str()) => {
+                    debcargo_warn!("You ({}) are not in Uploaders; adding \"Team upload\" to d/changelog", author);
+                    true
                 }
+                None => false,
+            };
+            let team_upload_item = changelog::COMMENT_TEAM_UPLOAD.to_string();
+            if team_upload && !changelog_items.contains(&team_upload_item) {
+                changelog_items.insert(0, team_upload_item);
+            } else if config.team_upload == Some(false) {
+                changelog_items.retain(|i| *i != team_upload_item);
             }
+            let distribution = match backport {
+                Some(b) => b.distribution(),
+                None => changelog::DEFAULT_DIST.to_string(),
+            };
+
+            // d/NEWS, with an entry for this version if the config has any
+            let news_items = vec_opt_iter(config.news_entries.as_ref())
+                .map(|e| format!("  * {}", e))
+                .collect::<Vec<_>>();
+            if !news_items.is_empty() {
+                let news_data = fs::read_to_string(tempdir.path().join("NEWS")).unwrap_or_default();
+                // like d/changelog, an unreleased entry is written over
+                let news_old = match ChangelogIterator::from(&news_data).next() {
+                    Some(x) => {
+                        let e = ChangelogEntry::from_str(x)?;
+                        if e.distribution.contains(changelog::DEFAULT_DIST) || e.version == source_deb_version {
+                            &news_data[x.len()..]
+                        } else {
+                            news_data.as_str()
+                        }
+                    }
+                    None => "",
+                };
+                let news_new_entry = ChangelogEntry::new(
+                    source.srcname().to_string(),
+                    source_deb_version.clone(),
+                    distribution.clone(),
+                    "urgency=medium".to_string(),
+                    author.clone(),
+                    changelog::local_now(),
+                    news_items,
+                );
+                let news = if news_old.is_empty() {
+                    news_new_entry.to_string()
+                } else {
+                    format!("{}\n{}", news_new_entry, news_old)
+                };
+                fs::write(tempdir.path().join("NEWS"), news)?;
+                if overlay.is_some() {
+                    new_hints.push("NEWS".to_string());
+                }
+            }
+
             let changelog_new_entry = ChangelogEntry::new(
                 source.srcname().to_string(),
                 source_deb_version,
-                changelog::DEFAULT_DIST.to_string(),
+                distribution,
                 "urgency=medium".to_string(),
                 author,
                 changelog::local_now(),
@@ -626,26 +1182,405 @@ pub fn prepare_debian_folder(
         }
     }
 
+    // debian/debcargo.stamp
+    let stamp_path = tempdir.path().join(STAMP_FILE);
+    let previous = match fs::read_to_string(&stamp_path) {
+        Ok(data) => Stamp::parse(&data),
+        Err(_) => fs::read_to_string(tempdir.path().join("changelog"))
+            .ok()
+            .and_then(|data| Stamp::from_changelog(&data)),
+    };
+    let stamp = Stamp {
+        debcargo_version: pkgbase.debcargo_version().to_string(),
+        config_sha256: config.digest.clone(),
+    };
+    for change in previous.iter().flat_map(|p| stamp.changes_since(p)) {
+        debcargo_warn!("{}", change);
+    }
+    fs::write(&stamp_path, stamp.to_string())?;
+    new_hints.push(STAMP_FILE.to_string());
+
+    let binaries = update_include_binaries(tempdir.path())?;
+    if !binaries.is_empty() {
+        debcargo_info!("Listed binary files in debian/source/include-binaries: {}", binaries.join(", "));
+    }
+
+    let syntax_errors = syntax::check_debian_dir(tempdir.path())?;
+    if !syntax_errors.is_empty() {
+        let errors = syntax_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        debcargo_bail!("Generated files are malformed, this is probably a bug in debcargo \
+                        or in the overlay:\n{}", errors.join("\n"));
+    }
+
+    // what ftpmasters check in NEW: d/copyright must cover every file
+    if let Ok(copyright) = fs::read_to_string(tempdir.path().join("copyright")) {
+        let files = source_files(pkg_srcdir, tempdir.path())?;
+        let unmatched = copyright::unmatched_files(&copyright, &files);
+        if !unmatched.is_empty() {
+            let unmatched = unmatched.iter().map(|p| p.display().to_string()).collect::<Vec<_>>();
+            if strict {
+                debcargo_bail!("No Files paragraph of d/copyright covers:\n{}", unmatched.join("\n"));
+            }
+            if json_warnings() {
+                for f in &unmatched {
+                    let message = "No Files paragraph of d/copyright covers this file";
+                    warn_with_code("copyright-unmatched", Some(Path::new(f)), None, message);
+                }
+            } else {
+                debcargo_warn!("No Files paragraph of d/copyright covers: {}", unmatched.join(", "));
+            }
+        }
+    }
+
     if overlay_write_back {
-        overlay.as_ref().map(|p| {
+        if let Some(ref p) = overlay {
             if !changelog_ready {
                 // Special-case d/changelog:
                 // Always write it back, this is safe because of our prepending logic
                 new_hints.push("changelog".to_string());
             }
-            for hint in &new_hints {
-                let newpath = tempdir.path().join(hint);
-                let oldpath = p.join(hint);
-                fs::copy(newpath, oldpath).expect("could not write back");
-                debcargo_info!("Wrote back file to overlay: {}", hint);
-            }
-        });
+            write_back_overlay(tempdir.path(), p, &new_hints)?;
+        }
     }
 
     fs::rename(tempdir.path(), pkg_srcdir.join("debian"))?;
     Ok(())
 }
 
+/// Copy the files named by `hints`, relative to the generated `debian_dir`,
+/// back into the overlay, creating the directories that it doesn't have yet,
+/// e.g. patches/ for the first patch that debcargo generates.
+pub fn write_back_overlay(debian_dir: &Path, overlay: &Path, hints: &[String]) -> Result<()> {
+    for hint in hints {
+        let oldpath = overlay.join(hint);
+        if let Some(parent) = oldpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(debian_dir.join(hint), &oldpath)
+            .context(format!("Couldn't write back {} to the overlay", hint))?;
+        debcargo_info!("Wrote back file to overlay: {}", hint);
+    }
+    Ok(())
+}
+
+/// Write a patch that debcargo generates, add it to the end of the series,
+/// and apply it to the unpacked source package, after the others. These must
+/// all be applied already, since the patch is generated on top of them.
+fn push_generated_patch(pkg_srcdir: &Path, series_path: &Path, name: &str, patch: &str) -> Result<()> {
+    let patches_dir = series_path.parent().unwrap();
+    // quilt next only succeeds if some patch of the series is unapplied,
+    // which is the one that failed to apply
+    let next = Command::new("quilt")
+        .current_dir(pkg_srcdir)
+        .env("QUILT_PATCHES", patches_dir)
+        .args(&["next", "--quiltrc=-"])
+        .output()?;
+    if next.status.success() {
+        let unapplied = String::from_utf8_lossy(&next.stdout);
+        let unapplied = Path::new(unapplied.trim());
+        debcargo_bail!("The patch {} in the overlay doesn't apply, so debcargo can't generate {} \
+                        on top of it; rebase or drop it",
+                       unapplied.strip_prefix(patches_dir).unwrap_or(unapplied).display(), name);
+    }
+    fs::create_dir_all(patches_dir)?;
+    fs::write(series_path.with_file_name(name), patch)?;
+    let mut series = fs::read_to_string(series_path).unwrap_or_default();
+    series.push_str(&format!("{}\n", name));
+    fs::write(series_path, series)?;
+    // by name, so that it is this patch that is pushed and no other
+    let pushed = Command::new("quilt")
+        .current_dir(pkg_srcdir)
+        .env("QUILT_PATCHES", patches_dir)
+        .args(&["push", "--quiltrc=-", name])
+        .status()?;
+    if !pushed.success() {
+        debcargo_bail!("The patch {} that debcargo generated doesn't apply, this is probably a bug in debcargo", name);
+    }
+    Ok(())
+}
+
+/// The dev-dependencies of a crate, as named in its Cargo.toml, that no
+/// package in Debian satisfies, as far as apt knows.
+fn unavailable_dev_dependencies(crate_info: &CrateInfo, naming: &PackageNaming) -> Vec<String> {
+    let mut archive = tree::Archive::default();
+    let mut unavailable = vec![];
+    for dep in crate_info.dev_dependencies() {
+        let version = match crate_info.newest_registry_version(&dep) {
+            Ok(Some(v)) => v,
+            _ => continue,
+        };
+        match archive.status(naming, &dep.package_name(), dep.version_req(), &version) {
+            DebianStatus::Missing | DebianStatus::Mismatch(..) => unavailable.push(dep.name_in_toml().to_string()),
+            DebianStatus::Unknown => {
+                debcargo_warn!("Couldn't look up the dev-dependencies with apt-cache, not dropping any");
+                return vec![];
+            }
+            DebianStatus::Ok(..) => (),
+        }
+    }
+    unavailable.sort();
+    unavailable.dedup();
+    unavailable
+}
+
+/// The files of a source package, relative to its root, with the packaging
+/// taken from `debian_dir` since it isn't in place yet.
+fn source_files(pkg_srcdir: &Path, debian_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for (root, prefix) in &[(pkg_srcdir, Path::new("")), (debian_dir, Path::new("debian"))] {
+        // skip quilt's state, and any packaging not taken from debian_dir
+        let walker = walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| e.depth() != 1 || (e.file_name() != ".pc" && e.file_name() != "debian"));
+        for entry in walker {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                files.push(prefix.join(entry.path().strip_prefix(root)?));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The names of the targets defined in some makefile lines.
+fn rules_targets(lines: &[String]) -> Vec<String> {
+    let target = Regex::new(r"^([^\s:#=]+)\s*:([^=]|$)").unwrap();
+    lines
+        .iter()
+        .filter_map(|l| target.captures(l).map(|c| c[1].to_string()))
+        .collect()
+}
+
+/// d/upstream/metadata recording where the packaged source came from, so that
+/// the orig tarball can be checked against it: the commit if it came from git,
+/// and otherwise the .crate it was made from.
+pub fn upstream_metadata(
+    origin: Option<&GitOrigin>,
+    checksum: Option<&str>,
+    provenance: &Provenance,
+) -> Option<String> {
+    if origin.is_none() && checksum.is_none() {
+        return None;
+    }
+    let mut metadata = "---\n".to_string();
+    if let Some(origin) = origin {
+        writeln!(metadata, "Repository: {}", origin.url).unwrap();
+        writeln!(metadata, "X-Git-Selector: {}", origin.selector).unwrap();
+        writeln!(metadata, "X-Git-Commit: {}", origin.commit).unwrap();
+    }
+    if let Some(ref url) = provenance.download_url {
+        writeln!(metadata, "X-Crate-Download: {}", url).unwrap();
+    }
+    if let Some(checksum) = checksum {
+        writeln!(metadata, "X-Crate-Sha256: {}", checksum).unwrap();
+    }
+    if let Some(ref published) = provenance.published {
+        writeln!(metadata, "X-Crate-Published: {}", published).unwrap();
+    }
+    Some(metadata)
+}
+
+/// What the packaging changes relative to the upstream crate, and why, as
+/// documented in d/README.source.
+#[derive(Debug, Default)]
+pub struct SourceChanges<'a> {
+    /// The globs of files excluded from the orig tarball.
+    pub excludes: &'a [String],
+    /// The suffix of the upstream version of the repacked orig tarball.
+    pub repack_suffix: Option<&'a str>,
+    /// The globs of suspicious files that are kept anyway.
+    pub whitelist: &'a [String],
+    /// The features that are not packaged.
+    pub unpackageable: &'a [String],
+    /// The features that are not packaged since they enable those.
+    pub unpackageable_enabling: &'a [&'a str],
+    /// The packages whose tests are known to fail, as keys of the packages
+    /// table of debcargo.toml, e.g. "lib+std".
+    pub broken_tests: Vec<String>,
+    /// The patches applied to the upstream source, in order.
+    pub patches: Vec<String>,
+}
+
+/// d/README.source documenting what the packaging changes relative to the
+/// upstream crate, if anything: the files excluded from the orig tarball,
+/// the features left out, the tests that are known to fail, and the patches.
+pub fn readme_source(changes: &SourceChanges) -> Option<String> {
+    let mut sections = Vec::new();
+    if !changes.excludes.is_empty() {
+        let mut section = match changes.repack_suffix {
+            Some(suffix) => format!(
+                "The orig tarball is repacked, with the version suffix {}, to leave out the\n\
+                 following files of the crate (excludes in debcargo.toml):\n\n", suffix),
+            None => "The following files of the crate are left out of the orig tarball\n\
+                     (excludes in debcargo.toml):\n\n".to_string(),
+        };
+        for e in changes.excludes {
+            writeln!(section, " - {}", e).unwrap();
+        }
+        sections.push(section);
+    }
+    if !changes.whitelist.is_empty() {
+        let mut section = concat!(
+            "The following files of the crate look suspicious, e.g. as sources of another\n",
+            "language, but were reviewed and are kept (whitelist in debcargo.toml):\n\n",
+        ).to_string();
+        for w in changes.whitelist {
+            writeln!(section, " - {}", w).unwrap();
+        }
+        sections.push(section);
+    }
+    if !changes.unpackageable.is_empty() {
+        let mut section = concat!(
+            "The following features of the crate are not packaged, since they cannot be\n",
+            "built in Debian (unpackageable_features in debcargo.toml):\n\n",
+        ).to_string();
+        for f in changes.unpackageable {
+            writeln!(section, " - {}", f).unwrap();
+        }
+        if !changes.unpackageable_enabling.is_empty() {
+            writeln!(section, "\nNeither are the features that enable them:\n").unwrap();
+            for f in changes.unpackageable_enabling {
+                writeln!(section, " - {}", f).unwrap();
+            }
+        }
+        sections.push(section);
+    }
+    if !changes.broken_tests.is_empty() {
+        let mut section = concat!(
+            "The tests of the following packages are known to fail, so their autopkgtests\n",
+            "are marked as flaky (test_is_broken in debcargo.toml):\n\n",
+        ).to_string();
+        for p in &changes.broken_tests {
+            writeln!(section, " - {}", p).unwrap();
+        }
+        sections.push(section);
+    }
+    if !changes.patches.is_empty() {
+        let mut section = "The following patches are applied to the crate (debian/patches):\n\n".to_string();
+        for p in &changes.patches {
+            writeln!(section, " - {}", p).unwrap();
+        }
+        sections.push(section);
+    }
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n"))
+    }
+}
+
+/// The d/changelog item for features that were left out of the packages.
+pub fn unpackageable_changelog_item(unpackageable: &[String]) -> Option<String> {
+    if unpackageable.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "  * Do not package the {} {}, which cannot be built in Debian; see\n    README.source.",
+        if unpackageable.len() == 1 { "feature" } else { "features" },
+        unpackageable.join(", ")
+    ))
+}
+
+/// d/rules with the given extra lines appended verbatim. Generated targets
+/// that the extra lines define again are left out, so that make uses the
+/// custom recipes without warning about overriding them.
+pub fn with_extra_rules(generated: &str, extra: &[String]) -> String {
+    if extra.is_empty() {
+        return generated.to_string();
+    }
+    let redefined = rules_targets(extra);
+    let mut kept: Vec<String> = Vec::new();
+    let mut skipping = false;
+    for line in generated.lines() {
+        if skipping && line.starts_with('\t') {
+            continue;
+        }
+        skipping = false;
+        let line = line.to_string();
+        if rules_targets(&[line.clone()]).iter().any(|t| redefined.contains(t)) {
+            skipping = true;
+            // along with the blank line separating it from the previous one
+            if kept.last().map_or(false, |l| l.is_empty()) {
+                kept.pop();
+            }
+            continue;
+        }
+        kept.push(line);
+    }
+    while kept.last().map_or(false, |l| l.is_empty()) {
+        kept.pop();
+    }
+    format!("{}\n\n{}\n", kept.join("\n"), extra.join("\n"))
+}
+
+/// Whether dpkg-source would consider a file binary, which like diff(1) it
+/// does if it contains a NUL byte.
+pub fn is_binary(data: &[u8]) -> bool {
+    data.contains(&0)
+}
+
+/// dpkg-source refuses binary files in debian/, e.g. test fixtures or images
+/// from the overlay, unless they are listed in debian/source/include-binaries.
+/// Add those that are missing from it, returning them.
+pub fn update_include_binaries(debian_dir: &Path) -> Result<Vec<String>> {
+    let mut binaries = Vec::new();
+    for entry in walkdir::WalkDir::new(debian_dir).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry?;
+        if !entry.file_type().is_file() || !is_binary(&fs::read(entry.path())?) {
+            continue;
+        }
+        let path = entry.path().strip_prefix(debian_dir)?;
+        binaries.push(format!("debian/{}", path.display()));
+    }
+    let list_path = debian_dir.join("source").join("include-binaries");
+    let existing = fs::read_to_string(&list_path).unwrap_or_default();
+    let listed = existing.lines().map(str::trim).collect::<Vec<_>>();
+    binaries.retain(|b| !listed.contains(&b.as_str()));
+    if !binaries.is_empty() {
+        let mut content = existing.clone();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for b in &binaries {
+            content.push_str(b);
+            content.push('\n');
+        }
+        fs::create_dir_all(list_path.parent().unwrap())?;
+        fs::write(&list_path, content)?;
+    }
+    Ok(binaries)
+}
+
+/// Find a description for a crate whose Cargo.toml does not have one.
+fn fallback_description(
+    crate_info: &CrateInfo,
+    pkg_srcdir: &Path,
+    guess_harder: bool,
+) -> Option<String> {
+    if let Some(d) = crate_info.readme_description(pkg_srcdir) {
+        debcargo_info!("Crate has no description, using the first paragraph of its README");
+        debcargo_explain!("The summary and description are from the first paragraph of the README");
+        return Some(d);
+    }
+    if !guess_harder {
+        return None;
+    }
+    let repository = crate_info.metadata().repository.as_ref()?;
+    match github_description(repository) {
+        Ok(Some(d)) => {
+            debcargo_info!("Crate has no description, using the one from {}", repository);
+            debcargo_explain!("The summary and description are from the repository {}", repository);
+            Some(d)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            debcargo_warn!("Failed to get description from {}: {}", repository, e);
+            None
+        }
+    }
+}
+
 fn changelog_or_new(tempdir: &Path) -> Result<(fs::File, String)> {
     let mut changelog = fs::OpenOptions::new()
         .read(true)
@@ -0,0 +1,345 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use regex::Regex;
+use semver::VersionReq;
+
+use errors::*;
+
+/// What happened to a quilt patch when applying it to a new upstream version.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchStatus {
+    Applied,
+    /// Applied with offsets or fuzz, and refreshed to apply cleanly.
+    Refreshed,
+    /// Didn't apply; the output of quilt.
+    Failed(String),
+    /// Not tried, since an earlier patch failed.
+    Untested,
+}
+
+impl fmt::Display for PatchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatchStatus::Applied => write!(f, "applies"),
+            PatchStatus::Refreshed => write!(f, "refreshed"),
+            PatchStatus::Failed(_) => write!(f, "NO LONGER APPLIES"),
+            PatchStatus::Untested => write!(f, "untested, an earlier patch failed"),
+        }
+    }
+}
+
+/// The patch names of a quilt series file, without any options.
+pub fn parse_series(series: &str) -> Vec<String> {
+    series
+        .lines()
+        .map(|l| l.splitn(2, '#').next().unwrap().trim())
+        .filter_map(|l| l.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The start of the comment marking a patch in the series that no longer
+/// applies, on the line before it.
+pub const FAILED_MARKER: &'static str = "# FIXME: no longer applies to";
+
+/// The series with a comment before each patch that failed to apply to the
+/// upstream `version`, so that it is listed with the other FIXMEs until it is
+/// rebased or dropped, and without those of earlier runs.
+pub fn mark_failed_patches(series: &str, results: &[(String, PatchStatus)], version: &str) -> String {
+    let failed = results
+        .iter()
+        .filter(|&&(_, ref s)| match *s {
+            PatchStatus::Failed(_) => true,
+            _ => false,
+        })
+        .map(|&(ref p, _)| p.as_str())
+        .collect::<Vec<_>>();
+    let mut marked = String::new();
+    for line in series.lines() {
+        if line.starts_with(FAILED_MARKER) {
+            continue;
+        }
+        let patch = line.splitn(2, '#').next().unwrap().split_whitespace().next();
+        if patch.map_or(false, |p| failed.contains(&p)) {
+            marked.push_str(&format!("{} {}, rebase or drop it:\n", FAILED_MARKER, version));
+        }
+        marked.push_str(line);
+        marked.push('\n');
+    }
+    marked
+}
+
+/// Whether `quilt push` applied a patch only approximately.
+pub fn push_was_inexact(output: &str) -> bool {
+    output
+        .lines()
+        .any(|l| l.contains("with fuzz") || l.contains("(offset"))
+}
+
+fn quilt(pkg_srcdir: &Path, args: &[&str]) -> Result<Output> {
+    Ok(Command::new("quilt")
+        .current_dir(pkg_srcdir)
+        .env("QUILT_PATCHES", "debian/patches")
+        .arg("--quiltrc=-")
+        .args(args)
+        .output()?)
+}
+
+/// Apply the patches in debian/patches of an unpacked source package one by
+/// one, refreshing those that need it, then unapply them again. Patches after
+/// one that fails are not tried, since they may depend on it.
+pub fn refresh_patches(pkg_srcdir: &Path) -> Result<Vec<(String, PatchStatus)>> {
+    let series_path = pkg_srcdir.join("debian/patches/series");
+    if !series_path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut results = Vec::new();
+    let mut failed = false;
+    for patch in parse_series(&fs::read_to_string(&series_path)?) {
+        if failed {
+            results.push((patch, PatchStatus::Untested));
+            continue;
+        }
+        let push = quilt(pkg_srcdir, &["push"])?;
+        let output = String::from_utf8_lossy(&push.stdout).into_owned()
+            + &String::from_utf8_lossy(&push.stderr);
+        let status = if !push.status.success() {
+            failed = true;
+            PatchStatus::Failed(output)
+        } else if push_was_inexact(&output) {
+            let refresh = quilt(pkg_srcdir, &["refresh", "--no-timestamps", "--no-index", "-p", "ab"])?;
+            if !refresh.status.success() {
+                debcargo_bail!(
+                    "quilt refresh failed for {}: {}",
+                    patch,
+                    String::from_utf8_lossy(&refresh.stderr)
+                );
+            }
+            PatchStatus::Refreshed
+        } else {
+            PatchStatus::Applied
+        };
+        results.push((patch, status));
+    }
+    let applied = results
+        .iter()
+        .filter(|&&(_, ref s)| *s == PatchStatus::Applied || *s == PatchStatus::Refreshed)
+        .count();
+    if applied > 0 {
+        let pop = quilt(pkg_srcdir, &["pop", "-a"])?;
+        if !pop.status.success() {
+            debcargo_bail!("quilt pop failed: {}", String::from_utf8_lossy(&pop.stderr));
+        }
+    }
+    let pc = pkg_srcdir.join(".pc");
+    if pc.exists() {
+        fs::remove_dir_all(pc)?;
+    }
+    Ok(results)
+}
+
+/// The patch that debcargo generates to drop dev-dependencies that are not in
+/// Debian, which it regenerates each time.
+pub const DROP_DEV_DEPS_PATCH: &'static str = "drop-unavailable-dev-deps.patch";
+
+/// The patch that debcargo generates from the `relax` rules of the config,
+/// which it regenerates each time.
+pub const RELAX_DEPS_PATCH: &'static str = "debcargo-relax-deps.patch";
+
+const DEPENDENCY_TABLES: &'static [&'static str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "dev_dependencies",
+    "build-dependencies",
+    "build_dependencies",
+];
+
+/// The name of a key or table of Cargo.toml, without quotes.
+fn unquoted(key: &str) -> &str {
+    key.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// A line of a Cargo.toml that is about a dependency.
+struct DependencyLine<'a> {
+    /// As named in Cargo.toml.
+    name: &'a str,
+    dev: bool,
+    /// The key and the value, in a table of dependencies or in the table of
+    /// the dependency, or None for the header of its table and other lines.
+    key_value: Option<(&'a str, &'a str)>,
+}
+
+/// The lines of a Cargo.toml that are about dependencies, also when they are
+/// target-specific, numbered from 0.
+fn dependency_lines<'a>(manifest: &'a str) -> Vec<(usize, DependencyLine<'a>)> {
+    let mut lines = Vec::new();
+    // the table of dependencies, or the table of a dependency, that we're in
+    let mut table: Option<(&str, Option<&str>)> = None;
+    for (i, line) in manifest.lines().enumerate() {
+        let trimmed = line.trim();
+        let key_value = {
+            let mut split = trimmed.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(k), Some(v)) if !trimmed.starts_with('#') => Some((unquoted(k), v.trim())),
+                _ => None,
+            }
+        };
+        if trimmed.starts_with('[') {
+            let header = trimmed.trim_start_matches('[').trim_end_matches(']').trim();
+            table = DEPENDENCY_TABLES
+                .iter()
+                .filter_map(|&t| {
+                    if header == t || header.ends_with(&format!(".{}", t)) {
+                        Some((t, None))
+                    } else if header.starts_with(&format!("{}.", t)) {
+                        Some((t, Some(unquoted(&header[t.len() + 1..]))))
+                    } else {
+                        header.find(&format!(".{}.", t)).map(|p| (t, Some(unquoted(&header[p + t.len() + 2..]))))
+                    }
+                })
+                .next();
+            if let Some((t, Some(name))) = table {
+                lines.push((i, DependencyLine { name: name, dev: t.starts_with("dev"), key_value: None }));
+            }
+            continue;
+        }
+        match (table, key_value) {
+            (Some((t, None)), Some((name, value))) => lines.push((i, DependencyLine {
+                name: name,
+                dev: t.starts_with("dev"),
+                key_value: Some((name, value)),
+            })),
+            (Some((t, Some(name))), key_value) => lines.push((i, DependencyLine {
+                name: name,
+                dev: t.starts_with("dev"),
+                key_value: key_value,
+            })),
+            _ => (),
+        }
+    }
+    lines
+}
+
+/// The lines of a Cargo.toml, numbered from 0, that declare the given
+/// dev-dependencies, as `[dev-dependencies.foo]` tables or as `foo = ...` in
+/// `[dev-dependencies]`, also when they are target-specific.
+pub fn dev_dependency_lines(manifest: &str, names: &[&str]) -> Vec<usize> {
+    dependency_lines(manifest)
+        .into_iter()
+        .filter(|&(_, ref d)| d.dev && names.contains(&d.name))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// A rule of the `relax` config key, like "serde >= 1": the name of a
+/// dependency in Cargo.toml and the version requirement to relax it to.
+pub fn parse_relax_rule(rule: &str) -> Result<(String, String)> {
+    let rule = rule.trim();
+    let (name, req) = match rule.find(char::is_whitespace) {
+        Some(p) => (&rule[..p], rule[p..].trim()),
+        None => debcargo_bail!("relax rule \"{}\" is not of the form \"crate requirement\"", rule),
+    };
+    if VersionReq::parse(req).is_err() {
+        debcargo_bail!("relax rule \"{}\" has an invalid version requirement \"{}\"", rule, req);
+    }
+    Ok((name.to_string(), req.to_string()))
+}
+
+/// The lines of a Cargo.toml, numbered from 0, that set the version
+/// requirement of the dependency `name`, with it replaced by `req`.
+pub fn relaxed_dependency_lines(manifest: &str, name: &str, req: &str) -> Vec<(usize, String)> {
+    let quoted = Regex::new(r#""[^"]*""#).unwrap();
+    let in_table = Regex::new(r#"(\bversion\s*=\s*)"[^"]*""#).unwrap();
+    let text = manifest.lines().collect::<Vec<_>>();
+    let mut relaxed = Vec::new();
+    for (i, d) in dependency_lines(manifest) {
+        let (key, value) = match d.key_value {
+            Some(kv) if d.name == name => kv,
+            _ => continue,
+        };
+        let line = text[i];
+        let new = if key != name {
+            // in the table of the dependency
+            if key != "version" {
+                continue;
+            }
+            quoted.replace(line, format!("\"{}\"", req).as_str()).into_owned()
+        } else if value.starts_with('"') {
+            // foo = "1.2"
+            let at = line.find('=').unwrap();
+            format!("{}{}", &line[..at], quoted.replace(&line[at..], format!("\"{}\"", req).as_str()))
+        } else {
+            // foo = { version = "1.2", ... }
+            in_table.replace(line, format!("${{1}}\"{}\"", req).as_str()).into_owned()
+        };
+        if new != line {
+            relaxed.push((i, new));
+        }
+    }
+    relaxed
+}
+
+/// A patch in the unified format editing lines, numbered from 0, of the file
+/// `path` with the contents `text`, with a DEP-3 header of `description`.
+/// Lines are replaced by the given ones, or deleted if there is none.
+pub fn line_patch(path: &str, text: &str, edits: &[(usize, Option<String>)], description: &str) -> String {
+    const CONTEXT: usize = 3;
+    let lines = text.lines().collect::<Vec<_>>();
+    let edited = |i: usize| edits.iter().find(|&&(e, _)| e == i).map(|&(_, ref new)| new);
+    let mut patch = String::new();
+    for line in description.lines() {
+        patch.push_str(line);
+        patch.push('\n');
+    }
+    patch.push_str(&format!("--- a/{}\n+++ b/{}\n", path, path));
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &(e, _) in edits {
+        let (start, end) = (e.saturating_sub(CONTEXT), (e + CONTEXT + 1).min(lines.len()));
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    // how many lines the new file has more than the old one, before a hunk
+    let mut offset = 0isize;
+    for (start, end) in hunks {
+        let mut old = String::new();
+        let mut new = String::new();
+        let mut body = String::new();
+        let (mut removed, mut added) = (0, 0);
+        for i in start..end {
+            match edited(i) {
+                None => body.push_str(&format!(" {}\n", lines[i])),
+                Some(replacement) => {
+                    old.push_str(&format!("-{}\n", lines[i]));
+                    removed += 1;
+                    if let Some(ref r) = *replacement {
+                        new.push_str(&format!("+{}\n", r));
+                        added += 1;
+                    }
+                    // consecutive edits make one change
+                    if edited(i + 1).is_none() || i + 1 == end {
+                        body.push_str(&old);
+                        body.push_str(&new);
+                        old.clear();
+                        new.clear();
+                    }
+                }
+            }
+        }
+        let len = end - start;
+        patch.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start + 1,
+            len,
+            (start as isize + 1 + offset),
+            len - removed + added
+        ));
+        patch.push_str(&body);
+        offset += added as isize - removed as isize;
+    }
+    patch
+}
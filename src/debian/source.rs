@@ -0,0 +1,321 @@
+use cargo::util::Sha256;
+use md5;
+use tar::{Builder, EntryType, Header};
+use walkdir;
+use xz2::write::XzEncoder;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+use errors::*;
+
+use super::changelog::{ChangelogEntry, ChangelogIterator};
+use super::syntax::{parse_deb822, Paragraph};
+
+pub const FORMAT: &'static str = "3.0 (quilt)";
+
+/// Fields of the source paragraph of d/control that are copied to the .dsc,
+/// in the order dpkg-source writes them.
+const SOURCE_FIELDS: &'static [&'static str] = &[
+    "Maintainer",
+    "Uploaders",
+    "Homepage",
+    "Standards-Version",
+    "Vcs-Browser",
+    "Vcs-Git",
+];
+
+const BUILD_DEPENDS_FIELDS: &'static [&'static str] = &[
+    "Build-Depends",
+    "Build-Depends-Arch",
+    "Build-Depends-Indep",
+    "Build-Conflicts",
+    "Build-Conflicts-Indep",
+];
+
+/// The size and hashes of a file of a source package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub name: String,
+    pub size: u64,
+    pub md5: String,
+    pub sha256: String,
+}
+
+impl SourceFile {
+    pub fn new(path: &Path) -> Result<SourceFile> {
+        let mut file = fs::File::open(path)?;
+        let mut md5 = md5::Context::new();
+        let mut sha256 = Sha256::new();
+        let mut size = 0;
+        let mut buf = [0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            md5.consume(&buf[..n]);
+            sha256.update(&buf[..n]);
+            size += n as u64;
+        }
+        Ok(SourceFile {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            size: size,
+            md5: format!("{:x}", md5.compute()),
+            sha256: sha256.finish().iter().map(|b| format!("{:02x}", b)).collect(),
+        })
+    }
+}
+
+/// A value of d/control on one line, as in the .dsc.
+fn folded(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A version without its epoch, as in the names of the files.
+fn without_epoch(version: &str) -> &str {
+    version.splitn(2, ':').last().unwrap()
+}
+
+/// The upstream part of a Debian version, without its epoch or revision.
+fn upstream_version(version: &str) -> &str {
+    let version = without_epoch(version);
+    version.rsplitn(2, '-').last().unwrap()
+}
+
+/// The Testsuite-Triggers of a d/tests/control: the packages its tests
+/// depend on, other than those built from the source, as dpkg-source lists
+/// them.
+fn testsuite_triggers(tests_control: &str, binaries: &[String]) -> Result<BTreeSet<String>> {
+    let paragraphs = parse_deb822("tests/control", tests_control).map_err(|errors| {
+        format_err!("{}", errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))
+    })?;
+    let mut triggers = BTreeSet::new();
+    for paragraph in &paragraphs {
+        let depends = match paragraph.get("Depends") {
+            Some(d) => folded(&d.value),
+            None => continue,
+        };
+        for alternative in depends.split(|c| c == ',' || c == '|') {
+            let name = alternative
+                .trim()
+                .split(|c: char| c.is_whitespace() || c == '(' || c == '[' || c == '<' || c == ':')
+                .next()
+                .unwrap_or("");
+            // @ and @builddeps@ stand for packages of the source itself
+            if !name.is_empty() && !name.starts_with('@') && !binaries.iter().any(|b| b == name) {
+                triggers.insert(name.to_string());
+            }
+        }
+    }
+    Ok(triggers)
+}
+
+/// The .dsc of a 3.0 (quilt) source package, from d/control, the version and
+/// the files, which are the orig tarballs followed by the debian tarball.
+/// `tests_control` is the d/tests/control, if there is one.
+pub fn dsc(control: &str, version: &str, files: &[SourceFile], tests_control: Option<&str>) -> Result<String> {
+    let paragraphs = parse_deb822("control", control).map_err(|errors| {
+        format_err!("{}", errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))
+    })?;
+    let (source, binaries) = match paragraphs.split_first() {
+        Some((source, binaries)) if source.get("Source").is_some() => (source, binaries),
+        _ => debcargo_bail!("debian/control has no Source paragraph"),
+    };
+    let get = |p: &Paragraph, name: &str| p.get(name).map(|f| folded(&f.value));
+
+    let mut dsc = Vec::new();
+    dsc.push(("Format".to_string(), FORMAT.to_string()));
+    dsc.push(("Source".to_string(), get(source, "Source").unwrap()));
+    let names = binaries.iter().filter_map(|b| get(b, "Package")).collect::<Vec<_>>();
+    dsc.push(("Binary".to_string(), names.join(", ")));
+    let mut archs = Vec::new();
+    for b in binaries {
+        for arch in get(b, "Architecture").unwrap_or_default().split_whitespace() {
+            if !archs.contains(&arch.to_string()) {
+                archs.push(arch.to_string());
+            }
+        }
+    }
+    dsc.push(("Architecture".to_string(), archs.join(" ")));
+    dsc.push(("Version".to_string(), version.to_string()));
+    for name in SOURCE_FIELDS {
+        if let Some(value) = get(source, name) {
+            dsc.push((name.to_string(), value));
+        }
+    }
+    // like dpkg-source, add autopkgtest to the test suites of d/control
+    // when there is a d/tests/control
+    let mut testsuites = get(source, "Testsuite")
+        .unwrap_or_default()
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect::<BTreeSet<_>>();
+    if tests_control.is_some() {
+        testsuites.insert("autopkgtest".to_string());
+    }
+    if !testsuites.is_empty() {
+        let testsuites = testsuites.into_iter().collect::<Vec<_>>();
+        dsc.push(("Testsuite".to_string(), testsuites.join(", ")));
+    }
+    if let Some(tests_control) = tests_control {
+        let triggers = testsuite_triggers(tests_control, &names)?;
+        if !triggers.is_empty() {
+            let triggers = triggers.into_iter().collect::<Vec<_>>();
+            dsc.push(("Testsuite-Triggers".to_string(), triggers.join(", ")));
+        }
+    }
+    for name in BUILD_DEPENDS_FIELDS {
+        if let Some(value) = get(source, name) {
+            dsc.push((name.to_string(), value));
+        }
+    }
+
+    let section = get(source, "Section").unwrap_or_else(|| "misc".to_string());
+    let priority = get(source, "Priority").unwrap_or_else(|| "optional".to_string());
+    let package_list = binaries
+        .iter()
+        .filter_map(|b| {
+            Some(format!(
+                "\n {} deb {} {} arch={}",
+                get(b, "Package")?,
+                get(b, "Section").unwrap_or_else(|| section.clone()),
+                get(b, "Priority").unwrap_or_else(|| priority.clone()),
+                get(b, "Architecture").unwrap_or_default().replace(' ', ",")
+            ))
+        })
+        .collect::<String>();
+    dsc.push(("Package-List".to_string(), package_list));
+    let checksums = files
+        .iter()
+        .map(|f| format!("\n {} {} {}", f.sha256, f.size, f.name))
+        .collect::<String>();
+    dsc.push(("Checksums-Sha256".to_string(), checksums));
+    let md5s = files
+        .iter()
+        .map(|f| format!("\n {} {} {}", f.md5, f.size, f.name))
+        .collect::<String>();
+    dsc.push(("Files".to_string(), md5s));
+
+    Ok(dsc
+        .iter()
+        .map(|&(ref name, ref value)| {
+            if value.starts_with('\n') {
+                format!("{}:{}\n", name, value)
+            } else {
+                format!("{}: {}\n", name, value)
+            }
+        })
+        .collect())
+}
+
+/// Write the debian/ directory of an unpacked source package as a debian
+/// tarball, as dpkg-source does: sorted, owned by root, and with the files'
+/// times clamped to `mtime`, so that the same packaging gives the same tarball.
+pub fn write_debian_tarball(pkg_srcdir: &Path, tarball: &Path, mtime: u64) -> Result<()> {
+    let mut builder = Builder::new(XzEncoder::new(fs::File::create(tarball)?, 6));
+    let walker = walkdir::WalkDir::new(pkg_srcdir.join("debian"))
+        .follow_links(false)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()));
+    for entry in walker {
+        let entry = entry?;
+        let path = entry.path().strip_prefix(pkg_srcdir).unwrap();
+        let meta = fs::symlink_metadata(entry.path())?;
+        let mut header = Header::new_gnu();
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_username("root")?;
+        header.set_groupname("root")?;
+        let modified = meta.modified()?.duration_since(UNIX_EPOCH).map_or(mtime, |d| d.as_secs());
+        header.set_mtime(modified.min(mtime));
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            header.set_entry_type(EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder.append_data(&mut header, path, io::empty())?;
+        } else if file_type.is_symlink() {
+            header.set_entry_type(EntryType::Symlink);
+            header.set_mode(0o777);
+            header.set_size(0);
+            header.set_link_name(fs::read_link(entry.path())?)?;
+            builder.append_data(&mut header, path, io::empty())?;
+        } else {
+            header.set_entry_type(EntryType::Regular);
+            header.set_mode(if meta.permissions().mode() & 0o111 != 0 { 0o755 } else { 0o644 });
+            header.set_size(meta.len());
+            builder.append_data(&mut header, path, fs::File::open(entry.path())?)?;
+        }
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// The orig tarballs of a version of a source package in `dir`: the main one
+/// first, then the components, e.g. .orig-vendor.tar.gz.
+pub fn orig_tarballs(dir: &Path, source: &str, upstream_version: &str) -> Result<Vec<PathBuf>> {
+    let prefix = format!("{}_{}.orig", source, upstream_version);
+    let main = format!("{}.tar.", prefix);
+    let mut origs = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|p| {
+            let name = p.file_name().unwrap().to_string_lossy();
+            name.starts_with(&prefix) && name.contains(".tar.") && !name.ends_with(".asc")
+        })
+        .collect::<Vec<_>>();
+    origs.sort_by_key(|p| {
+        let name = p.file_name().unwrap().to_string_lossy().into_owned();
+        (!name.starts_with(&main), name)
+    });
+    match origs.first() {
+        Some(o) if o.file_name().unwrap().to_string_lossy().starts_with(&main) => Ok(origs),
+        _ => debcargo_bail!("No orig tarball {}* in {}", main, dir.display()),
+    }
+}
+
+/// Build a 3.0 (quilt) source package from an unpacked one, without
+/// dpkg-source, e.g. on hosts that are not Debian: write the debian tarball
+/// and the unsigned .dsc next to the orig tarballs, which must be there
+/// already. Changes to the upstream source outside debian/ must be patches in
+/// debian/patches, as dpkg-source would otherwise record them as one.
+/// Returns the path of the .dsc.
+pub fn build_source_package(pkg_srcdir: &Path) -> Result<PathBuf> {
+    let debian = pkg_srcdir.join("debian");
+    let format = fs::read_to_string(debian.join("source/format")).unwrap_or_default();
+    if format.trim() != FORMAT {
+        debcargo_bail!("debian/source/format is not {}, but \"{}\"", FORMAT, format.trim());
+    }
+    let changelog = fs::read_to_string(debian.join("changelog"))?;
+    let entry = match ChangelogIterator::from(&changelog).next() {
+        Some(e) => ChangelogEntry::from_str(e)?,
+        None => debcargo_bail!("debian/changelog is empty"),
+    };
+    let control = fs::read_to_string(debian.join("control"))?;
+
+    let outdir = match pkg_srcdir.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let origs = orig_tarballs(&outdir, &entry.source, upstream_version(&entry.version))?;
+
+    let base = format!("{}_{}", entry.source, without_epoch(&entry.version));
+    let debian_tarball = outdir.join(format!("{}.debian.tar.xz", base));
+    write_debian_tarball(pkg_srcdir, &debian_tarball, entry.date.timestamp() as u64)?;
+
+    let mut files = Vec::new();
+    for path in origs.iter().chain(Some(&debian_tarball)) {
+        files.push(SourceFile::new(path)?);
+    }
+    let tests_control = fs::read_to_string(debian.join("tests/control")).ok();
+    let dsc_path = outdir.join(format!("{}.dsc", base));
+    fs::write(&dsc_path, dsc(&control, &entry.version, &files, tests_control.as_ref().map(String::as_str))?)?;
+    Ok(dsc_path)
+}
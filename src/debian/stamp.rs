@@ -0,0 +1,79 @@
+use regex::Regex;
+
+use std::fmt;
+
+pub const STAMP_FILE: &'static str = "debcargo.stamp";
+
+/// What generated the packaging, kept next to it, so that regenerating it
+/// can tell which differences come from debcargo rather than from the crate
+/// or the config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamp {
+    pub debcargo_version: String,
+    /// The digest of the config, if there was one.
+    pub config_sha256: Option<String>,
+}
+
+impl Stamp {
+    pub fn parse(text: &str) -> Option<Stamp> {
+        let mut debcargo_version = None;
+        let mut config_sha256 = None;
+        for line in text.lines() {
+            let mut split = line.splitn(2, ':');
+            let (name, value) = match (split.next(), split.next()) {
+                (Some(n), Some(v)) => (n.trim().to_lowercase(), v.trim().to_string()),
+                _ => continue,
+            };
+            match name.as_str() {
+                "debcargo-version" => debcargo_version = Some(value),
+                "config-sha256" => config_sha256 = Some(value),
+                _ => (),
+            }
+        }
+        Some(Stamp {
+            debcargo_version: debcargo_version?,
+            config_sha256: config_sha256,
+        })
+    }
+
+    /// The stamp of packaging from before there were stamps, from the last
+    /// entry of d/changelog that debcargo wrote.
+    pub fn from_changelog(changelog: &str) -> Option<Stamp> {
+        let re = Regex::new(r"(?m)^  \* Package \S+ \S+ from crates.io using debcargo (\S+)$").unwrap();
+        re.captures(changelog).map(|c| Stamp {
+            debcargo_version: c[1].to_string(),
+            config_sha256: None,
+        })
+    }
+
+    /// What changed since the packaging was generated with `previous`, to
+    /// tell the maintainer when reviewing the regenerated files.
+    pub fn changes_since(&self, previous: &Stamp) -> Vec<String> {
+        let mut changes = Vec::new();
+        if previous.debcargo_version != self.debcargo_version {
+            changes.push(format!(
+                "The packaging was generated by debcargo {} before, and is now by debcargo {}: \
+                 expect differences in d/control, d/rules, d/tests/control and d/copyright \
+                 from its templates, besides those from the crate and the config",
+                previous.debcargo_version, self.debcargo_version
+            ));
+        }
+        match (previous.config_sha256.as_ref(), self.config_sha256.as_ref()) {
+            (Some(old), Some(new)) if old != new => {
+                changes.push("The config changed since the packaging was generated".to_string())
+            }
+            _ => (),
+        }
+        changes
+    }
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Debcargo-Version: {}", self.debcargo_version)?;
+        if let Some(ref sha256) = self.config_sha256 {
+            writeln!(f, "Config-Sha256: {}", sha256)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+use chrono::DateTime;
+use regex::Regex;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use debian::copyright::license_names;
+
+/// A problem at a specific line of a generated file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "debian/{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+/// A field of a deb822 paragraph, with the line it starts at. The value is
+/// the text after the colon, with continuation lines joined by newlines,
+/// less the space or tab that marks them and any trailing whitespace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    pub line: usize,
+}
+
+/// A deb822 paragraph, with the line it starts at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paragraph {
+    pub fields: Vec<Field>,
+    pub line: usize,
+}
+
+impl Paragraph {
+    pub fn get(&self, name: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Parse deb822 paragraphs, as in d/control and d/copyright. Comment lines
+/// starting with # are skipped, as dpkg does for d/control.
+pub fn parse_deb822(file: &str, text: &str) -> Result<Vec<Paragraph>, Vec<SyntaxError>> {
+    let field_re = Regex::new(r"^([!-9;-~][!-9;-~]*):(.*)$").unwrap();
+    let mut paragraphs = Vec::new();
+    let mut errors = Vec::new();
+    let mut current: Option<Paragraph> = None;
+    let error = |line: usize, message: String| SyntaxError {
+        file: file.to_string(),
+        line: line,
+        message: message,
+    };
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.is_empty() {
+            paragraphs.extend(current.take());
+            continue;
+        }
+        if line.starts_with(' ') || line.starts_with('\t') {
+            match current.as_mut().and_then(|p| p.fields.last_mut()) {
+                None => errors.push(error(n, "continuation line outside of any field".to_string())),
+                Some(_) if line.trim().is_empty() => errors.push(error(
+                    n,
+                    "whitespace-only continuation line; use \" .\" for an empty line".to_string(),
+                )),
+                Some(field) => {
+                    field.value.push('\n');
+                    field.value.push_str(line[1..].trim_end());
+                }
+            }
+            continue;
+        }
+        let caps = match field_re.captures(line) {
+            Some(c) => c,
+            None => {
+                errors.push(error(n, format!("not a field or continuation line: {:?}", line)));
+                continue;
+            }
+        };
+        let name = caps[1].to_string();
+        if name.starts_with('-') {
+            errors.push(error(n, format!("field name {} must not start with -", name)));
+        }
+        let paragraph = current.get_or_insert_with(|| Paragraph {
+            fields: Vec::new(),
+            line: n,
+        });
+        if paragraph.get(&name).is_some() {
+            errors.push(error(n, format!("duplicate field {}", name)));
+        }
+        paragraph.fields.push(Field {
+            name: name,
+            value: caps[2].trim().to_string(),
+            line: n,
+        });
+    }
+    paragraphs.extend(current.take());
+    if errors.is_empty() {
+        Ok(paragraphs)
+    } else {
+        Err(errors)
+    }
+}
+
+const RELATION_FIELDS: &[&str] = &[
+    "Pre-Depends", "Depends", "Recommends", "Suggests", "Enhances", "Breaks", "Conflicts",
+    "Replaces", "Provides", "Build-Depends", "Build-Depends-Indep", "Build-Depends-Arch",
+    "Build-Conflicts", "Build-Conflicts-Indep", "Build-Conflicts-Arch",
+];
+
+fn check_relations(field: &Field, errors: &mut Vec<SyntaxError>) {
+    let relation_re = Regex::new(concat!(
+        r"^(?:[a-z0-9][a-z0-9+.-]+|\$\{[^}]+\})(?::[a-z0-9-]+)?",
+        r"(?:\s*\((?:<<|<=|=|>=|>>)\s*[^\s()]+\))?",
+        r"(?:\s*\[[^\]]+\])?",
+        r"(?:\s*<[^<>]+>)*$",
+    )).unwrap();
+    let items = field.value.split(',').map(str::trim).collect::<Vec<_>>();
+    for (i, item) in items.iter().enumerate() {
+        if item.is_empty() {
+            // dpkg tolerates a trailing comma, but nothing else empty
+            if i + 1 != items.len() || items.len() == 1 {
+                errors.push(SyntaxError {
+                    file: "control".to_string(),
+                    line: field.line,
+                    message: format!("{} has an empty relation", field.name),
+                });
+            }
+            continue;
+        }
+        for alternative in item.split('|').map(str::trim) {
+            if !relation_re.is_match(alternative) {
+                errors.push(SyntaxError {
+                    file: "control".to_string(),
+                    line: field.line,
+                    message: format!("{} has a malformed relation: {:?}", field.name, alternative),
+                });
+            }
+        }
+    }
+}
+
+/// Check d/control: a source paragraph, then binary paragraphs with the
+/// required fields, valid package names, and well-formed relationships.
+pub fn check_control(text: &str) -> Vec<SyntaxError> {
+    let paragraphs = match parse_deb822("control", text) {
+        Ok(p) => p,
+        Err(errors) => return errors,
+    };
+    let name_re = Regex::new(r"^[a-z0-9][a-z0-9+.-]+$").unwrap();
+    let mut errors = Vec::new();
+    let error = |line: usize, message: String| SyntaxError {
+        file: "control".to_string(),
+        line: line,
+        message: message,
+    };
+    if paragraphs.is_empty() {
+        errors.push(error(1, "no paragraphs".to_string()));
+        return errors;
+    }
+    let mut names = BTreeSet::new();
+    for (i, paragraph) in paragraphs.iter().enumerate() {
+        let (kind, required): (&str, &[&str]) = if i == 0 {
+            ("Source", &["Source", "Maintainer"])
+        } else {
+            ("Package", &["Package", "Architecture", "Description"])
+        };
+        for name in required {
+            if paragraph.get(name).is_none() {
+                errors.push(error(paragraph.line, format!("paragraph has no {} field", name)));
+            }
+        }
+        if let Some(field) = paragraph.get(kind) {
+            if !name_re.is_match(&field.value) {
+                errors.push(error(field.line, format!("invalid package name {:?}", field.value)));
+            } else if i > 0 && !names.insert(field.value.clone()) {
+                errors.push(error(field.line, format!("duplicate package {}", field.value)));
+            }
+        }
+        if let Some(field) = paragraph.get("Description") {
+            if field.value.lines().next().map_or(true, |l| l.trim().is_empty()) {
+                errors.push(error(field.line, "Description has an empty synopsis".to_string()));
+            }
+        }
+        for field in &paragraph.fields {
+            if RELATION_FIELDS.iter().any(|r| r.eq_ignore_ascii_case(&field.name)) {
+                check_relations(field, &mut errors);
+            }
+        }
+    }
+    errors
+}
+
+/// Check d/copyright against the machine-readable format: a header with
+/// Format, Files paragraphs with Copyright and License, and a standalone
+/// License paragraph for every license that is named without its text.
+pub fn check_copyright(text: &str) -> Vec<SyntaxError> {
+    let paragraphs = match parse_deb822("copyright", text) {
+        Ok(p) => p,
+        Err(errors) => return errors,
+    };
+    let mut errors = Vec::new();
+    let error = |line: usize, message: String| SyntaxError {
+        file: "copyright".to_string(),
+        line: line,
+        message: message,
+    };
+    match paragraphs.first() {
+        Some(header) if header.get("Format").is_some() => (),
+        Some(header) => errors.push(error(header.line, "header paragraph has no Format field".to_string())),
+        None => {
+            errors.push(error(1, "no paragraphs".to_string()));
+            return errors;
+        }
+    }
+
+    let mut named = Vec::new();
+    let mut texts = BTreeSet::new();
+    for paragraph in &paragraphs[1..] {
+        let license = paragraph.get("License");
+        if paragraph.get("Files").is_some() {
+            if paragraph.get("Copyright").is_none() {
+                errors.push(error(paragraph.line, "Files paragraph has no Copyright field".to_string()));
+            }
+            match license {
+                None => errors.push(error(paragraph.line, "Files paragraph has no License field".to_string())),
+                Some(l) if !l.value.contains('\n') => named.push(l),
+                Some(_) => (),
+            }
+        } else {
+            match license {
+                Some(l) if l.value.contains('\n') => {
+                    texts.insert(l.value.lines().next().unwrap().trim().to_lowercase());
+                }
+                Some(l) => errors.push(error(l.line, format!(
+                    "standalone License paragraph for {} has no license text", l.value))),
+                None => errors.push(error(paragraph.line, "paragraph has neither Files nor License".to_string())),
+            }
+        }
+    }
+    for license in named {
+        let first = license.value.lines().next().unwrap_or("");
+        // placeholders that are flagged as FIXMEs elsewhere
+        if first.contains("FIXME") || first.contains("UNKNOWN") {
+            continue;
+        }
+        for name in license_names(first) {
+            if !texts.contains(&name.to_lowercase()) {
+                errors.push(error(license.line, format!(
+                    "license {} has no standalone License paragraph with its text", name)));
+            }
+        }
+    }
+    errors
+}
+
+/// Check d/changelog: every entry has a well-formed header, indented
+/// changes, and a trailer with a maintainer and an RFC 2822 date.
+pub fn check_changelog(text: &str) -> Vec<SyntaxError> {
+    let header_re = Regex::new(
+        r"(?i)^\w[-+0-9a-z.]* \(([^() \t]+)\)(?:\s+[-+0-9a-z.]+)+;\s*urgency=\w+.*$",
+    ).unwrap();
+    let trailer_re = Regex::new(r"^ -- (.*) <(.*)>  (\w.*\S)\s*$").unwrap();
+    let version_re = Regex::new(r"^(?:\d+:)?[0-9][A-Za-z0-9.+~:-]*$").unwrap();
+    let mut errors = Vec::new();
+    let error = |line: usize, message: String| SyntaxError {
+        file: "changelog".to_string(),
+        line: line,
+        message: message,
+    };
+    // the line of the header of the entry we're in, if any
+    let mut entry: Option<usize> = None;
+    let mut entries = 0;
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match entry {
+            None => {
+                if line.starts_with("Local variables:") || line.starts_with("Old Changelog:") {
+                    break;
+                }
+                match header_re.captures(line) {
+                    Some(caps) => {
+                        if !version_re.is_match(&caps[1]) {
+                            errors.push(error(n, format!("invalid version {:?}", &caps[1])));
+                        }
+                        entry = Some(n);
+                        entries += 1;
+                    }
+                    None => errors.push(error(n, format!(
+                        "expected an entry header like \"source (version) distribution; \
+                         urgency=medium\", found {:?}", line))),
+                }
+            }
+            Some(_) if line.starts_with(" -- ") => {
+                match trailer_re.captures(line) {
+                    Some(caps) => {
+                        if let Err(e) = DateTime::parse_from_rfc2822(&caps[3]) {
+                            errors.push(error(n, format!("invalid date {:?}: {}", &caps[3], e)));
+                        }
+                    }
+                    None => errors.push(error(n, format!(
+                        "malformed trailer, expected \" -- Name <email>  date\", found {:?}", line))),
+                }
+                entry = None;
+            }
+            Some(_) if line.starts_with("  ") => (),
+            Some(_) => errors.push(error(n, format!(
+                "expected an indented change or a \" -- \" trailer, found {:?}", line))),
+        }
+    }
+    if let Some(start) = entry {
+        errors.push(error(start, "entry has no trailer line".to_string()));
+    }
+    if entries == 0 && errors.is_empty() {
+        errors.push(error(1, "no entries".to_string()));
+    }
+    errors
+}
+
+/// Check the syntax of the control, copyright and changelog files in a
+/// debian/ directory, so that bugs in our templates show up at generation time
+/// rather than as dpkg-source or dpkg-buildpackage errors later.
+pub fn check_debian_dir(debian_dir: &Path) -> io::Result<Vec<SyntaxError>> {
+    let checks: &[(&str, fn(&str) -> Vec<SyntaxError>)] = &[
+        ("control", check_control),
+        ("copyright", check_copyright),
+        ("changelog", check_changelog),
+    ];
+    let mut errors = Vec::new();
+    for &(file, check) in checks {
+        let path = debian_dir.join(file);
+        if path.exists() {
+            errors.extend(check(&fs::read_to_string(path)?));
+        }
+    }
+    Ok(errors)
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde_json;
+
+use crates::crates_io_api;
+use errors::*;
+
+/// Crates that upstream abandoned in favour of others, and what to package
+/// instead.
+pub const RENAMED_CRATES: &'static [(&'static str, &'static str)] = &[
+    ("ansi_term", "nu-ansi-term"),
+    ("atty", "std::io::IsTerminal"),
+    ("failure", "anyhow or thiserror"),
+    ("net2", "socket2"),
+    ("proc-macro-error", "proc-macro-error2"),
+    ("rustc-serialize", "serde"),
+    ("tempdir", "tempfile"),
+    ("term_size", "terminal_size"),
+];
+
+/// How long without a release until a crate looks abandoned.
+pub const STALE_YEARS: i64 = 3;
+
+/// What to package instead of a crate that upstream abandoned for another.
+pub fn successor(name: &str) -> Option<&'static str> {
+    let name = name.replace('_', "-");
+    RENAMED_CRATES
+        .iter()
+        .find(|&&(n, _)| n.replace('_', "-") == name)
+        .map(|&(_, s)| s)
+}
+
+/// Why a crate looks abandoned or renamed upstream, from the JSON of its
+/// crates.io API as of `now`: all its versions are yanked, its description
+/// says so, or it had no release for years.
+pub fn deprecation_notices(name: &str, json: &str, now: DateTime<Utc>) -> Result<Vec<String>> {
+    let info: serde_json::Value = serde_json::from_str(json)?;
+    let versions = match info["versions"].as_array() {
+        Some(versions) => versions,
+        None => debcargo_bail!("Unexpected reply from the crates.io API for {}", name),
+    };
+    let mut notices = Vec::new();
+
+    if !versions.is_empty() && versions.iter().all(|v| v["yanked"].as_bool() == Some(true)) {
+        notices.push(format!("All versions of {} are yanked on crates.io", name));
+    }
+
+    let description = info["crate"]["description"].as_str().unwrap_or("");
+    let marker = Regex::new(
+        r"(?i)\b(deprecated|unmaintained|no longer maintained|not maintained|abandoned|renamed|superseded|moved to)\b",
+    ).unwrap();
+    if let Some(m) = marker.find(description) {
+        let successor = Regex::new(
+            r"(?i)(?:renamed to|moved to|superseded by|replaced by|in favou?r of)\s+(?:the\s+)?`?([a-z0-9_-]+)`?",
+        ).unwrap()
+        .captures(description)
+        .or_else(|| Regex::new(r"(?i)\buse\s+`?([a-z0-9_-]+)`?\s+instead").unwrap().captures(description))
+        .map(|c| c[1].to_string());
+        notices.push(format!(
+            "The description of {} on crates.io says \"{}\": {}{}",
+            name,
+            m.as_str(),
+            description.split_whitespace().collect::<Vec<_>>().join(" "),
+            successor.map_or_else(String::new, |s| format!(" (successor: {})", s))
+        ));
+    }
+
+    let newest = versions
+        .iter()
+        .filter(|v| v["yanked"].as_bool() != Some(true))
+        .filter_map(|v| v["created_at"].as_str())
+        .filter_map(|d| DateTime::parse_from_rfc3339(d).ok())
+        .max();
+    if let Some(newest) = newest {
+        if now.signed_duration_since(newest) > Duration::days(STALE_YEARS * 365) {
+            notices.push(format!(
+                "{} has had no release on crates.io since {}",
+                name,
+                newest.format("%Y-%m-%d")
+            ));
+        }
+    }
+    Ok(notices)
+}
+
+/// Why a crate looks abandoned or renamed upstream, per crates.io.
+pub fn crates_io_deprecation_notices(name: &str) -> Result<Vec<String>> {
+    let data = crates_io_api(&format!("crates/{}", name))?;
+    deprecation_notices(name, &String::from_utf8_lossy(&data), Utc::now())
+}
@@ -1,8 +1,224 @@
+use ansi_term::Colour::{Cyan, RGB};
 use failure;
+use serde_json;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub type Result<T> = ::std::result::Result<T, failure::Error>;
 pub use failure::ResultExt;
 
+/// Classes of failure that the debcargo command exits with distinct codes
+/// for, so that scripts can tell them apart. Errors are marked with one with
+/// `ResultKindExt::kind` or `debcargo_bail_kind!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The config is unreadable, invalid, or has unknown keys.
+    Config,
+    /// A download or a query of a registry or API failed.
+    Network,
+    /// No crate matches what was asked for.
+    CrateNotFound,
+    /// The crate contains files that debcargo refuses to package.
+    SuspiciousFiles,
+    /// FIXMEs are left in the packaging, with --strict.
+    Fixmes,
+    /// Something was warned about, with --strict.
+    Warnings,
+}
+
+impl ErrorKind {
+    /// The exit code for this class of failure; 1 is for all other failures.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Config => 2,
+            ErrorKind::Network => 3,
+            ErrorKind::CrateNotFound => 4,
+            ErrorKind::SuspiciousFiles => 5,
+            ErrorKind::Fixmes => 6,
+            ErrorKind::Warnings => 7,
+        }
+    }
+
+    /// `e`, marked as of this class.
+    pub fn mark(self, e: failure::Error) -> failure::Error {
+        KindError { kind: self, error: e }.into()
+    }
+
+    /// The class of an error, from the outermost mark in its chain.
+    pub fn of(e: &failure::Error) -> Option<ErrorKind> {
+        e.iter_chain()
+            .filter_map(|f| f.downcast_ref::<KindError>())
+            .map(|k| k.kind)
+            .next()
+    }
+}
+
+/// An error marked with its class, otherwise showing as the error itself.
+struct KindError {
+    kind: ErrorKind,
+    error: failure::Error,
+}
+
+impl fmt::Display for KindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Debug for KindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl failure::Fail for KindError {
+    fn backtrace(&self) -> Option<&failure::Backtrace> {
+        Some(self.error.backtrace())
+    }
+}
+
+pub trait ResultKindExt<T> {
+    /// Mark the error, if any, as of the class `kind`.
+    fn kind(self, kind: ErrorKind) -> Result<T>;
+}
+
+impl<T, E: Into<failure::Error>> ResultKindExt<T> for ::std::result::Result<T, E> {
+    fn kind(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|e| kind.mark(e.into()))
+    }
+}
+
+/// Environment variable selecting how warnings are shown, as by
+/// --warnings-format: "text", the default, or "json".
+pub const WARNINGS_FORMAT_ENV: &str = "DEBCARGO_WARNINGS_FORMAT";
+
+/// Whether warnings are shown as JSON lines, for scripts.
+pub fn json_warnings() -> bool {
+    env::var(WARNINGS_FORMAT_ENV).map_or(false, |f| f == "json")
+}
+
+#[derive(Serialize)]
+struct JsonWarning<'a> {
+    code: &'a str,
+    file: Option<String>,
+    line: Option<usize>,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<&'a str>,
+}
+
+/// A warning as a line of JSON, or none if it has no message.
+pub fn warning_json(code: &str, file: Option<&Path>, line: Option<usize>, message: &str) -> Option<String> {
+    // without the layout of the text, e.g. the bullets of lists
+    let message = message.trim().trim_start_matches("(•)").trim_start_matches('•').trim();
+    if message.is_empty() {
+        return None;
+    }
+    let warning = JsonWarning {
+        code: code,
+        file: file.map(|f| f.display().to_string()),
+        line: line,
+        message: message,
+        kind: None,
+        action: None,
+    };
+    Some(serde_json::to_string(&warning).unwrap())
+}
+
+/// A FIXME left in the packaging as a line of JSON, with its `kind` and the
+/// `action` to take on it.
+pub fn fixme_json(code: &str, file: &Path, line: usize, message: &str, kind: &str, action: &str) -> String {
+    let warning = JsonWarning {
+        code: code,
+        file: Some(file.display().to_string()),
+        line: Some(line),
+        message: message,
+        kind: Some(kind),
+        action: Some(action),
+    };
+    serde_json::to_string(&warning).unwrap()
+}
+
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// How many warnings were shown so far, not counting the empty lines and
+/// bullets of their layout; for --strict.
+pub fn warning_count() -> usize {
+    WARNINGS.load(Ordering::SeqCst)
+}
+
+/// Show a warning, about `file` and its `line` if given: as text, or as a
+/// line of JSON on stderr. `code` is stable, for scripts to tell warnings
+/// apart by, e.g. "fixme"; it is "warning" for all those without their own.
+pub fn warn_with_code(code: &str, file: Option<&Path>, line: Option<usize>, message: &str) {
+    if warning_json(code, file, line, message).is_some() {
+        WARNINGS.fetch_add(1, Ordering::SeqCst);
+    }
+    if !json_warnings() {
+        println!("{}", RGB(255, 165, 0).bold().paint(message));
+    } else if let Some(json) = warning_json(code, file, line, message) {
+        eprintln!("{}", json);
+    }
+}
+
+/// Environment variable naming the file to record the decisions taken in
+/// generating the packaging in, as by --explain; "-" prints them instead.
+pub const EXPLAIN_ENV: &str = "DEBCARGO_EXPLAIN";
+
+thread_local! {
+    static EXPLAINED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// Record a decision that is not obvious from the generated files, once,
+/// if asked to with --explain.
+pub fn explain(decision: &str) {
+    let dest = match env::var_os(EXPLAIN_ENV) {
+        Some(ref d) if !d.is_empty() => d.clone(),
+        _ => return,
+    };
+    // the same dependency is translated for every package that has it
+    if !EXPLAINED.with(|e| e.borrow_mut().insert(decision.to_string())) {
+        return;
+    }
+    if dest == "-" {
+        println!("{}", Cyan.paint(format!("Decision: {}", decision)));
+        return;
+    }
+    let written = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&dest)
+        .and_then(|mut f| writeln!(f, "{}", decision));
+    if let Err(e) = written {
+        let message = format!("Couldn't record a decision in {}: {}", Path::new(&dest).display(), e);
+        warn_with_code("warning", None, None, &message);
+    }
+}
+
+#[macro_export]
+macro_rules! debcargo_explain {
+    ($e:expr) => {
+        {
+            $crate::errors::explain(&format!("{}", $e));
+        }
+    };
+
+    ($fmt:expr, $( $arg:tt)+) => {
+        {
+            $crate::errors::explain(&format!($fmt, $($arg)+));
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! debcargo_info {
     ($e:expr) => {
@@ -25,16 +241,14 @@ macro_rules! debcargo_info {
 macro_rules! debcargo_warn {
     ($e:expr) => {
         {
-            use ansi_term::Colour::RGB;
-            println!("{}", RGB(255,165,0).bold().paint($e));
+            $crate::errors::warn_with_code("warning", None, None, &format!("{}", $e));
         }
     };
 
     ($fmt:expr, $( $arg:tt)+) => {
         {
-            use ansi_term::Colour::RGB;
-            let print_string = RGB(255,165,0).bold().paint(format!($fmt, $($arg)+));
-            println!("{}", print_string);
+            let print_string = format!($fmt, $($arg)+);
+            $crate::errors::warn_with_code("warning", None, None, &print_string);
         }
     };
 
@@ -53,3 +267,18 @@ macro_rules! debcargo_bail {
         }
     };
 }
+
+/// Like debcargo_bail!, marking the error as of the given `ErrorKind`.
+#[macro_export]
+macro_rules! debcargo_bail_kind {
+    ($kind:expr, $e:expr) => {{
+        return Err($kind.mark(format_err!("{}", $e)));
+    }};
+
+    ($kind:expr, $fmt:expr, $( $arg:tt)+) => {
+        {
+            let error_string = format!($fmt, $($arg)+);
+            return Err($kind.mark(format_err!("{}", error_string)));
+        }
+    };
+}
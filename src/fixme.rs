@@ -0,0 +1,120 @@
+use walkdir;
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use errors::*;
+use util::is_hint_file;
+
+/// What a FIXME left in the generated packaging is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FixmeKind {
+    CopyrightYears,
+    License,
+    CopyrightNotices,
+    Description,
+    Section,
+    Distribution,
+    Patch,
+    Other,
+}
+
+impl FixmeKind {
+    /// The kind of the FIXME on a line, from the placeholders debcargo writes.
+    pub fn of(line: &str) -> FixmeKind {
+        let lower = line.to_lowercase();
+        if line.contains("UNRELEASED-FIXME") {
+            FixmeKind::Distribution
+        } else if lower.contains("no longer applies") {
+            FixmeKind::Patch
+        } else if line.contains("UNKNOWN-YEARS") || lower.contains("copyright years") {
+            FixmeKind::CopyrightYears
+        } else if line.contains("UNKNOWN-LICENSE") || lower.contains("license") {
+            FixmeKind::License
+        } else if lower.contains("notices") {
+            FixmeKind::CopyrightNotices
+        } else if lower.contains("summar") || lower.contains("description") {
+            FixmeKind::Description
+        } else if lower.contains("section") {
+            FixmeKind::Section
+        } else {
+            FixmeKind::Other
+        }
+    }
+
+    /// A stable name, e.g. for the JSON warnings.
+    pub fn code(self) -> &'static str {
+        match self {
+            FixmeKind::CopyrightYears => "copyright-years",
+            FixmeKind::License => "license",
+            FixmeKind::CopyrightNotices => "copyright-notices",
+            FixmeKind::Description => "description",
+            FixmeKind::Section => "section",
+            FixmeKind::Distribution => "distribution",
+            FixmeKind::Patch => "patch",
+            FixmeKind::Other => "other",
+        }
+    }
+
+    /// What to do about it.
+    pub fn action(self) -> &'static str {
+        match self {
+            FixmeKind::CopyrightYears => {
+                "Fill in the years from the upstream history, in d/copyright in the overlay"
+            }
+            FixmeKind::License => "Find the license of the files, and set it in d/copyright in the overlay",
+            FixmeKind::CopyrightNotices => {
+                "Review the notices extracted from the files, in d/copyright in the overlay"
+            }
+            FixmeKind::Description => "Set summary and description in debcargo.toml",
+            FixmeKind::Section => "Set the section of the source or of the package in debcargo.toml",
+            FixmeKind::Distribution => "Set the distribution in d/changelog when releasing the package",
+            FixmeKind::Patch => "Rebase the patch on the new upstream version, or drop it, in the overlay",
+            FixmeKind::Other => "Review it, and fix it in debcargo.toml or in the overlay",
+        }
+    }
+}
+
+/// A line with FIXME in the generated packaging.
+#[derive(Debug, Clone)]
+pub struct Fixme {
+    pub file: PathBuf,
+    /// Numbered from 1.
+    pub line: usize,
+    pub text: String,
+    pub kind: FixmeKind,
+    /// Whether it is in a hint file, where FIXMEs are what to review rather
+    /// than something to fix.
+    pub hint: bool,
+}
+
+/// The FIXMEs in the files under `dir`, file by file.
+pub fn find_fixmes(dir: &Path) -> Result<Vec<Fixme>> {
+    let mut fixmes = Vec::new();
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let file = entry.path().to_path_buf();
+        let hint = is_hint_file(&file);
+        let reader = BufReader::new(fs::File::open(&file)?);
+        for (i, line) in reader.lines().enumerate() {
+            let text = match line {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+            if text.contains("FIXME") {
+                fixmes.push(Fixme {
+                    file: file.clone(),
+                    line: i + 1,
+                    kind: FixmeKind::of(&text),
+                    text: text.trim().to_string(),
+                    hint: hint,
+                });
+            }
+        }
+    }
+    Ok(fixmes)
+}
@@ -0,0 +1,83 @@
+use semver::Version;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crates::semver_suffix_of;
+use errors::*;
+
+/// The packaging of a crate in a checkout of debcargo-conf.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfPackaging {
+    /// The name of its directory in src/, e.g. rand or rand-0.7.
+    pub name: String,
+    /// Its debcargo.toml, with the overlay next to it.
+    pub config: PathBuf,
+    /// Where to generate the source package.
+    pub build_dir: PathBuf,
+}
+
+/// A checkout of debcargo-conf: the packaging of each crate is in
+/// src/<crate>/debian, with its debcargo.toml, or in src/<crate>-<suffix> for
+/// semver-suffixed packages, and the source packages are generated in
+/// build/<crate>.
+pub struct ConfLayout {
+    root: PathBuf,
+}
+
+/// The semver suffix of a version of a crate as given on the command line,
+/// e.g. "0.7" or "=0.7.3", if it names one.
+fn suffix_of_requested(version: &str) -> Option<String> {
+    let version = version.trim().trim_start_matches(|c| "=^~ ".contains(c));
+    let mut parts = version.split('.').collect::<Vec<_>>();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    Version::parse(&parts.join(".")).ok().map(|v| semver_suffix_of(&v))
+}
+
+impl ConfLayout {
+    pub fn new(root: &Path) -> ConfLayout {
+        ConfLayout { root: root.to_path_buf() }
+    }
+
+    fn packaging_at(&self, name: String) -> ConfPackaging {
+        ConfPackaging {
+            config: self.root.join("src").join(&name).join("debian/debcargo.toml"),
+            build_dir: self.root.join("build").join(&name),
+            name: name,
+        }
+    }
+
+    /// The packaging of a version of a crate, preferring a semver-suffixed
+    /// one for that version if it exists, whether or not the crate is
+    /// packaged yet.
+    pub fn packaging(&self, crate_name: &str, version: Option<&str>) -> ConfPackaging {
+        let base = crate_name.replace('_', "-").to_lowercase();
+        version
+            .and_then(suffix_of_requested)
+            .map(|suffix| self.packaging_at(format!("{}{}", base, suffix)))
+            .filter(|p| p.config.is_file())
+            .unwrap_or_else(|| self.packaging_at(base))
+    }
+
+    /// Like `packaging`, but start the packaging if the crate has none yet,
+    /// with a debcargo.toml using the directory as overlay, as debcargo-conf
+    /// does.
+    pub fn packaging_or_new(&self, crate_name: &str, version: Option<&str>) -> Result<ConfPackaging> {
+        if !self.root.join("src").is_dir() {
+            debcargo_bail!("{} is not a checkout of debcargo-conf, as it has no src/", self.root.display());
+        }
+        let packaging = self.packaging(crate_name, version);
+        if !packaging.config.exists() {
+            fs::create_dir_all(packaging.config.parent().unwrap())?;
+            fs::write(&packaging.config, "overlay = \".\"\n")?;
+            debcargo_info!("Started the packaging of {} in {}", crate_name, packaging.config.display());
+        }
+        fs::create_dir_all(packaging.build_dir.parent().unwrap())?;
+        Ok(packaging)
+    }
+}
@@ -1,6 +1,7 @@
 extern crate ansi_term;
 extern crate cargo;
 extern crate chrono;
+extern crate curl;
 #[macro_use]
 extern crate failure;
 extern crate filetime;
@@ -8,21 +9,45 @@ extern crate flate2;
 extern crate glob;
 extern crate git2;
 extern crate itertools;
+extern crate md5;
 extern crate regex;
 extern crate semver;
 extern crate semver_parser;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate tar;
 extern crate tempfile;
 extern crate textwrap;
 extern crate toml;
+extern crate unicode_normalization;
+extern crate unicode_width;
 extern crate walkdir;
+extern crate xz2;
 
 #[macro_use]
 pub mod errors;
+pub mod attestation;
+pub mod build;
+pub mod build_script;
+pub mod clean;
+pub mod compare;
 pub mod crates;
 pub mod debian;
+pub mod deprecation;
 pub mod config;
+pub mod fixme;
+pub mod layout;
+pub mod outdated;
+pub mod regenerate;
+pub mod sbom;
+pub mod selftest;
+pub mod transition;
+pub mod tree;
+pub mod upstream;
 pub mod util;
+pub mod validate;
+pub mod vendor;
+pub mod verify;
+pub mod versions;
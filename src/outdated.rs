@@ -0,0 +1,283 @@
+use cargo::core::registry::PackageRegistry;
+use cargo::core::{Dependency, SourceId};
+use cargo::Config;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use walkdir;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use config::parse_config_for_crate;
+use crates::{crates_io_registry, fetch_candidates, semver_suffix_of};
+use debian::changelog::ChangelogIterator;
+use debian::control::PackageNaming;
+use errors::*;
+use transition::Transition;
+use tree::Archive;
+
+/// A crate packaged with debcargo, as found in a packaging checkout.
+#[derive(Debug, Clone)]
+pub struct Packaging {
+    pub dir: PathBuf,
+    pub crate_name: String,
+    pub version: Version,
+    pub semver_suffix: bool,
+    pub naming: PackageNaming,
+}
+
+/// The crate and version most recently packaged according to a changelog, as
+/// recorded by the item debcargo puts in each entry it generates.
+pub fn packaged_crate(changelog: &str) -> Option<(String, Version)> {
+    let re = Regex::new(r"^  \* Package (\S+) (\S+) from crates.io using debcargo").unwrap();
+    for entry in ChangelogIterator::from(changelog) {
+        for line in entry.lines() {
+            if let Some(c) = re.captures(line) {
+                if let Ok(v) = Version::parse(&c[2]) {
+                    return Some((c[1].to_string(), v));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Find the packagings under a directory, like the src/ directory of
+/// debcargo-conf: any debian/changelog that debcargo has written to, with the
+/// debcargo.toml next to it if there is one.
+pub fn find_packagings(root: &Path) -> Result<Vec<Packaging>> {
+    let mut packagings = Vec::new();
+    let walker = walkdir::WalkDir::new(root)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for entry in walker {
+        let entry = entry?;
+        if entry.file_name() != "changelog" || !entry.file_type().is_file() {
+            continue;
+        }
+        let debian = entry.path().parent().unwrap();
+        if debian.file_name().map_or(true, |n| n != "debian") {
+            continue;
+        }
+        let (crate_name, version) = match packaged_crate(&fs::read_to_string(entry.path())?) {
+            Some(c) => c,
+            None => continue,
+        };
+        let config_path = debian.join("debcargo.toml");
+        let (semver_suffix, naming) = if config_path.is_file() {
+            let config = parse_config_for_crate(&config_path, &crate_name)
+                .map_err(|e| format_err!("{}: {}", config_path.display(), e))?;
+            let naming = PackageNaming::from_config(&config)
+                .map_err(|e| format_err!("{}: {}", config_path.display(), e))?;
+            (config.semver_suffix, naming)
+        } else {
+            (false, PackageNaming::default())
+        };
+        packagings.push(Packaging {
+            dir: debian.parent().unwrap().to_path_buf(),
+            crate_name: crate_name,
+            version: version,
+            semver_suffix: semver_suffix,
+            naming: naming,
+        });
+    }
+    Ok(packagings)
+}
+
+/// The newest of the available versions that a packaging should be updated
+/// to, if any. Packagings with a semver suffix stay within their suffix.
+pub fn newest_update<'a, I>(current: &Version, semver_suffix: bool, available: I) -> Option<Version>
+where
+    I: IntoIterator<Item = &'a Version>,
+{
+    available
+        .into_iter()
+        .filter(|v| v > &current && v.pre.is_empty())
+        .filter(|v| !semver_suffix || semver_suffix_of(v) == semver_suffix_of(current))
+        .max()
+        .cloned()
+}
+
+/// A packaging with a newer upstream version available.
+#[derive(Debug)]
+pub struct Outdated {
+    pub packaging: Packaging,
+    pub newest: Version,
+    /// The newest version with the same semver suffix, when `newest` crosses
+    /// a semver-suffix boundary and there is a newer one than packaged.
+    pub newest_compatible: Option<Version>,
+    /// Other packagings in the checkout whose dependencies on this crate
+    /// aren't satisfied by `newest`, nor by another packaging of the crate.
+    pub affected: Vec<String>,
+    /// What crossing the semver-suffix boundary breaks in the archive, if
+    /// that was checked.
+    pub transition: Option<Transition>,
+}
+
+impl Outdated {
+    pub fn crosses_suffix(&self) -> bool {
+        semver_suffix_of(&self.newest) != semver_suffix_of(&self.packaging.version)
+    }
+}
+
+impl fmt::Display for Outdated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let p = &self.packaging;
+        write!(f, "{} {} -> {}", p.crate_name, p.version, self.newest)?;
+        if self.crosses_suffix() {
+            write!(
+                f,
+                " (crosses semver boundary {} -> {})",
+                semver_suffix_of(&p.version),
+                semver_suffix_of(&self.newest)
+            )?;
+        }
+        writeln!(f, " [{}]", p.dir.display())?;
+        if let Some(ref v) = self.newest_compatible {
+            writeln!(f, "  newest without crossing: {}", v)?;
+        }
+        for a in &self.affected {
+            writeln!(f, "  affects {}", a)?;
+        }
+        if let Some(ref t) = self.transition {
+            // the first line repeats the above
+            for line in t.to_string().lines().skip(1) {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reverse dependencies among `packagings` whose requirements on the crate of
+/// `updated` would no longer be satisfied once it is updated to `new`, nor by
+/// any other packaging of the crate. `dependencies` gives the dependencies of
+/// each packaging, in the same order.
+pub fn affected_reverse_dependencies(
+    updated: &Packaging,
+    new: &Version,
+    packagings: &[Packaging],
+    dependencies: &[Vec<(String, VersionReq)>],
+) -> Vec<String> {
+    let crate_name = &updated.crate_name;
+    let mut affected = Vec::new();
+    for (p, deps) in packagings.iter().zip(dependencies) {
+        if &p.crate_name == crate_name {
+            continue;
+        }
+        for &(ref name, ref req) in deps {
+            // only those that the packaged version satisfies now
+            if name != crate_name || req.matches(new) || !req.matches(&updated.version) {
+                continue;
+            }
+            let provided = packagings.iter().any(|q| {
+                &q.crate_name == crate_name && q.dir != updated.dir && req.matches(&q.version)
+            });
+            if !provided {
+                affected.push(format!("{} {} (needs {} {})", p.crate_name, p.version, name, req));
+            }
+        }
+    }
+    affected.sort();
+    affected.dedup();
+    affected
+}
+
+/// Checks packagings against the registry index for newer versions.
+pub struct OutdatedChecker<'cfg> {
+    registry: PackageRegistry<'cfg>,
+    source_id: SourceId,
+    archive: Archive,
+    transitions: bool,
+}
+
+impl<'cfg> OutdatedChecker<'cfg> {
+    pub fn new(config: &'cfg Config, update: bool) -> Result<Self> {
+        let (registry, source_id) = crates_io_registry(config, update)?;
+        Ok(OutdatedChecker {
+            registry: registry,
+            source_id: source_id,
+            archive: Archive::default(),
+            transitions: false,
+        })
+    }
+
+    /// Whether to look up in the Debian archive what updates crossing a
+    /// semver-suffix boundary break.
+    pub fn set_transitions(&mut self, transitions: bool) {
+        self.transitions = transitions;
+    }
+
+    fn versions(&mut self, name: &str) -> Result<Vec<Version>> {
+        let dep = Dependency::parse_no_deprecated(name, Some("*"), self.source_id)?;
+        Ok(fetch_candidates(&mut self.registry, &dep)?
+            .iter()
+            .map(|s| s.version().clone())
+            .collect())
+    }
+
+    fn dependencies(&mut self, p: &Packaging) -> Result<Vec<(String, VersionReq)>> {
+        let req = format!("={}", p.version);
+        let dep = Dependency::parse_no_deprecated(&p.crate_name, Some(&req), self.source_id)?;
+        let summary = match fetch_candidates(&mut self.registry, &dep)?.into_iter().next() {
+            Some(s) => s,
+            None => {
+                debcargo_warn!("{} {} is not in the index", p.crate_name, p.version);
+                return Ok(Vec::new());
+            }
+        };
+        Ok(summary
+            .dependencies()
+            .iter()
+            .map(|d| (d.package_name().to_string(), d.version_req().clone()))
+            .collect())
+    }
+
+    pub fn check(&mut self, packagings: &[Packaging]) -> Result<Vec<Outdated>> {
+        let mut versions = BTreeMap::new();
+        let mut dependencies = Vec::new();
+        for p in packagings {
+            if !versions.contains_key(&p.crate_name) {
+                let v = self.versions(&p.crate_name)?;
+                versions.insert(p.crate_name.clone(), v);
+            }
+            dependencies.push(self.dependencies(p)?);
+        }
+
+        let mut outdated = Vec::new();
+        for p in packagings {
+            let available = &versions[&p.crate_name];
+            let newest = match newest_update(&p.version, p.semver_suffix, available) {
+                Some(v) => v,
+                None => continue,
+            };
+            let newest_compatible = if semver_suffix_of(&newest) != semver_suffix_of(&p.version) {
+                newest_update(&p.version, true, available)
+            } else {
+                None
+            };
+            let transition = if self.transitions && semver_suffix_of(&newest) != semver_suffix_of(&p.version) {
+                match Transition::check(&mut self.archive, &p.naming, &p.crate_name, Some(&p.version), &newest) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        debcargo_warn!("Couldn't check the transition of {} to {}: {}", p.crate_name, newest, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            outdated.push(Outdated {
+                packaging: p.clone(),
+                affected: affected_reverse_dependencies(p, &newest, packagings, &dependencies),
+                newest: newest,
+                newest_compatible: newest_compatible,
+                transition: transition,
+            });
+        }
+        Ok(outdated)
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use outdated::Packaging;
+use selftest::compare;
+use verify::Contents;
+
+/// What regenerating a packaging did to its generated debian/.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// There was no earlier output to compare with.
+    New,
+    Unchanged,
+    /// The differences, one per file.
+    Changed(Vec<String>),
+    Failed(String),
+}
+
+impl Outcome {
+    /// The outcome of a regeneration that succeeded, from the generated files
+    /// before, if there were any, and after.
+    pub fn of(previous: Option<&Contents>, current: &Contents) -> Outcome {
+        match previous {
+            None => Outcome::New,
+            Some(previous) => {
+                let differences = compare(previous, current)
+                    .into_iter()
+                    .map(|d| d.replace("expected:", "before:").replace("actual:  ", "after: "))
+                    .collect::<Vec<_>>();
+                if differences.is_empty() {
+                    Outcome::Unchanged
+                } else {
+                    Outcome::Changed(differences)
+                }
+            }
+        }
+    }
+}
+
+/// A packaging and what regenerating it did.
+pub struct Regenerated {
+    pub packaging: Packaging,
+    /// Where it was generated.
+    pub dir: PathBuf,
+    pub outcome: Outcome,
+}
+
+impl Regenerated {
+    /// Where to generate a packaging found under `root`, in `output_dir`:
+    /// under the same relative path, e.g. build/clap for src/clap.
+    pub fn output_path(root: &Path, packaging: &Packaging, output_dir: &Path) -> PathBuf {
+        let relative = packaging.dir.strip_prefix(root).unwrap_or(&packaging.dir);
+        if relative.as_os_str().is_empty() {
+            output_dir.join(&packaging.crate_name)
+        } else {
+            output_dir.join(relative)
+        }
+    }
+}
+
+impl fmt::Display for Regenerated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let p = &self.packaging;
+        write!(f, "{} {} [{}]: ", p.crate_name, p.version, p.dir.display())?;
+        match self.outcome {
+            Outcome::New => writeln!(f, "generated in {}", self.dir.display()),
+            Outcome::Unchanged => writeln!(f, "unchanged"),
+            Outcome::Changed(ref differences) => {
+                writeln!(f, "{} files changed", differences.len())?;
+                for d in differences {
+                    for line in d.lines() {
+                        writeln!(f, "  {}", line)?;
+                    }
+                }
+                Ok(())
+            }
+            Outcome::Failed(ref e) => writeln!(f, "FAILED: {}", e),
+        }
+    }
+}
+
+/// How many regenerations were new, unchanged, changed and failed, in words.
+pub fn summary(regenerated: &[Regenerated]) -> String {
+    let (mut changed, mut unchanged, mut new, mut failed) = (0, 0, 0, 0);
+    for r in regenerated {
+        match r.outcome {
+            Outcome::New => new += 1,
+            Outcome::Unchanged => unchanged += 1,
+            Outcome::Changed(_) => changed += 1,
+            Outcome::Failed(_) => failed += 1,
+        }
+    }
+    format!(
+        "Regenerated {} packagings: {} changed, {} unchanged, {} new, {} failed",
+        regenerated.len(),
+        changed,
+        unchanged,
+        new,
+        failed
+    )
+}
@@ -0,0 +1,290 @@
+use cargo::util::Sha256;
+use semver::Version;
+use serde_json;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use errors::*;
+use tree::TreeNode;
+
+/// A crate in a software bill of materials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SbomPackage {
+    pub name: String,
+    pub version: Version,
+    /// The SHA-256 of the .crate, if known.
+    pub checksum: Option<String>,
+    /// The license expression, in SPDX syntax, if the crate declares one.
+    pub license: Option<String>,
+}
+
+impl SbomPackage {
+    fn purl(&self) -> String {
+        format!("pkg:cargo/{}@{}", self.name, self.version)
+    }
+
+    fn download_url(&self) -> String {
+        format!("https://crates.io/api/v1/crates/{}/{}/download", self.name, self.version)
+    }
+
+    /// An SPDX identifier, which may only have letters, digits, . and -.
+    fn spdx_id(&self) -> String {
+        let id = format!("{}-{}", self.name, self.version)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' })
+            .collect::<String>();
+        format!("SPDXRef-Package-{}", id)
+    }
+}
+
+/// The crates of a source package and how they depend on each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sbom {
+    /// The name of the source package.
+    pub source: String,
+    /// The packaged crate itself comes first.
+    pub packages: Vec<SbomPackage>,
+    /// A crate, and one that it depends on, as indices into `packages`.
+    pub depends: Vec<(usize, usize)>,
+}
+
+/// A license expression of Cargo.toml in SPDX syntax, e.g. "MIT/Apache-2.0",
+/// which crates.io used to allow, to "MIT OR Apache-2.0".
+pub fn spdx_license_expression(license: &str) -> String {
+    license
+        .split('/')
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" OR ")
+        .split_whitespace()
+        .map(|w| match w.to_lowercase().as_str() {
+            "or" => "OR",
+            "and" => "AND",
+            "with" => "WITH",
+            _ => w,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Sbom {
+    /// The bill of materials of the source package `source`, for a crate and
+    /// the crates in its dependency tree. `license` looks up the license of a
+    /// crate, as given in its Cargo.toml.
+    pub fn from_tree<F>(source: &str, tree: &TreeNode, mut license: F) -> Result<Sbom>
+    where
+        F: FnMut(&str, &Version) -> Result<Option<String>>,
+    {
+        let mut sbom = Sbom {
+            source: source.to_string(),
+            packages: Vec::new(),
+            depends: Vec::new(),
+        };
+        let mut index = BTreeMap::new();
+        sbom.add(tree, &mut index, &mut license)?;
+        sbom.depends.sort();
+        sbom.depends.dedup();
+        Ok(sbom)
+    }
+
+    fn add<F>(
+        &mut self,
+        node: &TreeNode,
+        index: &mut BTreeMap<(String, Version), usize>,
+        license: &mut F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&str, &Version) -> Result<Option<String>>,
+    {
+        let key = (node.name.clone(), node.version.clone());
+        let i = match index.get(&key) {
+            Some(&i) => i,
+            None => {
+                self.packages.push(SbomPackage {
+                    name: node.name.clone(),
+                    version: node.version.clone(),
+                    checksum: node.checksum.clone(),
+                    license: license(&node.name, &node.version)?.map(|l| spdx_license_expression(&l)),
+                });
+                index.insert(key, self.packages.len() - 1);
+                self.packages.len() - 1
+            }
+        };
+        for child in &node.children {
+            let c = self.add(child, index, license)?;
+            self.depends.push((i, c));
+        }
+        Ok(i)
+    }
+
+    /// A hash of the crates, to make identifiers for the document that are
+    /// unique, but the same for the same crates.
+    fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for p in &self.packages {
+            hasher.update(format!("{} {} {:?}\n", p.name, p.version, p.checksum).as_bytes());
+        }
+        hasher.finish().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn namespace(&self) -> String {
+        format!("https://spdx.org/spdxdocs/{}-{}-{}", self.source, self.packages[0].version, &self.hash()[..16])
+    }
+
+    /// An SPDX 2.3 document in the tag-value format, created at `created`,
+    /// e.g. "2019-01-01T00:00:00Z".
+    pub fn to_spdx(&self, created: &str) -> String {
+        let mut doc = String::new();
+        let root = &self.packages[0];
+        writeln!(doc, "SPDXVersion: SPDX-2.3").unwrap();
+        writeln!(doc, "DataLicense: CC0-1.0").unwrap();
+        writeln!(doc, "SPDXID: SPDXRef-DOCUMENT").unwrap();
+        writeln!(doc, "DocumentName: {}-{}", self.source, root.version).unwrap();
+        writeln!(doc, "DocumentNamespace: {}", self.namespace()).unwrap();
+        writeln!(doc, "Creator: Tool: debcargo-{}", env!("CARGO_PKG_VERSION")).unwrap();
+        writeln!(doc, "Created: {}", created).unwrap();
+        for p in &self.packages {
+            writeln!(doc).unwrap();
+            writeln!(doc, "PackageName: {}", p.name).unwrap();
+            writeln!(doc, "SPDXID: {}", p.spdx_id()).unwrap();
+            writeln!(doc, "PackageVersion: {}", p.version).unwrap();
+            writeln!(doc, "PackageSupplier: NOASSERTION").unwrap();
+            writeln!(doc, "PackageDownloadLocation: {}", p.download_url()).unwrap();
+            writeln!(doc, "FilesAnalyzed: false").unwrap();
+            if let Some(ref checksum) = p.checksum {
+                writeln!(doc, "PackageChecksum: SHA256: {}", checksum).unwrap();
+            }
+            let license = p.license.as_ref().map_or("NOASSERTION", String::as_str);
+            writeln!(doc, "PackageLicenseConcluded: NOASSERTION").unwrap();
+            writeln!(doc, "PackageLicenseDeclared: {}", license).unwrap();
+            writeln!(doc, "PackageCopyrightText: NOASSERTION").unwrap();
+            writeln!(doc, "ExternalRef: PACKAGE-MANAGER purl {}", p.purl()).unwrap();
+        }
+        writeln!(doc).unwrap();
+        writeln!(doc, "Relationship: SPDXRef-DOCUMENT DESCRIBES {}", root.spdx_id()).unwrap();
+        for &(a, b) in &self.depends {
+            writeln!(
+                doc,
+                "Relationship: {} DEPENDS_ON {}",
+                self.packages[a].spdx_id(),
+                self.packages[b].spdx_id()
+            ).unwrap();
+        }
+        doc
+    }
+
+    /// A CycloneDX 1.4 document in JSON, created at `created`.
+    pub fn to_cyclonedx(&self, created: &str) -> String {
+        let component = |p: &SbomPackage| CdxComponent {
+            kind: "library",
+            bom_ref: p.purl(),
+            name: p.name.clone(),
+            version: p.version.to_string(),
+            purl: p.purl(),
+            hashes: p.checksum.iter().map(|c| CdxHash { alg: "SHA-256", content: c.clone() }).collect(),
+            licenses: p.license.iter().map(|l| CdxLicense { expression: l.clone() }).collect(),
+            external_references: vec![CdxReference { kind: "distribution", url: p.download_url() }],
+        };
+        let hash = self.hash();
+        let bom = CdxBom {
+            bom_format: "CycloneDX",
+            spec_version: "1.4",
+            serial_number: format!(
+                "urn:uuid:{}-{}-{}-{}-{}",
+                &hash[0..8], &hash[8..12], &hash[12..16], &hash[16..20], &hash[20..32]
+            ),
+            version: 1,
+            metadata: CdxMetadata {
+                timestamp: created.to_string(),
+                tools: vec![CdxTool { name: "debcargo", version: env!("CARGO_PKG_VERSION") }],
+                component: component(&self.packages[0]),
+            },
+            components: self.packages[1..].iter().map(component).collect(),
+            dependencies: self
+                .packages
+                .iter()
+                .enumerate()
+                .map(|(i, p)| CdxDependency {
+                    reference: p.purl(),
+                    depends_on: self
+                        .depends
+                        .iter()
+                        .filter(|&&(a, _)| a == i)
+                        .map(|&(_, b)| self.packages[b].purl())
+                        .collect(),
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&bom).unwrap() + "\n"
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CdxBom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    serial_number: String,
+    version: u32,
+    metadata: CdxMetadata,
+    components: Vec<CdxComponent>,
+    dependencies: Vec<CdxDependency>,
+}
+
+#[derive(Serialize)]
+struct CdxMetadata {
+    timestamp: String,
+    tools: Vec<CdxTool>,
+    component: CdxComponent,
+}
+
+#[derive(Serialize)]
+struct CdxTool {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CdxComponent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    version: String,
+    purl: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CdxHash>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CdxLicense>,
+    external_references: Vec<CdxReference>,
+}
+
+#[derive(Serialize)]
+struct CdxHash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct CdxLicense {
+    expression: String,
+}
+
+#[derive(Serialize)]
+struct CdxReference {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CdxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    depends_on: Vec<String>,
+}
@@ -0,0 +1,232 @@
+use chrono::{self, Datelike};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use regex::Regex;
+use tar::Builder;
+use tempfile;
+use toml;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use config::{parse_config, Config};
+use crates::CrateInfo;
+use debian::{self, BaseInfo};
+use errors::*;
+use util::copy_tree;
+use verify::{dir_contents, Contents};
+
+/// Stands in for the debcargo version in generated files, so that the
+/// expected output doesn't change with every release.
+pub const DEBCARGO_VERSION: &str = "SELFTEST";
+const AUTHOR_NAME: &str = "Debcargo Selftest";
+const AUTHOR_EMAIL: &str = "selftest@debcargo.invalid";
+
+/// A test crate and its expected packaging, laid out as:
+///
+/// - `crate/`: the crate source, with a Cargo.toml; or `crate-of`: the name
+///   of another case, whose crate is packaged here with a different config
+/// - `debcargo.toml`: optional config to package it with
+/// - `expected/`: the expected contents of debian/
+pub struct Case {
+    pub name: String,
+    pub dir: PathBuf,
+    pub crate_dir: PathBuf,
+}
+
+impl Case {
+    fn expected_dir(&self) -> PathBuf {
+        self.dir.join("expected")
+    }
+}
+
+pub fn find_cases(cases_dir: &Path) -> Result<Vec<Case>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(cases_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let crate_dir = if entry.path().join("crate").join("Cargo.toml").is_file() {
+            entry.path().join("crate")
+        } else if entry.path().join("crate-of").is_file() {
+            let other = fs::read_to_string(entry.path().join("crate-of"))?;
+            let crate_dir = cases_dir.join(other.trim()).join("crate");
+            if !crate_dir.join("Cargo.toml").is_file() {
+                debcargo_bail!("{}: crate-of names {}, which has no crate", name, other.trim());
+            }
+            crate_dir
+        } else {
+            continue;
+        };
+        cases.push(Case {
+            name: name,
+            dir: entry.path(),
+            crate_dir: crate_dir,
+        });
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// Replace the parts of the generated files that change from run to run.
+pub fn normalize(path: &Path, data: &[u8]) -> Vec<u8> {
+    let text = match ::std::str::from_utf8(data) {
+        Ok(t) => t,
+        Err(_) => return data.to_vec(),
+    };
+    let text = if path == Path::new("changelog") || path == Path::new("NEWS") {
+        let re = Regex::new(r"(?m)^( -- .*>  ).*$").unwrap();
+        re.replace_all(text, "${1}DATE").into_owned()
+    } else if path == Path::new("copyright") {
+        let year = chrono::Local::now().year().to_string();
+        text.replace(&year, "YEAR")
+    } else {
+        text.to_string()
+    };
+    text.into_bytes()
+}
+
+fn name_and_version(manifest: &Path) -> Result<(String, String)> {
+    let manifest: toml::Value = toml::from_str(&fs::read_to_string(manifest)?)?;
+    let field = |k: &str| {
+        manifest
+            .get("package")
+            .and_then(|p| p.get(k))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    match (field("name"), field("version")) {
+        (Some(n), Some(v)) => Ok((n, v)),
+        _ => debcargo_bail!("Cargo.toml has no package name or version"),
+    }
+}
+
+/// Package a copy of the case's crate, as `debcargo package --path` would
+/// after `cargo package`, returning the normalized contents of debian/.
+/// `strict` is as for `debcargo package --strict`.
+pub fn generate(case: &Case, work_dir: &Path, strict: bool) -> Result<Contents> {
+    let crate_dir = work_dir.join("crate");
+    fs::create_dir_all(&crate_dir)?;
+    copy_tree(&case.crate_dir, &crate_dir)?;
+    let crate_dir = crate_dir.canonicalize()?;
+
+    // what `cargo package` would produce, which CrateInfo::new_from_path wants
+    let (name, version) = name_and_version(&crate_dir.join("Cargo.toml"))?;
+    let package_dir = crate_dir.join("target").join("package");
+    fs::create_dir_all(&package_dir)?;
+    let top = format!("{}-{}", name, version);
+    let mut builder = Builder::new(GzEncoder::new(
+        fs::File::create(package_dir.join(format!("{}.crate", top)))?,
+        Compression::default(),
+    ));
+    for entry in fs::read_dir(&crate_dir)? {
+        let entry = entry?;
+        if entry.file_name() == "target" {
+            continue;
+        }
+        let dest = Path::new(&top).join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            builder.append_dir_all(dest, entry.path())?;
+        } else {
+            builder.append_path_with_name(entry.path(), dest)?;
+        }
+    }
+    builder.into_inner()?.finish()?;
+
+    let config_path = case.dir.join("debcargo.toml");
+    let config = if config_path.is_file() {
+        let mut config = parse_config(&config_path)?;
+        config.expand_uploaders_file()?;
+        config
+    } else {
+        Config::default()
+    };
+
+    let mut crate_info = CrateInfo::new_from_path(&crate_dir, None, false)?;
+    let pkgbase = BaseInfo::new(&name, &crate_info, DEBCARGO_VERSION, config.semver_suffix, config.repack_suffix());
+    let pkg_srcdir = work_dir.join(pkgbase.package_source_dir());
+    let orig_tar_gz = work_dir.join(pkgbase.orig_tarball_path());
+    crate_info.set_includes_excludes(config.orig_tar_excludes(), config.orig_tar_whitelist())?;
+    let source_modified = crate_info.extract_crate(&pkg_srcdir)?;
+    debian::prepare_orig_tarball(&crate_info, &orig_tar_gz, source_modified, &pkg_srcdir)?;
+
+    env::set_var("DEBFULLNAME", AUTHOR_NAME);
+    env::set_var("DEBEMAIL", AUTHOR_EMAIL);
+    debian::prepare_debian_folder(
+        &pkgbase,
+        &mut crate_info,
+        &pkg_srcdir,
+        &config,
+        false,
+        false,
+        false,
+        strict,
+        None,
+        None,
+    )?;
+
+    Ok(dir_contents(&pkg_srcdir.join("debian"))?
+        .into_iter()
+        .map(|(p, data)| {
+            let data = normalize(&p, &data);
+            (p, data)
+        })
+        .collect())
+}
+
+/// Human-readable differences between the expected and generated files,
+/// showing the first differing line of each modified file.
+pub fn compare(expected: &Contents, actual: &Contents) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (path, data) in expected {
+        match actual.get(path) {
+            None => differences.push(format!("missing: {}", path.display())),
+            Some(a) if a == data => (),
+            Some(a) => {
+                let (e, a) = (String::from_utf8_lossy(data), String::from_utf8_lossy(a));
+                let mut lines = e.lines().zip(a.lines()).enumerate();
+                match lines.find(|&(_, (e, a))| e != a) {
+                    Some((i, (e, a))) => differences.push(format!(
+                        "modified: {}, line {}:\n  expected: {}\n  actual:   {}",
+                        path.display(), i + 1, e, a)),
+                    None => differences.push(format!(
+                        "modified: {}, {} lines expected, {} lines generated",
+                        path.display(), e.lines().count(), a.lines().count())),
+                }
+            }
+        }
+    }
+    for path in actual.keys() {
+        if !expected.contains_key(path) {
+            differences.push(format!("extra: {}", path.display()));
+        }
+    }
+    differences
+}
+
+/// Regenerate the packaging for a case, and compare it with the expected
+/// output; or with `bless`, replace the expected output with it.
+pub fn run_case(case: &Case, bless: bool) -> Result<Vec<String>> {
+    // not in /tmp, since prepare_debian_folder renames its tempdir in . into
+    // place, which doesn't work across filesystems
+    let work_dir = tempfile::Builder::new().prefix("debcargo").tempdir_in(".")?;
+    let actual = generate(case, work_dir.path(), false)?;
+    let expected_dir = case.expected_dir();
+    if bless {
+        if expected_dir.exists() {
+            fs::remove_dir_all(&expected_dir)?;
+        }
+        for (path, data) in &actual {
+            let dest = expected_dir.join(path);
+            fs::create_dir_all(dest.parent().unwrap())?;
+            fs::write(dest, data)?;
+        }
+        return Ok(Vec::new());
+    }
+    let expected = if expected_dir.is_dir() {
+        dir_contents(&expected_dir)?
+    } else {
+        Contents::new()
+    };
+    Ok(compare(&expected, &actual))
+}
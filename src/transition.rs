@@ -0,0 +1,158 @@
+use semver::Version;
+
+use std::fmt;
+
+use crates::semver_suffix_of;
+use debian::control::PackageNaming;
+use errors::*;
+use tree::{deb_upstream_version, Archive};
+
+/// An update of a crate that changes its semver suffix, e.g. from 0.8 to 0.9,
+/// and so the versioned package names that its Debian package provides, on
+/// which other packages depend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub crate_name: String,
+    pub from: Version,
+    pub to: Version,
+    /// The semver-suffixed package in the archive that keeps providing the
+    /// old version, if any, in which case nothing breaks.
+    pub kept_by: Option<String>,
+    /// The provided packages that go away.
+    pub dropped: Vec<String>,
+    /// The packages in the archive that depend on those, with their source
+    /// packages, which need sourceful rebuilds against the new version.
+    pub rdepends: Vec<(String, String)>,
+}
+
+pub fn crosses_suffix(from: &Version, to: &Version) -> bool {
+    semver_suffix_of(from) != semver_suffix_of(to)
+}
+
+/// Of the packages that the Debian package of a crate provides, those
+/// versioned within the semver suffix of `version`, e.g.
+/// librust-foo-0.8+default-dev and librust-foo-0.8.5-dev for 0.8.5.
+pub fn suffixed_provides(naming: &PackageNaming, provides: &[String], crate_name: &str, version: &Version) -> Vec<String> {
+    let base = crate_name.replace('_', "-").to_lowercase();
+    let suffixed_base = format!("{}{}", base, semver_suffix_of(version));
+    let mut suffixed = provides
+        .iter()
+        .filter(|p| match naming.parse(p) {
+            Some((head, _)) => {
+                head == suffixed_base
+                    || (head.starts_with(&suffixed_base) && head[suffixed_base.len()..].starts_with('.'))
+            }
+            None => false,
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    suffixed.sort();
+    suffixed.dedup();
+    suffixed
+}
+
+impl Transition {
+    /// Check in the archive what updating a crate from `from`, or else the
+    /// version in the archive, to `to` would break: nothing unless it crosses
+    /// a semver-suffix boundary, in which case the transition is returned.
+    pub fn check(
+        archive: &mut Archive,
+        naming: &PackageNaming,
+        crate_name: &str,
+        from: Option<&Version>,
+        to: &Version,
+    ) -> Result<Option<Transition>> {
+        let base = crate_name.replace('_', "-").to_lowercase();
+        let package = naming.name(&base);
+        let from = match from {
+            Some(from) => from.clone(),
+            None => match archive.version(&package).as_ref().and_then(|v| deb_upstream_version(v)) {
+                Some(v) => v,
+                None => debcargo_bail!("{} is not in the archive; give the version to update from", package),
+            },
+        };
+        if !crosses_suffix(&from, to) {
+            return Ok(None);
+        }
+        let mut transition = Transition {
+            crate_name: crate_name.to_string(),
+            from: from.clone(),
+            to: to.clone(),
+            kept_by: None,
+            dropped: Vec::new(),
+            rdepends: Vec::new(),
+        };
+
+        let old_suffixed = naming.name(&format!("{}{}", base, semver_suffix_of(&from)));
+        match archive.has_package(&old_suffixed) {
+            None => debcargo_bail!("Couldn't run apt-cache to look up {} in the archive", old_suffixed),
+            Some(true) => {
+                transition.kept_by = Some(old_suffixed);
+                return Ok(Some(transition));
+            }
+            Some(false) => (),
+        }
+        let (provides, source) = match archive.show(&package) {
+            Some(show) => show,
+            None => return Ok(Some(transition)),
+        };
+        transition.dropped = suffixed_provides(naming, &provides, crate_name, &from);
+
+        let mut rdepends = Vec::new();
+        for dropped in &transition.dropped {
+            for rdep in archive.reverse_depends(dropped).unwrap_or_default() {
+                let rdep_source = archive.show(&rdep).map_or_else(|| rdep.clone(), |(_, s)| s);
+                // the crate's own feature packages move along with it
+                if rdep_source != source {
+                    rdepends.push((rdep, rdep_source));
+                }
+            }
+        }
+        rdepends.sort();
+        rdepends.dedup();
+        transition.rdepends = rdepends;
+        Ok(Some(transition))
+    }
+
+    /// The source packages to rebuild, each once.
+    pub fn sources(&self) -> Vec<&str> {
+        let mut sources = self.rdepends.iter().map(|&(_, ref s)| s.as_str()).collect::<Vec<_>>();
+        sources.sort();
+        sources.dedup();
+        sources
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} -> {} changes the semver suffix {} -> {}",
+            self.crate_name,
+            self.from,
+            self.to,
+            semver_suffix_of(&self.from),
+            semver_suffix_of(&self.to)
+        )?;
+        if let Some(ref p) = self.kept_by {
+            return writeln!(f, "  {} keeps providing {}, nothing needs rebuilding", p, self.from);
+        }
+        if !self.dropped.is_empty() {
+            writeln!(f, "  drops {}", self.dropped.join(", "))?;
+        }
+        let sources = self.sources();
+        if sources.is_empty() {
+            writeln!(f, "  no reverse dependencies in the archive")?;
+        }
+        for source in sources {
+            let packages = self
+                .rdepends
+                .iter()
+                .filter(|&&(_, ref s)| s == source)
+                .map(|&(ref p, _)| p.as_str())
+                .collect::<Vec<_>>();
+            writeln!(f, "  rebuild {} ({})", source, packages.join(", "))?;
+        }
+        Ok(())
+    }
+}
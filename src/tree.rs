@@ -0,0 +1,379 @@
+use cargo::core::registry::PackageRegistry;
+use cargo::core::{Dependency, SourceId, Summary};
+use cargo::Config;
+use semver::{Version, VersionReq};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::process::Command;
+
+use crates::{crates_io_registry, fetch_candidates, semver_suffix_of, summary_dependencies_and_features,
+             version_req_from_arg, DependencySelection, NewFeatureSyntax};
+use debian::control::PackageNaming;
+use errors::*;
+
+/// Whether the Debian archive has a package that satisfies a dependency.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebianStatus {
+    /// The package and its version, which satisfies the requirement.
+    Ok(String, String),
+    /// The package and its version, which doesn't satisfy the requirement.
+    Mismatch(String, String),
+    Missing,
+    /// We couldn't ask apt.
+    Unknown,
+}
+
+impl fmt::Display for DebianStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DebianStatus::Ok(ref p, ref v) => write!(f, "{} {}", p, v),
+            DebianStatus::Mismatch(ref p, ref v) => write!(f, "{} {}, does not satisfy", p, v),
+            DebianStatus::Missing => write!(f, "not in Debian"),
+            DebianStatus::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+pub struct TreeNode {
+    pub name: String,
+    pub req: String,
+    pub version: Version,
+    /// The SHA-256 of the .crate, from the index.
+    pub checksum: Option<String>,
+    /// The features of the crate that the crate depending on it enables,
+    /// including "default" unless it turns off the default features.
+    pub features: Vec<String>,
+    pub debian: DebianStatus,
+    /// Already shown further up, so its dependencies are not repeated.
+    pub repeated: bool,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn write(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{}{} {} ({}) [{}]{}",
+            "  ".repeat(depth),
+            self.name,
+            self.version,
+            self.req,
+            self.debian,
+            if self.repeated { " (*)" } else { "" }
+        )?;
+        for child in &self.children {
+            child.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TreeNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// The dependency tree as a Graphviz dot graph: a node for each crate, colored
+/// by whether Debian has it, and for each feature of a crate that is enabled
+/// by a crate depending on it, with edges from the crate depending on it, via
+/// the features, to the crate.
+pub fn dot_graph(tree: &TreeNode) -> String {
+    fn crate_id(node: &TreeNode) -> String {
+        format!("{} {}", node.name, node.version)
+    }
+    fn walk(node: &TreeNode, nodes: &mut BTreeMap<String, String>, edges: &mut BTreeSet<(String, String)>) {
+        let color = match node.debian {
+            DebianStatus::Ok(..) => "palegreen",
+            DebianStatus::Mismatch(..) => "orange",
+            DebianStatus::Missing => "salmon",
+            DebianStatus::Unknown => "lightgrey",
+        };
+        let id = crate_id(node);
+        nodes
+            .entry(id.clone())
+            .or_insert_with(|| format!("shape=box, style=filled, fillcolor={}, tooltip=\"{}\"", color, node.debian));
+        for child in &node.children {
+            let child_id = crate_id(child);
+            if child.features.is_empty() {
+                edges.insert((id.clone(), child_id.clone()));
+            }
+            for f in &child.features {
+                let feature_id = format!("{}/{}", child_id, f);
+                nodes.entry(feature_id.clone()).or_insert_with(|| format!("shape=ellipse, label=\"{}\"", f));
+                edges.insert((id.clone(), feature_id.clone()));
+                edges.insert((feature_id, child_id.clone()));
+            }
+            walk(child, nodes, edges);
+        }
+    }
+    let mut nodes = BTreeMap::new();
+    let mut edges = BTreeSet::new();
+    walk(tree, &mut nodes, &mut edges);
+    let mut dot = format!("digraph \"{}\" {{\n", tree.name);
+    for (id, attrs) in &nodes {
+        dot.push_str(&format!("    \"{}\" [{}];\n", id, attrs));
+    }
+    for &(ref a, ref b) in &edges {
+        dot.push_str(&format!("    \"{}\" -> \"{}\";\n", a, b));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Extract the candidate version from the output of `apt-cache policy`.
+pub fn parse_apt_policy(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter_map(|l| l.trim().splitn(2, "Candidate:").nth(1))
+        .map(|v| v.trim().to_string())
+        .find(|v| v != "(none)")
+}
+
+/// The packages that a package provides and its source package, from the
+/// first stanza of the output of `apt-cache show`.
+pub fn parse_apt_show(package: &str, output: &str) -> (Vec<String>, String) {
+    let stanza = output.split("\n\n").next().unwrap_or("");
+    let field = |name: &str| {
+        let prefix = format!("{}:", name);
+        stanza
+            .lines()
+            .find(|l| l.starts_with(&prefix))
+            .map(|l| l[prefix.len()..].trim().to_string())
+    };
+    let provides = field("Provides").map_or_else(Vec::new, |p| {
+        p.split(',')
+            .filter_map(|p| p.split_whitespace().next())
+            .map(str::to_string)
+            .collect()
+    });
+    let source = field("Source")
+        .and_then(|s| s.split_whitespace().next().map(str::to_string))
+        .unwrap_or_else(|| package.to_string());
+    (provides, source)
+}
+
+/// The packages that depend on a package, from the output of
+/// `apt-cache rdepends`, with alternatives too.
+pub fn parse_apt_rdepends(output: &str) -> Vec<String> {
+    let mut rdepends = output
+        .lines()
+        .skip_while(|l| !l.starts_with("Reverse Depends:"))
+        .skip(1)
+        .map(|l| l.trim().trim_start_matches('|').to_string())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+    rdepends.sort();
+    rdepends.dedup();
+    rdepends
+}
+
+/// Whether the upstream part of a Debian version satisfies a cargo version
+/// requirement; e.g. "1:1.2.3~beta1-2" is taken as 1.2.3-beta1.
+pub fn deb_version_satisfies(deb_version: &str, req: &VersionReq) -> bool {
+    deb_upstream_version(deb_version).map_or(false, |v| req.matches(&v))
+}
+
+/// The crate version of a Debian version, e.g. 1.2.3-beta1 for
+/// "1:1.2.3~beta1+dfsg-2".
+pub fn deb_upstream_version(deb_version: &str) -> Option<Version> {
+    let upstream = deb_version.splitn(2, ':').last().unwrap();
+    let upstream = match upstream.rfind('-') {
+        Some(p) => &upstream[..p],
+        None => upstream,
+    };
+    // strip any repacking suffix like +dfsg
+    let upstream = upstream.split('+').next().unwrap();
+    Version::parse(&upstream.replacen('~', "-", 1)).ok()
+}
+
+/// Looks up crates in the Debian archive, via apt.
+#[derive(Default)]
+pub struct Archive {
+    cache: HashMap<String, Option<Option<String>>>,
+}
+
+impl Archive {
+    fn candidate(&mut self, package: &str) -> Option<Option<String>> {
+        if let Some(c) = self.cache.get(package) {
+            return c.clone();
+        }
+        let c = Archive::apt_cache(&["policy", package]).map(|o| parse_apt_policy(&o));
+        self.cache.insert(package.to_string(), c.clone());
+        c
+    }
+
+    fn apt_cache(args: &[&str]) -> Option<String> {
+        Command::new("apt-cache")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+    }
+
+    /// The candidate version of a package, if it's in the archive.
+    pub fn version(&mut self, package: &str) -> Option<String> {
+        self.candidate(package).and_then(|c| c)
+    }
+
+    /// Whether the archive has a real package, not just one provided by
+    /// others, or None if we couldn't ask apt.
+    pub fn has_package(&mut self, package: &str) -> Option<bool> {
+        self.candidate(package).map(|c| c.is_some())
+    }
+
+    /// The packages that a package provides and its source package, or None
+    /// if it's not in the archive or we couldn't ask apt.
+    pub fn show(&mut self, package: &str) -> Option<(Vec<String>, String)> {
+        Archive::apt_cache(&["show", "--no-all-versions", package])
+            .filter(|o| !o.trim().is_empty())
+            .map(|o| parse_apt_show(package, &o))
+    }
+
+    /// The packages that depend on a package, or None if we couldn't ask apt.
+    pub fn reverse_depends(&mut self, package: &str) -> Option<Vec<String>> {
+        Archive::apt_cache(&["rdepends", "--no-recommends", "--no-suggests", "--no-enhances", package])
+            .map(|o| parse_apt_rdepends(&o))
+    }
+
+    /// The status of a crate dependency in Debian, looking first for a
+    /// semver-suffixed package, then the unsuffixed one.
+    pub fn status(&mut self, naming: &PackageNaming, name: &str, req: &VersionReq, version: &Version) -> DebianStatus {
+        let base = name.replace('_', "-").to_lowercase();
+        let candidates = vec![
+            naming.name(&format!("{}{}", base, semver_suffix_of(version))),
+            naming.name(&base),
+        ];
+        let mut found = None;
+        for package in candidates {
+            match self.candidate(&package) {
+                None => return DebianStatus::Unknown,
+                Some(None) => continue,
+                Some(Some(v)) => {
+                    if deb_version_satisfies(&v, req) {
+                        return DebianStatus::Ok(package, v);
+                    }
+                    found = found.or(Some((package, v)));
+                }
+            }
+        }
+        match found {
+            Some((p, v)) => DebianStatus::Mismatch(p, v),
+            None => DebianStatus::Missing,
+        }
+    }
+}
+
+/// Builds the dependency tree of a crate from the registry index, picking the
+/// newest version matching each requirement, like cargo would for a fresh
+/// lockfile. Dev-dependencies are left out, as are the optional dependencies
+/// that the enabled features don't enable, unless `optional` is given.
+pub struct TreeBuilder<'cfg> {
+    registry: PackageRegistry<'cfg>,
+    source_id: SourceId,
+    archive: Archive,
+    archive_lookups: bool,
+    naming: PackageNaming,
+    optional: bool,
+    seen: BTreeSet<(String, Version)>,
+}
+
+impl<'cfg> TreeBuilder<'cfg> {
+    pub fn new(config: &'cfg Config, update: bool, optional: bool) -> Result<Self> {
+        let (registry, source_id) = crates_io_registry(config, update)?;
+        Ok(TreeBuilder {
+            registry: registry,
+            source_id: source_id,
+            archive: Archive::default(),
+            archive_lookups: true,
+            naming: PackageNaming::default(),
+            optional: optional,
+            seen: BTreeSet::new(),
+        })
+    }
+
+    /// Whether to look up the Debian packages of the crates; without, their
+    /// status is unknown.
+    pub fn set_archive_lookups(&mut self, lookups: bool) {
+        self.archive_lookups = lookups;
+    }
+
+    /// How the packages of the crates are named, if not by default.
+    pub fn set_naming(&mut self, naming: PackageNaming) {
+        self.naming = naming;
+    }
+
+    fn newest(&mut self, name: &str, req: &str) -> Result<Summary> {
+        let dep = Dependency::parse_no_deprecated(name, Some(req), self.source_id)?;
+        match fetch_candidates(&mut self.registry, &dep)?.into_iter().next() {
+            Some(summary) => Ok(summary),
+            None => debcargo_bail_kind!(ErrorKind::CrateNotFound, "Couldn't find any crate matching {} {}", name, req),
+        }
+    }
+
+    pub fn build(&mut self, name: &str, version: Option<&str>) -> Result<TreeNode> {
+        let req = match version.filter(|v| !v.is_empty()) {
+            Some(v) => version_req_from_arg(v)?,
+            None => "*".to_string(),
+        };
+        self.node(name, &req, vec!["default".to_string()])
+    }
+
+    fn node(&mut self, name: &str, req: &str, features: Vec<String>) -> Result<TreeNode> {
+        let summary = self.newest(name, req)?;
+        let version = summary.version().clone();
+        let debian = if self.archive_lookups {
+            self.archive.status(&self.naming, name, &VersionReq::parse(req)?, &version)
+        } else {
+            DebianStatus::Unknown
+        };
+        let repeated = !self.seen.insert((name.to_string(), version.clone()));
+        let mut children = Vec::new();
+        if !repeated {
+            // what the features enabled by the crates depending on this one
+            // need, or what any of its features need with optional
+            let mut features_with_deps =
+                summary_dependencies_and_features(&summary, summary.dependencies(), &NewFeatureSyntax::default());
+            let selection = DependencySelection {
+                features: if self.optional {
+                    None
+                } else {
+                    // foo/bar enables the optional dependency foo, if it is one
+                    Some(features
+                        .iter()
+                        .map(|f| f.splitn(2, '/').next().unwrap().to_string())
+                        .filter(|f| features_with_deps.contains_key(f.as_str()))
+                        .collect())
+                },
+            };
+            selection.apply(&mut features_with_deps)?;
+            // the same dependency may appear for several targets, or for
+            // several features, with the features enabled for each of them
+            let mut deps: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+            for d in features_with_deps.values().flat_map(|&(_, ref deps)| deps) {
+                let features = deps
+                    .entry((d.package_name().to_string(), d.version_req().to_string()))
+                    .or_default();
+                features.extend(d.features().iter().map(|f| f.to_string()));
+                if d.uses_default_features() {
+                    features.insert("default".to_string());
+                }
+            }
+            for ((dep_name, dep_req), features) in deps {
+                children.push(self.node(&dep_name, &dep_req, features.into_iter().collect())?);
+            }
+        }
+        Ok(TreeNode {
+            name: name.to_string(),
+            req: req.to_string(),
+            version: version,
+            checksum: summary.checksum().map(str::to_string),
+            features: features,
+            debian: debian,
+            repeated: repeated,
+            children: children,
+        })
+    }
+}
@@ -0,0 +1,166 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use git2::{build::RepoBuilder, ObjectType, Oid, TreeWalkMode};
+use semver::Version;
+use serde_json;
+use tar::{Builder, Header};
+use tempfile;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use errors::*;
+use verify::Contents;
+
+/// The name of the orig tarball component holding upstream files that were
+/// left out of the published crate.
+pub const COMPONENT: &str = "upstream";
+
+/// Where a crate was packaged from, as recorded by `cargo package` in
+/// .cargo_vcs_info.json; older crates don't have this.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VcsInfo {
+    pub sha1: Option<String>,
+    /// Directory of the crate within the repository, empty for the top level.
+    pub path_in_vcs: String,
+}
+
+/// Read the .cargo_vcs_info.json of a crate's contents, if any.
+pub fn vcs_info(crate_contents: &Contents) -> VcsInfo {
+    let json = crate_contents
+        .get(Path::new(".cargo_vcs_info.json"))
+        .and_then(|data| serde_json::from_slice::<serde_json::Value>(data).ok());
+    let json = match json {
+        Some(json) => json,
+        None => return VcsInfo::default(),
+    };
+    VcsInfo {
+        sha1: json
+            .get("git")
+            .and_then(|g| g.get("sha1"))
+            .and_then(|s| s.as_str())
+            .map(str::to_string),
+        path_in_vcs: json
+            .get("path_in_vcs")
+            .and_then(|p| p.as_str())
+            .unwrap_or("")
+            .to_string(),
+    }
+}
+
+/// Whether a file matters to the packaging when it's missing from the crate:
+/// tests and benchmarks, which the autopkgtests would run, and license files.
+pub fn is_packaging_relevant(path: &Path) -> bool {
+    if path.starts_with("tests") || path.starts_with("benches") {
+        return true;
+    }
+    if path.components().count() != 1 {
+        return false;
+    }
+    let name = path.to_string_lossy().to_uppercase();
+    ["LICENSE", "LICENCE", "COPYING", "COPYRIGHT"]
+        .iter()
+        .any(|p| name.starts_with(p))
+}
+
+/// The relevant files of the upstream repository that the crate lacks.
+pub fn excluded_files(crate_contents: &Contents, upstream: &Contents) -> Vec<PathBuf> {
+    upstream
+        .keys()
+        .filter(|p| is_packaging_relevant(p) && !crate_contents.contains_key(*p))
+        .cloned()
+        .collect()
+}
+
+/// The revisions that the release of a crate might be at, most likely first:
+/// the commit recorded by cargo, then the usual tag names.
+pub fn candidate_revisions(crate_name: &str, version: &Version, vcs: &VcsInfo) -> Vec<String> {
+    let mut revisions = Vec::new();
+    if let Some(ref sha1) = vcs.sha1 {
+        revisions.push(sha1.clone());
+    }
+    for tag in &[
+        format!("v{}", version),
+        format!("{}", version),
+        format!("{}-v{}", crate_name, version),
+        format!("{}-{}", crate_name, version),
+    ] {
+        revisions.push(format!("refs/tags/{}", tag));
+    }
+    revisions
+}
+
+/// The files of an upstream repository at the release of a crate, under the
+/// crate's directory, along with the revision they were taken from. If no
+/// revision for the release can be found, this fails unless `from_head` is
+/// given, in which case the files are taken from HEAD.
+pub fn fetch_upstream_files(
+    repository: &str,
+    crate_name: &str,
+    version: &Version,
+    vcs: &VcsInfo,
+    from_head: bool,
+) -> Result<(String, Contents)> {
+    let tempdir = tempfile::Builder::new().prefix("debcargo").tempdir_in(".")?;
+    let repo = RepoBuilder::new().bare(true).clone(repository, tempdir.path())?;
+    let candidates = candidate_revisions(crate_name, version, vcs);
+    let (revision, object) = match candidates
+        .iter()
+        .filter_map(|r| repo.revparse_single(r).ok().map(|o| (r.clone(), o)))
+        .next()
+    {
+        Some(found) => found,
+        None if from_head => {
+            debcargo_warn!(
+                "Found no commit or tag for {} {} in {}, comparing with HEAD instead",
+                crate_name,
+                version,
+                repository
+            );
+            ("HEAD".to_string(), repo.revparse_single("HEAD")?)
+        }
+        None => debcargo_bail!(
+            "Found no commit or tag for {} {} in {}; pass --upstream-files-from-head to compare \
+             with HEAD instead",
+            crate_name,
+            version,
+            repository
+        ),
+    };
+    let mut tree = object.peel_to_tree()?;
+    if !vcs.path_in_vcs.is_empty() {
+        tree = tree
+            .get_path(Path::new(&vcs.path_in_vcs))?
+            .to_object(&repo)?
+            .peel_to_tree()?;
+    }
+    let mut blobs: Vec<(PathBuf, Oid)> = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            if let Some(name) = entry.name() {
+                blobs.push((Path::new(root).join(name), entry.id()));
+            }
+        }
+        0
+    })?;
+    let mut contents = Contents::new();
+    for (path, id) in blobs {
+        contents.insert(path, repo.find_blob(id)?.content().to_vec());
+    }
+    Ok((revision, contents))
+}
+
+/// Write files as an orig tarball component, with the component name as the
+/// top-level directory.
+pub fn write_component_tarball(files: &Contents, tarball: &Path) -> Result<()> {
+    let mut builder = Builder::new(GzEncoder::new(fs::File::create(tarball)?, Compression::best()));
+    for (path, data) in files {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, Path::new(COMPONENT).join(path), &data[..])?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
@@ -5,6 +5,8 @@ use std::path::{Path, PathBuf};
 use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::symlink;
 
+use unicode_normalization::UnicodeNormalization;
+use unicode_width::UnicodeWidthStr;
 use walkdir;
 
 pub const HINT_SUFFIX: &'static str = ".debcargo.hint";
@@ -38,3 +40,15 @@ pub fn copy_tree(oldtree: &Path, newtree: &Path) -> Result<(), Error> {
 pub fn vec_opt_iter<'a, T>(option: Option<&'a Vec<T>>) -> impl Iterator<Item = &T> + 'a {
     option.into_iter().flat_map(|v| v.iter())
 }
+
+/// Normalize text taken from crate metadata to NFC, so that combining
+/// characters don't throw off length calculations or comparisons.
+pub fn normalize_text(text: &str) -> String {
+    text.nfc().collect()
+}
+
+/// The number of terminal columns the text takes up, counting e.g. CJK
+/// characters as two columns and combining characters as zero.
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
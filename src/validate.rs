@@ -0,0 +1,431 @@
+use glob::Pattern;
+use regex::Regex;
+use toml;
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::Path;
+
+use config::{config_crates, is_package_key, overrides_table, parse_config, parse_config_for_crate,
+             read_uploaders_file, Config};
+use crates::{CrateInfo, DependencySelection};
+use debian::changelog::Backport;
+use debian::control::PackageNaming;
+use debian::description::DescriptionRules;
+use errors::*;
+use util::vec_opt_iter;
+use verify::tarball_contents;
+
+/// Keys that debcargo.toml may have, per table. Keep in sync with the structs
+/// in config.rs and with debcargo.toml.example.
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "bin", "bin_name", "semver_suffix", "epoch", "initial_revision", "vendor", "vendored",
+    "single_package", "provide_zero_dep_features", "max_feature_packages", "all_features_package",
+    "optional_deps_as_suggests", "unpackageable_features", "package_name_template",
+    "feature_package_name_template", "overlay", "excludes", "whitelist", "repack_suffix",
+    "allow_prerelease_deps", "drop_unavailable_dev_deps", "relax", "summary", "description",
+    "description_rules_disabled", "description_strip_patterns", "uploaders", "uploaders_file",
+    "changelog_author", "team_upload", "changelog_entries", "news_entries",
+    "copyright_guess", "source", "packages", "backports",
+];
+const SOURCE_KEYS: &[&str] = &[
+    "section", "policy", "requires_root", "testsuite", "test_examples", "dh_cargo_version",
+    "debhelper_compat", "cargo_version", "rustc_version", "homepage", "vcs_git", "vcs_browser",
+    "build_depends", "build_depends_excludes", "extra_lines", "rules_extra_lines",
+];
+const PACKAGE_KEYS: &[&str] = &[
+    "name", "section", "summary", "description", "depends", "recommends", "suggests",
+    "provides", "extra_lines", "test_is_broken",
+];
+const BACKPORT_KEYS: &[&str] = &["build_depends_versions"];
+
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    /// The config is wrong, or asks for something that can't happen.
+    Error(String),
+    /// The config is probably not what was meant, e.g. it has no effect.
+    Warning(String),
+}
+
+impl Issue {
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Issue::Error(_) => true,
+            Issue::Warning(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Issue::Error(ref m) => write!(f, "error: {}", m),
+            Issue::Warning(ref m) => write!(f, "warning: {}", m),
+        }
+    }
+}
+
+fn unknown_in(table: &toml::value::Table, known: &[&str], prefix: &str, unknown: &mut Vec<String>) {
+    for key in table.keys() {
+        if !known.contains(&key.as_str()) {
+            unknown.push(format!("{}{}", prefix, key));
+        }
+    }
+}
+
+fn unknown_in_config(top: &toml::value::Table, prefix: &str, unknown: &mut Vec<String>) {
+    unknown_in(top, TOP_LEVEL_KEYS, prefix, unknown);
+    if let Some(source) = top.get("source").and_then(|s| s.as_table()) {
+        unknown_in(source, SOURCE_KEYS, &format!("{}source.", prefix), unknown);
+    }
+    let subtables = [("packages", PACKAGE_KEYS), ("backports", BACKPORT_KEYS)];
+    for &(name, keys) in &subtables {
+        if let Some(t) = top.get(name).and_then(|t| t.as_table()) {
+            for (sub, value) in t {
+                let value = match value.as_table() {
+                    Some(v) => v,
+                    None => continue,
+                };
+                let sub_prefix = format!("{}{}.\"{}\".", prefix, name, sub);
+                if name == "packages" && !is_package_key(sub) {
+                    // the section of another crate, with its own settings
+                    if prefix.is_empty() {
+                        unknown_in_config(value, &sub_prefix, unknown);
+                    } else {
+                        unknown.push(format!("{}{}.\"{}\"", prefix, name, sub));
+                    }
+                } else {
+                    unknown_in(value, keys, &sub_prefix, unknown);
+                }
+            }
+        }
+    }
+}
+
+/// Keys of a debcargo.toml that debcargo doesn't know, which are otherwise
+/// silently ignored; e.g. typos, or keys in the wrong table.
+pub fn unknown_keys(content: &str) -> Result<Vec<String>> {
+    let value: toml::Value = toml::from_str(content)?;
+    let top = match value.as_table() {
+        Some(t) => t,
+        None => return Ok(Vec::new()),
+    };
+    let mut unknown = Vec::new();
+    unknown_in_config(top, "", &mut unknown);
+    Ok(unknown)
+}
+
+/// Keys set by config overrides that debcargo doesn't know.
+pub fn unknown_override_keys(overrides: &[String]) -> Result<Vec<String>> {
+    let mut unknown = Vec::new();
+    unknown_in_config(&overrides_table(overrides)?, "", &mut unknown);
+    Ok(unknown)
+}
+
+/// Problems with a config that don't depend on the crate it is for.
+pub fn check_options(config: &Config) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let default = Config::default();
+
+    if config.semver_suffix && config.bin == Some(true) {
+        issues.push(Issue::Warning(
+            "bin = true with semver_suffix = true; the bin package will conflict with the \
+             one from the unsuffixed package, unless they are co-installable".to_string()));
+    }
+    if !config.build_bin_package() && config.bin_name != default.bin_name {
+        issues.push(Issue::Warning("bin_name has no effect, since no bin package is built".to_string()));
+    }
+    if config.vendored && config.semver_suffix {
+        issues.push(Issue::Warning(
+            "vendored = true with semver_suffix = true; vendored applications provide no \
+             library packages for a semver suffix to distinguish".to_string()));
+    }
+    if let Some(overlay) = config.overlay_dir() {
+        if !overlay.is_dir() {
+            issues.push(Issue::Error(format!("overlay directory {} does not exist", overlay.display())));
+        }
+    }
+    if let Some(path) = config.uploaders_file_path() {
+        match read_uploaders_file(&path) {
+            Ok(ref u) if u.is_empty() => issues.push(Issue::Warning(format!(
+                "uploaders_file {} lists no uploaders", path.display()))),
+            Ok(_) => (),
+            Err(e) => issues.push(Issue::Error(format!(
+                "uploaders_file {} cannot be read: {}", path.display(), e))),
+        }
+    }
+    if let Some(ref author) = config.changelog_author {
+        if !Regex::new(r"^[^<>]+ <[^<>@\s]+@[^<>\s]+>$").unwrap().is_match(author) {
+            issues.push(Issue::Error(format!(
+                "changelog_author \"{}\" should be of the form \"Full Name <email@example.org>\"", author)));
+        }
+    }
+    if let Some(r) = config.requires_root() {
+        if !(r.is_empty() || r == "no" || r == "binary-targets" || r.contains('/')) {
+            issues.push(Issue::Error(format!(
+                "source.requires_root \"{}\" should be \"no\", \"binary-targets\", or \
+                 implementation-specific keywords like \"dpkg/target-subcommand\"", r)));
+        }
+    }
+    let versions = [
+        ("dh_cargo_version", config.dh_cargo_version()),
+        ("cargo_version", config.cargo_version()),
+        ("rustc_version", config.rustc_version()),
+    ];
+    for &(key, version) in &versions {
+        if let Some(v) = version {
+            let valid = v.starts_with(|c: char| c.is_ascii_digit())
+                && v.chars().all(|c| c.is_ascii_alphanumeric() || ".+~-:".contains(c));
+            if !valid {
+                issues.push(Issue::Error(format!(
+                    "source.{} \"{}\" should be a bare version like \"1.2\", without an operator", key, v)));
+            }
+        }
+    }
+
+    let excludes = config.orig_tar_excludes().cloned().unwrap_or_default();
+    let whitelist = config.orig_tar_whitelist().cloned().unwrap_or_default();
+    for (key, globs) in &[("excludes", &excludes), ("whitelist", &whitelist)] {
+        for g in globs.iter() {
+            if let Err(e) = Pattern::new(g) {
+                issues.push(Issue::Error(format!("{} has an invalid glob \"{}\": {}", key, g, e)));
+            }
+        }
+    }
+    for g in &whitelist {
+        if excludes.contains(g) {
+            issues.push(Issue::Warning(format!(
+                "\"{}\" is both excluded and whitelisted; excludes take precedence", g)));
+        }
+    }
+    if let Err(e) = DescriptionRules::from_config(config) {
+        issues.push(Issue::Error(e.to_string()));
+    }
+    if config.optional_deps_as_suggests && config.single_package {
+        issues.push(Issue::Warning(
+            "optional_deps_as_suggests is ignored with single_package = true".to_string()));
+    }
+    if config.all_features_package && config.single_package {
+        issues.push(Issue::Warning(
+            "all_features_package is ignored with single_package = true".to_string()));
+    }
+    if config.max_feature_packages == Some(0) {
+        issues.push(Issue::Error(
+            "max_feature_packages = 0; it counts the main package, so must be at least 1".to_string()));
+    }
+    if let Err(e) = PackageNaming::from_config(config) {
+        issues.push(Issue::Error(e.to_string()));
+    }
+
+    if let Some(ref packages) = config.packages {
+        let mut keys = packages.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            if key != "lib" && key != "bin" && !key.starts_with("lib+") {
+                issues.push(Issue::Error(format!(
+                    "packages.\"{}\" is not a package key; use \"lib\", \"bin\" or \"lib+FEATURE\"", key)));
+            }
+        }
+        if packages.contains_key("bin") && !config.build_bin_package() {
+            issues.push(Issue::Warning("packages.bin has no effect, since no bin package is built".to_string()));
+        }
+    }
+    if let Some(ref backports) = config.backports {
+        let mut codenames = backports.keys().collect::<Vec<_>>();
+        codenames.sort();
+        for codename in codenames {
+            if let Err(e) = Backport::parse(codename) {
+                issues.push(Issue::Error(format!("backports.\"{}\": {}", codename, e)));
+            }
+        }
+    }
+    issues
+}
+
+/// Problems with a config that only show up against the crate it is for,
+/// such as overrides for features the crate doesn't have.
+pub fn check_against_crate(config: &Config, crate_info: &CrateInfo) -> Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+    let name = crate_info.package_id().name();
+
+    let contents = tarball_contents(fs::File::open(crate_info.crate_file().path())?)?;
+    let paths = contents.keys().map(|p| Path::new("crate").join(p)).collect::<Vec<_>>();
+    let globs = [
+        ("excludes", config.orig_tar_excludes()),
+        ("whitelist", config.orig_tar_whitelist()),
+    ];
+    for &(key, globs) in &globs {
+        for g in globs.iter().flat_map(|g| g.iter()) {
+            // as in CrateInfo::set_includes_excludes
+            let pattern = match Pattern::new(&format!("*/{}", g)) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+            if !paths.iter().any(|p| pattern.matches_path(p)) {
+                issues.push(Issue::Warning(format!(
+                    "{} glob \"{}\" matches nothing in {} {}", key, g, name, crate_info.version())));
+            }
+        }
+    }
+
+    if config.test_examples() && crate_info.get_example_targets().is_empty() {
+        issues.push(Issue::Warning(format!(
+            "source.test_examples has no effect, {} {} has no examples", name, crate_info.version())));
+    }
+
+    let features = crate_info.all_dependencies_and_features(&DependencySelection::default())?;
+    for f in vec_opt_iter(config.unpackageable_features.as_ref()) {
+        if !features.contains_key(f.as_str()) {
+            issues.push(Issue::Error(format!(
+                "unpackageable_features: {} {} has no feature or optional dependency \"{}\"",
+                name, crate_info.version(), f)));
+        }
+    }
+
+    if let Some(ref packages) = config.packages {
+        let mut keys = packages.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            if key == "lib" && !crate_info.is_lib() {
+                issues.push(Issue::Warning(format!("packages.lib has no effect, {} is not a library", name)));
+            } else if key == "bin" && crate_info.get_binary_targets().is_empty() {
+                issues.push(Issue::Warning(format!("packages.bin has no effect, {} has no binaries", name)));
+            } else if key.starts_with("lib+") {
+                let feature = &key["lib+".len()..];
+                if feature != "@" && !features.contains_key(feature) {
+                    issues.push(Issue::Error(format!(
+                        "packages.\"{}\": {} {} has no feature or optional dependency \"{}\"",
+                        key, name, crate_info.version(), feature)));
+                }
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Validate a debcargo.toml, optionally against the crate it is for.
+pub fn validate_config(config_path: &Path, crate_info: Option<&CrateInfo>) -> Result<Vec<Issue>> {
+    let content = fs::read_to_string(config_path)?;
+    let mut issues = match unknown_keys(&content) {
+        Ok(keys) => keys
+            .into_iter()
+            .map(|k| Issue::Warning(format!("unknown key {}, it will be ignored", k)))
+            .collect::<Vec<_>>(),
+        Err(e) => return Ok(vec![Issue::Error(format!("not valid TOML: {}", e))]),
+    };
+    let parsed = match crate_info {
+        Some(crate_info) => parse_config_for_crate(config_path, &crate_info.package_id().name()),
+        None => parse_config(config_path),
+    };
+    let config = match parsed {
+        Ok(config) => config,
+        Err(e) => {
+            issues.push(Issue::Error(format!("{}", e)));
+            return Ok(issues);
+        }
+    };
+    let shared = check_options(&config);
+    if let Some(crate_info) = crate_info {
+        issues.extend(shared);
+        issues.extend(check_against_crate(&config, crate_info)?);
+    } else {
+        let mut sections = Vec::new();
+        for name in config_crates(config_path)? {
+            let in_section = |m: String| format!("packages.\"{}\": {}", name, m);
+            match parse_config_for_crate(config_path, &name) {
+                Ok(c) => sections.extend(
+                    check_options(&c)
+                        .into_iter()
+                        .filter(|i| !shared.contains(i))
+                        .map(|i| match i {
+                            Issue::Error(m) => Issue::Error(in_section(m)),
+                            Issue::Warning(m) => Issue::Warning(in_section(m)),
+                        }),
+                ),
+                Err(e) => sections.push(Issue::Error(in_section(e.to_string()))),
+            }
+        }
+        issues.extend(shared);
+        issues.extend(sections);
+    }
+    Ok(issues)
+}
+
+/// A commented debcargo.toml for a crate, with stubs for the overrides of its
+/// features, optional dependencies and binaries, for a new packaging. Only
+/// the overlay is set; everything else is commented out.
+pub fn config_skeleton(crate_info: &CrateInfo) -> Result<String> {
+    let name = crate_info.package_id().name();
+    let mut s = String::new();
+    writeln!(s, "# debcargo.toml for {} {}, see debcargo.toml.example for all the keys.", name, crate_info.version()).unwrap();
+    writeln!(s, "overlay = \".\"").unwrap();
+    writeln!(s, "#uploaders = [\"Jane Doe <jane@example.org>\"]").unwrap();
+    writeln!(s).unwrap();
+    match crate_info.metadata().description {
+        Some(ref d) => {
+            writeln!(s, "# From Cargo.toml:").unwrap();
+            for line in d.trim().lines() {
+                writeln!(s, "#   {}", line.trim()).unwrap();
+            }
+        }
+        None => writeln!(s, "# Cargo.toml has no description.").unwrap(),
+    }
+    writeln!(s, "#summary = \"\"").unwrap();
+    writeln!(s, "#description = \"\"\"\n#\"\"\"").unwrap();
+
+    let features = crate_info.all_dependencies_and_features(&DependencySelection::default())?;
+    let optional = crate_info
+        .dependencies()
+        .iter()
+        .filter(|d| d.is_optional())
+        .map(|d| d.name_in_toml().to_string())
+        .collect::<Vec<_>>();
+    if crate_info.is_lib() && features.len() > 1 {
+        writeln!(s).unwrap();
+        writeln!(s, "# Features and optional dependencies, and what they need:").unwrap();
+        for (f, &(ref ff, ref deps)) in &features {
+            if f.is_empty() {
+                continue;
+            }
+            let mut needs = ff.iter().filter(|f| !f.is_empty()).map(|f| f.to_string()).collect::<Vec<_>>();
+            needs.extend(deps.iter().map(|d| format!("{} {}", d.package_name(), d.version_req())));
+            let kind = if optional.iter().any(|o| o == f) { " (optional dependency)" } else { "" };
+            writeln!(s, "#   {}{}: {}", f, kind, if needs.is_empty() { "nothing".to_string() } else { needs.join(", ") }).unwrap();
+        }
+        writeln!(s, "# Those that cannot be packaged, e.g. since they need crates not in Debian:").unwrap();
+        writeln!(s, "#unpackageable_features = []").unwrap();
+    }
+
+    let bins = crate_info.get_binary_targets();
+    if !bins.is_empty() {
+        writeln!(s).unwrap();
+        writeln!(s, "# Binaries: {}", bins.join(", ")).unwrap();
+        writeln!(s, "#bin = true").unwrap();
+        writeln!(s, "#bin_name = \"{}\"", name.replace('_', "-")).unwrap();
+    }
+
+    writeln!(s).unwrap();
+    writeln!(s, "[source]").unwrap();
+    writeln!(s, "#section = \"{}\"", if crate_info.is_lib() { "rust" } else { "utils" }).unwrap();
+    writeln!(s, "#build_depends = []").unwrap();
+    writeln!(s, "#extra_lines = []").unwrap();
+
+    if crate_info.is_lib() {
+        writeln!(s).unwrap();
+        writeln!(s, "[packages.lib]").unwrap();
+        writeln!(s, "#depends = []").unwrap();
+        writeln!(s, "#test_is_broken = true").unwrap();
+        for f in features.keys().filter(|f| !f.is_empty()) {
+            writeln!(s).unwrap();
+            writeln!(s, "#[packages.\"lib+{}\"]", f).unwrap();
+            writeln!(s, "#test_is_broken = true").unwrap();
+        }
+    }
+    if !bins.is_empty() {
+        writeln!(s).unwrap();
+        writeln!(s, "[packages.bin]").unwrap();
+        writeln!(s, "#section = \"utils\"").unwrap();
+        writeln!(s, "#depends = []").unwrap();
+    }
+    Ok(s)
+}
@@ -0,0 +1,206 @@
+use flate2::write::GzEncoder;
+use flate2::read::GzDecoder;
+use flate2::Compression;
+use semver::Version;
+use serde_json;
+use tar::{Archive, Builder};
+use toml;
+use walkdir;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crates::CrateInfo;
+use errors::*;
+use verify::sha256_hex;
+
+/// Cargo config that makes a build use the vendored crates instead of
+/// crates.io, for the vendor/ directory of an unpacked source package.
+pub const CARGO_CONFIG: &str = "\
+[source.crates-io]
+replace-with = \"vendored-sources\"
+
+[source.vendored-sources]
+directory = \"vendor\"
+";
+
+/// The name of the orig tarball component holding the vendored crates.
+pub const COMPONENT: &str = "vendor";
+
+/// A crates.io package recorded in a Cargo.lock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockedCrate {
+    pub name: String,
+    pub version: Version,
+    pub checksum: Option<String>,
+}
+
+/// A crate unpacked into the vendor directory, with what d/copyright needs.
+#[derive(Debug, Clone)]
+pub struct VendoredCrate {
+    /// Directory under vendor/.
+    pub dir: String,
+    pub name: String,
+    pub version: Version,
+    pub authors: Vec<String>,
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+}
+
+/// The vendored dependencies of an application packaged in vendoring mode.
+#[derive(Debug, Clone)]
+pub struct Vendoring {
+    pub crates: Vec<VendoredCrate>,
+    /// The Cargo.lock to build with, if the crate doesn't ship one itself.
+    pub lockfile: Option<String>,
+}
+
+/// The crates.io packages of a Cargo.lock, other than `root` itself. Path
+/// dependencies are skipped since they come with the source; git dependencies
+/// can't be vendored from crates.io, so these are an error.
+pub fn locked_registry_crates(lockfile: &str, root: &str) -> Result<Vec<LockedCrate>> {
+    let lock: toml::Value = toml::from_str(lockfile)?;
+    // lockfiles before format v2 keep checksums in a separate table
+    let old_checksums = lock
+        .get("metadata")
+        .and_then(|m| m.as_table())
+        .cloned()
+        .unwrap_or_default();
+    let packages = lock
+        .get("package")
+        .and_then(|p| p.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let mut crates = Vec::new();
+    for p in packages {
+        let field = |k: &str| p.get(k).and_then(|v| v.as_str());
+        let (name, version) = match (field("name"), field("version")) {
+            (Some(n), Some(v)) => (n, v),
+            _ => debcargo_bail!("Cargo.lock has a package without name or version"),
+        };
+        let source = match field("source") {
+            Some(s) => s,
+            None => continue,
+        };
+        if !source.starts_with("registry+") {
+            debcargo_bail!(
+                "Cannot vendor {} {} from {}; only crates.io dependencies are supported",
+                name, version, source
+            );
+        }
+        if name == root {
+            continue;
+        }
+        let checksum = field("checksum").map(str::to_string).or_else(|| {
+            let key = format!("checksum {} {} ({})", name, version, source);
+            old_checksums.get(&key).and_then(|v| v.as_str()).map(str::to_string)
+        });
+        crates.push(LockedCrate {
+            name: name.to_string(),
+            version: Version::parse(version)
+                .map_err(|e| format_err!("Cargo.lock: bad version {} for {}: {}", version, name, e))?,
+            checksum: checksum,
+        });
+    }
+    Ok(crates)
+}
+
+/// Directory names under vendor/, like `cargo vendor` picks them: the crate
+/// name, or name-version for crates locked at several versions.
+pub fn vendor_dir_names(crates: &[LockedCrate]) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let dups: BTreeSet<&str> = crates
+        .iter()
+        .filter(|c| !seen.insert(c.name.as_str()))
+        .map(|c| c.name.as_str())
+        .collect();
+    crates
+        .iter()
+        .map(|c| if dups.contains(c.name.as_str()) {
+            format!("{}-{}", c.name, c.version)
+        } else {
+            c.name.clone()
+        })
+        .collect()
+}
+
+/// The .cargo-checksum.json that cargo wants in each vendored crate.
+pub fn cargo_checksum_json(dir: &Path, package: Option<&str>) -> Result<String> {
+    let mut files = BTreeMap::new();
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() || entry.file_name() == ".cargo-checksum.json" {
+            continue;
+        }
+        let path = entry.path().strip_prefix(dir)?.to_string_lossy().into_owned();
+        files.insert(path, sha256_hex(entry.path())?);
+    }
+    let mut json = serde_json::Map::new();
+    json.insert("files".to_string(), serde_json::to_value(files)?);
+    json.insert("package".to_string(), serde_json::to_value(package)?);
+    Ok(serde_json::Value::Object(json).to_string())
+}
+
+/// Download the locked crates and unpack them under `dest`, as-is, replacing
+/// anything already there.
+pub fn vendor_crates(crates: &[LockedCrate], dest: &Path, update: bool) -> Result<Vec<VendoredCrate>> {
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::create_dir_all(dest)?;
+    let mut vendored = Vec::new();
+    for (c, dir) in crates.iter().zip(vendor_dir_names(crates)) {
+        let crate_info = CrateInfo::new_from_crates_io(&c.name, Some(&c.version.to_string()), update)?;
+        let checksum = crate_info.checksum().map(str::to_string);
+        if let (Some(locked), Some(actual)) = (c.checksum.as_ref(), checksum.as_ref()) {
+            if locked != actual {
+                debcargo_bail!(
+                    "Checksum of {} {} in Cargo.lock is {}, but the index says {}",
+                    c.name, c.version, locked, actual
+                );
+            }
+        }
+        let crate_dir = dest.join(&dir);
+        let unpacked = dest.join(format!("{}-{}", c.name, c.version));
+        let mut archive = Archive::new(GzDecoder::new(fs::File::open(crate_info.crate_file().path())?));
+        for entry in archive.entries()? {
+            if !entry?.unpack_in(dest)? {
+                debcargo_bail!("Crate {} contained path traversals via '..'", c.name);
+            }
+        }
+        if unpacked != crate_dir {
+            fs::rename(&unpacked, &crate_dir)?;
+        }
+        fs::write(
+            crate_dir.join(".cargo-checksum.json"),
+            cargo_checksum_json(&crate_dir, checksum.as_ref().map(String::as_str))?,
+        )?;
+        let meta = crate_info.metadata();
+        vendored.push(VendoredCrate {
+            dir: dir,
+            name: c.name.clone(),
+            version: c.version.clone(),
+            authors: meta.authors.clone(),
+            license: meta.license.clone(),
+            license_file: meta.license_file.clone(),
+        });
+    }
+    Ok(vendored)
+}
+
+/// The path of a component next to an orig tarball, e.g. the vendor one.
+pub fn component_tarball_path(orig_tarball: &Path, component: &str) -> PathBuf {
+    let name = orig_tarball.file_name().unwrap().to_string_lossy();
+    let name = name.replace(".orig.tar.", &format!(".orig-{}.tar.", component));
+    orig_tarball.with_file_name(name)
+}
+
+/// Write a directory as an orig tarball component, e.g.
+/// rust-foo_1.0.0.orig-vendor.tar.gz, with the directory as its top level.
+pub fn write_component_tarball(dir: &Path, tarball: &Path) -> Result<()> {
+    let mut builder = Builder::new(GzEncoder::new(fs::File::create(tarball)?, Compression::best()));
+    builder.append_dir_all(COMPONENT, dir)?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
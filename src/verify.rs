@@ -0,0 +1,123 @@
+use cargo::util::Sha256;
+use cargo::Config;
+use flate2::read::GzDecoder;
+use tar::Archive;
+use walkdir;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crates::CrateInfo;
+use errors::*;
+
+/// The files of a crate or something derived from it, relative to its
+/// top-level directory.
+pub type Contents = BTreeMap<PathBuf, Vec<u8>>;
+
+#[derive(Debug, PartialEq)]
+pub enum Difference {
+    Missing(PathBuf),
+    Extra(PathBuf),
+    Modified(PathBuf),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Difference::Missing(ref p) => write!(f, "missing: {}", p.display()),
+            Difference::Extra(ref p) => write!(f, "extra: {}", p.display()),
+            Difference::Modified(ref p) => write!(f, "modified: {}", p.display()),
+        }
+    }
+}
+
+pub fn sha256_hex(path: &Path) -> Result<String> {
+    let mut data = Vec::new();
+    fs::File::open(path)?.read_to_end(&mut data)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&data);
+    Ok(hasher.finish().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Read the files of a .crate or .orig.tar.gz, stripping the top-level
+/// directory.
+pub fn tarball_contents<R: Read>(reader: R) -> Result<Contents> {
+    let mut contents = BTreeMap::new();
+    let mut archive = Archive::new(GzDecoder::new(reader));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.components().skip(1).collect::<PathBuf>();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        contents.insert(path, data);
+    }
+    Ok(contents)
+}
+
+/// Read the files of an unpacked source package, skipping the packaging in
+/// debian/ and quilt's .pc/.
+pub fn dir_contents(dir: &Path) -> Result<Contents> {
+    let mut contents = BTreeMap::new();
+    let walker = walkdir::WalkDir::new(dir).into_iter().filter_entry(|e| {
+        e.depth() != 1 || (e.file_name() != "debian" && e.file_name() != ".pc")
+    });
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let mut data = Vec::new();
+        fs::File::open(entry.path())?.read_to_end(&mut data)?;
+        contents.insert(entry.path().strip_prefix(dir)?.to_path_buf(), data);
+    }
+    Ok(contents)
+}
+
+/// Compare the files of a crate against something claiming to be derived
+/// from it. Files excluded by the config may be missing, and Cargo.toml may be
+/// in the normalized form debcargo writes, with the original moved to
+/// Cargo.toml.orig.
+pub fn compare_contents(
+    crate_info: &CrateInfo,
+    expected: &Contents,
+    actual: &Contents,
+) -> Result<Vec<Difference>> {
+    let registry_toml = crate_info
+        .package()
+        .to_registry_toml(&Config::default()?)?
+        .into_bytes();
+    let cargo_toml = Path::new("Cargo.toml");
+    let cargo_toml_orig = Path::new("Cargo.toml.orig");
+
+    let mut differences = Vec::new();
+    for (path, data) in expected {
+        let excluded = crate_info
+            .filter_path(&Path::new("crate").join(path))
+            .unwrap_or(false);
+        match actual.get(path) {
+            None if excluded => (),
+            // debcargo's rewriting of Cargo.toml replaces the original .orig
+            None if path == cargo_toml_orig => (),
+            None => differences.push(Difference::Missing(path.clone())),
+            Some(a) if a == data => (),
+            Some(a) if path == cargo_toml && *a == registry_toml => (),
+            Some(a) if path == cargo_toml_orig && Some(a) == expected.get(cargo_toml) => (),
+            Some(_) => differences.push(Difference::Modified(path.clone())),
+        }
+    }
+    for (path, data) in actual {
+        if path == cargo_toml_orig && Some(data) == expected.get(cargo_toml) {
+            continue;
+        }
+        if !expected.contains_key(path) {
+            differences.push(Difference::Extra(path.clone()));
+        }
+    }
+    Ok(differences)
+}
@@ -0,0 +1,102 @@
+use cargo::core::Dependency;
+use cargo::Config;
+use semver::Version;
+use serde_json;
+
+use std::collections::BTreeMap;
+
+use crates::{crates_io_api, crates_io_registry, fetch_candidates, semver_suffix_of};
+use errors::*;
+
+/// A version of a crate, to help decide which one to package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedVersion {
+    pub version: Version,
+    pub yanked: bool,
+    /// When it was published on crates.io, if known.
+    pub published: Option<String>,
+}
+
+impl ListedVersion {
+    /// The semver suffix of its package, if it's packaged with one.
+    pub fn semver_suffix(&self) -> String {
+        semver_suffix_of(&self.version)
+    }
+}
+
+/// The versions of a crate from the JSON of its crates.io API, yanked ones
+/// too, with their publish dates.
+pub fn crates_io_versions(name: &str, json: &str) -> Result<Vec<ListedVersion>> {
+    let info: serde_json::Value = serde_json::from_str(json)?;
+    let versions = match info["versions"].as_array() {
+        Some(versions) => versions,
+        None => debcargo_bail!("Unexpected reply from the crates.io API for {}", name),
+    };
+    Ok(versions
+        .iter()
+        .filter_map(|v| {
+            let version = Version::parse(v["num"].as_str()?).ok()?;
+            Some(ListedVersion {
+                version: version,
+                yanked: v["yanked"].as_bool().unwrap_or(false),
+                published: v["created_at"].as_str().map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+/// The versions of a crate per crates.io.
+pub fn crates_io_listed_versions(name: &str) -> Result<Vec<ListedVersion>> {
+    let data = crates_io_api(&format!("crates/{}", name))?;
+    crates_io_versions(name, &String::from_utf8_lossy(&data))
+}
+
+/// The versions of a crate in the index, which leaves out yanked ones.
+pub fn index_versions(config: &Config, name: &str, update: bool) -> Result<Vec<Version>> {
+    let (mut registry, source_id) = crates_io_registry(config, update)?;
+    let dep = Dependency::parse_no_deprecated(name, None, source_id)?;
+    Ok(fetch_candidates(&mut registry, &dep)?
+        .iter()
+        .map(|s| s.version().clone())
+        .collect())
+}
+
+/// All versions of a crate, newest first: those in the index, which leaves
+/// out yanked ones, and those that crates.io lists, if we asked it, which
+/// also tells when they were published.
+pub fn list_versions(index: &[Version], crates_io: Option<&[ListedVersion]>) -> Vec<ListedVersion> {
+    let mut versions = BTreeMap::new();
+    for v in index {
+        versions.insert(v.clone(), ListedVersion {
+            version: v.clone(),
+            yanked: false,
+            published: None,
+        });
+    }
+    for v in crates_io.unwrap_or(&[]) {
+        versions.insert(v.version.clone(), v.clone());
+    }
+    versions.into_iter().rev().map(|(_, v)| v).collect()
+}
+
+/// A table of versions, one per line, with the semver suffix each would be
+/// packaged with and the day it was published.
+pub fn format_versions(versions: &[ListedVersion]) -> String {
+    let width = versions.iter().map(|v| v.version.to_string().len()).max().unwrap_or(0);
+    let suffix_width = versions.iter().map(|v| v.semver_suffix().len()).max().unwrap_or(0);
+    let mut s = String::new();
+    for v in versions {
+        let line = format!(
+            "{:<w$}  {:<sw$}  {:<10}{}",
+            v.version.to_string(),
+            v.semver_suffix(),
+            v.published.as_ref().map_or("", |p| p.get(..10).unwrap_or(p)),
+            if v.yanked { "  yanked" } else { "" },
+            w = width,
+            sw = suffix_width
+        );
+        s.push_str(line.trim_end());
+        s.push('\n');
+    }
+    s
+}
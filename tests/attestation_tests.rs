@@ -0,0 +1,72 @@
+extern crate debcargo;
+extern crate semver;
+extern crate serde_json;
+extern crate tempfile;
+
+use debcargo::attestation::{Attestation, BUILD_TYPE, PREDICATE_TYPE, STATEMENT_TYPE};
+use debcargo::crates::{GitOrigin, GitSelector};
+use debcargo::debian::source::SourceFile;
+use semver::Version;
+use serde_json::Value;
+
+use std::fs;
+
+#[test]
+fn provenance_statements() {
+    let dir = tempfile::tempdir().unwrap();
+    let orig = dir.path().join("rust-foo_1.0.0.orig.tar.gz");
+    fs::write(&orig, "orig").unwrap();
+    let config = dir.path().join("debcargo.toml");
+    fs::write(&config, "overlay = \".\"\n").unwrap();
+    let version = Version::parse("1.0.0").unwrap();
+    let overrides = vec!["source.section=rust".to_string()];
+    let attestation = Attestation {
+        crate_name: "foo",
+        version: &version,
+        crate_sha256: Some("abc123"),
+        download_url: Some("https://crates.io/api/v1/crates/foo/1.0.0/download"),
+        git_origin: None,
+        configs: vec![SourceFile::new(&config).unwrap()],
+        overrides: &overrides,
+        outputs: vec![SourceFile::new(&orig).unwrap()],
+    };
+    let statement: Value = serde_json::from_str(&attestation.to_statement("2019-06-01T12:00:00Z")).unwrap();
+    assert_eq!(statement["_type"], STATEMENT_TYPE);
+    assert_eq!(statement["predicateType"], PREDICATE_TYPE);
+    assert_eq!(statement["subject"][0]["name"], "rust-foo_1.0.0.orig.tar.gz");
+    assert_eq!(statement["subject"][0]["digest"]["sha256"],
+               "14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f");
+    let definition = &statement["predicate"]["buildDefinition"];
+    assert_eq!(definition["buildType"], BUILD_TYPE);
+    assert_eq!(definition["externalParameters"]["crate"], "foo");
+    assert_eq!(definition["externalParameters"]["version"], "1.0.0");
+    assert_eq!(definition["externalParameters"]["configs"][0]["name"], "debcargo.toml");
+    assert_eq!(definition["externalParameters"]["overrides"][0], "source.section=rust");
+    assert_eq!(definition["resolvedDependencies"][0]["name"], "foo-1.0.0.crate");
+    assert_eq!(definition["resolvedDependencies"][0]["uri"], "https://crates.io/api/v1/crates/foo/1.0.0/download");
+    assert_eq!(definition["resolvedDependencies"][0]["digest"]["sha256"], "abc123");
+    let details = &statement["predicate"]["runDetails"];
+    assert_eq!(details["builder"]["version"]["debcargo"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(details["metadata"]["startedOn"], "2019-06-01T12:00:00Z");
+
+    let origin = GitOrigin {
+        url: "https://github.com/foo/foo".to_string(),
+        selector: GitSelector::Tag("v1.0.0".to_string()),
+        commit: "0123456789abcdef".to_string(),
+    };
+    let attestation = Attestation {
+        crate_sha256: None,
+        download_url: None,
+        git_origin: Some(&origin),
+        configs: Vec::new(),
+        overrides: &[],
+        outputs: vec![SourceFile::new(&orig).unwrap()],
+        ..attestation
+    };
+    let statement: Value = serde_json::from_str(&attestation.to_statement("2019-06-01T12:00:00Z")).unwrap();
+    let dependencies = statement["predicate"]["buildDefinition"]["resolvedDependencies"].as_array().unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(dependencies[0]["uri"], "git+https://github.com/foo/foo@0123456789abcdef");
+    assert_eq!(dependencies[0]["digest"]["gitCommit"], "0123456789abcdef");
+    assert!(statement["predicate"]["buildDefinition"]["externalParameters"].get("overrides").is_none());
+}
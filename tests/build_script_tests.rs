@@ -0,0 +1,77 @@
+extern crate debcargo;
+
+use debcargo::build_script::{native_library_package, BuildScriptReport};
+
+use std::path::PathBuf;
+
+const BUILD_RS: &str = r#"
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=FOO_STATIC");
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    if env::var_os("FOO_NO_PKG_CONFIG").is_none()
+        && pkg_config::Config::new().atleast_version("1.2").probe("foo").is_ok()
+    {
+        return;
+    }
+    cc::Build::new().file("foo/foo.c").compile("foo");
+    bindgen::Builder::default()
+        .header("foo/foo.h")
+        .generate()
+        .unwrap()
+        .write_to_file(out.join("bindings.rs"))
+        .unwrap();
+    fs::write(out.join("version.rs"), env::var("CARGO_PKG_VERSION").unwrap()).unwrap();
+}
+"#;
+
+#[test]
+fn analysis() {
+    let report = BuildScriptReport::analyze(PathBuf::from("build.rs"), BUILD_RS, Some("foo"));
+    assert_eq!(report, BuildScriptReport {
+        path: PathBuf::from("build.rs"),
+        links: Some("foo".to_string()),
+        uses_cc: true,
+        uses_cmake: false,
+        pkg_config: vec!["foo".to_string()],
+        uses_bindgen: true,
+        env_vars: vec!["FOO_NO_PKG_CONFIG".to_string(), "FOO_STATIC".to_string()],
+        network: false,
+        codegen: true,
+    });
+    assert_eq!(report.build_depends(), vec!["libclang-dev", "libfoo-dev", "pkg-config"]);
+    assert_eq!(report.warnings(), vec![
+        "build.rs compiles native code; if it is bundled, build against the Debian package of the library \
+         instead, and exclude the copy",
+        "build.rs generates code at build time; check that it needs nothing outside the crate",
+        "build.rs reads the environment variables FOO_NO_PKG_CONFIG, FOO_STATIC, which may change the build",
+    ]);
+}
+
+#[test]
+fn network_and_links() {
+    let source = r#"fn main() {
+        let body = reqwest::blocking::get("https://example.org/foo.tar.gz").unwrap();
+    }"#;
+    let report = BuildScriptReport::analyze(PathBuf::from("build/main.rs"), source, Some("ssl"));
+    assert!(report.network);
+    assert!(!report.codegen);
+    assert_eq!(report.build_depends(), vec!["libssl-dev"]);
+    assert_eq!(report.warnings(), vec![
+        "build/main.rs seems to access the network, which Debian builds can't; patch it out",
+    ]);
+
+    let quiet = BuildScriptReport::analyze(PathBuf::from("build.rs"), "fn main() {}", None);
+    assert!(quiet.build_depends().is_empty());
+    assert!(quiet.warnings().is_empty());
+}
+
+#[test]
+fn native_libraries() {
+    assert_eq!(native_library_package("z"), "zlib1g-dev");
+    assert_eq!(native_library_package("libfoo"), "libfoo-dev");
+    assert_eq!(native_library_package("foo"), "libfoo-dev");
+}
@@ -0,0 +1,41 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::build::{cargo_config, verify_build};
+
+use std::fs;
+use std::path::Path;
+
+fn write_crate(dir: &Path, lib_rs: &str) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::create_dir_all(dir.join("debian")).unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"buildcheck\"\nversion = \"0.1.0\"\nauthors = []\n",
+    ).unwrap();
+    fs::write(dir.join("src/lib.rs"), lib_rs).unwrap();
+}
+
+#[test]
+fn config_replaces_crates_io() {
+    let config = cargo_config(Path::new("/usr/share/cargo/registry"));
+    assert!(config.contains("[source.crates-io]\nreplace-with = \"local-crates\""));
+    assert!(config.contains("directory = \"/usr/share/cargo/registry\""));
+}
+
+#[test]
+fn verify_build_leaves_source_alone() {
+    let registry = tempfile::tempdir().unwrap();
+    let good = tempfile::tempdir().unwrap();
+    write_crate(good.path(), "pub fn f() -> u32 { 1 }\n");
+    verify_build(good.path(), registry.path()).unwrap();
+    assert!(!good.path().join("target").exists());
+    assert!(!good.path().join("Cargo.lock").exists());
+
+    let bad = tempfile::tempdir().unwrap();
+    write_crate(bad.path(), "pub fn f() -> u32 { \"not a number\" }\n");
+    let err = verify_build(bad.path(), registry.path()).unwrap_err();
+    assert!(err.to_string().contains("cargo build failed"));
+
+    assert!(verify_build(good.path(), &registry.path().join("missing")).is_err());
+}
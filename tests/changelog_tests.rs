@@ -0,0 +1,115 @@
+extern crate debcargo;
+extern crate semver;
+
+use std::collections::HashMap;
+
+use debcargo::crates::semver_suffix_of;
+use debcargo::debian::changelog::{bump_revision, initial_revision, previous_upstream_version, Backport,
+                                   ChangelogEntry};
+use debcargo::debian::control::Source;
+use semver::Version;
+
+#[test]
+fn backport_suites() {
+    let b = Backport::parse("bookworm-backports").unwrap();
+    assert_eq!(b, Backport::parse("bookworm").unwrap());
+    assert_eq!(b.release, 12);
+    assert_eq!(b.distribution(), "bookworm-backports");
+    assert!(Backport::parse("sid").is_err());
+}
+
+#[test]
+fn backport_revisions() {
+    let b = Backport::parse("bookworm").unwrap();
+    assert_eq!(b.revision("1"), "1~bpo12+1");
+    assert_eq!(b.revision("2"), "2~bpo12+1");
+    assert_eq!(b.revision("2~bpo12+1"), "2~bpo12+2");
+    // backports for an older release are based on the same revision
+    assert_eq!(b.revision("2~bpo11+3"), "2~bpo12+1");
+}
+
+#[test]
+fn backport_build_depends_versions() {
+    let mut source = Source::new(
+        "foo",
+        None,
+        "foo",
+        "",
+        true,
+        false,
+        vec![],
+        vec![
+            "debhelper (>= 12)".to_string(),
+            "dh-cargo (>= 31)".to_string(),
+            "librust-bar-1+default-dev (>= 1.2-~~) <!nocheck>".to_string(),
+        ],
+    ).unwrap();
+    let mut versions = HashMap::new();
+    versions.insert("dh-cargo".to_string(), ">= 28".to_string());
+    versions.insert("debhelper".to_string(), "".to_string());
+    versions.insert("librust-bar-1+default-dev".to_string(), ">= 1.1-~~".to_string());
+    source.set_build_depends_versions(&versions);
+    let control = format!("{}", source);
+    assert!(control.contains(
+        "Build-Depends: debhelper,\n dh-cargo (>= 28),\n librust-bar-1+default-dev (>= 1.1-~~) <!nocheck>\n"
+    ));
+}
+
+#[test]
+fn revisions_with_vendor() {
+    assert_eq!(initial_revision(None, None), "1");
+    assert_eq!(initial_revision(Some("1~exp1"), None), "1~exp1");
+    assert_eq!(initial_revision(None, Some("ubuntu")), "0ubuntu1");
+
+    assert_eq!(bump_revision("1", None), "2");
+    assert_eq!(bump_revision("1~exp1", None), "1~exp2");
+    assert_eq!(bump_revision("1", Some("ubuntu")), "1ubuntu1");
+    assert_eq!(bump_revision("0ubuntu1", Some("ubuntu")), "0ubuntu2");
+    assert_eq!(bump_revision("2ubuntu1", Some("ubuntu")), "2ubuntu2");
+}
+
+#[test]
+fn previous_upstream_versions() {
+    let changelog = "\
+rust-foo (1.0.0-1) UNRELEASED-FIXME-AUTOGENERATED-DEBCARGO; urgency=medium
+
+  * Package foo 1.0.0 from crates.io using debcargo 2.2.10
+
+ -- Jane Doe <jane@example.org>  Sat, 17 Oct 2026 12:00:00 +0000
+
+rust-foo (1:0.9.3~beta1-2) unstable; urgency=medium
+
+  * Package foo 0.9.3-beta1 from crates.io using debcargo 2.2.9
+
+ -- Jane Doe <jane@example.org>  Fri, 16 Oct 2026 12:00:00 +0000
+";
+    let old = previous_upstream_version(changelog, "1.0.0").unwrap();
+    assert_eq!(old, Version::parse("0.9.3-beta1").unwrap());
+    assert_eq!(semver_suffix_of(&old), "-0.9");
+    assert_eq!(semver_suffix_of(&Version::parse("1.0.0").unwrap()), "-1");
+    assert!(previous_upstream_version(changelog, "0.9.3~beta1").is_none());
+}
+
+#[test]
+fn malformed_entries_are_errors() {
+    let entries = [
+        "",
+        "\n\n",
+        "rust-foo (1.0-1) unstable; urgency=medium\n",
+        "rust-foo (1.0-1) unstable; urgency=medium\n\n  * Foo.\n\n",
+        "rust-foo 1.0-1 unstable; urgency=medium\n\n  * Foo.\n\n -- A <a@example.org>  Mon, 01 Jan 2018 00:00:00 +0000\n",
+        "rust-foo (1.0) unstable; urgency=medium\n\n  * Foo.\n\n -- A <a@example.org>  Mon, 01 Jan 2018 00:00:00 +0000\n",
+        "rust-foo (1.0-1) unstable; urgency=medium\n\n  * Foo.\n\n -- A <a@example.org>  yesterday\n",
+        "rust-foo (1.0-1) unstable; urgency=medium\n\n -- A  Mon, 01 Jan 2018 00:00:00 +0000\n",
+    ];
+    for e in entries.iter() {
+        assert!(e.parse::<ChangelogEntry>().is_err(), "{:?}", e);
+    }
+
+    let e = "rust-foo (1.0-1) unstable; urgency=medium\n\n  * Foo.\n\n -- A <a@example.org>  Mon, 01 Jan 2018 00:00:00 +0000\n"
+        .parse::<ChangelogEntry>()
+        .unwrap();
+    assert_eq!(e.maintainer_name(), "A");
+    assert_eq!(e.items, vec!["  * Foo."]);
+    assert_eq!(bump_revision("99999999999999999999", None), "99999999999999999999.1");
+}
@@ -0,0 +1,47 @@
+extern crate debcargo;
+extern crate semver;
+extern crate tempfile;
+
+use debcargo::clean::{generated_outputs, leftover_tempdirs, output_tarballs,
+                      parse_output_dir_name, stale_outputs};
+use semver::Version;
+
+use std::fs;
+
+#[test]
+fn output_dir_names() {
+    let v = |s: &str| Version::parse(s).unwrap();
+    assert_eq!(parse_output_dir_name("rust-clap-2.33.0"), Some(("rust-clap".to_string(), v("2.33.0"))));
+    assert_eq!(parse_output_dir_name("rust-rand-0.7-0.7.3"), Some(("rust-rand-0.7".to_string(), v("0.7.3"))));
+    assert_eq!(parse_output_dir_name("rust-foo-1.0.0~beta1"), Some(("rust-foo".to_string(), v("1.0.0-beta1"))));
+    assert_eq!(parse_output_dir_name("debcargo-conf"), None);
+    assert_eq!(parse_output_dir_name("rust-foo"), None);
+}
+
+#[test]
+fn stale_outputs_and_tempdirs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    for name in &["rust-foo-1.0.0", "rust-foo-1.1.0", "rust-bar-0.1.0"] {
+        fs::create_dir_all(dir.join(name).join("debian")).unwrap();
+        fs::write(dir.join(name).join("debian/cargo-checksum.json"), "{}").unwrap();
+    }
+    fs::create_dir_all(dir.join("rust-baz-1.0.0/debian")).unwrap();
+    fs::write(dir.join("rust-foo_1.0.0.orig.tar.gz"), "").unwrap();
+    fs::write(dir.join("rust-foo_1.0.0.orig-vendor.tar.gz"), "").unwrap();
+    fs::write(dir.join("rust-foo_1.1.0.orig.tar.gz"), "").unwrap();
+    fs::create_dir(dir.join("debcargoAb12Cd")).unwrap();
+    fs::create_dir(dir.join("debcargo-conf")).unwrap();
+
+    assert_eq!(leftover_tempdirs(dir).unwrap(), vec![dir.join("debcargoAb12Cd")]);
+
+    let outputs = generated_outputs(dir).unwrap();
+    assert_eq!(outputs.len(), 3);
+    let stale = stale_outputs(&outputs);
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].dir, dir.join("rust-foo-1.0.0"));
+    assert_eq!(
+        output_tarballs(stale[0]).unwrap(),
+        vec![dir.join("rust-foo_1.0.0.orig-vendor.tar.gz"), dir.join("rust-foo_1.0.0.orig.tar.gz")]
+    );
+}
@@ -0,0 +1,136 @@
+extern crate cargo;
+extern crate debcargo;
+extern crate semver;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use cargo::core::dependency::Kind;
+use cargo::core::{Dependency, PackageId, SourceId, Summary};
+use cargo::Config;
+use debcargo::compare::{changelog_excerpt, diff_contents, diff_dependencies, diff_features, CrateDiff};
+use semver::Version;
+
+fn source_id() -> SourceId {
+    SourceId::crates_io(&Config::default().unwrap()).unwrap()
+}
+
+fn dep(name: &str, req: &str, kind: Kind) -> Dependency {
+    let mut d = Dependency::parse_no_deprecated(name, Some(req), source_id()).unwrap();
+    d.set_kind(kind);
+    d
+}
+
+fn summary(deps: Vec<Dependency>, features: &[(&str, &[&str])]) -> Summary {
+    let features = features
+        .iter()
+        .map(|&(f, ff)| (f.to_string(), ff.to_vec()))
+        .collect::<BTreeMap<_, _>>();
+    let pkgid = PackageId::new("foo", "1.0.0", source_id()).unwrap();
+    Summary::new(pkgid, deps, &features, None::<&str>, false).unwrap()
+}
+
+#[test]
+fn contents() {
+    let files = |fs: &[(&str, &str)]| {
+        fs.iter()
+            .map(|&(p, d)| (PathBuf::from(p), d.as_bytes().to_vec()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let old = files(&[("src/lib.rs", "a"), ("build.rs", "b"), ("README.md", "c")]);
+    let new = files(&[("src/lib.rs", "a2"), ("README.md", "c"), ("LICENSE-MIT", "d")]);
+    let (added, removed, modified) = diff_contents(&old, &new);
+    assert_eq!(added, vec![PathBuf::from("LICENSE-MIT")]);
+    assert_eq!(removed, vec![PathBuf::from("build.rs")]);
+    assert_eq!(modified, vec![PathBuf::from("src/lib.rs")]);
+}
+
+#[test]
+fn dependencies() {
+    let old = vec![
+        dep("bar", "1", Kind::Normal),
+        dep("baz", "0.3", Kind::Normal),
+        dep("cc", "1", Kind::Build),
+    ];
+    let new = vec![
+        dep("bar", "2", Kind::Normal),
+        dep("cc", "1", Kind::Build),
+        dep("quickcheck", "0.9", Kind::Development),
+    ];
+    assert_eq!(
+        diff_dependencies(&old, &new),
+        vec![
+            "changed bar ^1 -> ^2",
+            "removed baz ^0.3",
+            "added quickcheck (dev) ^0.9",
+        ]
+    );
+}
+
+#[test]
+fn features() {
+    let old = summary(vec![], &[("default", &["std"]), ("std", &[]), ("nightly", &[])]);
+    let new = summary(vec![], &[("default", &["std", "alloc"]), ("std", &[]), ("alloc", &[])]);
+    assert_eq!(
+        diff_features(&old, &new),
+        vec![
+            "changed default = [std] -> [std, alloc]",
+            "removed nightly = []",
+            "added alloc = []",
+        ]
+    );
+}
+
+#[test]
+fn changelog_excerpts() {
+    let changelog = "\
+# Changelog
+
+## [Unreleased]
+- Not released yet
+
+## [1.3.0] - 2019-06-01
+### Added
+- A new `frob` method, which takes
+  care of frobbing.
+  - with a nested detail
+### Fixed
+* Overflow in `parse`
+
+## 1.2.1
+- Documentation fixes
+
+1.2.0 (2019-01-01)
+------------------
+- Old change
+";
+    let v = |s: &str| Version::parse(s).unwrap();
+    assert_eq!(changelog_excerpt(changelog, &v("1.2.0"), &v("1.3.0")), vec![
+        "A new `frob` method, which takes care of frobbing.",
+        "Overflow in `parse`",
+        "Documentation fixes",
+    ]);
+    assert_eq!(changelog_excerpt(changelog, &v("1.1.0"), &v("1.2.0")), vec!["Old change"]);
+    assert!(changelog_excerpt(changelog, &v("1.3.0"), &v("1.3.0")).is_empty());
+}
+
+#[test]
+fn changelog_entries() {
+    let mut diff = CrateDiff::default();
+    diff.dependencies = vec!["changed serde 1.0 -> 1.1".to_string()];
+    diff.added = vec![PathBuf::from("src/new.rs")];
+    diff.modified = vec![PathBuf::from("src/lib.rs"), PathBuf::from("Cargo.toml")];
+    assert_eq!(diff.changelog_entry(&Version::parse("1.2.0").unwrap()), "\
+Upstream changes since 1.2.0:
+    - Dependencies: changed serde 1.0 -> 1.1
+    - 1 files added, 0 removed, 2 modified");
+
+    diff.changelog = (1..13).map(|i| format!("Change number {} of this release, with a \
+                                              description long enough to be wrapped", i)).collect();
+    let entry = diff.changelog_entry(&Version::parse("1.2.0").unwrap());
+    let lines = entry.lines().collect::<Vec<_>>();
+    assert_eq!(lines[1], "    - Change number 1 of this release, with a description long enough to be");
+    assert_eq!(lines[2], "      wrapped");
+    assert_eq!(lines.last(), Some(&"    - and 3 more"));
+    assert_eq!(lines.len(), 1 + 9 * 2 + 1);
+}
@@ -1,7 +1,14 @@
 extern crate debcargo;
+extern crate tempfile;
+extern crate toml;
 
+use std::env;
+use std::fs;
 use std::path::Path;
-use debcargo::config::{parse_config, PackageKey};
+use debcargo::config::{config_crates, load_config, load_config_with_upstream, overrides_table, parse_config,
+                       parse_config_for_crate, read_uploaders_file, upstream_config_table, Config, PackageKey};
+use debcargo::debian::copyright::CopyrightGuess;
+use debcargo::errors::ErrorKind;
 
 #[test]
 fn source_package_override() {
@@ -83,3 +90,326 @@ Debian Rust team.
 "
     );
 }
+
+#[test]
+fn crate_sections() {
+    let filepath = Path::new("tests/workspace_override.toml");
+    assert_eq!(config_crates(filepath).unwrap(), vec!["project-cli", "project-core"]);
+
+    // the shared settings, without any crate's section
+    let shared = parse_config(filepath).unwrap();
+    assert_eq!(shared.homepage(), Some("https://example.org/project"));
+    assert_eq!(shared.package_test_is_broken(PackageKey::BareLib), Some(true));
+    assert!(shared.package_section(PackageKey::Bin).is_none());
+
+    let core = parse_config_for_crate(filepath, "project-core").unwrap();
+    assert_eq!(core.homepage(), Some("https://example.org/project/core"));
+    assert_eq!(core.section(), Some("rust"));
+    assert_eq!(core.summary, "Core of the project");
+    assert_eq!(core.package_test_is_broken(PackageKey::BareLib), Some(true));
+
+    let cli = parse_config_for_crate(filepath, "project-cli").unwrap();
+    assert_eq!(cli.homepage(), Some("https://example.org/project"));
+    assert_eq!(cli.bin_name, "project");
+    assert_eq!(cli.package_section(PackageKey::Bin), Some("utils"));
+    assert!(cli.uploaders.is_some());
+
+    // crates without a section get the shared settings
+    let other = parse_config_for_crate(filepath, "project-extra").unwrap();
+    assert_eq!(other.homepage(), Some("https://example.org/project"));
+    assert_eq!(other.bin_name, "<default>");
+}
+
+#[test]
+fn misspelled_package_sections() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("debcargo.toml");
+    fs::write(&path, "[packages.\"lib-std\"]\ntest_is_broken = true\n").unwrap();
+    let err = parse_config(&path).unwrap_err().to_string();
+    assert!(err.contains("[packages.\"lib-std\"] has test_is_broken"), "{}", err);
+    assert!(parse_config_for_crate(&path, "foo").is_err());
+    assert!(config_crates(&path).is_err());
+
+    // a crate section may have the keys that the top level has
+    fs::write(&path, "[packages.\"libfoo\"]\nsummary = \"Foo\"\n").unwrap();
+    assert_eq!(config_crates(&path).unwrap(), vec!["libfoo"]);
+}
+
+#[test]
+fn repack_suffix_follows_excludes() {
+    let config = |content: &str| toml::from_str::<Config>(content).unwrap();
+    assert_eq!(config("").repack_suffix(), None);
+    assert_eq!(config("excludes = []").repack_suffix(), None);
+    assert_eq!(config("excludes = [\"data/*\"]").repack_suffix(), Some("+ds"));
+    assert_eq!(config("excludes = [\"data/*\"]\nrepack_suffix = \"+dfsg\"").repack_suffix(), Some("+dfsg"));
+    assert_eq!(config("excludes = [\"data/*\"]\nrepack_suffix = \"\"").repack_suffix(), None);
+    assert_eq!(config("repack_suffix = \"+dfsg\"").repack_suffix(), None);
+}
+
+#[test]
+fn overrides() {
+    let overrides = |o: &[&str]| o.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let filepath = Path::new("tests/workspace_override.toml");
+
+    let config = load_config(
+        &[filepath],
+        "project-core",
+        &overrides(&[
+            "source.homepage=https://example.org/other",
+            "semver_suffix = true",
+            "packages.\"lib+std\".test_is_broken=true",
+            "excludes=[\"data/*\"]",
+        ]),
+    ).unwrap();
+    assert_eq!(config.homepage(), Some("https://example.org/other"));
+    // the rest of the crate's section and the shared settings still apply
+    assert_eq!(config.section(), Some("rust"));
+    assert_eq!(config.summary, "Core of the project");
+    assert!(config.semver_suffix);
+    assert_eq!(config.package_test_is_broken(PackageKey::feature("std")), Some(true));
+    assert_eq!(config.package_test_is_broken(PackageKey::BareLib), Some(true));
+    assert_eq!(config.orig_tar_excludes(), Some(&vec!["data/*".to_string()]));
+
+    // without a config file, and later overrides win
+    let config = load_config(&[], "foo", &overrides(&["bin_name=foo", "bin_name=bar"])).unwrap();
+    assert_eq!(config.bin_name, "bar");
+
+    assert!(overrides_table(&overrides(&["semver_suffix"])).is_err());
+    assert!(overrides_table(&overrides(&["source..section=utils"])).is_err());
+    assert!(overrides_table(&overrides(&["packages.\"lib+std=true"])).is_err());
+    // values of the wrong type are caught when the config is parsed
+    let e = load_config(&[], "foo", &overrides(&["semver_suffix=maybe"])).unwrap_err();
+    assert_eq!(ErrorKind::of(&e), Some(ErrorKind::Config));
+}
+
+#[test]
+fn overrides_take_the_type_of_the_key() {
+    let overrides = |o: &[&str]| o.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let config = load_config(
+        &[],
+        "foo",
+        &overrides(&[
+            "source.rustc_version=1.41",
+            "source.dh_cargo_version=24",
+            "initial_revision=1",
+            "epoch=2",
+        ]),
+    ).unwrap();
+    assert_eq!(config.rustc_version(), Some("1.41"));
+    assert_eq!(config.dh_cargo_version(), Some("24"));
+    assert_eq!(config.initial_revision, Some("1".to_string()));
+    assert_eq!(config.epoch, Some(2));
+
+    // crate sections take the keys of the top level
+    let table = overrides_table(&overrides(&["packages.\"foo\".summary=1.0"])).unwrap();
+    assert_eq!(
+        table["packages"]["foo"]["summary"],
+        toml::Value::String("1.0".to_string())
+    );
+}
+
+#[test]
+fn uploaders_from_roster() {
+    let dir = tempfile::tempdir().unwrap();
+    let lines = dir.path().join("uploaders");
+    fs::write(&lines, "# the team\nA <a@example.org>\n\nB <b@example.org>  # on leave\n").unwrap();
+    assert_eq!(read_uploaders_file(&lines).unwrap(), vec!["A <a@example.org>", "B <b@example.org>"]);
+    let toml = dir.path().join("team.toml");
+    fs::write(&toml, "uploaders = [\"C <c@example.org>\"]\n").unwrap();
+    assert_eq!(read_uploaders_file(&toml).unwrap(), vec!["C <c@example.org>"]);
+
+    // relative to the config, and after the uploaders given directly
+    let config_path = dir.path().join("debcargo.toml");
+    fs::write(&config_path, "uploaders = [\"B <b@example.org>\"]\nuploaders_file = \"uploaders\"\n").unwrap();
+    let config = load_config(&[&config_path], "foo", &[]).unwrap();
+    assert_eq!(config.uploaders().unwrap(), &vec!["B <b@example.org>", "A <a@example.org>"]);
+
+    fs::write(&config_path, "uploaders_file = \"missing\"\n").unwrap();
+    assert!(load_config(&[&config_path], "foo", &[]).is_err());
+}
+
+#[test]
+fn templates_in_strings() {
+    let config: Config = toml::from_str(r#"
+summary = "Fast $crate"
+description = "Version $upstream_version of $crate."
+
+[source]
+homepage = "https://example.org/$crate/$version"
+vcs_git = "https://example.org/rust-$crate$semver_suffix.git"
+section = "$crate"
+
+[packages.lib]
+extra_lines = ["X-Version: $version"]
+"#).unwrap();
+    let config = config.with_templates_expanded(&[
+        ("$crate", "foo"),
+        ("$version", "1.0.0~rc1"),
+        ("$upstream_version", "1.0.0-rc1"),
+        ("$semver_suffix", "-1"),
+    ]);
+    assert_eq!(config.summary, "Fast foo");
+    assert_eq!(config.description, "Version 1.0.0-rc1 of foo.");
+    assert_eq!(config.homepage(), Some("https://example.org/foo/1.0.0~rc1"));
+    assert_eq!(config.vcs_git(), Some("https://example.org/rust-foo-1.git"));
+    assert_eq!(config.package_extra_lines(PackageKey::BareLib).unwrap(), &vec!["X-Version: 1.0.0~rc1"]);
+    // only free text and URLs are templates
+    assert_eq!(config.section(), Some("$crate"));
+}
+
+#[test]
+fn layered_configs() {
+    let dir = tempfile::tempdir().unwrap();
+    let team = dir.path().join("team.toml");
+    fs::write(&team, "\
+uploaders_file = \"roster\"
+semver_suffix = true
+
+[source]
+section = \"rust\"
+homepage = \"https://example.org/team\"
+").unwrap();
+    fs::write(dir.path().join("roster"), "A <a@example.org>\n").unwrap();
+    fs::create_dir_all(dir.path().join("foo/debian")).unwrap();
+    let own = dir.path().join("foo/debian/debcargo.toml");
+    fs::write(&own, "\
+overlay = \".\"
+semver_suffix = false
+
+[source]
+homepage = \"https://example.org/foo\"
+").unwrap();
+
+    let config = load_config(&[&team, &own], "foo", &[]).unwrap();
+    assert!(!config.semver_suffix);
+    assert_eq!(config.section(), Some("rust"));
+    assert_eq!(config.homepage(), Some("https://example.org/foo"));
+    // paths are relative to the file that sets them
+    assert_eq!(config.uploaders().unwrap(), &vec!["A <a@example.org>"]);
+    assert_eq!(config.overlay_dir(), Some(dir.path().join("foo/debian/.")));
+
+    let config = load_config(&[&own, &team], "foo", &[]).unwrap();
+    assert!(config.semver_suffix);
+    assert_eq!(config.homepage(), Some("https://example.org/team"));
+}
+
+#[test]
+fn relative_config_paths() {
+    // as with a relative --config
+    let tmp = tempfile::Builder::new().prefix("debcargo").tempdir_in(".").unwrap();
+    let dir = tmp.path().strip_prefix(env::current_dir().unwrap()).unwrap();
+    fs::create_dir_all(dir.join("foo/overlay")).unwrap();
+    fs::write(dir.join("foo/roster"), "A <a@example.org>\n").unwrap();
+    let own = dir.join("foo/debcargo.toml");
+    fs::write(&own, "overlay = \"overlay\"\nuploaders_file = \"roster\"\n").unwrap();
+
+    let config = load_config(&[&own], "foo", &[]).unwrap();
+    assert_eq!(config.overlay_dir(), Some(dir.join("foo/overlay")));
+    assert!(config.overlay_dir().unwrap().is_dir());
+    assert_eq!(config.uploaders().unwrap(), &vec!["A <a@example.org>"]);
+
+    let mut config = parse_config(&own).unwrap();
+    config.expand_uploaders_file().unwrap();
+    assert_eq!(config.overlay_dir(), Some(dir.join("foo/overlay")));
+    assert_eq!(config.uploaders().unwrap(), &vec!["A <a@example.org>"]);
+}
+
+#[test]
+fn copyright_guess_modes() {
+    let config = |content: &str| toml::from_str::<Config>(content);
+    assert_eq!(config("").unwrap().copyright_guess, None);
+    assert_eq!(config("copyright_guess = \"off\"").unwrap().copyright_guess, Some(CopyrightGuess::Off));
+    assert_eq!(config("copyright_guess = \"harder\"").unwrap().copyright_guess, Some(CopyrightGuess::Harder));
+    assert!(config("copyright_guess = \"maybe\"").is_err());
+    assert_eq!(CopyrightGuess::parse("normal").unwrap(), CopyrightGuess::Normal);
+    assert!(CopyrightGuess::parse("Normal").is_err());
+}
+
+#[test]
+fn malformed_configs_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let configs = [
+        "[",
+        "source = 1",
+        "packages = []",
+        "[packages.lib]\ndepends = 3",
+        "[packages.\"lib+\"]\ntest_is_broken = \"yes\"",
+        "excludes = 5",
+        "excludes = [\"[\"]",
+        "overlay = []",
+        "\u{0}",
+        "[source]\n[source]",
+    ];
+    for (i, contents) in configs.iter().enumerate() {
+        let path = dir.path().join(format!("{}.toml", i));
+        fs::write(&path, contents).unwrap();
+        // only the missing bracket of the glob is caught later, when it is used
+        if let Err(e) = load_config(&[&path], "foo", &[]) {
+            assert_eq!(ErrorKind::of(&e), Some(ErrorKind::Config), "{}", contents);
+        }
+    }
+
+    let overrides = [
+        "", "=", "=x", "\"\"=1", ".", "a.", ".a", "\"", "\"a\".", "source.section",
+        "source=", "source=[", "packages.lib=1", "overlay=", "excludes=[1]",
+    ];
+    for o in overrides.iter() {
+        if let Err(e) = load_config(&[], "foo", &[o.to_string()]) {
+            assert_eq!(ErrorKind::of(&e), Some(ErrorKind::Config), "{}", o);
+        }
+    }
+    let config = load_config(&[], "foo", &["overlay=over".to_string()]).unwrap();
+    assert_eq!(config.overlay_dir(), Some(Path::new("over").to_path_buf()));
+}
+
+#[test]
+fn config_digests() {
+    let overrides = |o: &[&str]| o.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("debcargo.toml");
+    fs::write(&config_path, "overlay = \".\"\nsemver_suffix = true\n").unwrap();
+    let digest = load_config(&[&config_path], "foo", &[]).unwrap().digest.unwrap();
+    assert_eq!(digest.len(), 64);
+    // not the paths the config is read from
+    fs::write(dir.path().join("other.toml"), "semver_suffix = true\noverlay = \".\"\n").unwrap();
+    let other = load_config(&[&dir.path().join("other.toml")], "foo", &[]).unwrap();
+    assert_eq!(other.digest, Some(digest.clone()));
+    let overridden = load_config(&[&config_path], "foo", &overrides(&["semver_suffix=false"])).unwrap();
+    assert_ne!(overridden.digest, Some(digest));
+}
+
+#[test]
+fn upstream_hints() {
+    let manifest = r#"
+[package]
+name = "project-core"
+version = "1.0.0"
+
+[package.metadata.debian]
+summary = "Upstream summary"
+description = "Upstream description."
+build_depends = ["libfoo-dev"]
+max_feature_packages = "many"
+maintainer = "Jane Doe <jane@example.org>"
+
+[package.metadata.other]
+ignored = true
+"#;
+    let upstream = upstream_config_table(manifest).unwrap();
+    // the rest is warned about and ignored
+    assert_eq!(upstream.keys().cloned().collect::<Vec<_>>(), vec!["description", "source", "summary"]);
+    assert!(upstream_config_table("[package]\nname = \"foo\"\n").unwrap().is_empty());
+
+    let config = load_config_with_upstream(&[], "project-core", &[], upstream.clone()).unwrap();
+    assert_eq!(config.summary, "Upstream summary");
+    assert_eq!(config.description, "Upstream description.");
+    assert_eq!(config.build_depends(), Some(&vec!["libfoo-dev".to_string()]));
+
+    // debcargo.toml wins, key by key
+    let filepath = Path::new("tests/workspace_override.toml");
+    let config = load_config_with_upstream(&[filepath], "project-core", &[], upstream).unwrap();
+    assert_eq!(config.summary, "Core of the project");
+    assert_eq!(config.description, "Upstream description.");
+    assert_eq!(config.section(), Some("rust"));
+    assert_eq!(config.build_depends(), Some(&vec!["libfoo-dev".to_string()]));
+}
@@ -0,0 +1,293 @@
+extern crate debcargo;
+extern crate semver;
+extern crate tempfile;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use debcargo::config::{parse_config, PackageKey};
+use debcargo::crates::{has_offline_tests, normalize_homepage};
+use debcargo::debian::control::{
+    check_deb_feature_collisions, deb_feature, deb_feature_name, with_epoch, Package, PackageNaming,
+    Source,
+};
+use debcargo::util::{display_width, normalize_text};
+use semver::Version;
+
+fn lib_package(summary: &str, description: &str) -> String {
+    let version = Version::parse("1.2.3").unwrap();
+    let package = Package::new(
+        "foo",
+        None,
+        &version,
+        None,
+        "foo",
+        Some(summary),
+        Some(description),
+        None,
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        &PackageNaming::default(),
+    ).unwrap();
+    format!("{}", package)
+}
+
+fn description_lines(control: &str) -> Vec<&str> {
+    control
+        .lines()
+        .skip_while(|l| !l.starts_with("Description:"))
+        .skip(1)
+        .collect()
+}
+
+#[test]
+fn cjk_description_wrapped_by_width() {
+    let description = "这是一个用于解析和生成各种格式数据的库。".repeat(8);
+    let control = lib_package("数据解析库", &description);
+
+    assert!(control.contains("Description: 数据解析库 - Rust source code\n"));
+    let lines = description_lines(&control);
+    assert!(lines.len() > 1);
+    for line in lines {
+        assert!(display_width(line) <= 80, "line too wide: {:?}", line);
+    }
+}
+
+#[test]
+fn combining_characters_normalized() {
+    // "e" followed by U+0301 COMBINING ACUTE ACCENT
+    let decomposed = "Cafe\u{301} de\u{301}ja\u{300} vu";
+    assert_eq!(normalize_text(decomposed), "Café déjà vu");
+    assert_eq!(display_width(decomposed), display_width("Café déjà vu"));
+
+    let control = lib_package(decomposed, decomposed);
+    assert!(control.contains("Description: Café déjà vu - Rust source code\n"));
+    assert!(control.contains("\n Café déjà vu\n"));
+    assert!(!control.contains('\u{301}'));
+}
+
+#[test]
+fn display_width_counts_columns() {
+    assert_eq!(display_width("abc"), 3);
+    assert_eq!(display_width("日本語"), 6);
+    assert_eq!(display_width("e\u{301}"), 1);
+}
+
+#[test]
+fn feature_names_mangled() {
+    assert_eq!(deb_feature("std"), "std");
+    assert_eq!(deb_feature("serde_derive"), "serde-derive");
+    assert_eq!(deb_feature("Unstable"), "unstable");
+    assert_eq!(deb_feature("c++20"), "c--20");
+    assert_eq!(deb_feature("v1.2"), "v1.2");
+    assert_eq!(deb_feature("x/y z"), "x-y-z");
+    assert_eq!(deb_feature_name("foo_bar", "SIMD_Accel"), "librust-foo-bar+simd-accel-dev");
+}
+
+#[test]
+fn feature_name_collisions() {
+    assert!(check_deb_feature_collisions(vec!["", "default", "std", "serde_derive"]).is_ok());
+    assert!(check_deb_feature_collisions(vec!["serde_derive", "serde-derive"]).is_err());
+    assert!(check_deb_feature_collisions(vec!["Nightly", "nightly"]).is_err());
+    assert!(check_deb_feature_collisions(vec!["a+b", "a_b"]).is_err());
+}
+
+fn source_stanza(config: Option<&str>) -> String {
+    let mut source = Source::new(
+        "foo",
+        None,
+        "foo",
+        "https://example.org",
+        true,
+        true,
+        vec![],
+        vec!["dh-cargo (>= 18)".to_string()],
+    ).unwrap();
+    if let Some(config) = config {
+        source.apply_overrides(&parse_config(Path::new(config)).unwrap());
+    }
+    format!("{}", source)
+}
+
+#[test]
+fn source_modern_fields() {
+    let control = source_stanza(None);
+    assert!(control.contains("\nRules-Requires-Root: no\n"));
+    assert!(control.contains("\nStandards-Version: 4.2.0\n"));
+    assert!(control.contains("\nTestsuite: autopkgtest-pkg-rust\n"));
+
+    let control = source_stanza(Some("tests/source_fields_override.toml"));
+    assert!(control.contains("\nRules-Requires-Root: binary-targets\n"));
+    assert!(control.contains("\nStandards-Version: 4.0.0\n"));
+    assert!(control.ends_with("\nX-Extra-Test: yes\n"));
+}
+
+#[test]
+fn source_vcs_templates() {
+    let control = source_stanza(None);
+    assert!(control.contains(
+        "\nVcs-Git: https://salsa.debian.org/rust-team/debcargo-conf.git [src/foo]\n"
+    ));
+    assert!(control.contains(
+        "\nVcs-Browser: https://salsa.debian.org/rust-team/debcargo-conf/tree/master/src/foo\n"
+    ));
+
+    let control = source_stanza(Some("tests/vcs_override.toml"));
+    assert!(control.contains("\nVcs-Git: https://git.example.org/rust-foo.git\n"));
+    assert!(control.contains("/tree/master/src/foo\n"));
+}
+
+#[test]
+fn homepage_normalized() {
+    let n = |u: &str| normalize_homepage(u);
+    let s = |u: &str| Some(u.to_string());
+    assert_eq!(n("https://github.com/serde-rs/serde"), s("https://github.com/serde-rs/serde"));
+    assert_eq!(n("https://github.com/serde-rs/serde.git"), s("https://github.com/serde-rs/serde"));
+    assert_eq!(n("http://github.com/serde-rs/serde/"), s("https://github.com/serde-rs/serde"));
+    assert_eq!(n("git@github.com:serde-rs/serde.git"), s("https://github.com/serde-rs/serde"));
+    assert_eq!(n("git://gitlab.com/foo/bar.git"), s("https://gitlab.com/foo/bar"));
+    assert_eq!(n(" http://docs.rs/foo "), s("https://docs.rs/foo"));
+    // unknown hosts might not support https
+    assert_eq!(n("http://example.org/foo"), s("http://example.org/foo"));
+    assert_eq!(n("not a url"), None);
+}
+
+#[test]
+fn offline_tests_detected() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("lib.rs"), "pub fn get() { reqwest::get(\"https://x\"); }\n").unwrap();
+    // network use outside of tests doesn't matter, but there are no tests yet
+    assert!(!has_offline_tests(dir.path()).unwrap());
+
+    fs::write(
+        src.join("parse.rs"),
+        "fn parse() {}\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn t() {}\n}\n",
+    ).unwrap();
+    assert!(has_offline_tests(dir.path()).unwrap());
+
+    // URLs that are only parsed or printed need no network
+    fs::write(
+        src.join("url.rs"),
+        "#[cfg(test)]\nmod tests {\n    #[test]\n    fn t() {\n        \
+         assert_eq!(parse(\"https://example.org/a\").host(), \"example.org\");\n        \
+         assert!(parse(\"http://[::1]\").is_ok());\n    }\n}\n",
+    ).unwrap();
+    assert!(has_offline_tests(dir.path()).unwrap());
+
+    fs::create_dir_all(dir.path().join("tests")).unwrap();
+    fs::write(
+        dir.path().join("tests/online.rs"),
+        "#[test]\nfn fetch() { TcpStream::connect(\"example.org:80\"); }\n",
+    ).unwrap();
+    assert!(!has_offline_tests(dir.path()).unwrap());
+}
+
+#[test]
+fn epoch_in_semver_suffix_breaks() {
+    let version = Version::parse("1.2.3").unwrap();
+    let package = Package::new(
+        "foo",
+        Some("-1"),
+        &version,
+        Some(1),
+        "foo",
+        None,
+        None,
+        None,
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        &PackageNaming::default(),
+    ).unwrap();
+    let control = format!("{}", package);
+    assert!(control.contains("\nReplaces: librust-foo-dev (<< 1:1.2.4-~~)\n"));
+    assert!(control.contains("\nBreaks: librust-foo-dev (<< 1:1.2.4-~~)\n"));
+    assert_eq!(with_epoch(None, "1.2.3"), "1.2.3");
+}
+
+#[test]
+fn package_name_overrides() {
+    let config = parse_config(Path::new("tests/rename_override.toml")).unwrap();
+    let version = Version::parse("1.2.3").unwrap();
+    let package = |feature: Option<&str>, recommends: Vec<&str>| {
+        Package::new(
+            "foo", None, &version, None, "foo", None, None, feature,
+            vec![], vec![], vec![], recommends, vec![], &PackageNaming::default(),
+        ).unwrap()
+    };
+    let mut renames = BTreeMap::new();
+    renames.insert(
+        "librust-foo+pcre2-dev".to_string(),
+        "librust-foo-pcre2-dev".to_string(),
+    );
+
+    let mut lib = package(None, vec!["pcre2"]);
+    lib.apply_overrides(&config, PackageKey::feature(""), vec![]);
+    lib.rename_relations(&renames);
+    let control = format!("{}", lib);
+    assert!(control.starts_with("Package: librust-foo-dev\n"));
+    assert!(control.contains("Recommends:\n librust-foo-pcre2-dev (= ${binary:Version})\n"));
+
+    let mut pcre2 = package(Some("pcre2"), vec![]);
+    pcre2.apply_overrides(&config, PackageKey::feature("pcre2"), vec![]);
+    assert_eq!(pcre2.name(), "librust-foo-pcre2-dev");
+    // other crates still depend on the generated name
+    assert!(format!("{}", pcre2).contains("\n librust-foo+pcre2-dev (= ${binary:Version})"));
+
+    let mut bin = Package::new_bin("rust-foo", None, "foo", None, None, None, "");
+    bin.apply_overrides(&config, PackageKey::Bin, vec![]);
+    assert_eq!(bin.name(), "foo");
+    assert!(!format!("{}", bin).contains("rust-foo"));
+}
+
+#[test]
+fn package_naming_templates() {
+    let naming = PackageNaming::new("rust-$crate-src", "rust-$crate--$feature-src").unwrap();
+    assert_eq!(naming.name("foo_bar-1"), "rust-foo-bar-1-src");
+    assert_eq!(naming.feature_name("foo_bar", "SIMD_Accel"), "rust-foo-bar--simd-accel-src");
+    assert_eq!(naming.name_for("foo", ""), "rust-foo-src");
+
+    let version = Version::parse("1.2.3").unwrap();
+    let package = Package::new(
+        "foo", None, &version, None, "foo", None, None, Some("std"),
+        vec![""], vec![], vec![], vec![], vec![], &naming,
+    ).unwrap();
+    let control = format!("{}", package);
+    assert!(control.starts_with("Package: rust-foo--std-src\n"));
+    assert!(control.contains("\n rust-foo-src (= ${binary:Version})"));
+    assert!(control.contains("\n rust-foo-1.2.3--std-src (= ${binary:Version})"));
+
+    assert_eq!(naming.parse("rust-foo-1.2.3--std-src"), Some(("foo-1.2.3".to_string(), Some("std".to_string()))));
+    assert_eq!(naming.parse("rust-foo-bar-src"), Some(("foo-bar".to_string(), None)));
+    assert_eq!(naming.parse("librust-foo-dev"), None);
+    let default = PackageNaming::default();
+    assert_eq!(default.parse("librust-foo-1+std-dev"), Some(("foo-1".to_string(), Some("std".to_string()))));
+    assert_eq!(default.parse("librust-foo-bar-dev"), Some(("foo-bar".to_string(), None)));
+    assert_eq!(default.parse("libfoo-dev"), None);
+
+    assert!(PackageNaming::new("librust-dev", "librust-$crate+$feature-dev").is_err());
+    assert!(PackageNaming::new("librust-$crate-dev", "librust-$crate-dev").is_err());
+}
+
+#[test]
+fn feature_description_overrides() {
+    let config = parse_config(Path::new("tests/rename_override.toml")).unwrap();
+    let version = Version::parse("1.2.3").unwrap();
+    let mut pcre2 = Package::new(
+        "foo", None, &version, None, "foo", Some("Foo"), Some("Foo does things."), Some("pcre2"),
+        vec![""], vec![], vec![], vec![], vec![], &PackageNaming::default(),
+    ).unwrap();
+    pcre2.apply_overrides(&config, PackageKey::feature("pcre2"), vec![]);
+    let control = format!("{}", pcre2);
+    assert!(control.contains(
+        "\nDescription: Foo - PCRE2 regex support\n Foo does things.\n .\n Lets patterns use the PCRE2 syntax.\n"));
+    assert!(!control.contains("metapackage"));
+}
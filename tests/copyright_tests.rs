@@ -0,0 +1,59 @@
+extern crate debcargo;
+
+use debcargo::debian::copyright::{files_pattern_matches, license_names, unmatched_files};
+
+use std::path::{Path, PathBuf};
+
+#[test]
+fn files_patterns() {
+    assert!(files_pattern_matches("*", Path::new("src/lib.rs")));
+    assert!(files_pattern_matches("debian/*", Path::new("debian/tests/control")));
+    assert!(files_pattern_matches("./src/lib.rs", Path::new("src/lib.rs")));
+    assert!(files_pattern_matches("src/*.rs", Path::new("src/a/b.rs")));
+    assert!(files_pattern_matches("LICENSE-???", Path::new("LICENSE-MIT")));
+    assert!(files_pattern_matches(r"weird\*name", Path::new("weird*name")));
+    assert!(!files_pattern_matches(r"weird\*name", Path::new("weirdXname")));
+    assert!(!files_pattern_matches("src/*", Path::new("benches/b.rs")));
+    assert!(!files_pattern_matches("lib.rs", Path::new("src/lib.rs")));
+}
+
+#[test]
+fn files_without_paragraph() {
+    let copyright = "\
+Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+
+Files: src/* Cargo.toml
+Copyright: 2019 A
+License: MIT
+
+Files: debian/*
+Copyright: 2019 B
+License: MIT
+
+License: MIT
+ Text.
+";
+    let files = ["Cargo.toml", "src/lib.rs", "debian/rules", "benches/b.rs", "README.md"]
+        .iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unmatched_files(copyright, &files),
+        vec![PathBuf::from("benches/b.rs"), PathBuf::from("README.md")]
+    );
+    let catch_all = copyright.replace("Files: src/* Cargo.toml", "Files: *");
+    assert!(unmatched_files(&catch_all, &files).is_empty());
+}
+
+#[test]
+fn license_expressions() {
+    assert_eq!(license_names("MIT OR Apache-2.0"), vec!["MIT", "Apache-2.0"]);
+    assert_eq!(license_names("MIT/Apache-2.0"), vec!["MIT", "Apache-2.0"]);
+    assert_eq!(license_names("MIT or Apache-2.0, Zlib"), vec!["MIT", "Apache-2.0", "Zlib"]);
+    assert_eq!(license_names("(MIT OR Apache-2.0) AND Unicode-DFS-2016"),
+               vec!["MIT", "Apache-2.0", "Unicode-DFS-2016"]);
+    // not split inside the names
+    assert_eq!(license_names("GPL-3.0-or-later"), vec!["GPL-3.0-or-later"]);
+    assert_eq!(license_names("LGPL-2.1-or-later AND GPL-2.0-only"), vec!["LGPL-2.1-or-later", "GPL-2.0-only"]);
+    assert_eq!(license_names("BSD-3-Clause OR Unlicense"), vec!["BSD-3-Clause", "Unlicense"]);
+}
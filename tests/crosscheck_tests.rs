@@ -0,0 +1,159 @@
+extern crate cargo;
+extern crate debcargo;
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use cargo::core::dependency::Kind;
+use cargo::core::{Dependency, PackageId, SourceId, Summary};
+use cargo::Config;
+use debcargo::crates::{summary_dependencies_and_features, NewFeatureSyntax};
+use debcargo::debian::control::PackageNaming;
+use debcargo::debian::crosscheck::{cargo_requirements, cross_check, feature_report};
+
+/// The crate of the lib-features golden case.
+fn golden_lib() -> Summary {
+    let source_id = SourceId::crates_io(&Config::default().unwrap()).unwrap();
+    let libc = Dependency::parse_no_deprecated("libc", Some("0.2.40"), source_id).unwrap();
+    let mut serde = Dependency::parse_no_deprecated("serde", Some("1.0"), source_id).unwrap();
+    serde.set_optional(true);
+    let mut tempfile = Dependency::parse_no_deprecated("tempfile", Some("3"), source_id).unwrap();
+    tempfile.set_kind(Kind::Development);
+    let features = vec![
+        ("default", vec!["std"]),
+        ("std", vec![]),
+        ("derive", vec!["serde"]),
+    ].into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let pkgid = PackageId::new("golden_lib", "0.3.1", source_id).unwrap();
+    Summary::new(pkgid, vec![libc, serde, tempfile], &features, None::<&str>, false).unwrap()
+}
+
+fn golden_control() -> String {
+    fs::read_to_string("tests/golden/lib-features/expected/control").unwrap()
+}
+
+fn messages(summary: &Summary, control: &str) -> Vec<String> {
+    named_messages(summary, control, &PackageNaming::default())
+}
+
+fn features_of(summary: &Summary) -> BTreeMap<&str, (Vec<&str>, Vec<Dependency>)> {
+    summary_dependencies_and_features(summary, summary.dependencies(), &NewFeatureSyntax::default())
+}
+
+fn named_messages(summary: &Summary, control: &str, naming: &PackageNaming) -> Vec<String> {
+    cross_check(&features_of(summary), control, naming)
+        .unwrap()
+        .iter()
+        .map(|d| d.to_string())
+        .collect()
+}
+
+#[test]
+fn requirements_follow_features() {
+    let s = golden_lib();
+    let features = features_of(&s);
+    let names = |feature| {
+        cargo_requirements(&features, feature)
+            .into_iter()
+            .map(|r| r.crate_name)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(""), vec!["libc"]);
+    assert_eq!(names("default"), vec!["libc"]);
+    assert_eq!(names("serde"), vec!["libc", "serde"]);
+    assert_eq!(names("derive"), vec!["libc", "serde"]);
+}
+
+#[test]
+fn requirements_follow_new_feature_syntax() {
+    let s = golden_lib();
+    // as if derive = ["dep:serde"] and std = ["serde?/std"]
+    let mut new_syntax = NewFeatureSyntax::default();
+    new_syntax.dep_only.insert("serde".to_string());
+    new_syntax
+        .weak
+        .insert("std".to_string(), vec![("serde".to_string(), "std".to_string())]);
+    let features = summary_dependencies_and_features(&s, s.dependencies(), &new_syntax);
+    assert!(!features.contains_key("serde"));
+    assert_eq!(
+        cargo_requirements(&features, "std").into_iter().map(|r| r.crate_name).collect::<Vec<_>>(),
+        vec!["libc"]
+    );
+    let derive = cargo_requirements(&features, "derive");
+    assert_eq!(derive[1].crate_name, "serde");
+    assert_eq!(
+        derive[1].features.iter().cloned().collect::<Vec<_>>(),
+        vec!["default", "std"]
+    );
+}
+
+#[test]
+fn golden_depends_match_cargo() {
+    assert!(messages(&golden_lib(), &golden_control()).is_empty());
+}
+
+#[test]
+fn package_name_templates() {
+    let naming = PackageNaming::new("rust-$crate-dev", "rust-$crate+$feature-dev").unwrap();
+    let s = golden_lib();
+    let control = golden_control().replace("librust-", "rust-");
+    assert!(named_messages(&golden_lib(), &control, &naming).is_empty());
+    assert!(!messages(&golden_lib(), &control).is_empty());
+
+    let report = feature_report(&features_of(&s), &control, &naming).unwrap();
+    assert!(report.contains("\n## rust-golden-lib+serde-dev\n\nFeatures: `derive`, `serde`\n"));
+}
+
+#[test]
+fn wrong_depends_are_flagged() {
+    let s = golden_lib();
+    let control = golden_control();
+
+    let lax = control.replace(" librust-libc-0.2+default-dev (>= 0.2.40-~~)\n", " librust-libc-0.2+default-dev\n");
+    assert_eq!(
+        messages(&s, &lax),
+        vec!["librust-golden-lib-dev: too lax, the dependency on libc with feature \"default\" \
+              allows 0.2.39 but cargo requires ^0.2.40"]
+    );
+
+    let strict = control.replace("(>= 0.2.40-~~)\nSuggests", "(>= 0.2.41-~~)\nSuggests");
+    assert_eq!(
+        messages(&s, &strict),
+        vec!["librust-golden-lib-dev: too strict, the dependency on libc with feature \"default\" \
+              excludes 0.2.40 which cargo allows with ^0.2.40"]
+    );
+
+    let missing = control.replace(",\n librust-serde-1+default-dev", "");
+    assert_eq!(
+        messages(&s, &missing),
+        vec!["librust-golden-lib+serde-dev: no dependency on serde with feature \"default\" ^1.0"]
+    );
+
+    let unprovided = control.replace(" librust-golden-lib+derive-dev (= ${binary:Version}),\n", "");
+    assert_eq!(
+        messages(&s, &unprovided),
+        vec!["librust-golden-lib+derive-dev: no package provides feature \"derive\""]
+    );
+}
+
+#[test]
+fn feature_report_lists_packages() {
+    let s = golden_lib();
+    let report = feature_report(
+        &features_of(&s),
+        &golden_control(),
+        &PackageNaming::default(),
+    ).unwrap();
+    assert_eq!(
+        report,
+        "# Cargo features of the packages of rust-golden-lib\n\
+         \n## librust-golden-lib-dev\n\n\
+         Features: (none), `default`, `std`\n\n\
+         - libc ^0.2.40: `default`\n\
+         \n## librust-golden-lib+serde-dev\n\n\
+         Features: `derive`, `serde`\n\n\
+         - libc ^0.2.40: `default`\n\
+         - serde ^1.0: `default`\n"
+    );
+}
@@ -0,0 +1,88 @@
+extern crate debcargo;
+
+use debcargo::debian::copyright::copyright_changes;
+use debcargo::debian::dep5::{Copyright, Files, Header, License};
+
+use std::fs;
+
+#[test]
+fn generated_copyrights_round_trip() {
+    for entry in fs::read_dir("tests/golden").unwrap() {
+        let path = entry.unwrap().path().join("expected/copyright");
+        if !path.exists() {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let copyright = text.parse::<Copyright>().unwrap();
+        assert_eq!(copyright.to_string(), text, "{}", path.display());
+    }
+}
+
+#[test]
+fn written_normalized() {
+    let mut header = Header::new("foo");
+    header.upstream_contact = vec!["A <a@example.org>".to_string(), "B <b@example.org>".to_string()];
+    header.files_excluded = vec!["data/* tests/big".to_string()];
+    let copyright = Copyright {
+        header: header,
+        files: vec![Files::new("*", &["2019 A", "\n2020 B \n"], "MIT", "\nSome   \n\nnotes\n\n")],
+        licenses: vec![License::new("MIT", Some("  indented\n\ntext"))],
+    };
+    let text = copyright.to_string();
+    assert_eq!(text, concat!(
+        "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/\n",
+        "Upstream-Name: foo\n",
+        "Upstream-Contact:\n A <a@example.org>\n B <b@example.org>\n",
+        "Files-Excluded:\n data/*\n tests/big\n",
+        "\n",
+        "Files: *\n",
+        "Copyright:\n 2019 A\n 2020 B\n",
+        "License: MIT\n",
+        "Comment:\n Some\n .\n notes\n",
+        "\n",
+        "License: MIT\n   indented\n .\n text\n",
+    ));
+    let parsed = text.parse::<Copyright>().unwrap();
+    assert_eq!(parsed.to_string(), text);
+    assert_eq!(parsed.header.files_excluded, vec!["data/*", "tests/big"]);
+    assert_eq!(parsed.files[0].copyright, vec!["2019 A", "2020 B"]);
+    assert_eq!(parsed.files[0].comment.as_ref().unwrap(), "Some\n\nnotes");
+    assert_eq!(parsed.licenses[0].text.as_ref().unwrap(), "  indented\n\ntext");
+}
+
+#[test]
+fn other_fields_are_kept() {
+    let text = concat!(
+        "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/\n",
+        "Upstream-Name: foo\n",
+        "Disclaimer: not\n part of Debian\n",
+        "\n",
+        "Files: src/*\n",
+        "Copyright: 2019 A\n",
+        "License: MIT\n",
+        "X-Note: kept\n",
+    );
+    assert_eq!(text.parse::<Copyright>().unwrap().to_string(), text);
+    assert!("Upstream-Name: foo\n".parse::<Copyright>().is_err());
+    assert!(concat!("Format: x\n", "\n", "Files: *\n", "Copyright: A\n").parse::<Copyright>().is_err());
+}
+
+#[test]
+fn changes_to_carry_over() {
+    let copyright = |license: &str, excluded: &[&str]| {
+        let mut header = Header::new("foo");
+        header.files_excluded = excluded.iter().map(|e| e.to_string()).collect();
+        Copyright {
+            header: header,
+            files: vec![Files::new("*", &["2019 A"], license, "")],
+            licenses: license.split(" or ").map(|l| License::new(l, Some("text"))).collect(),
+        }
+    };
+    let old = copyright("MIT", &[]);
+    assert!(copyright_changes(&old, &old).is_empty());
+    assert_eq!(copyright_changes(&old, &copyright("MIT or Apache-2.0", &["data/*"])), vec![
+        "the license of the crate is now MIT or Apache-2.0, was MIT",
+        "add a License paragraph for Apache-2.0",
+        "set Files-Excluded to: data/*",
+    ]);
+}
@@ -0,0 +1,42 @@
+extern crate chrono;
+extern crate debcargo;
+
+use chrono::{TimeZone, Utc};
+use debcargo::deprecation::{deprecation_notices, successor};
+
+#[test]
+fn successors() {
+    assert_eq!(successor("tempdir"), Some("tempfile"));
+    assert_eq!(successor("rustc_serialize"), Some("serde"));
+    assert_eq!(successor("serde"), None);
+}
+
+#[test]
+fn notices() {
+    let now = Utc.ymd(2024, 6, 1).and_hms(0, 0, 0);
+    let json = r#"{
+        "crate": {"name": "foo", "description": "DEPRECATED: this crate was renamed to `foo-next`."},
+        "versions": [
+            {"num": "0.2.0", "yanked": true, "created_at": "2023-01-01T00:00:00.000000+00:00"},
+            {"num": "0.1.0", "yanked": false, "created_at": "2019-03-01T12:00:00.000000+00:00"}
+        ]
+    }"#;
+    assert_eq!(deprecation_notices("foo", json, now).unwrap(), vec![
+        "The description of foo on crates.io says \"DEPRECATED\": DEPRECATED: this crate was renamed to `foo-next`. \
+         (successor: foo-next)",
+        "foo has had no release on crates.io since 2019-03-01",
+    ]);
+
+    let yanked = r#"{
+        "crate": {"description": "Use bar instead, which is maintained."},
+        "versions": [{"num": "1.0.0", "yanked": true, "created_at": "2024-01-01T00:00:00+00:00"}]
+    }"#;
+    assert_eq!(deprecation_notices("foo", yanked, now).unwrap(), vec!["All versions of foo are yanked on crates.io"]);
+
+    let fine = r#"{
+        "crate": {"description": "A maintained crate"},
+        "versions": [{"num": "1.0.0", "yanked": false, "created_at": "2024-01-01T00:00:00+00:00"}]
+    }"#;
+    assert!(deprecation_notices("foo", fine, now).unwrap().is_empty());
+    assert!(deprecation_notices("foo", "{}", now).is_err());
+}
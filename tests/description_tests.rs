@@ -0,0 +1,173 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use std::fs;
+use std::path::Path;
+
+use debcargo::crates::readme_first_paragraph;
+use debcargo::debian::description::DescriptionRules;
+use debcargo::selftest::{generate, Case};
+
+fn summarize(name: &str, description: &str) -> (Option<String>, Option<String>) {
+    DescriptionRules::default().summarize(name, description)
+}
+
+fn s(x: &str) -> Option<String> {
+    Some(x.to_string())
+}
+
+#[test]
+fn corpus() {
+    // (crate, description from Cargo.toml, expected summary, expected description)
+    let corpus = vec![
+        (
+            "serde",
+            "A generic serialization/deserialization framework",
+            s("Generic serialization/deserialization framework"),
+            None,
+        ),
+        (
+            "regex",
+            "An implementation of regular expressions for Rust. This implementation uses \
+             finite automata and guarantees linear time matching on all inputs.",
+            s("Regular expressions for Rust"),
+            s("This implementation uses finite automata and guarantees linear time \
+               matching on all inputs."),
+        ),
+        (
+            "rand",
+            "Random number generators and other randomness functionality.\n",
+            s("Random number generators and other randomness functionality"),
+            None,
+        ),
+        (
+            "log",
+            "A lightweight logging facade for Rust\n",
+            s("Lightweight logging facade for Rust"),
+            None,
+        ),
+        (
+            "textwrap",
+            "Textwrap is a small library for word wrapping, indenting, and\ndedenting \
+             strings.\n\nYou can use it to format strings (such as help and error\nmessages) \
+             for display in commandline applications.",
+            s("Small library for word wrapping, indenting, and dedenting strings"),
+            s("You can use it to format strings (such as help and error messages) for \
+               display in commandline applications."),
+        ),
+        (
+            "core-foundation-sys",
+            "Bindings to Core Foundation for OS X",
+            s("Bindings to Core Foundation for OS X"),
+            None,
+        ),
+        (
+            "semver_parser",
+            "semver-parser is a crate for parsing semver strings",
+            s("Parsing semver strings"),
+            None,
+        ),
+        (
+            "ios-sys",
+            "iOS system bindings, e.g. for UIKit. Unsafe.",
+            s("iOS system bindings, e.g. for UIKit"),
+            s("Unsafe."),
+        ),
+    ];
+    for (name, description, summary, long) in corpus {
+        assert_eq!(summarize(name, description), (summary, long), "crate {}", name);
+    }
+}
+
+#[test]
+fn disabled_rules() {
+    let rules = DescriptionRules::new(
+        &["strip-article".to_string(), "capitalize".to_string()],
+        &[],
+    ).unwrap();
+    assert_eq!(
+        rules.summarize("log", "a lightweight logging facade"),
+        (s("a lightweight logging facade"), None)
+    );
+    assert!(DescriptionRules::new(&["no-such-rule".to_string()], &[]).is_err());
+}
+
+#[test]
+fn custom_strip_patterns() {
+    let rules = DescriptionRules::new(&[], &[r"^(?i)blazing(ly)? fast,?\s+".to_string()]).unwrap();
+    assert_eq!(
+        rules.summarize("foo", "Blazingly fast, a JSON parser"),
+        (s("JSON parser"), None)
+    );
+    assert!(DescriptionRules::new(&[], &["(".to_string()]).is_err());
+}
+
+#[test]
+fn readme_paragraph_after_heading_and_badges() {
+    let readme = "# foo\n\
+                  \n\
+                  [![Build Status](https://example.org/badge.svg)](https://example.org/ci)\n\
+                  [![Crates.io](https://img.shields.io/crates/v/foo.svg)](https://crates.io/crates/foo)\n\
+                  \n\
+                  A library for parsing\n\
+                  foo files.\n\
+                  \n\
+                  More details.\n";
+    assert_eq!(readme_first_paragraph(readme), s("A library for parsing foo files."));
+
+    let setext = "foo\n===\n\nParses foo files.\n";
+    assert_eq!(readme_first_paragraph(setext), s("Parses foo files."));
+}
+
+#[test]
+fn readme_without_paragraph() {
+    let readme = "# foo\n\n```rust\nfoo::parse();\n```\n\n## Usage\n";
+    assert_eq!(readme_first_paragraph(readme), None);
+    assert_eq!(readme_first_paragraph(""), None);
+}
+
+/// A case with a crate that has no description, and optionally a README.
+fn undescribed_case(dir: &Path, readme: Option<&str>) -> Case {
+    let crate_dir = dir.join("crate");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        "[package]\nname = \"undescribed\"\nversion = \"0.1.0\"\n\
+         authors = [\"Jane Doe <jane@example.org>\"]\nlicense = \"MIT\"\n",
+    ).unwrap();
+    fs::write(crate_dir.join("src").join("lib.rs"), "").unwrap();
+    if let Some(readme) = readme {
+        fs::write(crate_dir.join("README.md"), readme).unwrap();
+    }
+    Case {
+        name: "undescribed".to_string(),
+        dir: dir.to_path_buf(),
+        crate_dir: crate_dir,
+    }
+}
+
+fn control(case: &Case, strict: bool) -> Result<String, String> {
+    // in ., like the golden cases, for prepare_debian_folder's rename
+    let work_dir = tempfile::Builder::new().prefix("debcargo").tempdir_in(".").unwrap();
+    generate(case, work_dir.path(), strict)
+        .map(|contents| String::from_utf8(contents[Path::new("control")].clone()).unwrap())
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn missing_description_from_readme() {
+    let tmp = tempfile::tempdir().unwrap();
+    let case = undescribed_case(tmp.path(), Some("# undescribed\n\nParses undescribed files.\n"));
+    let control = control(&case, true).unwrap();
+    assert!(control.contains("Parses undescribed files"));
+    assert!(!control.contains("FIXME (summary)"));
+}
+
+#[test]
+fn missing_description_is_fatal_with_strict() {
+    let tmp = tempfile::tempdir().unwrap();
+    let case = undescribed_case(tmp.path(), None);
+    assert!(control(&case, false).unwrap().contains("FIXME (summary)"));
+    let err = control(&case, true).unwrap_err();
+    assert!(err.contains("Crate has no description"), "{}", err);
+}
@@ -0,0 +1,34 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::crates::download_resumable;
+use debcargo::errors::ErrorKind;
+
+use std::fs;
+
+#[test]
+fn interrupted_downloads_are_resumed() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let source = dir.path().join("foo-1.0.0.crate");
+    fs::write(&source, &data).unwrap();
+    let url = format!("file://{}", source.display());
+
+    let dest = dir.path().join("fresh.part");
+    download_resumable(&url, &[], &dest).unwrap();
+    assert_eq!(fs::read(&dest).unwrap(), data);
+
+    let dest = dir.path().join("interrupted.part");
+    fs::write(&dest, &data[..4000]).unwrap();
+    download_resumable(&url, &[], &dest).unwrap();
+    assert_eq!(fs::read(&dest).unwrap(), data);
+}
+
+#[test]
+fn missing_files_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("file://{}", dir.path().join("missing.crate").display());
+    let e = download_resumable(&url, &[], &dir.path().join("missing.part")).unwrap_err();
+    assert_eq!(ErrorKind::of(&e), Some(ErrorKind::Network));
+    assert_eq!(ErrorKind::Network.exit_code(), 3);
+}
@@ -0,0 +1,31 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::debian::description::DescriptionRules;
+use debcargo::errors::EXPLAIN_ENV;
+
+use std::env;
+use std::fs;
+
+// One test, since it sets an environment variable for the whole process.
+#[test]
+fn decisions_are_recorded_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("decisions");
+    env::set_var(EXPLAIN_ENV, &log);
+    let rules = DescriptionRules::default();
+    for _ in 0..2 {
+        rules.summarize("regex", "An implementation of regular expressions. Fast.");
+    }
+    env::remove_var(EXPLAIN_ENV);
+    rules.summarize("regex", "The regular expressions.");
+
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "Description rule strip-article turned \"An implementation of regular expressions. Fast.\" \
+         into \"implementation of regular expressions. Fast.\"\n\
+         Description rule strip-library-of turned \"implementation of regular expressions. Fast.\" \
+         into \"regular expressions. Fast.\"\n\
+         The summary is the first sentence of the description\n"
+    );
+}
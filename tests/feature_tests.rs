@@ -0,0 +1,456 @@
+extern crate cargo;
+extern crate debcargo;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cargo::core::{Dependency, PackageId, SourceId, Summary};
+use cargo::Config;
+use debcargo::crates::{
+    cap_feature_packages, inline_optional_dependencies, remove_unpackageable_features,
+    rewrite_new_features, summary_dependencies_and_features, zero_dependency_features,
+    DependencySelection, NewFeatureSyntax,
+};
+
+fn dep(source_id: SourceId, name: &str, optional: bool) -> Dependency {
+    let mut d = Dependency::parse_no_deprecated(name, Some("1"), source_id).unwrap();
+    d.set_optional(optional);
+    d
+}
+
+fn summary(deps: Vec<Dependency>, features: &[(&str, &[&str])]) -> Summary {
+    let config = Config::default().unwrap();
+    let source_id = SourceId::crates_io(&config).unwrap();
+    let features = features
+        .iter()
+        .map(|&(f, ff)| (f.to_string(), ff.to_vec()))
+        .collect::<BTreeMap<_, _>>();
+    let pkgid = PackageId::new("foo", "1.0.0", source_id).unwrap();
+    Summary::new(pkgid, deps, &features, None::<&str>, false).unwrap()
+}
+
+fn source_id() -> SourceId {
+    SourceId::crates_io(&Config::default().unwrap()).unwrap()
+}
+
+fn features_of<'a>(
+    s: &'a Summary,
+    dep_only: &[&str],
+) -> BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)> {
+    let new_syntax = NewFeatureSyntax {
+        dep_only: dep_only.iter().map(|d| d.to_string()).collect::<BTreeSet<_>>(),
+        ..Default::default()
+    };
+    summary_dependencies_and_features(s, s.dependencies(), &new_syntax)
+}
+
+fn features_with_weak<'a>(
+    s: &'a Summary,
+    weak: &[(&str, &str, &str)],
+) -> BTreeMap<&'a str, (Vec<&'a str>, Vec<Dependency>)> {
+    let mut new_syntax = NewFeatureSyntax::default();
+    for &(feature, dep, dep_feature) in weak {
+        new_syntax
+            .weak
+            .entry(feature.to_string())
+            .or_insert_with(Vec::new)
+            .push((dep.to_string(), dep_feature.to_string()));
+    }
+    summary_dependencies_and_features(s, s.dependencies(), &new_syntax)
+}
+
+fn names(deps: &[Dependency]) -> Vec<String> {
+    deps.iter().map(|d| d.package_name().to_string()).collect()
+}
+
+#[test]
+fn implicit_default() {
+    let s = summary(vec![dep(source_id(), "bar", false)], &[]);
+    let features = features_of(&s, &[]);
+    assert_eq!(features.keys().cloned().collect::<Vec<_>>(), vec!["", "default"]);
+    assert_eq!(features[""].0, Vec::<&str>::new());
+    assert_eq!(names(&features[""].1), vec!["bar"]);
+    assert_eq!(features["default"].0, vec![""]);
+    assert!(features["default"].1.is_empty());
+}
+
+#[test]
+fn default_enables_optional_dep() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "bar", false), dep(sid, "baz", true)],
+        &[("default", &["baz"])],
+    );
+    let features = features_of(&s, &[]);
+    // default should go through the feature for the optional dependency,
+    // rather than duplicating its dependencies
+    assert_eq!(features["default"].0, vec!["", "baz"]);
+    assert!(features["default"].1.is_empty());
+    assert_eq!(features["baz"].0, vec![""]);
+    assert_eq!(names(&features["baz"].1), vec!["baz"]);
+    assert_eq!(names(&features[""].1), vec!["bar"]);
+}
+
+#[test]
+fn default_with_features_and_crate_features() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "bar", false), dep(sid, "baz", true)],
+        &[
+            ("default", &["std", "bar/extra"]),
+            ("std", &["baz/std"]),
+        ],
+    );
+    let features = features_of(&s, &[]);
+    assert_eq!(features["default"].0, vec!["", "std"]);
+    let default_deps = &features["default"].1;
+    assert_eq!(names(default_deps), vec!["bar"]);
+    assert!(!default_deps[0].uses_default_features());
+    assert_eq!(default_deps[0].features()[0].as_str(), "extra");
+    assert_eq!(names(&features["std"].1), vec!["baz"]);
+    // optional dependency still has its own feature
+    assert!(features.contains_key("baz"));
+}
+
+#[test]
+fn explicit_empty_default() {
+    let sid = source_id();
+    let s = summary(vec![dep(sid, "baz", true)], &[("default", &[])]);
+    let features = features_of(&s, &[]);
+    assert_eq!(features["default"].0, vec![""]);
+    assert!(features["default"].1.is_empty());
+}
+
+#[test]
+fn optional_dep_for_several_targets() {
+    let sid = source_id();
+    let mut unix = dep(sid, "baz", true);
+    unix.set_platform(Some("cfg(unix)".parse().unwrap()));
+    let mut windows = dep(sid, "baz", true);
+    windows.set_platform(Some("cfg(windows)".parse().unwrap()));
+    let s = summary(vec![unix, windows], &[]);
+    let features = features_of(&s, &[]);
+    assert_eq!(names(&features["baz"].1), vec!["baz", "baz"]);
+}
+
+#[test]
+fn dep_features_rewritten() {
+    let manifest = r#"
+[package]
+name = "foo"
+version = "1.0.0"
+
+[dependencies]
+serde_json = { version = "1", optional = true }
+rand = { version = "0.7", optional = true }
+
+[features]
+json = ["dep:serde_json", "rand"]
+"#;
+    let (rewritten, new_syntax) = rewrite_new_features(manifest).unwrap().unwrap();
+    assert!(rewritten.contains(r#"json = ["serde_json", "rand"]"#));
+    assert_eq!(
+        new_syntax.dep_only.into_iter().collect::<Vec<_>>(),
+        vec!["serde_json"]
+    );
+
+    let plain = manifest.replace("dep:serde_json", "serde_json");
+    assert!(rewrite_new_features(&plain).unwrap().is_none());
+
+    // a feature named after the dependency it enables is left to the
+    // implicit feature of the dependency, and the rest of it recorded
+    let same_name = manifest.replace("rand\"]", "rand\"]\nserde_json = [\"dep:serde_json\", \"rand\", \"rand?/std\"]");
+    let (rewritten, new_syntax) = rewrite_new_features(&same_name).unwrap().unwrap();
+    assert!(rewritten.contains(r#"json = ["serde_json", "rand"]"#));
+    assert!(!rewritten.contains("serde_json = ["));
+    assert!(new_syntax.dep_only.is_empty());
+    assert_eq!(new_syntax.same_name["serde_json"], vec!["rand"]);
+    assert_eq!(
+        new_syntax.weak["serde_json"],
+        vec![("rand".to_string(), "std".to_string())]
+    );
+
+    // but one that doesn't enable it can't be
+    let clash = manifest.replace("rand\"]", "rand\"]\nserde_json = [\"rand\"]");
+    assert!(rewrite_new_features(&clash).is_err());
+}
+
+#[test]
+fn dep_feature_of_the_same_name() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "serde", true), dep(sid, "serde_json", true), dep(sid, "rand", true)],
+        &[("std", &[]), ("derive", &["serde"])],
+    );
+    let mut new_syntax = NewFeatureSyntax::default();
+    new_syntax
+        .same_name
+        .insert("serde".to_string(), vec!["std".to_string(), "rand".to_string(), "serde_json/serde".to_string()]);
+    let features = summary_dependencies_and_features(&s, s.dependencies(), &new_syntax);
+    assert_eq!(features["serde"].0, vec!["", "std", "rand", "serde_json"]);
+    assert_eq!(names(&features["serde"].1), vec!["serde", "serde_json"]);
+    assert_eq!(features["serde"].1[1].features()[0].as_str(), "serde");
+    assert_eq!(features["derive"].0, vec!["", "serde"]);
+}
+
+#[test]
+fn dep_features_have_no_implicit_feature() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "serde_json", true), dep(sid, "rand", true)],
+        &[("json", &["serde_json", "rand"])],
+    );
+    let features = features_of(&s, &["serde_json"]);
+    assert!(!features.contains_key("serde_json"));
+    assert!(features.contains_key("rand"));
+    assert_eq!(features["json"].0, vec!["", "rand"]);
+    assert_eq!(names(&features["json"].1), vec!["serde_json"]);
+}
+
+#[test]
+fn weak_dep_features_rewritten() {
+    let manifest = r#"
+[package]
+name = "foo"
+version = "1.0.0"
+
+[dependencies]
+serde = { version = "1", optional = true }
+
+[features]
+std = ["serde?/std"]
+"#;
+    let (rewritten, new_syntax) = rewrite_new_features(manifest).unwrap().unwrap();
+    assert!(rewritten.contains("std = []"));
+    assert!(new_syntax.dep_only.is_empty());
+    assert_eq!(
+        new_syntax.weak["std"],
+        vec![("serde".to_string(), "std".to_string())]
+    );
+}
+
+#[test]
+fn weak_dep_feature_not_enabled() {
+    let sid = source_id();
+    let s = summary(vec![dep(sid, "serde", true)], &[("std", &[])]);
+    let features = features_with_weak(&s, &[("std", "serde", "std")]);
+    // std must not pull in the optional dependency by itself
+    assert!(features["std"].1.is_empty());
+    // but whatever enables it also needs its std feature
+    let serde_deps = &features["serde"].1;
+    assert_eq!(names(serde_deps), vec!["serde", "serde"]);
+    assert_eq!(serde_deps[1].features()[0].as_str(), "std");
+}
+
+#[test]
+fn weak_dep_feature_enabled_by_another_feature() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "serde", true)],
+        &[("std", &[]), ("json", &["serde"])],
+    );
+    let features = features_with_weak(&s, &[("std", "serde", "std")]);
+    assert!(features["std"].1.is_empty());
+    let json_deps = &features["json"].1;
+    assert_eq!(names(json_deps), vec!["serde"]);
+    assert_eq!(json_deps[0].features()[0].as_str(), "std");
+    assert!(!json_deps[0].uses_default_features());
+}
+
+#[test]
+fn weak_dep_feature_enabled_elsewhere() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "serde", true)],
+        &[("std", &[]), ("full", &["std", "serde"])],
+    );
+    let features = features_with_weak(&s, &[("std", "serde", "std"), ("full", "serde", "std")]);
+    assert!(features["std"].1.is_empty());
+    let full_deps = &features["full"].1;
+    assert_eq!(names(full_deps), vec!["serde"]);
+    assert_eq!(full_deps[0].features()[0].as_str(), "std");
+    assert!(!full_deps[0].uses_default_features());
+}
+
+#[test]
+fn weak_dep_feature_of_required_dep() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "foo", false), dep(sid, "serde", true)],
+        &[("std", &[]), ("json", &["serde"])],
+    );
+    let features = features_with_weak(&s, &[("std", "foo", "std")]);
+    // like foo/std, as foo is always enabled
+    let std_deps = &features["std"].1;
+    assert_eq!(names(std_deps), vec!["foo"]);
+    assert_eq!(std_deps[0].features()[0].as_str(), "std");
+    assert!(!std_deps[0].uses_default_features());
+    assert!(features["json"].1.iter().all(|d| d.package_name().as_str() != "foo"));
+    assert!(features[""].1[0].features().is_empty());
+}
+
+#[test]
+fn zero_dependency_features_through_other_features() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "bar", false), dep(sid, "serde", true)],
+        &[
+            ("default", &["std"]),
+            ("std", &[]),
+            ("alloc", &[]),
+            ("full", &["std", "alloc"]),
+            ("derive", &["serde"]),
+            ("everything", &["full", "derive"]),
+        ],
+    );
+    let features = features_of(&s, &[]);
+    assert_eq!(zero_dependency_features(&features), vec!["alloc", "default", "full", "std"]);
+}
+
+#[test]
+fn feature_packages_capped() {
+    // external dependencies stand in as numbers
+    let mut features: BTreeMap<&str, (Vec<&str>, Vec<u32>)> = vec![
+        ("", (vec![], vec![0])),
+        ("big", (vec![""], vec![1, 2, 3])),
+        ("small", (vec![""], vec![4])),
+        ("uses-small", (vec!["small"], vec![5])),
+        ("uses-std", (vec!["std"], vec![6, 7])),
+    ].into_iter().collect();
+    // as from calculate_provides, with "std" provided by "big"
+    let mut provides: BTreeMap<&str, Vec<&str>> = vec![
+        ("", vec!["default"]),
+        ("big", vec!["std"]),
+        ("small", vec![]),
+        ("uses-small", vec![]),
+        ("uses-std", vec![]),
+    ].into_iter().collect();
+
+    assert!(cap_feature_packages(&mut features, &mut provides, 5).is_empty());
+    assert_eq!(cap_feature_packages(&mut features, &mut provides, 4), vec!["small"]);
+    // "uses-std" needs "big" through "std", so both go: 5 dependencies in all,
+    // against 1 for "uses-small", which now needs nothing else
+    assert_eq!(cap_feature_packages(&mut features, &mut provides, 3), vec!["uses-small"]);
+    assert_eq!(cap_feature_packages(&mut features, &mut provides, 0), vec!["big", "std", "uses-std"]);
+    assert_eq!(features.keys().cloned().collect::<Vec<_>>(), vec![""]);
+    assert_eq!(features[""].1, vec![0, 4, 5, 1, 2, 3, 6, 7]);
+    assert_eq!(provides[""], vec!["big", "default", "small", "std", "uses-small", "uses-std"]);
+}
+
+#[test]
+fn unpackageable_features_removed() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "bar", false), dep(sid, "cuda-sys", true)],
+        &[
+            ("default", &["std"]),
+            ("std", &[]),
+            ("cuda", &["cuda-sys"]),
+            ("gpu", &["cuda"]),
+            ("full", &["gpu", "std"]),
+        ],
+    );
+    let mut features = features_of(&s, &[]);
+    let enabling = remove_unpackageable_features(&mut features, &["cuda-sys".to_string()]).unwrap();
+    assert_eq!(enabling, vec!["cuda", "full", "gpu"]);
+    assert_eq!(features.keys().cloned().collect::<Vec<_>>(), vec!["", "default", "std"]);
+
+    let mut features = features_of(&s, &[]);
+    assert!(remove_unpackageable_features(&mut features, &["std".to_string()]).is_err());
+    assert!(remove_unpackageable_features(&mut features, &["nonesuch".to_string()]).is_err());
+}
+
+#[test]
+fn optional_dependencies_inlined() {
+    let mut features: BTreeMap<&str, (Vec<&str>, Vec<u32>)> = vec![
+        ("", (vec![], vec![0])),
+        ("default", (vec!["std"], vec![])),
+        ("std", (vec!["", "log"], vec![])),
+        ("log", (vec![""], vec![1])),
+        ("derive", (vec!["serde"], vec![])),
+        ("serde", (vec![""], vec![2])),
+    ].into_iter().collect();
+    let inlined = inline_optional_dependencies(&mut features, &["log", "serde"]);
+    assert_eq!(inlined.into_iter().collect::<Vec<_>>(), vec![("log", vec![1]), ("serde", vec![2])]);
+    assert_eq!(features["std"], (vec![""], vec![1]));
+    assert_eq!(features["derive"], (vec![""], vec![2]));
+    assert_eq!(features["default"], (vec!["std"], vec![]));
+}
+
+#[test]
+fn crate_features_enable_optional_deps() {
+    // as in tokio, whose signal feature enables the optional mio, declared
+    // without default features, only through features of it
+    let sid = source_id();
+    let mut mio = dep(sid, "mio", true);
+    mio.set_default_features(false);
+    let s = summary(
+        vec![mio, dep(sid, "libc", true), dep(sid, "windows-sys", true)],
+        &[("signal", &["libc", "mio/os-poll", "mio/net", "windows-sys/Win32_Foundation"])],
+    );
+    let features = features_of(&s, &[]);
+    assert_eq!(features["signal"].0, vec!["", "libc", "mio", "windows-sys"]);
+    let signal_deps = &features["signal"].1;
+    assert_eq!(names(signal_deps), vec!["mio", "mio", "windows-sys"]);
+    assert!(signal_deps.iter().all(|d| !d.uses_default_features()));
+    assert_eq!(names(&features["mio"].1), vec!["mio"]);
+    assert!(!features["mio"].1[0].uses_default_features());
+}
+
+#[test]
+fn crate_feature_keeps_declared_default_features() {
+    // as in chrono, where enabling a feature of the optional serde enables
+    // serde itself with its default features
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "serde", true)],
+        &[("serde-derive", &["serde", "serde/derive"]), ("rkyv", &["serde/rc"])],
+    );
+    let features = features_of(&s, &[]);
+    assert_eq!(features["serde-derive"].0, vec!["", "serde"]);
+    assert_eq!(features["rkyv"].0, vec!["", "serde"]);
+    assert!(features["serde"].1[0].uses_default_features());
+    let derive_deps = &features["serde-derive"].1;
+    assert_eq!(names(derive_deps), vec!["serde"]);
+    assert_eq!(derive_deps[0].features()[0].as_str(), "derive");
+
+    // without an implicit feature, the dependency itself comes along
+    let s = summary(vec![dep(sid, "serde", true)], &[("derive", &["serde", "serde/derive"])]);
+    let features = features_of(&s, &["serde"]);
+    assert_eq!(features["derive"].0, vec![""]);
+    let derive_deps = &features["derive"].1;
+    assert_eq!(names(derive_deps), vec!["serde", "serde"]);
+    assert!(derive_deps[0].uses_default_features());
+    assert_eq!(derive_deps[1].features()[0].as_str(), "derive");
+}
+
+#[test]
+fn selection_of_features() {
+    let sid = source_id();
+    let s = summary(
+        vec![dep(sid, "bar", false), dep(sid, "baz", true), dep(sid, "qux", true)],
+        &[("default", &["std"]), ("std", &["baz"]), ("extra", &["qux"])],
+    );
+    let mut features = features_of(&s, &[]);
+    DependencySelection::default().apply(&mut features).unwrap();
+    assert_eq!(features.keys().cloned().collect::<Vec<_>>(), vec!["", "baz", "default", "extra", "qux", "std"]);
+
+    let selection = DependencySelection {
+        features: Some(vec!["default".to_string(), "nonexistent".to_string()]),
+    };
+    let e = selection.apply(&mut features).unwrap_err();
+    assert_eq!(e.to_string(), "The crate has no features nonexistent");
+    // and leaves the features alone
+    assert_eq!(features.len(), 6);
+
+    let selection = DependencySelection {
+        features: Some(vec!["default".to_string()]),
+    };
+    selection.apply(&mut features).unwrap();
+    assert_eq!(features.keys().cloned().collect::<Vec<_>>(), vec!["", "baz", "default", "std"]);
+
+    let selection = DependencySelection {
+        features: Some(vec![]),
+    };
+    selection.apply(&mut features).unwrap();
+    assert_eq!(features.keys().cloned().collect::<Vec<_>>(), vec![""]);
+}
@@ -0,0 +1,55 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::fixme::{find_fixmes, FixmeKind};
+
+use std::fs;
+
+#[test]
+fn fixmes_by_kind() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("copyright"),
+        "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/\n\
+         \n\
+         Files: *\n\
+         Copyright: FIXME (overlay) UNKNOWN-YEARS Jane Doe <jane@example.org>\n\
+         License: UNKNOWN-LICENSE; FIXME (overlay)\n",
+    ).unwrap();
+    fs::write(
+        dir.path().join("control.debcargo.hint"),
+        "Source: rust-foo\n\
+         \n\
+         # FIXME (summary) crate has no description, set summary and description in debcargo.toml\n",
+    ).unwrap();
+
+    let mut fixmes = find_fixmes(dir.path()).unwrap();
+    fixmes.sort_by_key(|f| (f.file.clone(), f.line));
+    let found = fixmes
+        .iter()
+        .map(|f| (f.file.file_name().unwrap().to_str().unwrap(), f.line, f.kind, f.hint))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![
+            ("control.debcargo.hint", 3, FixmeKind::Description, true),
+            ("copyright", 4, FixmeKind::CopyrightYears, false),
+            ("copyright", 5, FixmeKind::License, false),
+        ]
+    );
+    assert_eq!(fixmes[2].text, "License: UNKNOWN-LICENSE; FIXME (overlay)");
+
+    assert_eq!(
+        FixmeKind::of("rust-foo (1.0.0-1) UNRELEASED-FIXME-AUTOGENERATED-DEBCARGO; urgency=medium"),
+        FixmeKind::Distribution
+    );
+    assert_eq!(
+        FixmeKind::of("# FIXME (packages.\"(name)\".section) debcargo auto-generated summaries are very long"),
+        FixmeKind::Description
+    );
+    assert_eq!(FixmeKind::of("Section: FIXME-(source.section)"), FixmeKind::Section);
+    assert_eq!(
+        FixmeKind::of("# FIXME: no longer applies to 1.1.0, rebase or drop it:"),
+        FixmeKind::Patch
+    );
+}
@@ -0,0 +1,84 @@
+extern crate debcargo;
+extern crate git2;
+extern crate tempfile;
+
+use debcargo::crates::{CrateInfo, GitSelector};
+use git2::{Repository, Signature};
+
+use std::fs;
+use std::path::Path;
+
+fn commit_all(repo: &Repository, message: &str) -> git2::Oid {
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::now("Upstream", "upstream@example.org").unwrap();
+    let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap()
+}
+
+fn set_version(dir: &Path, version: &str) {
+    let manifest = fs::read_to_string("tests/golden/lib-features/crate/Cargo.toml").unwrap();
+    fs::write(
+        dir.join("golden_lib/Cargo.toml"),
+        manifest.replace("version = \"0.3.1\"", &format!("version = \"{}\"", version)),
+    ).unwrap();
+}
+
+/// A workspace with golden_lib 0.3.1 tagged and on the branch "stable", and
+/// 0.3.2 on the default branch.
+fn upstream_repo(dir: &Path) -> git2::Oid {
+    let repo = Repository::init(dir).unwrap();
+    fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = [\"golden_lib\"]\n").unwrap();
+    fs::create_dir_all(dir.join("golden_lib/src")).unwrap();
+    fs::write(dir.join("golden_lib/src/lib.rs"), "").unwrap();
+    set_version(dir, "0.3.1");
+    let release = commit_all(&repo, "release");
+    let release_commit = repo.find_commit(release).unwrap();
+    repo.tag_lightweight("v0.3.1", release_commit.as_object(), false).unwrap();
+    repo.branch("stable", &release_commit, false).unwrap();
+    set_version(dir, "0.3.2");
+    commit_all(&repo, "bump");
+    release
+}
+
+#[test]
+fn selected_commits() {
+    let dir = tempfile::tempdir().unwrap();
+    let release = upstream_repo(dir.path());
+    let url = format!("file://{}", dir.path().display());
+    let load = |selector| CrateInfo::new_from_git(&url, selector, "golden_lib", None).unwrap();
+
+    let head = load(GitSelector::DefaultBranch);
+    assert_eq!(head.version().to_string(), "0.3.2");
+
+    let tagged = load(GitSelector::Tag("v0.3.1".to_string()));
+    assert_eq!(tagged.version().to_string(), "0.3.1");
+    let origin = tagged.git_origin().unwrap();
+    assert_eq!(origin.commit, release.to_string());
+    assert_eq!(
+        origin.changelog_entry(),
+        format!("Package {} from git, tag v0.3.1 at commit {}.", url, release)
+    );
+
+    let branch = load(GitSelector::Branch("stable".to_string()));
+    assert_eq!(branch.version().to_string(), "0.3.1");
+
+    let rev = load(GitSelector::Rev(release.to_string()[..8].to_string()));
+    assert_eq!(rev.git_origin().unwrap().commit, release.to_string());
+}
+
+#[test]
+fn mismatches_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    upstream_repo(dir.path());
+    let url = format!("file://{}", dir.path().display());
+    let tag = || GitSelector::Tag("v0.3.1".to_string());
+
+    assert!(CrateInfo::new_from_git(&url, tag(), "golden_lib", Some("0.3.2")).is_err());
+    assert!(CrateInfo::new_from_git(&url, tag(), "no_such_crate", None).is_err());
+    let missing = GitSelector::Tag("v9".to_string());
+    assert!(CrateInfo::new_from_git(&url, missing, "golden_lib", None).is_err());
+}
@@ -0,0 +1,4 @@
+// Copyright 2019 John Roe
+fn main() {
+    println!("golden");
+}
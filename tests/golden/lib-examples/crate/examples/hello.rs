@@ -0,0 +1,5 @@
+extern crate golden_examples;
+
+fn main() {
+    println!("{}", golden_examples::greeting("world"));
+}
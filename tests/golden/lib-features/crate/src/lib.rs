@@ -0,0 +1,5 @@
+//! Copyright 2019 Jane Doe
+
+pub fn answer() -> u32 {
+    42
+}
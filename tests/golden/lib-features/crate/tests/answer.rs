@@ -0,0 +1,6 @@
+extern crate golden_lib;
+
+#[test]
+fn answer() {
+    assert_eq!(golden_lib::answer(), 42);
+}
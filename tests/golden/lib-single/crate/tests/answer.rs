@@ -0,0 +1,6 @@
+extern crate golden_single;
+
+#[test]
+fn answer() {
+    assert_eq!(golden_single::answer(), 42);
+}
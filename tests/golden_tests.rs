@@ -0,0 +1,23 @@
+extern crate debcargo;
+
+use debcargo::selftest::{find_cases, run_case};
+
+use std::path::Path;
+
+/// Regenerates the packaging of the crates in tests/golden and compares it
+/// with the expected output there. After intended changes to the generated
+/// files, update it with `debcargo selftest --bless`.
+#[test]
+fn golden_outputs() {
+    let cases = find_cases(Path::new("tests/golden")).unwrap();
+    assert!(!cases.is_empty());
+    let mut failed = Vec::new();
+    for case in &cases {
+        let differences = run_case(case, false).unwrap();
+        if !differences.is_empty() {
+            eprintln!("{}:\n{}", case.name, differences.join("\n"));
+            failed.push(case.name.as_str());
+        }
+    }
+    assert!(failed.is_empty(), "golden output differs for {:?}", failed);
+}
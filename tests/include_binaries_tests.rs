@@ -0,0 +1,45 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::debian::{is_binary, update_include_binaries};
+
+use std::fs;
+
+#[test]
+fn binary_detection() {
+    assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+    assert!(!is_binary("plain text, even with ünïcode\n".as_bytes()));
+    assert!(!is_binary(b"\xff\xfe not utf-8 but no NUL"));
+}
+
+#[test]
+fn include_binaries_is_extended() {
+    let dir = tempfile::tempdir().unwrap();
+    let debian = dir.path();
+    fs::create_dir_all(debian.join("tests/data")).unwrap();
+    fs::create_dir_all(debian.join("source")).unwrap();
+    fs::write(debian.join("control"), "Source: rust-foo\n").unwrap();
+    fs::write(debian.join("tests/data/b.bin"), b"\0\x01").unwrap();
+    fs::write(debian.join("tests/data/a.png"), b"PNG\0").unwrap();
+    fs::write(debian.join("logo.ico"), b"\0\0\x01\0").unwrap();
+    fs::write(debian.join("source/include-binaries"), "debian/logo.ico").unwrap();
+
+    assert_eq!(
+        update_include_binaries(debian).unwrap(),
+        vec!["debian/tests/data/a.png", "debian/tests/data/b.bin"]
+    );
+    assert_eq!(
+        fs::read_to_string(debian.join("source/include-binaries")).unwrap(),
+        "debian/logo.ico\ndebian/tests/data/a.png\ndebian/tests/data/b.bin\n"
+    );
+    // nothing more to add the second time
+    assert!(update_include_binaries(debian).unwrap().is_empty());
+}
+
+#[test]
+fn no_include_binaries_without_binaries() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("rules"), "#!/usr/bin/make -f\n").unwrap();
+    assert!(update_include_binaries(dir.path()).unwrap().is_empty());
+    assert!(!dir.path().join("source/include-binaries").exists());
+}
@@ -0,0 +1,32 @@
+extern crate cargo;
+extern crate debcargo;
+extern crate tempfile;
+
+use cargo::core::Shell;
+use cargo::Config;
+use debcargo::crates::{crates_io_mirror, crates_io_source_id, INDEX_URL_ENV};
+
+use std::env;
+use std::fs;
+
+#[test]
+fn index_url_takes_precedence() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+    fs::create_dir_all(dir.path().join("home")).unwrap();
+    fs::write(
+        dir.path().join(".cargo").join("config"),
+        "[source.crates-io]\nreplace-with = \"corp\"\n\n\
+         [source.corp]\nregistry = \"https://corp.example.org/index\"\n",
+    ).unwrap();
+    let config = Config::new(Shell::new(), dir.path().to_path_buf(), dir.path().join("home"));
+    assert!(crates_io_mirror(&config).unwrap().is_some());
+
+    env::set_var(INDEX_URL_ENV, "https://proxy.example.org/index");
+    let source_id = crates_io_source_id(&config).unwrap();
+    let mirror = crates_io_mirror(&config).unwrap();
+    env::remove_var(INDEX_URL_ENV);
+
+    assert_eq!(source_id.url().as_str(), "https://proxy.example.org/index");
+    assert!(mirror.is_none());
+}
@@ -0,0 +1,22 @@
+extern crate debcargo;
+
+use debcargo::debian::itp::{closes_item, itp_bugs, wnpp_query_url};
+
+#[test]
+fn bugs() {
+    let html = r#"
+<li><a href="bugreport.cgi?bug=912345">#912345: ITP: rust-foo -- Foo for Rust - Rust source code</a>
+<li><a href="bugreport.cgi?bug=912300">#912300: ITP: rust-foo-bar -- Foo bar - Rust source code</a>
+<li><a href="bugreport.cgi?bug=912001">#912001: RFP: rust-foo -- Foo for Rust</a>
+<li><a href="bugreport.cgi?bug=912999">#912999: ITP: Rust-Foo -- Foo for Rust, again</a>
+"#;
+    assert_eq!(itp_bugs(html, "rust-foo"), vec![912345, 912999]);
+    assert_eq!(itp_bugs(html, "rust-foo-bar"), vec![912300]);
+    assert_eq!(itp_bugs(html, "rust-baz"), Vec::<u32>::new());
+}
+
+#[test]
+fn changelog_item() {
+    assert_eq!(closes_item(912345), "Initial release (Closes: #912345)");
+    assert!(wnpp_query_url("rust-foo").contains("package=wnpp;include=subject:rust-foo"));
+}
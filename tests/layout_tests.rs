@@ -0,0 +1,34 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::layout::ConfLayout;
+
+use std::fs;
+
+#[test]
+fn conf_packagings() {
+    let dir = tempfile::tempdir().unwrap();
+    let layout = ConfLayout::new(dir.path());
+    assert!(layout.packaging_or_new("rand", None).is_err());
+
+    fs::create_dir_all(dir.path().join("src/rand-0.7/debian")).unwrap();
+    fs::write(dir.path().join("src/rand-0.7/debian/debcargo.toml"), "overlay = \".\"\nsemver_suffix = true\n").unwrap();
+    let packaging = layout.packaging("rand", Some("=0.7.3"));
+    assert_eq!(packaging.name, "rand-0.7");
+    assert_eq!(packaging.config, dir.path().join("src/rand-0.7/debian/debcargo.toml"));
+    assert_eq!(packaging.build_dir, dir.path().join("build/rand-0.7"));
+    assert_eq!(layout.packaging("rand", Some("0.7")).name, "rand-0.7");
+    // no packaging of that suffix, nor a version
+    assert_eq!(layout.packaging("rand", Some("0.8")).name, "rand");
+    assert_eq!(layout.packaging("rand", None).name, "rand");
+    assert_eq!(layout.packaging("lazy_static", Some(">= 1")).name, "lazy-static");
+
+    let new = layout.packaging_or_new("Serde_json", Some("1")).unwrap();
+    assert_eq!(new.name, "serde-json");
+    assert_eq!(fs::read_to_string(&new.config).unwrap(), "overlay = \".\"\n");
+    assert!(dir.path().join("build").is_dir());
+    // left alone once there
+    fs::write(&new.config, "overlay = \".\"\nbin = false\n").unwrap();
+    let again = layout.packaging_or_new("serde_json", None).unwrap();
+    assert_eq!(fs::read_to_string(&again.config).unwrap(), "overlay = \".\"\nbin = false\n");
+}
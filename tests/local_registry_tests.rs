@@ -0,0 +1,118 @@
+extern crate debcargo;
+extern crate flate2;
+extern crate tar;
+extern crate tempfile;
+
+use debcargo::crates::{local_registry, similar_crate_names, CrateInfo, LOCAL_REGISTRY_ENV};
+use debcargo::verify::sha256_hex;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Builder, Header};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn append_file(builder: &mut Builder<GzEncoder<fs::File>>, path: &str, data: &str) {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+}
+
+/// Add a crate with just a Cargo.toml and src/lib.rs to a local registry.
+fn publish(registry: &Path, name: &str, version: &str) {
+    let crate_path = registry.join(format!("{}-{}.crate", name, version));
+    let mut builder = Builder::new(GzEncoder::new(
+        fs::File::create(&crate_path).unwrap(),
+        Compression::default(),
+    ));
+    let top = format!("{}-{}", name, version);
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"{}\"\nauthors = [\"A <a@example.org>\"]\n\
+         description = \"A test crate\"\nlicense = \"MIT\"\n",
+        name, version
+    );
+    append_file(&mut builder, &format!("{}/Cargo.toml", top), &manifest);
+    append_file(&mut builder, &format!("{}/src/lib.rs", top), "pub fn f() {}\n");
+    builder.into_inner().unwrap().finish().unwrap();
+
+    // only for names of 4 or more characters, see the index layout
+    let index = registry.join("index").join(&name[0..2]).join(&name[2..4]);
+    fs::create_dir_all(&index).unwrap();
+    let line = format!(
+        "{{\"name\":\"{}\",\"vers\":\"{}\",\"deps\":[],\"cksum\":\"{}\",\"features\":{{}},\"yanked\":false}}\n",
+        name,
+        version,
+        sha256_hex(&crate_path).unwrap()
+    );
+    let index_file = index.join(name);
+    let mut entries = fs::read_to_string(&index_file).unwrap_or_default();
+    entries.push_str(&line);
+    fs::write(index_file, entries).unwrap();
+}
+
+// One test, since it sets environment variables for the whole process.
+#[test]
+fn crates_io_lookups_use_local_registry() {
+    let cargo_home = tempfile::tempdir().unwrap();
+    let registry = tempfile::tempdir().unwrap();
+    env::set_var("CARGO_HOME", cargo_home.path());
+
+    env::set_var(LOCAL_REGISTRY_ENV, registry.path());
+    assert!(local_registry().is_err());
+
+    publish(registry.path(), "fakecrate", "0.1.0");
+    publish(registry.path(), "fakecrate", "0.2.0");
+    // published out of order, as a point release of an older series would be
+    publish(registry.path(), "fakecrate", "0.1.1");
+    assert_eq!(
+        local_registry().unwrap(),
+        Some(registry.path().canonicalize().unwrap())
+    );
+
+    let latest = CrateInfo::new_from_crates_io("fakecrate", None, false).unwrap();
+    assert_eq!(latest.version().to_string(), "0.2.0");
+    assert!(latest.is_lib());
+    assert_eq!(
+        latest.crate_file().path(),
+        registry.path().canonicalize().unwrap().join("fakecrate-0.2.0.crate")
+    );
+
+    let candidates = latest.version_candidates().iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(candidates, vec!["0.2.0", "0.1.1", "0.1.0"]);
+
+    let older = CrateInfo::new_from_crates_io("fakecrate", Some("0.1.0"), false).unwrap();
+    assert_eq!(older.version().to_string(), "0.1.0");
+
+    let older = CrateInfo::new_from_crates_io("fakecrate", Some("0.1"), false).unwrap();
+    assert_eq!(older.version().to_string(), "0.1.1");
+    let candidates = older.version_candidates().iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(candidates, vec!["0.1.1", "0.1.0"]);
+
+    assert!(CrateInfo::new_from_crates_io("fakecrate", Some("1.0.0"), false).is_err());
+    assert!(CrateInfo::new_from_crates_io("otherfake", None, false).is_err());
+    let typo = CrateInfo::new_from_crates_io("fake_crat", None, false).err().unwrap();
+    assert!(typo.to_string().contains("Did you mean: fakecrate?"));
+
+    publish(registry.path(), "gonecrate", "1.0.0");
+    fs::remove_file(registry.path().join("gonecrate-1.0.0.crate")).unwrap();
+    let gone = CrateInfo::new_from_crates_io("gonecrate", None, false).err().unwrap();
+    assert!(gone.to_string().starts_with("gonecrate-1.0.0.crate was expected at "));
+
+    env::remove_var(LOCAL_REGISTRY_ENV);
+    assert_eq!(local_registry().unwrap(), None);
+}
+
+#[test]
+fn similar_names_are_suggested() {
+    let names = vec!["serde", "serde_json", "serde-json-core", "sered", "rand", "Inflector"];
+    let similar = |name| similar_crate_names(name, names.iter().cloned());
+    assert_eq!(similar("serde-json"), vec!["serde_json"]);
+    assert_eq!(similar("sedre"), vec!["serde"]);
+    assert_eq!(similar("inflector"), vec!["Inflector"]);
+    assert!(similar("tokio").is_empty());
+    // not itself
+    assert_eq!(similar("rand"), Vec::<String>::new());
+}
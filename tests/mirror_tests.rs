@@ -0,0 +1,115 @@
+extern crate cargo;
+extern crate debcargo;
+extern crate semver;
+extern crate tempfile;
+
+use cargo::core::Shell;
+use cargo::Config;
+use debcargo::crates::{crate_download_url, crates_io_mirror, registry_token, Mirror};
+use semver::Version;
+
+use std::fs;
+use std::path::Path;
+
+/// A cargo config as cargo would read it in `dir`, from `dir/.cargo/config`.
+fn cargo_config(dir: &Path, contents: &str) -> Config {
+    fs::create_dir_all(dir.join(".cargo")).unwrap();
+    fs::create_dir_all(dir.join("home")).unwrap();
+    fs::write(dir.join(".cargo").join("config"), contents).unwrap();
+    Config::new(Shell::new(), dir.to_path_buf(), dir.join("home"))
+}
+
+#[test]
+fn no_replacement() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let config = cargo_config(dir.path(), "[build]\njobs = 1\n");
+    assert!(crates_io_mirror(&config).unwrap().is_none());
+}
+
+#[test]
+fn replacement_chains_are_followed() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let config = cargo_config(
+        dir.path(),
+        r#"
+[source.crates-io]
+replace-with = "corp"
+
+[source.corp]
+replace-with = "vendored"
+
+[source.vendored]
+directory = "vendor"
+"#,
+    );
+    match crates_io_mirror(&config).unwrap() {
+        Some((ref name, Mirror::Directory(ref path))) => {
+            assert_eq!(name, "vendored");
+            assert_eq!(path, &dir.path().join("vendor"));
+        }
+        _ => panic!("expected the vendored directory"),
+    }
+}
+
+#[test]
+fn registry_mirror() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let config = cargo_config(
+        dir.path(),
+        r#"
+[source.crates-io]
+replace-with = "mirror"
+
+[source.mirror]
+registry = "https://mirror.example.org/index"
+"#,
+    );
+    match crates_io_mirror(&config).unwrap() {
+        Some((_, Mirror::Registry(id))) => {
+            assert_eq!(id.url().as_str(), "https://mirror.example.org/index")
+        }
+        _ => panic!("expected a registry"),
+    }
+}
+
+#[test]
+fn replacement_cycles_are_errors() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let config = cargo_config(
+        dir.path(),
+        r#"
+[source.crates-io]
+replace-with = "a"
+
+[source.a]
+replace-with = "crates-io"
+"#,
+    );
+    assert!(crates_io_mirror(&config).is_err());
+}
+
+#[test]
+fn tokens_from_credentials() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("home")).unwrap();
+    fs::write(
+        dir.path().join("home").join("credentials"),
+        "[registries.corp]\ntoken = \"s3cret\"\n",
+    ).unwrap();
+    let config = cargo_config(dir.path(), "[source.crates-io]\nreplace-with = \"corp\"\n");
+    assert_eq!(registry_token(&config, "corp").unwrap(), Some("s3cret".to_string()));
+    assert_eq!(registry_token(&config, "other").unwrap(), None);
+}
+
+#[test]
+fn download_urls() {
+    let version = Version::parse("1.2.3").unwrap();
+    assert_eq!(
+        crate_download_url("https://crates.example.org/api/v1/crates", "foo", &version),
+        "https://crates.example.org/api/v1/crates/foo/1.2.3/download"
+    );
+    assert_eq!(
+        crate_download_url("https://dl.example.org/{crate}-{version}.crate", "foo", &version),
+        "https://dl.example.org/foo-1.2.3.crate"
+    );
+}
@@ -0,0 +1,108 @@
+extern crate debcargo;
+extern crate semver;
+extern crate tempfile;
+
+use debcargo::debian::control::PackageNaming;
+use debcargo::outdated::{affected_reverse_dependencies, find_packagings, newest_update,
+                         packaged_crate, Packaging};
+use semver::{Version, VersionReq};
+
+use std::fs;
+use std::path::PathBuf;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+fn packaging(dir: &str, name: &str, version: &str, semver_suffix: bool) -> Packaging {
+    Packaging {
+        dir: PathBuf::from(dir),
+        crate_name: name.to_string(),
+        version: v(version),
+        semver_suffix: semver_suffix,
+        naming: PackageNaming::default(),
+    }
+}
+
+const CHANGELOG: &str = "\
+rust-rand (0.8.5-1) unstable; urgency=medium
+
+  * Team upload.
+  * Package rand 0.8.5 from crates.io using debcargo 2.6.0
+
+ -- Jane Doe <jane@example.org>  Mon, 01 Jan 2024 00:00:00 +0000
+
+rust-rand (0.8.4-1) unstable; urgency=medium
+
+  * Package rand 0.8.4 from crates.io using debcargo 2.5.0
+
+ -- Jane Doe <jane@example.org>  Mon, 01 Jan 2023 00:00:00 +0000
+";
+
+#[test]
+fn packaged_crate_from_changelog() {
+    assert_eq!(packaged_crate(CHANGELOG), Some(("rand".to_string(), v("0.8.5"))));
+    assert_eq!(packaged_crate("rust-foo (1.0-1) unstable; urgency=medium\n\n  * Manual.\n"), None);
+}
+
+#[test]
+fn newest_update_respects_semver_suffix() {
+    let available = vec![v("0.7.3"), v("0.8.4"), v("0.8.5"), v("0.9.0-alpha.1"), v("0.9.1")];
+    assert_eq!(newest_update(&v("0.8.4"), false, &available), Some(v("0.9.1")));
+    assert_eq!(newest_update(&v("0.8.4"), true, &available), Some(v("0.8.5")));
+    assert_eq!(newest_update(&v("0.9.1"), false, &available), None);
+    assert_eq!(newest_update(&v("0.7.3"), true, &available), None);
+}
+
+#[test]
+fn reverse_dependencies_affected_by_update() {
+    let packagings = vec![
+        packaging("rand", "rand", "0.8.5", false),
+        packaging("rand-0.7", "rand", "0.7.3", true),
+        packaging("foo", "foo", "1.0.0", false),
+        packaging("bar", "bar", "2.0.0", false),
+        packaging("baz", "baz", "0.1.0", false),
+    ];
+    let req = |s: &str| VersionReq::parse(s).unwrap();
+    let dependencies = vec![
+        vec![],
+        vec![],
+        vec![("rand".to_string(), req("^0.8"))],
+        vec![("rand".to_string(), req("^0.7"))],
+        vec![("rand".to_string(), req("^0.9"))],
+    ];
+    assert_eq!(
+        affected_reverse_dependencies(&packagings[0], &v("0.9.1"), &packagings, &dependencies),
+        vec!["foo 1.0.0 (needs rand ^0.8)".to_string()]
+    );
+    assert!(
+        affected_reverse_dependencies(&packagings[0], &v("0.8.6"), &packagings, &dependencies)
+            .is_empty()
+    );
+}
+
+#[test]
+fn find_packagings_in_checkout() {
+    let tmp = tempfile::tempdir().unwrap();
+    let rand = tmp.path().join("src/rand/debian");
+    fs::create_dir_all(&rand).unwrap();
+    fs::write(rand.join("changelog"), CHANGELOG).unwrap();
+    let old = tmp.path().join("src/rand-0.7/debian");
+    fs::create_dir_all(&old).unwrap();
+    fs::write(
+        old.join("changelog"),
+        CHANGELOG.replace("0.8.5", "0.7.3").replace("rust-rand (", "rust-rand-0.7 ("),
+    ).unwrap();
+    fs::write(old.join("debcargo.toml"), "semver_suffix = true\n").unwrap();
+    let unrelated = tmp.path().join("src/manual/debian");
+    fs::create_dir_all(&unrelated).unwrap();
+    fs::write(unrelated.join("changelog"), "rust-manual (1.0-1) unstable; urgency=medium\n").unwrap();
+
+    let found = find_packagings(&tmp.path().join("src")).unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!((found[0].crate_name.as_str(), found[0].version.to_string()), ("rand", "0.8.5".to_string()));
+    assert!(!found[0].semver_suffix);
+    assert_eq!(found[1].version, v("0.7.3"));
+    assert!(found[1].semver_suffix);
+    assert_eq!(found[1].dir, tmp.path().join("src/rand-0.7"));
+}
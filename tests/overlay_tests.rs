@@ -0,0 +1,36 @@
+extern crate debcargo;
+extern crate tempfile;
+
+use debcargo::debian::write_back_overlay;
+
+use std::fs;
+
+#[test]
+fn write_back_creates_patches_dir() {
+    let debian_dir = tempfile::tempdir().unwrap();
+    let overlay = tempfile::tempdir().unwrap();
+    fs::create_dir_all(debian_dir.path().join("patches")).unwrap();
+    fs::write(debian_dir.path().join("patches/series"), "relax.patch\n").unwrap();
+    fs::write(debian_dir.path().join("patches/relax.patch"), "--- a\n+++ b\n").unwrap();
+    fs::write(debian_dir.path().join("changelog"), "rust-foo (1.0.0-1) UNRELEASED\n").unwrap();
+    fs::write(overlay.path().join("copyright"), "Format: x\n").unwrap();
+
+    let hints = vec![
+        "patches/series".to_string(),
+        "patches/relax.patch".to_string(),
+        "changelog".to_string(),
+    ];
+    write_back_overlay(debian_dir.path(), overlay.path(), &hints).unwrap();
+    assert_eq!(fs::read_to_string(overlay.path().join("patches/series")).unwrap(), "relax.patch\n");
+    assert_eq!(fs::read_to_string(overlay.path().join("patches/relax.patch")).unwrap(), "--- a\n+++ b\n");
+    assert!(overlay.path().join("changelog").is_file());
+    assert!(overlay.path().join("copyright").is_file());
+}
+
+#[test]
+fn write_back_missing_file_is_an_error() {
+    let debian_dir = tempfile::tempdir().unwrap();
+    let overlay = tempfile::tempdir().unwrap();
+    let err = write_back_overlay(debian_dir.path(), overlay.path(), &["patches/series".to_string()]).unwrap_err();
+    assert!(err.to_string().contains("patches/series"), "{}", err);
+}
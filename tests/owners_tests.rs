@@ -0,0 +1,19 @@
+extern crate debcargo;
+
+use debcargo::crates::owner_names;
+
+#[test]
+fn users_are_named() {
+    let json = r#"{"users": [
+        {"id": 1, "login": "jdoe", "kind": "user", "name": "Jane Doe"},
+        {"id": 2, "login": "rsmith", "kind": "user", "name": null},
+        {"id": 3, "login": "github:example:maintainers", "kind": "team", "name": "maintainers"}
+    ]}"#;
+    assert_eq!(owner_names(json).unwrap(), vec!["Jane Doe", "rsmith"]);
+}
+
+#[test]
+fn unexpected_replies_are_errors() {
+    assert!(owner_names(r#"{"errors": [{"detail": "Not Found"}]}"#).is_err());
+    assert!(owner_names("<html>").is_err());
+}
@@ -0,0 +1,146 @@
+extern crate debcargo;
+
+use debcargo::debian::patches::{dev_dependency_lines, line_patch, mark_failed_patches, parse_relax_rule,
+                                 parse_series, push_was_inexact, relaxed_dependency_lines, PatchStatus};
+
+#[test]
+fn series_names() {
+    let series = "\
+# relax deps
+relax-deps.patch
+disable-net-tests.diff -p1
+  
+fix-build.patch # upstream #123
+";
+    assert_eq!(
+        parse_series(series),
+        vec!["relax-deps.patch", "disable-net-tests.diff", "fix-build.patch"]
+    );
+}
+
+#[test]
+fn inexact_pushes() {
+    assert!(!push_was_inexact("Applying patch relax-deps.patch\npatching file Cargo.toml\n\nNow at patch relax-deps.patch\n"));
+    assert!(push_was_inexact("Applying patch relax-deps.patch\npatching file Cargo.toml\nHunk #1 succeeded at 30 (offset 2 lines).\n"));
+    assert!(push_was_inexact("Applying patch x.patch\npatching file src/lib.rs\nHunk #2 succeeded at 10 with fuzz 1.\n"));
+}
+
+#[test]
+fn failed_patches_are_marked() {
+    let series = "\
+relax-deps.patch
+# FIXME: no longer applies to 1.0.0, rebase or drop it:
+fix-build.patch
+disable-net-tests.diff -p1
+";
+    let results = vec![
+        ("relax-deps.patch".to_string(), PatchStatus::Refreshed),
+        ("fix-build.patch".to_string(), PatchStatus::Applied),
+        ("disable-net-tests.diff".to_string(), PatchStatus::Failed("1 out of 1 hunk FAILED".to_string())),
+    ];
+    let marked = mark_failed_patches(series, &results, "1.1.0");
+    assert_eq!(marked, "\
+relax-deps.patch
+fix-build.patch
+# FIXME: no longer applies to 1.1.0, rebase or drop it:
+disable-net-tests.diff -p1
+");
+    assert_eq!(parse_series(&marked), parse_series(series));
+}
+
+const MANIFEST: &str = "\
+[package]
+name = \"foo\"
+version = \"1.0.0\"
+
+[dependencies.bar]
+version = \"1\"
+
+[dev-dependencies.criterion]
+version = \"0.3\"
+
+[dev-dependencies.proptest]
+version = \"0.9\"
+features = [\"std\"]
+
+[dev-dependencies.tempfile]
+version = \"3\"
+
+[target.\"cfg(unix)\".dev-dependencies]
+nix = \"0.17\"
+libc = \"0.2\"
+";
+
+#[test]
+fn dev_dependencies_are_found() {
+    assert_eq!(dev_dependency_lines(MANIFEST, &["proptest", "nix"]), vec![10, 11, 12, 13, 18]);
+    assert_eq!(dev_dependency_lines(MANIFEST, &["bar"]), Vec::<usize>::new());
+    assert_eq!(dev_dependency_lines("[dev-dependencies]\n\"criterion\" = { version = \"0.3\" }\n", &["criterion"]), vec![1]);
+}
+
+#[test]
+fn line_patches() {
+    let lines = dev_dependency_lines(MANIFEST, &["criterion", "libc"]);
+    let edits = lines.into_iter().map(|l| (l, None)).collect::<Vec<_>>();
+    let patch = line_patch("Cargo.toml", MANIFEST, &edits, "Description: drop\n---");
+    assert_eq!(patch, "\
+Description: drop
+---
+--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -5,9 +5,6 @@
+ [dependencies.bar]
+ version = \"1\"
+ 
+-[dev-dependencies.criterion]
+-version = \"0.3\"
+-
+ [dev-dependencies.proptest]
+ version = \"0.9\"
+ features = [\"std\"]
+@@ -17,4 +14,3 @@
+ 
+ [target.\"cfg(unix)\".dev-dependencies]
+ nix = \"0.17\"
+-libc = \"0.2\"
+");
+}
+
+#[test]
+fn relaxed_dependencies() {
+    assert_eq!(parse_relax_rule(" serde >= 1 ").unwrap(), ("serde".to_string(), ">= 1".to_string()));
+    assert!(parse_relax_rule("serde").is_err());
+    assert!(parse_relax_rule("serde latest").is_err());
+
+    assert_eq!(relaxed_dependency_lines(MANIFEST, "bar", ">= 0.9"), vec![(5, "version = \">= 0.9\"".to_string())]);
+    assert_eq!(relaxed_dependency_lines(MANIFEST, "nix", "0.16"), vec![(18, "nix = \"0.16\"".to_string())]);
+    // already relaxed
+    assert!(relaxed_dependency_lines(MANIFEST, "bar", "1").is_empty());
+    assert!(relaxed_dependency_lines(MANIFEST, "missing", "1").is_empty());
+    let inline = "[dependencies]\nrand = { version = \"0.8.5\", features = [\"std\"] }\n";
+    assert_eq!(
+        relaxed_dependency_lines(inline, "rand", "^0.8"),
+        vec![(1, "rand = { version = \"^0.8\", features = [\"std\"] }".to_string())]
+    );
+
+    let edits = vec![(5, Some("version = \"0.9\"".to_string())), (8, None), (9, None)];
+    assert_eq!(line_patch("Cargo.toml", MANIFEST, &edits, "Description: relax\n---"), "\
+Description: relax
+---
+--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -3,11 +3,9 @@
+ version = \"1.0.0\"
+ 
+ [dependencies.bar]
+-version = \"1\"
++version = \"0.9\"
+ 
+ [dev-dependencies.criterion]
+-version = \"0.3\"
+-
+ [dev-dependencies.proptest]
+ version = \"0.9\"
+ features = [\"std\"]
+");
+}
@@ -0,0 +1,217 @@
+extern crate cargo;
+extern crate debcargo;
+extern crate tempfile;
+
+use cargo::core::Dependency;
+use debcargo::config::Config;
+use debcargo::crates::{suspicious_files_report, CrateInfo, DependencySelection};
+use debcargo::debian::deb_deps;
+use debcargo::errors::ErrorKind;
+
+use std::fs;
+
+#[test]
+fn source_directory_without_crate_file() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let crate_dir = dir.path().join("golden_lib");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::copy(
+        "tests/golden/lib-features/crate/Cargo.toml",
+        crate_dir.join("Cargo.toml"),
+    ).unwrap();
+    fs::copy(
+        "tests/golden/lib-features/crate/src/lib.rs",
+        crate_dir.join("src/lib.rs"),
+    ).unwrap();
+
+    let crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+    assert_eq!(crate_info.package_id().name().as_str(), "golden_lib");
+    assert!(!crate_dir.join("target").exists());
+
+    let srcdir = dir.path().join("rust-golden-lib-0.3.1");
+    crate_info.extract_crate(&srcdir).unwrap();
+    assert!(srcdir.join("src/lib.rs").is_file());
+    assert!(srcdir.join("Cargo.toml.orig").is_file());
+    let manifest = fs::read_to_string(srcdir.join("Cargo.toml")).unwrap();
+    assert!(manifest.starts_with("# THIS FILE IS AUTOMATICALLY GENERATED BY CARGO"));
+}
+
+#[test]
+fn vendored_copy() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let vendor_dir = dir.path().join("vendor");
+    let manifest = fs::read_to_string("tests/golden/lib-features/crate/Cargo.toml").unwrap();
+    for version in &["0.2.0", "0.3.1", "0.3.4"] {
+        let crate_dir = vendor_dir.join(format!("golden_lib-{}", version));
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            manifest.replace("version = \"0.3.1\"", &format!("version = \"{}\"", version)),
+        ).unwrap();
+        fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+        fs::write(crate_dir.join(".cargo-checksum.json"), "{\"files\":{}}").unwrap();
+    }
+    // not the crate we want, despite the prefix
+    fs::create_dir_all(vendor_dir.join("golden_lib-derive")).unwrap();
+
+    let crate_info = CrateInfo::new_from_vendor_dir(&vendor_dir, "golden_lib", Some("0.3")).unwrap();
+    assert_eq!(crate_info.version().to_string(), "0.3.4");
+    let candidates = crate_info
+        .version_candidates()
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(candidates, vec!["0.3.4", "0.3.1"]);
+
+    let srcdir = dir.path().join("rust-golden-lib-0.3.4");
+    crate_info.extract_crate(&srcdir).unwrap();
+    assert!(srcdir.join("src/lib.rs").is_file());
+    assert!(!srcdir.join(".cargo-checksum.json").exists());
+
+    assert!(CrateInfo::new_from_vendor_dir(&vendor_dir, "golden_lib", Some("1")).is_err());
+}
+
+#[test]
+fn suspicious_files_can_be_allowed() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let crate_dir = dir.path().join("golden_lib");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::copy(
+        "tests/golden/lib-features/crate/Cargo.toml",
+        crate_dir.join("Cargo.toml"),
+    ).unwrap();
+    fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+    fs::write(crate_dir.join("src/shim.c"), "int f(void) { return 0; }\n").unwrap();
+
+    let crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+    let srcdir = dir.path().join("rust-golden-lib-0.3.1");
+    let e = crate_info.extract_crate(&srcdir).unwrap_err();
+    assert_eq!(ErrorKind::of(&e), Some(ErrorKind::SuspiciousFiles));
+    assert!(!srcdir.exists());
+
+    let mut crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+    crate_info.set_allow_suspicious(true);
+    crate_info.extract_crate(&srcdir).unwrap();
+    assert!(srcdir.join("src/shim.c").is_file());
+    let report = fs::read_to_string(suspicious_files_report(&srcdir)).unwrap();
+    assert_eq!(report.lines().nth(4), Some("    \"src/shim.c\","));
+    assert!(report.ends_with("# Files-Excluded: src/shim.c\n"));
+}
+
+fn reqs(deps: &[Dependency]) -> Vec<String> {
+    deps.iter().map(|d| format!("{} {}", d.package_name(), d.version_req())).collect()
+}
+
+#[test]
+fn locked_dependencies_are_pinned() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let crate_dir = dir.path().join("golden_lib");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::copy("tests/golden/lib-features/crate/Cargo.toml", crate_dir.join("Cargo.toml")).unwrap();
+    fs::copy("tests/golden/lib-features/crate/src/lib.rs", crate_dir.join("src/lib.rs")).unwrap();
+    let mut crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+
+    // serde is not in the lockfile, and libc 0.1 doesn't satisfy the dependency
+    let lockfile = dir.path().join("Cargo.lock");
+    fs::write(
+        &lockfile,
+        "[[package]]\nname = \"libc\"\nversion = \"0.1.12\"\n\n\
+         [[package]]\nname = \"libc\"\nversion = \"0.2.43\"\n\n\
+         [[package]]\nname = \"tempfile\"\nversion = \"3.0.4\"\n",
+    ).unwrap();
+    crate_info.set_lockfile(&lockfile).unwrap();
+
+    assert_eq!(reqs(&crate_info.packaged_dependencies()), vec!["libc = 0.2.43", "serde ^1.0", "tempfile = 3.0.4"]);
+    assert_eq!(reqs(&crate_info.dev_dependencies()), vec!["tempfile = 3.0.4"]);
+    let features = crate_info.all_dependencies_and_features(&DependencySelection::default()).unwrap();
+    assert_eq!(reqs(&features[""].1), vec!["libc = 0.2.43"]);
+    assert_eq!(reqs(&features["serde"].1), vec!["serde ^1.0"]);
+    assert_eq!(deb_deps(&Config::default(), &features[""].1).unwrap(), vec!["librust-libc-0.2.43+default-dev"]);
+
+    fs::write(&lockfile, "[[package]]\nname = \"libc\"\nversion = \"zero\"\n").unwrap();
+    let e = crate_info.set_lockfile(&lockfile).unwrap_err();
+    assert!(e.to_string().contains("bad version zero for libc"), "{}", e);
+}
+
+#[test]
+fn malformed_manifests_are_errors() {
+    let manifests = [
+        "",
+        "[package]",
+        "[package]\nname = \"\"\nversion = \"0.1.0\"",
+        "[package]\nname = \"foo\"\nversion = \"one\"",
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n[features]\na = [\"nope\"]",
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n[features]\na = [\"nope/x\"]",
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n[features]\na = [\"dep:\"]",
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n[features]\na = [\"t/x\"]\n[dev-dependencies]\nt = \"1\"",
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n[dependencies]\nt = \">=1.0.0-alpha\"",
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n[dependencies]\nt = \"^^1\"",
+    ];
+    let config = Config::default();
+    for contents in manifests.iter() {
+        let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+        let crate_dir = dir.path().join("foo");
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+        fs::write(crate_dir.join("Cargo.toml"), contents).unwrap();
+        let mut crate_info = match CrateInfo::new_from_path(&crate_dir, None, false) {
+            Ok(crate_info) => crate_info,
+            Err(_) => continue,
+        };
+        for (_, &(_, ref deps)) in crate_info.all_dependencies_and_features(&DependencySelection::default()).unwrap().iter() {
+            let _ = deb_deps(&config, deps);
+        }
+        assert!(crate_info.set_includes_excludes(Some(&vec!["[".to_string()]), None).is_err());
+    }
+}
+
+#[test]
+fn build_script_analysis() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let crate_dir = dir.path().join("golden_lib");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    let manifest = fs::read_to_string("tests/golden/lib-features/crate/Cargo.toml").unwrap();
+    fs::write(crate_dir.join("Cargo.toml"), manifest.replacen("[package]", "[package]\nlinks = \"z\"", 1)).unwrap();
+    fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+
+    let crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+    assert_eq!(crate_info.analyze_build_script().unwrap(), None);
+
+    fs::write(crate_dir.join("build.rs"), "fn main() { pkg_config::probe_library(\"zlib\").unwrap(); }\n").unwrap();
+    let crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+    let report = crate_info.analyze_build_script().unwrap().unwrap();
+    assert_eq!(report.path.to_str(), Some("build.rs"));
+    assert_eq!(report.links.as_ref().map(String::as_str), Some("z"));
+    assert_eq!(report.build_depends(), vec!["pkg-config", "zlib1g-dev"]);
+}
+
+#[test]
+fn archived_crate_file() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let crate_dir = dir.path().join("golden_lib");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::copy("tests/golden/lib-features/crate/Cargo.toml", crate_dir.join("Cargo.toml")).unwrap();
+    fs::copy("tests/golden/lib-features/crate/src/lib.rs", crate_dir.join("src/lib.rs")).unwrap();
+    let archived = dir.path().join("golden_lib-0.3.1.crate");
+    {
+        let packed = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+        fs::copy(packed.crate_file().path(), &archived).unwrap();
+    }
+
+    let crate_info = CrateInfo::new_with_local_crate("golden_lib", Some("0.3"), &archived).unwrap();
+    assert_eq!(crate_info.package_id().name().as_str(), "golden_lib");
+    assert_eq!(crate_info.version().to_string(), "0.3.1");
+    assert!(crate_info.is_lib());
+    assert!(crate_info.version_candidates().is_empty());
+    assert_eq!(crate_info.crate_file().path(), archived.canonicalize().unwrap().as_path());
+
+    let srcdir = dir.path().join("rust-golden-lib-0.3.1");
+    crate_info.extract_crate(&srcdir).unwrap();
+    assert!(srcdir.join("src/lib.rs").is_file());
+
+    let e = CrateInfo::new_with_local_crate("golden_lib", Some("0.4"), &archived).err().unwrap();
+    assert!(e.to_string().contains("which does not match"));
+    assert!(CrateInfo::new_with_local_crate("other", None, &archived).is_err());
+    let e = CrateInfo::new_with_local_crate("golden_lib", None, &dir.path().join("missing.crate")).err().unwrap();
+    assert_eq!(ErrorKind::of(&e), Some(ErrorKind::CrateNotFound));
+}
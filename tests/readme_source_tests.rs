@@ -0,0 +1,32 @@
+extern crate debcargo;
+
+use debcargo::debian::{readme_source, SourceChanges};
+
+#[test]
+fn nothing_changed() {
+    assert_eq!(readme_source(&SourceChanges::default()), None);
+}
+
+#[test]
+fn all_changes() {
+    let excludes = vec!["data/*".to_string()];
+    let unpackageable = vec!["simd".to_string()];
+    let changes = SourceChanges {
+        excludes: &excludes,
+        repack_suffix: Some("+ds"),
+        unpackageable: &unpackageable,
+        unpackageable_enabling: &["fast"],
+        broken_tests: vec!["lib+std".to_string()],
+        patches: vec!["relax-deps.patch".to_string()],
+        ..SourceChanges::default()
+    };
+    let readme = readme_source(&changes).unwrap();
+    let sections = readme.split("\n\n").collect::<Vec<_>>();
+    assert!(sections[0].starts_with("The orig tarball is repacked, with the version suffix +ds"));
+    assert_eq!(sections[1], " - data/*");
+    assert_eq!(sections[5], " - fast");
+    assert!(sections[6].ends_with("(test_is_broken in debcargo.toml):"));
+    assert_eq!(sections[7], " - lib+std");
+    assert_eq!(sections[9], " - relax-deps.patch\n");
+    assert!(!readme.contains("whitelist"));
+}
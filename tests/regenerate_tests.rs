@@ -0,0 +1,65 @@
+extern crate debcargo;
+extern crate semver;
+
+use debcargo::debian::control::PackageNaming;
+use debcargo::outdated::Packaging;
+use debcargo::regenerate::{summary, Outcome, Regenerated};
+use debcargo::verify::Contents;
+use semver::Version;
+
+use std::path::{Path, PathBuf};
+
+fn contents(files: &[(&str, &str)]) -> Contents {
+    files
+        .iter()
+        .map(|&(p, d)| (PathBuf::from(p), d.as_bytes().to_vec()))
+        .collect()
+}
+
+fn regenerated(outcome: Outcome) -> Regenerated {
+    Regenerated {
+        packaging: Packaging {
+            dir: PathBuf::from("src/foo"),
+            crate_name: "foo".to_string(),
+            version: Version::parse("1.0.0").unwrap(),
+            semver_suffix: false,
+            naming: PackageNaming::default(),
+        },
+        dir: PathBuf::from("build/foo"),
+        outcome: outcome,
+    }
+}
+
+#[test]
+fn outcomes() {
+    let before = contents(&[("control", "Source: rust-foo\nSection: rust\n"), ("rules", "%:\n")]);
+    assert_eq!(Outcome::of(None, &before), Outcome::New);
+    assert_eq!(Outcome::of(Some(&before), &before), Outcome::Unchanged);
+    let after = contents(&[("control", "Source: rust-foo\nSection: utils\n"), ("debcargo.stamp", "x\n")]);
+    assert_eq!(
+        Outcome::of(Some(&before), &after),
+        Outcome::Changed(vec![
+            "modified: control, line 2:\n  before: Section: rust\n  after:  Section: utils".to_string(),
+            "missing: rules".to_string(),
+            "extra: debcargo.stamp".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn reports() {
+    let packaging = regenerated(Outcome::New).packaging;
+    assert_eq!(
+        Regenerated::output_path(Path::new("src"), &packaging, Path::new("build")),
+        PathBuf::from("build/foo")
+    );
+
+    let all = vec![
+        regenerated(Outcome::Changed(vec!["extra: debcargo.stamp".to_string()])),
+        regenerated(Outcome::Unchanged),
+        regenerated(Outcome::Failed("no such version".to_string())),
+    ];
+    assert_eq!(all[0].to_string(), "foo 1.0.0 [src/foo]: 1 files changed\n  extra: debcargo.stamp\n");
+    assert_eq!(all[2].to_string(), "foo 1.0.0 [src/foo]: FAILED: no such version\n");
+    assert_eq!(summary(&all), "Regenerated 3 packagings: 1 changed, 1 unchanged, 0 new, 1 failed");
+}
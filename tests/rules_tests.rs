@@ -0,0 +1,46 @@
+extern crate debcargo;
+
+use debcargo::debian::with_extra_rules;
+
+const GENERATED: &str = "\
+#!/usr/bin/make -f
+%:
+\tdh $@ --buildsystem cargo
+
+override_dh_auto_test:
+\tdh_auto_test -- test --all
+";
+
+fn lines(l: &[&str]) -> Vec<String> {
+    l.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn no_extra_rules() {
+    assert_eq!(with_extra_rules(GENERATED, &[]), GENERATED);
+}
+
+#[test]
+fn extra_rules_are_appended() {
+    let extra = lines(&["export RUST_TEST_THREADS := 1", "", "override_dh_auto_build:", "\techo custom"]);
+    assert_eq!(
+        with_extra_rules(GENERATED, &extra),
+        format!("{}\n{}\n", GENERATED, extra.join("\n"))
+    );
+}
+
+#[test]
+fn redefined_targets_replace_generated_ones() {
+    let extra = lines(&["override_dh_auto_test:", "\tdh_auto_test -- test --all -- --skip net_"]);
+    assert_eq!(
+        with_extra_rules(GENERATED, &extra),
+        "\
+#!/usr/bin/make -f
+%:
+\tdh $@ --buildsystem cargo
+
+override_dh_auto_test:
+\tdh_auto_test -- test --all -- --skip net_
+"
+    );
+}
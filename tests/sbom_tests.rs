@@ -0,0 +1,75 @@
+extern crate debcargo;
+extern crate semver;
+
+use debcargo::sbom::{spdx_license_expression, Sbom};
+use debcargo::tree::{DebianStatus, TreeNode};
+use semver::Version;
+
+fn node(name: &str, version: &str, repeated: bool, children: Vec<TreeNode>) -> TreeNode {
+    TreeNode {
+        name: name.to_string(),
+        req: "*".to_string(),
+        version: Version::parse(version).unwrap(),
+        checksum: Some(format!("{}sum", name)),
+        features: vec!["default".to_string()],
+        debian: DebianStatus::Unknown,
+        repeated: repeated,
+        children: children,
+    }
+}
+
+#[test]
+fn license_expressions() {
+    assert_eq!(spdx_license_expression("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+    assert_eq!(spdx_license_expression("MIT or Apache-2.0"), "MIT OR Apache-2.0");
+    assert_eq!(spdx_license_expression("Apache-2.0 with LLVM-exception"), "Apache-2.0 WITH LLVM-exception");
+    assert_eq!(spdx_license_expression("(MIT OR Apache-2.0) AND Unicode-DFS-2016"),
+               "(MIT OR Apache-2.0) AND Unicode-DFS-2016");
+}
+
+#[test]
+fn spdx_documents() {
+    let tree = node("foo_bar", "1.2.0", false, vec![
+        node("libc", "0.2.40", false, vec![]),
+        node("log", "0.4.6", false, vec![node("libc", "0.2.40", true, vec![])]),
+    ]);
+    let sbom = Sbom::from_tree("rust-foo-bar", &tree, |name, _| {
+        Ok(if name == "log" { None } else { Some("MIT/Apache-2.0".to_string()) })
+    }).unwrap();
+    assert_eq!(sbom.packages.len(), 3);
+    assert_eq!(sbom.depends, vec![(0, 1), (0, 2), (2, 1)]);
+
+    let doc = sbom.to_spdx("2019-01-01T00:00:00Z");
+    let lines = doc.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "SPDXVersion: SPDX-2.3");
+    assert!(lines.contains(&"DocumentName: rust-foo-bar-1.2.0"));
+    assert!(lines.contains(&"SPDXID: SPDXRef-Package-foo-bar-1.2.0"));
+    assert!(lines.contains(&"PackageChecksum: SHA256: libcsum"));
+    assert!(lines.contains(&"PackageLicenseDeclared: MIT OR Apache-2.0"));
+    assert!(lines.contains(&"PackageLicenseDeclared: NOASSERTION"));
+    assert!(lines.contains(&"ExternalRef: PACKAGE-MANAGER purl pkg:cargo/log@0.4.6"));
+    assert_eq!(&lines[lines.len() - 4..], &[
+        "Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-Package-foo-bar-1.2.0",
+        "Relationship: SPDXRef-Package-foo-bar-1.2.0 DEPENDS_ON SPDXRef-Package-libc-0.2.40",
+        "Relationship: SPDXRef-Package-foo-bar-1.2.0 DEPENDS_ON SPDXRef-Package-log-0.4.6",
+        "Relationship: SPDXRef-Package-log-0.4.6 DEPENDS_ON SPDXRef-Package-libc-0.2.40",
+    ]);
+    // the same crates make the same namespace
+    assert_eq!(sbom.to_spdx("2020-01-01T00:00:00Z").lines().nth(4), Some(lines[4]));
+}
+
+#[test]
+fn cyclonedx_documents() {
+    let tree = node("foo", "1.0.0", false, vec![node("libc", "0.2.40", false, vec![])]);
+    let sbom = Sbom::from_tree("rust-foo", &tree, |_, _| Ok(Some("MIT".to_string()))).unwrap();
+    let doc = sbom.to_cyclonedx("2019-01-01T00:00:00Z");
+    assert!(doc.contains("\"bomFormat\": \"CycloneDX\""));
+    assert!(doc.contains("\"bom-ref\": \"pkg:cargo/foo@1.0.0\""));
+    assert!(doc.contains("\"expression\": \"MIT\""));
+    assert!(doc.contains("\"content\": \"libcsum\""));
+    let deps = &doc[doc.find("\"dependencies\"").unwrap()..];
+    assert!(deps.contains("\"ref\": \"pkg:cargo/foo@1.0.0\",\n      \"dependsOn\": [\n        \"pkg:cargo/libc@0.2.40\"\n      ]"));
+    assert!(deps.contains("\"ref\": \"pkg:cargo/libc@0.2.40\",\n      \"dependsOn\": []"));
+    let serial = doc.lines().find(|l| l.contains("serialNumber")).unwrap();
+    assert_eq!(serial.trim().len(), "\"serialNumber\": \"urn:uuid:01234567-89ab-cdef-0123-456789abcdef\",".len());
+}
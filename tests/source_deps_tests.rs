@@ -0,0 +1,45 @@
+extern crate cargo;
+extern crate debcargo;
+extern crate semver;
+
+use std::path::Path;
+
+use cargo::core::{Dependency, GitReference, SourceId};
+use cargo::util::ToUrl;
+use cargo::Config;
+use debcargo::crates::{is_source_dependency, registry_dependency_line};
+use semver::Version;
+
+fn crates_io() -> SourceId {
+    SourceId::crates_io(&Config::default().unwrap()).unwrap()
+}
+
+#[test]
+fn git_and_path_dependencies_are_flagged() {
+    let git = SourceId::for_git(
+        &"https://github.com/example/foo".to_url().unwrap(),
+        GitReference::Branch("master".to_string()),
+    ).unwrap();
+    let path = SourceId::for_path(Path::new("/src/bar")).unwrap();
+    let dep = |source| Dependency::parse_no_deprecated("foo", None, source).unwrap();
+    assert!(is_source_dependency(&dep(git)));
+    assert!(is_source_dependency(&dep(path)));
+    assert!(!is_source_dependency(&dep(crates_io())));
+}
+
+#[test]
+fn registry_lines_keep_the_dependency_settings() {
+    let version = Version::parse("1.2.3").unwrap();
+    let mut dep = Dependency::parse_no_deprecated("foo", None, crates_io()).unwrap();
+    assert_eq!(registry_dependency_line(&dep, &version), "foo = \"1.2.3\"");
+
+    dep.set_optional(true)
+        .set_default_features(false)
+        .set_features(vec!["std", "derive"])
+        .set_explicit_name_in_toml("bar");
+    assert_eq!(
+        registry_dependency_line(&dep, &version),
+        "bar = { package = \"foo\", version = \"1.2.3\", optional = true, \
+         default-features = false, features = [\"std\", \"derive\"] }"
+    );
+}
@@ -0,0 +1,146 @@
+extern crate debcargo;
+extern crate flate2;
+extern crate tar;
+extern crate tempfile;
+extern crate xz2;
+
+use debcargo::debian::source::{build_source_package, SourceFile};
+use debcargo::debian::syntax::parse_deb822;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+const CONTROL: &str = "\
+Source: rust-foo
+Section: rust
+Priority: optional
+Build-Depends: debhelper-compat (= 12),
+ dh-cargo (>= 24),
+ librust-bar-1+default-dev
+Maintainer: Debian Rust Maintainers <pkg-rust-maintainers@alioth-lists.debian.net>
+Uploaders:
+ Jane Doe <jane@example.org>
+Standards-Version: 4.2.0
+Homepage: https://foo.example.org
+Testsuite: autopkgtest-pkg-rust
+
+Package: librust-foo-dev
+Architecture: any
+Multi-Arch: same
+Description: Foo - Rust source code
+
+Package: foo
+Architecture: any
+Section: utils
+Description: Foo
+";
+
+const CHANGELOG: &str = "\
+rust-foo (1:1.0.0-2) unstable; urgency=medium
+
+  * Package foo 1.0.0 from crates.io using debcargo 2.3.0
+
+ -- Jane Doe <jane@example.org>  Sat, 01 Jun 2019 12:00:00 +0000
+";
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn source_packages() {
+    let dir = tempfile::tempdir().unwrap();
+    let pkg = dir.path().join("rust-foo-1.0.0");
+    write(&pkg.join("src/lib.rs"), "pub fn foo() {}\n");
+    let orig = dir.path().join("rust-foo_1.0.0.orig.tar.gz");
+    let mut builder = tar::Builder::new(GzEncoder::new(fs::File::create(&orig).unwrap(), Compression::best()));
+    builder.append_dir_all("rust-foo-1.0.0", &pkg).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    write(&pkg.join("debian/control"), CONTROL);
+    write(&pkg.join("debian/changelog"), CHANGELOG);
+    write(&pkg.join("debian/source/format"), "3.0 (quilt)\n");
+    write(&pkg.join("debian/tests/control"), "\
+Test-Command: true
+Depends: dh-cargo (>= 18), @
+
+Test-Command: false
+Depends: dh-cargo (>= 18), librust-foo-dev, python3:native | perl [amd64]
+");
+    write(&pkg.join("debian/rules"), "#!/usr/bin/make -f\n%:\n\tdh $@ --buildsystem cargo\n");
+    fs::set_permissions(pkg.join("debian/rules"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let dsc = build_source_package(&pkg).unwrap();
+    assert_eq!(dsc, dir.path().join("rust-foo_1.0.0-2.dsc"));
+    let text = fs::read_to_string(&dsc).unwrap();
+    let paragraphs = parse_deb822("dsc", &text).unwrap();
+    assert_eq!(paragraphs.len(), 1);
+    let get = |name: &str| paragraphs[0].get(name).unwrap().value.clone();
+    assert_eq!(get("Format"), "3.0 (quilt)");
+    assert_eq!(get("Source"), "rust-foo");
+    assert_eq!(get("Binary"), "librust-foo-dev, foo");
+    assert_eq!(get("Architecture"), "any");
+    assert_eq!(get("Version"), "1:1.0.0-2");
+    assert_eq!(get("Uploaders"), "Jane Doe <jane@example.org>");
+    assert_eq!(get("Testsuite"), "autopkgtest, autopkgtest-pkg-rust");
+    assert_eq!(get("Testsuite-Triggers"), "dh-cargo, perl, python3");
+    assert_eq!(get("Build-Depends"), "debhelper-compat (= 12), dh-cargo (>= 24), librust-bar-1+default-dev");
+    assert_eq!(get("Package-List"), "
+librust-foo-dev deb rust optional arch=any
+foo deb utils optional arch=any");
+
+    let debian_tarball = dir.path().join("rust-foo_1.0.0-2.debian.tar.xz");
+    let files = [SourceFile::new(&orig).unwrap(), SourceFile::new(&debian_tarball).unwrap()];
+    let sums = files
+        .iter()
+        .map(|f| format!("\n{} {} {}", f.sha256, f.size, f.name))
+        .collect::<String>();
+    assert_eq!(get("Checksums-Sha256"), sums);
+    let md5s = files
+        .iter()
+        .map(|f| format!("\n{} {} {}", f.md5, f.size, f.name))
+        .collect::<String>();
+    assert_eq!(get("Files"), md5s);
+
+    let mut archive = tar::Archive::new(xz2::read::XzDecoder::new(fs::File::open(&debian_tarball).unwrap()));
+    let entries = archive
+        .entries()
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (e.path().unwrap().to_string_lossy().into_owned(), e.header().mode().unwrap())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(entries, vec![
+        ("debian".to_string(), 0o755),
+        ("debian/changelog".to_string(), 0o644),
+        ("debian/control".to_string(), 0o644),
+        ("debian/rules".to_string(), 0o755),
+        ("debian/source".to_string(), 0o755),
+        ("debian/source/format".to_string(), 0o644),
+        ("debian/tests".to_string(), 0o755),
+        ("debian/tests/control".to_string(), 0o644),
+    ]);
+
+    // the same packaging gives the same debian tarball
+    let first = fs::read(&debian_tarball).unwrap();
+    build_source_package(&pkg).unwrap();
+    assert_eq!(fs::read(&debian_tarball).unwrap(), first);
+
+    fs::remove_file(&orig).unwrap();
+    assert!(build_source_package(&pkg).is_err());
+}
+
+#[test]
+fn other_formats_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let pkg = dir.path().join("rust-foo-1.0.0");
+    write(&pkg.join("debian/source/format"), "3.0 (native)\n");
+    write(&pkg.join("debian/changelog"), CHANGELOG);
+    write(&pkg.join("debian/control"), CONTROL);
+    assert!(build_source_package(&pkg).is_err());
+}
@@ -0,0 +1,41 @@
+extern crate debcargo;
+
+use debcargo::debian::stamp::Stamp;
+
+#[test]
+fn stamps() {
+    let stamp = Stamp {
+        debcargo_version: "2.4.0".to_string(),
+        config_sha256: Some("ab12".to_string()),
+    };
+    assert_eq!(stamp.to_string(), "Debcargo-Version: 2.4.0\nConfig-Sha256: ab12\n");
+    assert_eq!(Stamp::parse(&stamp.to_string()), Some(stamp.clone()));
+    assert_eq!(Stamp::parse("Config-Sha256: ab12\n"), None);
+    assert!(stamp.changes_since(&stamp).is_empty());
+
+    let changelog = "\
+rust-foo (1.1.0-1) unstable; urgency=medium
+
+  * Package foo 1.1.0 from crates.io using debcargo 2.3.0
+
+ -- Jane Doe <jane@example.org>  Sat, 01 Jun 2019 12:00:00 +0000
+
+rust-foo (1.0.0-1) unstable; urgency=medium
+
+  * Package foo 1.0.0 from crates.io using debcargo 2.2.0
+
+ -- Jane Doe <jane@example.org>  Sat, 01 Jan 2019 12:00:00 +0000
+";
+    let previous = Stamp::from_changelog(changelog).unwrap();
+    assert_eq!(previous.debcargo_version, "2.3.0");
+    assert_eq!(previous.config_sha256, None);
+    let changes = stamp.changes_since(&previous);
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].contains("debcargo 2.3.0 before"));
+
+    let previous = Stamp {
+        config_sha256: Some("cd34".to_string()),
+        ..stamp.clone()
+    };
+    assert_eq!(stamp.changes_since(&previous), vec!["The config changed since the packaging was generated"]);
+}
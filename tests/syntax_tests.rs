@@ -0,0 +1,159 @@
+extern crate debcargo;
+
+use debcargo::debian::syntax::{check_changelog, check_control, check_copyright, check_debian_dir,
+                               parse_deb822};
+
+use std::path::Path;
+
+fn lines_and_messages(errors: Vec<debcargo::debian::syntax::SyntaxError>) -> Vec<(usize, String)> {
+    errors.into_iter().map(|e| (e.line, e.message)).collect()
+}
+
+#[test]
+fn golden_outputs_are_well_formed() {
+    for case in &["lib-features", "lib-examples", "lib-or-later", "bin-config"] {
+        let dir = Path::new("tests/golden").join(case).join("expected");
+        let errors = check_debian_dir(&dir).unwrap();
+        // the expected changelogs have their dates normalized away
+        let errors = errors.iter().filter(|e| e.file != "changelog").collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{}: {:?}", case, errors);
+    }
+}
+
+#[test]
+fn deb822_paragraphs() {
+    let text = "Source: foo\n# comment\nBuild-Depends:\n a,\n b\n\nPackage: foo\nDescription: x\n long\n";
+    let paragraphs = parse_deb822("control", text).unwrap();
+    assert_eq!(paragraphs.len(), 2);
+    assert_eq!(paragraphs[0].get("build-depends").unwrap().value, "\na,\nb");
+    assert_eq!(paragraphs[1].line, 7);
+
+    let errors = parse_deb822("control", " orphan\nSource: foo\nSource: bar\nbroken line\nA: b\n \n")
+        .unwrap_err();
+    assert_eq!(
+        errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+        vec![1, 3, 4, 6]
+    );
+    assert_eq!(errors[1].to_string(), "debian/control:3: duplicate field Source");
+}
+
+#[test]
+fn control_problems() {
+    let text = "\
+Source: rust-foo
+Maintainer: A <a@example.org>
+Build-Depends: debhelper (>= 11), cargo:native <!nocheck>, librust-bar-1+default-dev (>= 1.2-~~)
+
+Package: librust-foo-dev
+Architecture: any
+Depends:
+ ${misc:Depends},
+ librust-baz-dev (> 1),
+ ,
+ librust-qux-dev | librust-quux-dev
+Description: Foo
+ Long.
+
+Package: librust_foo+Bar-dev
+Architecture: any
+Description:
+ No synopsis.
+
+Package: librust-foo-dev
+Description: Duplicate
+";
+    assert_eq!(
+        lines_and_messages(check_control(text)),
+        vec![
+            (7, "Depends has a malformed relation: \"librust-baz-dev (> 1)\"".to_string()),
+            (7, "Depends has an empty relation".to_string()),
+            (15, "invalid package name \"librust_foo+Bar-dev\"".to_string()),
+            (17, "Description has an empty synopsis".to_string()),
+            (20, "paragraph has no Architecture field".to_string()),
+            (20, "duplicate package librust-foo-dev".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn copyright_problems() {
+    let text = "\
+Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+
+Files: *
+Copyright: 2019 A
+License: MIT or Apache-2.0
+
+Files: src/*
+License: UNKNOWN-LICENSE; FIXME (overlay)
+
+License: MIT
+ Permission is hereby granted.
+
+License: GPL-3.0
+";
+    assert_eq!(
+        lines_and_messages(check_copyright(text)),
+        vec![
+            (7, "Files paragraph has no Copyright field".to_string()),
+            (13, "standalone License paragraph for GPL-3.0 has no license text".to_string()),
+            (5, "license Apache-2.0 has no standalone License paragraph with its text".to_string()),
+        ]
+    );
+    assert_eq!(
+        lines_and_messages(check_copyright("Files: *\nCopyright: A\nLicense: MIT\n")),
+        vec![(1, "header paragraph has no Format field".to_string())]
+    );
+    let or_later = "\
+Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+
+Files: *
+Copyright: 2019 A
+License: GPL-3.0-or-later or MIT
+
+License: GPL-3.0-or-later
+ Debian systems provide the GPL 3.0 in /usr/share/common-licenses/GPL-3
+
+License: MIT
+ Permission is hereby granted.
+";
+    assert_eq!(check_copyright(or_later), vec![]);
+}
+
+#[test]
+fn changelog_problems() {
+    let good = "\
+rust-foo (1.0.0-1) UNRELEASED-FIXME-AUTOGENERATED-DEBCARGO; urgency=medium
+
+  * Package foo 1.0.0 from crates.io using debcargo 2.4.0
+
+ -- A <a@example.org>  Mon, 02 Sep 2019 10:00:00 +0200
+
+rust-foo (0.9.0-1) unstable; urgency=medium
+
+  * Initial release.
+
+ -- A <a@example.org>  Sun, 01 Sep 2019 10:00:00 +0200
+";
+    assert!(check_changelog(good).is_empty());
+
+    let bad = "\
+rust-foo (1.0.0-1) unstable; urgency=medium
+
+* Not indented
+
+ -- A <a@example.org> Mon, 02 Sep 2019 10:00:00 +0200
+
+rust-foo (v0.9) unstable; urgency=medium
+
+  * Initial release.
+
+ -- A <a@example.org>  Someday
+
+rust-foo (0.8-1) unstable
+";
+    assert_eq!(
+        lines_and_messages(check_changelog(bad)).iter().map(|&(l, _)| l).collect::<Vec<_>>(),
+        vec![3, 5, 7, 11, 13]
+    );
+}
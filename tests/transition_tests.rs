@@ -0,0 +1,81 @@
+extern crate debcargo;
+extern crate semver;
+
+use debcargo::debian::control::PackageNaming;
+use debcargo::transition::{crosses_suffix, suffixed_provides, Transition};
+use semver::Version;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+#[test]
+fn suffix_boundaries() {
+    assert!(crosses_suffix(&v("0.8.5"), &v("0.9.0")));
+    assert!(crosses_suffix(&v("1.4.0"), &v("2.0.0")));
+    assert!(!crosses_suffix(&v("0.8.4"), &v("0.8.5")));
+    assert!(!crosses_suffix(&v("1.4.0"), &v("1.5.0")));
+}
+
+#[test]
+fn provides_within_suffix() {
+    let provides = vec![
+        "librust-rand+default-dev",
+        "librust-rand-0+default-dev",
+        "librust-rand-0.8-dev",
+        "librust-rand-0.8+std-dev",
+        "librust-rand-0.8.5-dev",
+        "librust-rand-0.80-dev",
+        "librust-rand-core-0.8-dev",
+    ].into_iter().map(str::to_string).collect::<Vec<_>>();
+    assert_eq!(suffixed_provides(&PackageNaming::default(), &provides, "rand", &v("0.8.5")), vec![
+        "librust-rand-0.8+std-dev",
+        "librust-rand-0.8-dev",
+        "librust-rand-0.8.5-dev",
+    ]);
+    assert_eq!(suffixed_provides(&PackageNaming::default(), &provides, "rand", &v("0.9.0")), Vec::<String>::new());
+
+    let naming = PackageNaming::new("rust-$crate-dev", "rust-$crate+$feature-dev").unwrap();
+    let provides = vec!["rust-rand-0.8-dev", "rust-rand-0.8+std-dev", "librust-rand-0.8-dev"]
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(suffixed_provides(&naming, &provides, "rand", &v("0.8.5")), vec![
+        "rust-rand-0.8+std-dev",
+        "rust-rand-0.8-dev",
+    ]);
+}
+
+#[test]
+fn rebuilds() {
+    let transition = Transition {
+        crate_name: "rand".to_string(),
+        from: v("0.8.5"),
+        to: v("0.9.0"),
+        kept_by: None,
+        dropped: vec!["librust-rand-0.8-dev".to_string()],
+        rdepends: vec![
+            ("librust-foo+rand-dev".to_string(), "rust-foo".to_string()),
+            ("librust-foo-dev".to_string(), "rust-foo".to_string()),
+            ("ripgrep".to_string(), "rust-ripgrep".to_string()),
+        ],
+    };
+    assert_eq!(transition.sources(), vec!["rust-foo", "rust-ripgrep"]);
+    assert_eq!(transition.to_string(), "\
+rand 0.8.5 -> 0.9.0 changes the semver suffix -0.8 -> -0.9
+  drops librust-rand-0.8-dev
+  rebuild rust-foo (librust-foo+rand-dev, librust-foo-dev)
+  rebuild rust-ripgrep (ripgrep)
+");
+
+    let kept = Transition {
+        kept_by: Some("librust-rand-0.8-dev".to_string()),
+        dropped: Vec::new(),
+        rdepends: Vec::new(),
+        ..transition
+    };
+    assert_eq!(kept.to_string(), "\
+rand 0.8.5 -> 0.9.0 changes the semver suffix -0.8 -> -0.9
+  librust-rand-0.8-dev keeps providing 0.8.5, nothing needs rebuilding
+");
+}
@@ -0,0 +1,166 @@
+extern crate cargo;
+extern crate debcargo;
+extern crate semver;
+extern crate tempfile;
+
+use debcargo::crates::LOCAL_REGISTRY_ENV;
+use debcargo::tree::{deb_upstream_version, deb_version_satisfies, dot_graph, parse_apt_policy, parse_apt_rdepends,
+                      parse_apt_show, DebianStatus, TreeBuilder, TreeNode};
+use semver::{Version, VersionReq};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn apt_policy_candidate() {
+    let output = "\
+librust-serde-dev:
+  Installed: (none)
+  Candidate: 1.0.210-1
+  Version table:
+     1.0.210-1 500
+        500 http://deb.debian.org/debian trixie/main amd64 Packages
+";
+    assert_eq!(parse_apt_policy(output), Some("1.0.210-1".to_string()));
+    assert_eq!(parse_apt_policy("librust-foo-dev:\n  Installed: (none)\n  Candidate: (none)\n"), None);
+    assert_eq!(parse_apt_policy(""), None);
+}
+
+#[test]
+fn deb_versions_against_requirements() {
+    let req = |r: &str| VersionReq::parse(r).unwrap();
+    assert!(deb_version_satisfies("1.0.210-1", &req("^1.0.100")));
+    assert!(!deb_version_satisfies("1.0.99-1", &req("^1.0.100")));
+    assert!(deb_version_satisfies("1:0.3.2+dfsg-2", &req("^0.3")));
+    assert!(!deb_version_satisfies("0.4.0-1", &req("^0.3")));
+    assert!(deb_version_satisfies("2.0.0~beta1-1", &req("=2.0.0-beta1")));
+}
+
+#[test]
+fn dot_graphs() {
+    let node = |name: &str, features: &[&str], debian: DebianStatus, children: Vec<TreeNode>| TreeNode {
+        name: name.to_string(),
+        req: "^1".to_string(),
+        version: Version::parse("1.0.0").unwrap(),
+        checksum: None,
+        features: features.iter().map(|f| f.to_string()).collect(),
+        debian: debian,
+        repeated: false,
+        children: children,
+    };
+    let ok = || DebianStatus::Ok("librust-x-dev".to_string(), "1.0.0-1".to_string());
+    let tree = node("a", &["default"], DebianStatus::Unknown, vec![
+        node("b", &["default", "std"], ok(), vec![node("d", &[], DebianStatus::Missing, vec![])]),
+        node("c", &[], ok(), vec![node("d", &[], DebianStatus::Missing, vec![])]),
+    ]);
+    assert_eq!(dot_graph(&tree), "\
+digraph \"a\" {
+    \"a 1.0.0\" [shape=box, style=filled, fillcolor=lightgrey, tooltip=\"unknown\"];
+    \"b 1.0.0\" [shape=box, style=filled, fillcolor=palegreen, tooltip=\"librust-x-dev 1.0.0-1\"];
+    \"b 1.0.0/default\" [shape=ellipse, label=\"default\"];
+    \"b 1.0.0/std\" [shape=ellipse, label=\"std\"];
+    \"c 1.0.0\" [shape=box, style=filled, fillcolor=palegreen, tooltip=\"librust-x-dev 1.0.0-1\"];
+    \"d 1.0.0\" [shape=box, style=filled, fillcolor=salmon, tooltip=\"not in Debian\"];
+    \"a 1.0.0\" -> \"b 1.0.0/default\";
+    \"a 1.0.0\" -> \"b 1.0.0/std\";
+    \"a 1.0.0\" -> \"c 1.0.0\";
+    \"b 1.0.0\" -> \"d 1.0.0\";
+    \"b 1.0.0/default\" -> \"b 1.0.0\";
+    \"b 1.0.0/std\" -> \"b 1.0.0\";
+    \"c 1.0.0\" -> \"d 1.0.0\";
+}
+");
+}
+
+#[test]
+fn apt_show_and_rdepends() {
+    let show = "\
+Package: librust-rand-dev
+Source: rust-rand (0.8.5-1)
+Version: 0.8.5-1+b1
+Provides: librust-rand+default-dev (= 0.8.5-1+b1), librust-rand-0.8-dev (= 0.8.5-1+b1)
+Description-en: Random number generators - Rust source code
+
+Package: librust-rand-dev
+Version: 0.8.4-1
+";
+    let (provides, source) = parse_apt_show("librust-rand-dev", show);
+    assert_eq!(provides, vec!["librust-rand+default-dev", "librust-rand-0.8-dev"]);
+    assert_eq!(source, "rust-rand");
+    assert_eq!(parse_apt_show("ripgrep", "Package: ripgrep\nVersion: 14.1.0-1\n"), (Vec::new(), "ripgrep".to_string()));
+
+    let rdepends = "\
+librust-rand-0.8-dev
+Reverse Depends:
+  librust-rand-pcg-dev
+ |librust-uuid+rand-dev
+  librust-rand-pcg-dev
+";
+    assert_eq!(parse_apt_rdepends(rdepends), vec!["librust-rand-pcg-dev", "librust-uuid+rand-dev"]);
+}
+
+#[test]
+fn upstream_version_of_deb_version() {
+    assert_eq!(deb_upstream_version("1:1.2.3~beta1+dfsg-2"), Some(Version::parse("1.2.3-beta1").unwrap()));
+    assert_eq!(deb_upstream_version("0.8.5-1+b1"), Some(Version::parse("0.8.5").unwrap()));
+    assert_eq!(deb_upstream_version("garbage"), None);
+}
+
+/// Add version 1.0.0 of a crate to the index of a local registry, with its
+/// dependencies as (name, optional), and `features` as JSON object members.
+fn publish(registry: &Path, name: &str, deps: &[(&str, bool)], features: &str) {
+    let deps = deps
+        .iter()
+        .map(|&(d, optional)| format!(
+            "{{\"name\":\"{}\",\"req\":\"^1\",\"features\":[],\"optional\":{},\
+             \"default_features\":true,\"target\":null,\"kind\":\"normal\"}}",
+            d, optional))
+        .collect::<Vec<_>>();
+    // only for names of 4 or more characters, see the index layout
+    let index = registry.join("index").join(&name[0..2]).join(&name[2..4]);
+    fs::create_dir_all(&index).unwrap();
+    fs::write(
+        index.join(name),
+        format!(
+            "{{\"name\":\"{}\",\"vers\":\"1.0.0\",\"deps\":[{}],\"cksum\":\"{}\",\
+             \"features\":{{{}}},\"yanked\":false}}\n",
+            name, deps.join(","), "0".repeat(64), features),
+    ).unwrap();
+}
+
+fn child_names(tree: &TreeNode) -> Vec<&str> {
+    tree.children.iter().map(|c| c.name.as_str()).collect()
+}
+
+// One test, since it sets environment variables for the whole process.
+#[test]
+fn tree_follows_enabled_features() {
+    let cargo_home = tempfile::tempdir().unwrap();
+    let registry = tempfile::tempdir().unwrap();
+    env::set_var("CARGO_HOME", cargo_home.path());
+    env::set_var(LOCAL_REGISTRY_ENV, registry.path());
+    publish(
+        registry.path(),
+        "rootcrate",
+        &[("reqdep", false), ("optdep", true), ("extradep", true)],
+        "\"default\":[\"optdep\"],\"extra\":[\"extradep\"]",
+    );
+    for name in &["reqdep", "optdep", "extradep"] {
+        publish(registry.path(), name, &[], "");
+    }
+
+    let config = cargo::Config::default().unwrap();
+    let mut builder = TreeBuilder::new(&config, false, false).unwrap();
+    builder.set_archive_lookups(false);
+    let tree = builder.build("rootcrate", None).unwrap();
+    // what the default features enable, but not the other optional ones
+    assert_eq!(child_names(&tree), vec!["optdep", "reqdep"]);
+
+    let mut builder = TreeBuilder::new(&config, false, true).unwrap();
+    builder.set_archive_lookups(false);
+    let tree = builder.build("rootcrate", None).unwrap();
+    assert_eq!(child_names(&tree), vec!["extradep", "optdep", "reqdep"]);
+
+    env::remove_var(LOCAL_REGISTRY_ENV);
+}
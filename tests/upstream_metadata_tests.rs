@@ -0,0 +1,46 @@
+extern crate debcargo;
+
+use debcargo::crates::{GitOrigin, GitSelector, Provenance};
+use debcargo::debian::upstream_metadata;
+
+#[test]
+fn nothing_to_record() {
+    assert_eq!(upstream_metadata(None, None, &Provenance::default()), None);
+}
+
+#[test]
+fn crate_provenance() {
+    let provenance = Provenance {
+        download_url: Some("https://crates.io/api/v1/crates/foo/1.0.0/download".to_string()),
+        published: Some("2019-03-01T12:00:00.000000+00:00".to_string()),
+    };
+    assert_eq!(
+        upstream_metadata(None, Some("0123abcd"), &provenance).unwrap(),
+        "---\n\
+         X-Crate-Download: https://crates.io/api/v1/crates/foo/1.0.0/download\n\
+         X-Crate-Sha256: 0123abcd\n\
+         X-Crate-Published: 2019-03-01T12:00:00.000000+00:00\n"
+    );
+
+    // e.g. from a local registry
+    assert_eq!(
+        upstream_metadata(None, Some("0123abcd"), &Provenance::default()).unwrap(),
+        "---\nX-Crate-Sha256: 0123abcd\n"
+    );
+}
+
+#[test]
+fn git_origin() {
+    let origin = GitOrigin {
+        url: "https://example.org/foo.git".to_string(),
+        selector: GitSelector::Tag("v1.0.0".to_string()),
+        commit: "0123abcd".to_string(),
+    };
+    assert_eq!(
+        upstream_metadata(Some(&origin), None, &Provenance::default()).unwrap(),
+        "---\n\
+         Repository: https://example.org/foo.git\n\
+         X-Git-Selector: tag v1.0.0\n\
+         X-Git-Commit: 0123abcd\n"
+    );
+}
@@ -0,0 +1,126 @@
+extern crate debcargo;
+extern crate git2;
+extern crate semver;
+extern crate tempfile;
+
+use debcargo::upstream::{candidate_revisions, excluded_files, fetch_upstream_files,
+                         is_packaging_relevant, vcs_info, VcsInfo};
+use debcargo::verify::Contents;
+use git2::{Repository, Signature};
+use semver::Version;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn contents(files: &[(&str, &str)]) -> Contents {
+    files
+        .iter()
+        .map(|&(p, d)| (PathBuf::from(p), d.as_bytes().to_vec()))
+        .collect()
+}
+
+#[test]
+fn relevant_files() {
+    assert!(is_packaging_relevant(Path::new("tests/it.rs")));
+    assert!(is_packaging_relevant(Path::new("benches/b.rs")));
+    assert!(is_packaging_relevant(Path::new("LICENSE-MIT")));
+    assert!(is_packaging_relevant(Path::new("COPYING")));
+    assert!(!is_packaging_relevant(Path::new("src/lib.rs")));
+    assert!(!is_packaging_relevant(Path::new("docs/LICENSE")));
+    assert!(!is_packaging_relevant(Path::new(".github/workflows/ci.yml")));
+}
+
+#[test]
+fn excluded_relevant_files() {
+    let krate = contents(&[("Cargo.toml", ""), ("src/lib.rs", ""), ("LICENSE-MIT", "")]);
+    let upstream = contents(&[
+        ("Cargo.toml", ""),
+        ("src/lib.rs", ""),
+        ("LICENSE-MIT", ""),
+        ("LICENSE-APACHE", ""),
+        ("tests/it.rs", ""),
+        ("ci/run.sh", ""),
+    ]);
+    assert_eq!(
+        excluded_files(&krate, &upstream),
+        vec![PathBuf::from("LICENSE-APACHE"), PathBuf::from("tests/it.rs")]
+    );
+}
+
+#[test]
+fn vcs_info_of_crate() {
+    let krate = contents(&[(
+        ".cargo_vcs_info.json",
+        r#"{"git": {"sha1": "0123abcd"}, "path_in_vcs": "foo-core"}"#,
+    )]);
+    assert_eq!(
+        vcs_info(&krate),
+        VcsInfo {
+            sha1: Some("0123abcd".to_string()),
+            path_in_vcs: "foo-core".to_string(),
+        }
+    );
+    assert_eq!(vcs_info(&contents(&[])), VcsInfo::default());
+
+    let revisions = candidate_revisions("foo", &Version::parse("1.2.0").unwrap(), &vcs_info(&krate));
+    assert_eq!(revisions[0], "0123abcd");
+    assert!(revisions.contains(&"refs/tags/v1.2.0".to_string()));
+    assert!(revisions.contains(&"refs/tags/foo-1.2.0".to_string()));
+}
+
+fn commit_all(repo: &Repository, message: &str) -> git2::Oid {
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::now("Upstream", "upstream@example.org").unwrap();
+    let parent = repo.head().ok().map(|h| h.peel_to_commit().unwrap());
+    let parents = parent.iter().collect::<Vec<_>>();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap()
+}
+
+#[test]
+fn files_at_release_tag() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    let root = dir.path().join("foo");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("tests")).unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+    fs::write(root.join("tests/old.rs"), "").unwrap();
+    let release = commit_all(&repo, "release");
+    let release = repo.find_object(release, None).unwrap();
+    repo.tag_lightweight("v0.1.0", &release, false).unwrap();
+    fs::write(root.join("tests/new.rs"), "").unwrap();
+    commit_all(&repo, "after the release");
+
+    let url = format!("file://{}", dir.path().display());
+    let version = Version::parse("0.1.0").unwrap();
+    let vcs = VcsInfo {
+        sha1: None,
+        path_in_vcs: "foo".to_string(),
+    };
+    let (revision, files) = fetch_upstream_files(&url, "foo", &version, &vcs, false).unwrap();
+    assert_eq!(revision, "refs/tags/v0.1.0");
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        vec![Path::new("src/lib.rs"), Path::new("tests/old.rs")]
+    );
+}
+
+#[test]
+fn files_from_head_only_when_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+    commit_all(&repo, "untagged");
+
+    let url = format!("file://{}", dir.path().display());
+    let version = Version::parse("0.1.0").unwrap();
+    let vcs = VcsInfo::default();
+    assert!(fetch_upstream_files(&url, "foo", &version, &vcs, false).is_err());
+    let (revision, files) = fetch_upstream_files(&url, "foo", &version, &vcs, true).unwrap();
+    assert_eq!(revision, "HEAD");
+    assert_eq!(files.keys().collect::<Vec<_>>(), vec![Path::new("src/lib.rs")]);
+}
@@ -0,0 +1,158 @@
+extern crate debcargo;
+extern crate tempfile;
+extern crate toml;
+
+use debcargo::config::Config;
+use debcargo::crates::CrateInfo;
+use debcargo::validate::{check_options, config_skeleton, unknown_keys, unknown_override_keys, validate_config,
+                         Issue};
+
+use std::fs;
+use std::path::PathBuf;
+
+fn config(content: &str) -> Config {
+    toml::from_str(content).unwrap()
+}
+
+#[test]
+fn unknown_keys_are_reported() {
+    let content = r#"
+semver_sufix = true
+summary = "ok"
+
+[source]
+vcs_git = "ok"
+build-depends = ["typo"]
+
+[packages.lib]
+depends = ["ok"]
+recomends = ["typo"]
+
+[backports.bookworm]
+build_depends_version = {}
+"#;
+    assert_eq!(
+        unknown_keys(content).unwrap(),
+        vec![
+            "semver_sufix",
+            "source.build-depends",
+            "packages.\"lib\".recomends",
+            "backports.\"bookworm\".build_depends_version",
+        ]
+    );
+}
+
+#[test]
+fn unknown_keys_in_crate_sections() {
+    let content = r#"
+[packages."foo"]
+semver_suffix = true
+bin-name = "typo"
+
+[packages."foo".source]
+testsuit = true
+
+[packages."foo".packages."lib+std"]
+test_is_broken = true
+
+[packages."foo".packages."bar"]
+summary = "nested"
+"#;
+    assert_eq!(
+        unknown_keys(content).unwrap(),
+        vec![
+            "packages.\"foo\".bin-name",
+            "packages.\"foo\".source.testsuit",
+            "packages.\"foo\".packages.\"bar\"",
+        ]
+    );
+}
+
+#[test]
+fn unknown_keys_in_overrides() {
+    let overrides = vec![
+        "source.section=utils".to_string(),
+        "source.sektion=utils".to_string(),
+        "packages.\"lib+std\".test_is_brokn=true".to_string(),
+    ];
+    assert_eq!(
+        unknown_override_keys(&overrides).unwrap(),
+        vec!["source.sektion", "packages.\"lib+std\".test_is_brokn"]
+    );
+}
+
+#[test]
+fn fixtures_have_no_unknown_keys() {
+    let mut fixtures = vec![
+        PathBuf::from("tests/clap_override.toml"),
+        PathBuf::from("tests/debcargo_override.toml"),
+        PathBuf::from("tests/rename_override.toml"),
+        PathBuf::from("tests/source_fields_override.toml"),
+        PathBuf::from("tests/vcs_override.toml"),
+        PathBuf::from("tests/workspace_override.toml"),
+    ];
+    for entry in fs::read_dir("tests/configs").unwrap() {
+        fixtures.push(entry.unwrap().path().join("debian/debcargo.toml"));
+    }
+    for f in &fixtures {
+        let issues = validate_config(f, None).unwrap();
+        assert_eq!(issues, vec![], "{}", f.display());
+    }
+}
+
+#[test]
+fn conflicting_options() {
+    let issues = check_options(&config("semver_suffix = true\nbin = true\n"));
+    assert_eq!(issues.len(), 1);
+    assert!(!issues[0].is_error());
+
+    let issues = check_options(&config("bin = false\nbin_name = \"foo\"\n"));
+    assert_eq!(issues, vec![Issue::Warning("bin_name has no effect, since no bin package is built".to_string())]);
+
+    let issues = check_options(&config("excludes = [\"a/**\"]\nwhitelist = [\"a/**\", \"[\"]\n"));
+    assert_eq!(issues.len(), 2);
+    assert!(issues.iter().any(|i| i.is_error()));
+
+    let issues = check_options(&config("[packages.libfoo]\nsection = \"rust\"\n"));
+    assert!(issues[0].is_error());
+
+    let issues = check_options(&config("overlay = \"does-not-exist\"\n"));
+    assert!(issues[0].is_error());
+
+    let issues = check_options(&config("[backports.notarelease]\n"));
+    assert!(issues[0].is_error());
+
+    assert_eq!(check_options(&config("[source]\nrequires_root = \"binary-targets\"\n")), vec![]);
+    assert!(check_options(&config("[source]\nrequires_root = \"yes\"\n"))[0].is_error());
+
+    assert_eq!(check_options(&config("[source]\ndh_cargo_version = \"24\"\nrustc_version = \"1.41~\"\n")), vec![]);
+    assert!(check_options(&config("[source]\ncargo_version = \">= 0.43\"\n"))[0].is_error());
+
+    assert_eq!(check_options(&config("changelog_author = \"Jane Doe <jane@example.org>\"\n")), vec![]);
+    assert!(check_options(&config("changelog_author = \"jane@example.org\"\n"))[0].is_error());
+}
+
+#[test]
+fn skeletons_are_valid() {
+    let dir = tempfile::Builder::new().prefix("debcargo").tempdir().unwrap();
+    let crate_dir = dir.path().join("golden_lib");
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::copy("tests/golden/lib-features/crate/Cargo.toml", crate_dir.join("Cargo.toml")).unwrap();
+    fs::copy("tests/golden/lib-features/crate/src/lib.rs", crate_dir.join("src/lib.rs")).unwrap();
+    let crate_info = CrateInfo::new_from_path(&crate_dir, None, false).unwrap();
+
+    let skeleton = config_skeleton(&crate_info).unwrap();
+    assert!(skeleton.starts_with("# debcargo.toml for golden_lib 0.3.1,"));
+    assert!(skeleton.contains("\n#   A small library for checking that debcargo's output doesn't change by accident.\n"));
+    assert!(skeleton.contains("\n#   derive: serde\n"));
+    assert!(skeleton.contains("\n#   serde (optional dependency): serde ^1.0\n"));
+    assert!(skeleton.contains("\n#   std: nothing\n"));
+    assert!(skeleton.contains("\n#[packages.\"lib+derive\"]\n"));
+    assert!(skeleton.contains("\n[packages.lib]\n"));
+    assert!(!skeleton.contains("[packages.bin]"));
+
+    let config_path = dir.path().join("debcargo.toml");
+    fs::write(&config_path, &skeleton).unwrap();
+    let issues = validate_config(&config_path, Some(&crate_info)).unwrap();
+    assert_eq!(issues, vec![]);
+}
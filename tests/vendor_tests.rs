@@ -0,0 +1,103 @@
+extern crate debcargo;
+extern crate semver;
+extern crate tempfile;
+
+use debcargo::vendor::{cargo_checksum_json, component_tarball_path, locked_registry_crates,
+                       vendor_dir_names, LockedCrate};
+use semver::Version;
+
+use std::fs;
+use std::path::Path;
+
+fn locked(name: &str, version: &str) -> LockedCrate {
+    LockedCrate {
+        name: name.to_string(),
+        version: Version::parse(version).unwrap(),
+        checksum: None,
+    }
+}
+
+#[test]
+fn registry_crates_from_lockfile() {
+    let lockfile = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["bitflags", "helper"]
+
+[[package]]
+name = "bitflags"
+version = "1.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a"
+
+[[package]]
+name = "helper"
+version = "0.1.0"
+"#;
+    let crates = locked_registry_crates(lockfile, "app").unwrap();
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0].name, "bitflags");
+    assert_eq!(crates[0].version, Version::parse("1.3.2").unwrap());
+    assert_eq!(
+        crates[0].checksum.as_ref().map(String::as_str),
+        Some("bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a")
+    );
+}
+
+#[test]
+fn registry_crates_from_old_lockfile() {
+    let lockfile = r#"
+[[package]]
+name = "bitflags"
+version = "1.0.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[metadata]
+"checksum bitflags 1.0.4 (registry+https://github.com/rust-lang/crates.io-index)" = "228047a76f468627ca71776ecdebd732a3423081fcf5125585bcd7c49886ce12"
+"#;
+    let crates = locked_registry_crates(lockfile, "app").unwrap();
+    assert_eq!(
+        crates[0].checksum.as_ref().map(String::as_str),
+        Some("228047a76f468627ca71776ecdebd732a3423081fcf5125585bcd7c49886ce12")
+    );
+}
+
+#[test]
+fn git_dependencies_cannot_be_vendored() {
+    let lockfile = r#"
+[[package]]
+name = "foo"
+version = "0.1.0"
+source = "git+https://example.org/foo#0123456789abcdef"
+"#;
+    assert!(locked_registry_crates(lockfile, "app").is_err());
+}
+
+#[test]
+fn vendor_dirs_like_cargo_vendor() {
+    let crates = vec![locked("bitflags", "1.3.2"), locked("rand", "0.7.3"), locked("rand", "0.8.5")];
+    assert_eq!(vendor_dir_names(&crates), vec!["bitflags", "rand-0.7.3", "rand-0.8.5"]);
+}
+
+#[test]
+fn checksum_json_lists_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::create_dir(tmp.path().join("src")).unwrap();
+    fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+    fs::write(tmp.path().join(".cargo-checksum.json"), "{}").unwrap();
+    assert_eq!(
+        cargo_checksum_json(tmp.path(), Some("abc")).unwrap(),
+        r#"{"files":{"src/lib.rs":"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"},"package":"abc"}"#
+    );
+}
+
+#[test]
+fn component_tarball_next_to_orig() {
+    assert_eq!(
+        component_tarball_path(Path::new("out/rust-app_0.1.0.orig.tar.gz"), "vendor"),
+        Path::new("out/rust-app_0.1.0.orig-vendor.tar.gz")
+    );
+}
@@ -0,0 +1,60 @@
+extern crate debcargo;
+extern crate flate2;
+extern crate tar;
+extern crate tempfile;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use debcargo::verify::{dir_contents, sha256_hex, tarball_contents};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for &(path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+#[test]
+fn tarball_and_dir_contents_agree() {
+    let files = [
+        ("foo-1.0.0/Cargo.toml", "[package]\n"),
+        ("foo-1.0.0/src/lib.rs", "pub fn foo() {}\n"),
+    ];
+    let from_tarball = tarball_contents(&tarball(&files)[..]).unwrap();
+    assert_eq!(
+        from_tarball.keys().cloned().collect::<Vec<_>>(),
+        vec![PathBuf::from("Cargo.toml"), PathBuf::from("src/lib.rs")]
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    for &(path, data) in &files {
+        let path = dir.path().join(Path::new(path).strip_prefix("foo-1.0.0").unwrap());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+    // packaging and quilt state are not part of the upstream source
+    fs::create_dir_all(dir.path().join("debian")).unwrap();
+    fs::write(dir.path().join("debian/control"), "Source: rust-foo\n").unwrap();
+    fs::create_dir_all(dir.path().join(".pc")).unwrap();
+    fs::write(dir.path().join(".pc/applied-patches"), "").unwrap();
+    assert_eq!(dir_contents(dir.path()).unwrap(), from_tarball);
+}
+
+#[test]
+fn sha256() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("abc");
+    fs::write(&path, "abc").unwrap();
+    assert_eq!(
+        sha256_hex(&path).unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
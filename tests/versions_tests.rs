@@ -0,0 +1,57 @@
+extern crate debcargo;
+extern crate semver;
+
+use debcargo::versions::{crates_io_versions, format_versions, list_versions, ListedVersion};
+use semver::Version;
+
+fn v(s: &str) -> Version {
+    Version::parse(s).unwrap()
+}
+
+const JSON: &str = r#"{
+    "crate": {"name": "foo"},
+    "versions": [
+        {"num": "1.0.0", "yanked": false, "created_at": "2024-06-01T12:00:00.000000+00:00"},
+        {"num": "0.9.1", "yanked": true, "created_at": "2024-01-15T08:30:00.000000+00:00"},
+        {"num": "0.9.0", "yanked": false, "created_at": "2023-11-02T10:00:00.000000+00:00"},
+        {"num": "0.10.0-rc.1", "yanked": false, "created_at": "2024-03-01T00:00:00.000000+00:00"}
+    ]
+}"#;
+
+#[test]
+fn from_crates_io() {
+    let versions = crates_io_versions("foo", JSON).unwrap();
+    assert_eq!(versions.len(), 4);
+    assert_eq!(versions[1], ListedVersion {
+        version: v("0.9.1"),
+        yanked: true,
+        published: Some("2024-01-15T08:30:00.000000+00:00".to_string()),
+    });
+    assert!(crates_io_versions("foo", "{}").is_err());
+}
+
+#[test]
+fn listing() {
+    // the local copy of the index doesn't have 1.0.0 yet, nor yanked versions
+    let index = vec![v("0.9.0"), v("0.10.0-rc.1"), v("0.8.3")];
+    let crates_io = crates_io_versions("foo", JSON).unwrap();
+    let listed = list_versions(&index, Some(&crates_io));
+    assert_eq!(
+        listed.iter().map(|l| l.version.to_string()).collect::<Vec<_>>(),
+        vec!["1.0.0", "0.10.0-rc.1", "0.9.1", "0.9.0", "0.8.3"]
+    );
+    assert_eq!(format_versions(&listed), "\
+1.0.0        -1     2024-06-01
+0.10.0-rc.1  -0.10  2024-03-01
+0.9.1        -0.9   2024-01-15  yanked
+0.9.0        -0.9   2023-11-02
+0.8.3        -0.8
+");
+
+    let offline = list_versions(&index, None);
+    assert_eq!(format_versions(&offline), "\
+0.10.0-rc.1  -0.10
+0.9.0        -0.9
+0.8.3        -0.8
+");
+}
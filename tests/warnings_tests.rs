@@ -0,0 +1,30 @@
+extern crate debcargo;
+
+use debcargo::errors::{warn_with_code, warning_count, warning_json, ErrorKind};
+
+use std::path::Path;
+
+#[test]
+fn warnings_as_json() {
+    assert_eq!(
+        warning_json("fixme", Some(Path::new("debian/copyright")), Some(7), "License: FIXME").unwrap(),
+        r#"{"code":"fixme","file":"debian/copyright","line":7,"message":"License: FIXME"}"#
+    );
+    // the bullets of lists are layout
+    assert_eq!(
+        warning_json("warning", None, None, "\t •  \"quoted\"").unwrap(),
+        r#"{"code":"warning","file":null,"line":null,"message":"\"quoted\""}"#
+    );
+    assert!(warning_json("warning", None, None, "\t(•) hint").unwrap().contains("\"message\":\"hint\""));
+    assert_eq!(warning_json("warning", None, None, " "), None);
+}
+
+#[test]
+fn warnings_are_counted() {
+    let before = warning_count();
+    warn_with_code("warning", None, None, "\t •  ");
+    assert_eq!(warning_count(), before);
+    warn_with_code("suspicious-file", Some(Path::new("src/shim.c")), None, "Suspicious file");
+    assert!(warning_count() > before);
+    assert_eq!(ErrorKind::Warnings.exit_code(), 7);
+}